@@ -1,13 +1,12 @@
 //! Criterion benchmarks for aadc performance testing.
 //!
-//! These benchmarks measure the performance of the aadc binary by invoking
-//! it as a subprocess. This approach tests real-world performance including
-//! process startup, file I/O, and the complete correction pipeline.
-//!
-//! For micro-benchmarks of internal functions, the code would need to be
-//! refactored to expose a library interface.
+//! Most of these benchmarks measure the aadc binary by invoking it as a
+//! subprocess, which tests real-world performance including process
+//! startup, file I/O, and the complete correction pipeline. The `lib_*`
+//! benchmarks call the library API in-process to isolate the correction
+//! pipeline itself.
 
-use criterion::{Criterion, criterion_group, criterion_main};
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
 use std::path::PathBuf;
 use std::process::Command;
 
@@ -115,11 +114,51 @@ fn bench_verbose_mode(c: &mut Criterion) {
     });
 }
 
+/// Read a fixture's lines, or None (with a note) if it is missing
+fn fixture_lines(input_file: &str, bench: &str) -> Option<Vec<String>> {
+    match std::fs::read_to_string(input_file) {
+        Ok(content) => Some(content.lines().map(str::to_string).collect()),
+        Err(_) => {
+            eprintln!("Skipping {}: {} not found", bench, input_file);
+            None
+        }
+    }
+}
+
+/// Benchmark block detection alone, in-process
+fn bench_lib_detect(c: &mut Criterion) {
+    let Some(lines) = fixture_lines("tests/fixtures/large/100_lines.input.txt", "lib_detect")
+    else {
+        return;
+    };
+    let opts = aadc::DetectOptions::default();
+
+    c.bench_function("lib_detect", |b| {
+        b.iter(|| aadc::find_diagram_blocks(black_box(&lines), &opts))
+    });
+}
+
+/// Benchmark the full correction pipeline in-process (no process startup
+/// or file I/O)
+fn bench_lib_correct(c: &mut Criterion) {
+    let Some(lines) = fixture_lines("tests/fixtures/large/cjk_content.input.txt", "lib_correct")
+    else {
+        return;
+    };
+    let config = aadc::Config::default();
+
+    c.bench_function("lib_correct", |b| {
+        b.iter(|| aadc::correct(black_box(lines.clone()), &config))
+    });
+}
+
 criterion_group!(
     benches,
     bench_small_file,
     bench_medium_file,
     bench_cjk_content,
-    bench_verbose_mode
+    bench_verbose_mode,
+    bench_lib_detect,
+    bench_lib_correct
 );
 criterion_main!(benches);
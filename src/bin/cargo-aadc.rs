@@ -0,0 +1,327 @@
+//! # cargo-aadc
+//!
+//! Cargo subcommand that runs aadc over a Rust workspace: `cargo aadc`
+//! corrects diagrams in place, `cargo aadc --check` reports them, in the
+//! same spirit as `cargo fmt` / `cargo fmt --check`.
+//!
+//! Workspace members are discovered with `cargo metadata`. In each member,
+//! `.md` files are processed whole. In `.rs` files only `///` and `//!` doc
+//! comments are corrected: each run of doc comment lines is corrected with
+//! the aadc library with its comment prefix stripped, then re-prefixed, so
+//! code (and line endings) are never touched. The target directory and
+//! gitignored paths are skipped.
+//!
+//! ## Exit Codes
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | Success (with `--check`: nothing to change) |
+//! | 1 | Error (cargo metadata failed, aadc could not run) |
+//! | 3 | `--check`: some files would change |
+//!
+//! Other aadc exit codes are passed through.
+
+#![forbid(unsafe_code)]
+
+use aadc::{Config, FileSnapshot};
+use anyhow::{Context, Result};
+use clap::{Args, Parser};
+use ignore::WalkBuilder;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// aadc's exit code for "changes would be made"
+const WOULD_CHANGE: i32 = 3;
+
+/// Cargo invokes `cargo-aadc aadc <args>`, so the subcommand name is
+/// parsed as the first argument
+#[derive(Parser, Debug)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum CargoCli {
+    /// Correct ASCII diagrams in a Rust workspace's docs and doc comments
+    #[command(version)]
+    Aadc(AadcArgs),
+}
+
+#[derive(Args, Debug)]
+struct AadcArgs {
+    /// Report files that would change (with diffs) instead of editing them
+    #[arg(long)]
+    check: bool,
+
+    /// Path to the workspace or package Cargo.toml
+    #[arg(long, value_name = "PATH")]
+    manifest_path: Option<PathBuf>,
+
+    /// Only process these workspace members (repeatable)
+    #[arg(short = 'p', long = "package", value_name = "NAME")]
+    packages: Vec<String>,
+
+    /// Extra arguments passed to every aadc invocation (after `--`)
+    #[arg(last = true, value_name = "AADC_ARGS")]
+    aadc_args: Vec<String>,
+}
+
+/// The subset of `cargo metadata` output used here
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+}
+
+fn main() {
+    let CargoCli::Aadc(args) = CargoCli::parse();
+    match run(&args) {
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("cargo-aadc: {:#}", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run(args: &AadcArgs) -> Result<i32> {
+    let metadata = cargo_metadata(args.manifest_path.as_deref())?;
+
+    let mut roots = Vec::new();
+    for name in &args.packages {
+        if !metadata.packages.iter().any(|p| &p.name == name) {
+            anyhow::bail!("package `{}` is not a member of the workspace", name);
+        }
+    }
+    for package in &metadata.packages {
+        if args.packages.is_empty() || args.packages.contains(&package.name) {
+            if let Some(dir) = package.manifest_path.parent() {
+                roots.push(dir.to_path_buf());
+            }
+        }
+    }
+
+    let mut files = BTreeSet::new();
+    for root in &roots {
+        collect_files(root, &metadata.target_directory, &mut files);
+    }
+    // A virtual workspace's top-level docs belong to no member
+    if args.packages.is_empty() {
+        collect_root_docs(&metadata.workspace_root, &mut files)?;
+    }
+
+    let (rust_files, doc_files): (Vec<PathBuf>, Vec<PathBuf>) = files
+        .into_iter()
+        .partition(|path| path.extension().is_some_and(|ext| ext == "rs"));
+
+    let mut exit_code = 0;
+    let mut record = |code: i32| {
+        if code != 0 && (exit_code == 0 || exit_code == WOULD_CHANGE) {
+            exit_code = code;
+        }
+    };
+
+    if !doc_files.is_empty() {
+        record(run_aadc_files(args, &doc_files)?);
+    }
+    if rust_files.is_empty() {
+        return Ok(exit_code);
+    }
+
+    let aadc_args = args.aadc_args.iter().map(String::as_str);
+    let config = Config::from_cli_args(std::iter::once("--no-ci").chain(aadc_args))
+        .context("Invalid aadc arguments")?;
+    for path in &rust_files {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let snapshot = FileSnapshot::of(&bytes);
+        let source = String::from_utf8(bytes)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let Some(corrected) = correct_doc_comments(&source, &config) else {
+            continue;
+        };
+
+        if args.check {
+            println!("Doc comments would change: {}", path.display());
+            record(WOULD_CHANGE);
+        } else {
+            aadc::write_in_place(path, corrected, Some(&snapshot), None, &config)?;
+            eprintln!("Corrected doc comments: {}", path.display());
+        }
+    }
+
+    Ok(exit_code)
+}
+
+/// Run `cargo metadata` for the workspace (members only)
+fn cargo_metadata(manifest_path: Option<&Path>) -> Result<Metadata> {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut cmd = Command::new(cargo);
+    cmd.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(path) = manifest_path {
+        cmd.arg("--manifest-path").arg(path);
+    }
+
+    let output = cmd.output().context("Failed to run cargo metadata")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "cargo metadata failed:\n{}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse cargo metadata output")
+}
+
+/// Collect `.rs` and `.md` files under `root`, honoring .gitignore and
+/// skipping the target directory
+fn collect_files(root: &Path, target_dir: &Path, files: &mut BTreeSet<PathBuf>) {
+    let target_dir = target_dir.to_path_buf();
+    let walker = WalkBuilder::new(root)
+        .filter_entry(move |entry| entry.path() != target_dir)
+        .build();
+
+    for entry in walker.flatten() {
+        let path = entry.path();
+        let wanted = path
+            .extension()
+            .is_some_and(|ext| ext == "rs" || ext == "md");
+        if wanted && entry.file_type().is_some_and(|t| t.is_file()) {
+            files.insert(path.to_path_buf());
+        }
+    }
+}
+
+/// Collect the `.md` files directly in the workspace root
+fn collect_root_docs(root: &Path, files: &mut BTreeSet<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(root).with_context(|| format!("Failed to read {}", root.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "md") && path.is_file() {
+            files.insert(path);
+        }
+    }
+    Ok(())
+}
+
+/// Characters that suggest a doc comment run contains a diagram; runs
+/// without any are not worth correcting
+const DIAGRAM_HINTS: &[char] = &['+', '|', '┌', '┐', '└', '┘', '│', '─', '║', '═'];
+
+/// A doc comment line split into its prefix (indent, `///` or `//!`, and
+/// one optional space) and its text
+fn split_doc_comment(line: &str) -> Option<(&str, &str)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+    if !(rest.starts_with("///") || rest.starts_with("//!")) || rest.starts_with("////") {
+        return None;
+    }
+    let mut prefix_len = indent + 3;
+    if line[prefix_len..].starts_with(' ') {
+        prefix_len += 1;
+    }
+    Some(line.split_at(prefix_len))
+}
+
+/// Correct diagrams in the doc comments of a Rust source file, returning
+/// the new source if anything changed. Every line keeps its own line
+/// ending.
+fn correct_doc_comments(source: &str, config: &Config) -> Option<String> {
+    let (mut lines, endings): (Vec<String>, Vec<&str>) = source
+        .split_inclusive('\n')
+        .map(|line| {
+            let text = line.trim_end_matches('\n').trim_end_matches('\r');
+            (text.to_string(), &line[text.len()..])
+        })
+        .unzip();
+    let mut changed = false;
+
+    let mut start = 0;
+    while start < lines.len() {
+        // A run is consecutive doc comment lines sharing the same marker
+        // and indentation
+        let Some(marker) =
+            split_doc_comment(&lines[start]).map(|(prefix, _)| prefix.trim_end().to_string())
+        else {
+            start += 1;
+            continue;
+        };
+        let mut end = start + 1;
+        while end < lines.len()
+            && split_doc_comment(&lines[end]).is_some_and(|(prefix, _)| prefix.trim_end() == marker)
+        {
+            end += 1;
+        }
+
+        let (prefixes, texts): (Vec<&str>, Vec<&str>) = lines[start..end]
+            .iter()
+            .filter_map(|line| split_doc_comment(line))
+            .unzip();
+        if texts.iter().any(|t| t.contains(DIAGRAM_HINTS)) {
+            let (corrected, _stats) =
+                aadc::correct(texts.iter().map(|t| t.to_string()).collect(), config);
+            if corrected.len() == texts.len() && corrected.iter().ne(texts.iter()) {
+                let rebuilt: Vec<String> = prefixes
+                    .iter()
+                    .zip(&corrected)
+                    .map(|(prefix, text)| {
+                        if text.is_empty() {
+                            prefix.trim_end().to_string()
+                        } else if prefix.ends_with(' ') {
+                            format!("{}{}", prefix, text)
+                        } else {
+                            format!("{} {}", prefix, text)
+                        }
+                    })
+                    .collect();
+                lines.splice(start..end, rebuilt);
+                changed = true;
+            }
+        }
+        start = end;
+    }
+
+    if !changed {
+        return None;
+    }
+    Some(
+        lines
+            .iter()
+            .zip(endings)
+            .flat_map(|(line, ending)| [line.as_str(), ending])
+            .collect(),
+    )
+}
+
+/// The aadc binary installed next to this one, falling back to PATH
+fn aadc_binary() -> PathBuf {
+    let name = format!("aadc{}", std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name(&name))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+const AADC_NOT_FOUND: &str = "Failed to run aadc (is it installed next to cargo-aadc or on PATH?)";
+
+/// Run aadc over `files` (in place, or as a dry run with diffs under
+/// `--check`), returning its exit code
+fn run_aadc_files(args: &AadcArgs, files: &[PathBuf]) -> Result<i32> {
+    let mut cmd = Command::new(aadc_binary());
+    cmd.arg("--no-ci");
+    if args.check {
+        cmd.args(["--dry-run", "--diff"]);
+    } else {
+        cmd.arg("--in-place");
+    }
+    cmd.args(&args.aadc_args).args(files);
+
+    let status = cmd.status().context(AADC_NOT_FOUND)?;
+    Ok(status.code().unwrap_or(1))
+}
@@ -0,0 +1,20807 @@
+//! # ASCII Art Diagram Corrector (aadc)
+//!
+//! A CLI tool that fixes misaligned right-hand borders in ASCII diagrams.
+//! Uses an iterative correction loop with scoring to achieve clean alignment.
+//!
+//! ## Overview
+//!
+//! `aadc` automatically detects ASCII diagram blocks in text files and aligns
+//! their right-hand borders by adding padding. It never removes content,
+//! making it safe to use on any text file.
+//!
+//! ## Key Components
+//!
+//! - **Block Detection**: Heuristic identification of diagram blocks based on
+//!   box-drawing characters (both ASCII `+|-` and Unicode `┌┐└┘│─`).
+//! - **Line Classification**: Lines are classified as Strong (horizontal borders),
+//!   Weak (content with vertical borders), Blank, or None.
+//! - **Iterative Correction**: Runs multiple passes until alignment converges
+//!   or the maximum iteration count is reached.
+//! - **Confidence Scoring**: Each proposed edit receives a score; only edits
+//!   above the threshold are applied.
+//!
+//! ## Algorithm Flow
+//!
+//! ```text
+//! Input → Tab Expansion → Block Detection → Iterative Correction → Output
+//!                              ↓
+//!                        For each block:
+//!                          - Analyze lines
+//!                          - Find target column (rightmost border)
+//!                          - Generate revisions
+//!                          - Score and filter
+//!                          - Apply revisions
+//!                          - Repeat until converged
+//! ```
+//!
+//! ## Exit Codes
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0 | Success |
+//! | 1 | General error (file not found, permission denied, I/O error) |
+//! | 2 | Invalid command-line arguments |
+//! | 3 | Dry-run mode: changes would be made |
+//! | 4 | Parse error (invalid UTF-8 or binary input) |
+//! | 5 | `aadc lint` found problems at error severity |
+//! | 6 | `--require-diagrams`: an input had no diagram blocks |
+//!
+//! ## Library Usage
+//!
+//! The `aadc` binary is a thin wrapper around this crate, so other Rust
+//! tools can correct diagrams in-process:
+//!
+//! ```
+//! let config = aadc::Config::from_toml("preset = \"aggressive\"").unwrap();
+//! let lines = vec!["+------+".to_string(), "| ab |".to_string(), "+------+".to_string()];
+//! let (corrected, stats) = aadc::correct(lines, &config);
+//! assert_eq!(corrected[1], "| ab   |");
+//! assert_eq!(stats.total_revisions, 1);
+//! ```
+//!
+//! The individual stages are public too: [`find_diagram_blocks`] groups
+//! lines into [`DiagramBlock`]s, [`analyze_line`] measures a line's
+//! borders, and [`correct`] runs the iterative [`Revision`] loop on each
+//! block, reporting what it did in [`Stats`].
+
+#![forbid(unsafe_code)]
+#![warn(missing_docs)]
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use clap::error::ErrorKind;
+use clap::{Parser, Subcommand};
+#[cfg(not(target_arch = "wasm32"))]
+use fs4::fs_std::FileExt;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+#[cfg(not(target_arch = "wasm32"))]
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+#[cfg(not(target_arch = "wasm32"))]
+use rich_rust::{ColorSystem, terminal};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, DiffTag, TextDiff};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError, mpsc};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Exit Codes
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Semantic exit codes for scripting and CI integration
+mod exit_codes {
+    /// Success - completed without errors
+    pub const SUCCESS: i32 = 0;
+    /// General error (file not found, permission denied, I/O error)
+    pub const ERROR: i32 = 1;
+    /// Invalid command-line arguments
+    pub const INVALID_ARGS: i32 = 2;
+    /// Dry-run mode: changes would be made
+    pub const WOULD_CHANGE: i32 = 3;
+    /// Parse error (invalid UTF-8 or binary file detected)
+    pub const PARSE_ERROR: i32 = 4;
+    /// Lint found problems at error severity
+    pub const LINT_ERRORS: i32 = 5;
+    /// --list-different: at least one file would change
+    pub const LIST_DIFFERENT: i32 = 1;
+    /// --require-diagrams: an input had no diagram blocks
+    pub const NO_DIAGRAMS: i32 = 6;
+}
+
+#[derive(Debug)]
+struct ArgError(String);
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+#[derive(Debug)]
+struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug)]
+struct RunOutcome {
+    dry_run: bool,
+    list_different: bool,
+    would_change: bool,
+    /// Some input had no diagram blocks under `--require-diagrams`
+    missing_diagrams: bool,
+}
+
+fn error_chain_has<T: std::error::Error + 'static>(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<T>())
+}
+
+fn exit_code_for_error(err: &anyhow::Error) -> i32 {
+    if error_chain_has::<ArgError>(err) {
+        exit_codes::INVALID_ARGS
+    } else if error_chain_has::<ParseError>(err) {
+        exit_codes::PARSE_ERROR
+    } else {
+        exit_codes::ERROR
+    }
+}
+
+/// Error category reported in structured error output
+fn error_kind(err: &anyhow::Error) -> &'static str {
+    if error_chain_has::<ArgError>(err) {
+        "invalid_args"
+    } else if error_chain_has::<ParseError>(err) {
+        "parse"
+    } else if error_chain_has::<io::Error>(err) {
+        "io"
+    } else {
+        "error"
+    }
+}
+
+/// Error written to stderr as one JSON line with `--error-format json`
+#[derive(Serialize, JsonSchema)]
+struct JsonError {
+    /// Exit code the error maps to
+    code: i32,
+    /// `invalid_args`, `parse`, `io`, or `error`
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    message: String,
+}
+
+impl JsonError {
+    fn of(err: &anyhow::Error, path: Option<&Path>) -> Self {
+        Self {
+            code: exit_code_for_error(err),
+            kind: error_kind(err),
+            path: path.map(|p| p.display().to_string()),
+            message: format!("{:#}", err),
+        }
+    }
+}
+
+/// Report an error on stderr, as text or as a JSON object
+fn report_error(err: &anyhow::Error, path: Option<&Path>, json: bool) {
+    if json {
+        let error = JsonError::of(err, path);
+        if let Ok(line) = serde_json::to_string(&error) {
+            eprintln!("{}", line);
+        }
+    } else if let Some(path) = path {
+        eprintln!("Error processing {}: {:#}", path.display(), err);
+    } else {
+        eprintln!("Error: {:#}", err);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Line Range Processing
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A range of lines to process (1-indexed, inclusive on both ends)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LineRange {
+    /// Start line (1-indexed, inclusive)
+    start: usize,
+    /// End line (1-indexed, inclusive, usize::MAX means "to end of file")
+    end: usize,
+}
+
+/// Parse a single range specification like "10-50", "50-", "-100", or "42"
+fn parse_single_range(s: &str) -> Result<LineRange, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("Empty range specification".to_string());
+    }
+
+    if let Some(dash_pos) = s.find('-') {
+        let (start_str, end_str) = s.split_at(dash_pos);
+        let end_str = &end_str[1..]; // Skip the dash
+
+        let start = if start_str.is_empty() {
+            1 // "-100" means "1-100"
+        } else {
+            start_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid start line: '{}'", start_str))?
+        };
+
+        let end = if end_str.is_empty() {
+            usize::MAX // "50-" means "50 to end"
+        } else {
+            end_str
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid end line: '{}'", end_str))?
+        };
+
+        if start == 0 {
+            return Err("Line numbers start at 1, not 0".to_string());
+        }
+
+        if start > end && end != usize::MAX {
+            return Err(format!("Invalid range: start ({}) > end ({})", start, end));
+        }
+
+        Ok(LineRange { start, end })
+    } else {
+        // Single line number
+        let line = s
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid line number: '{}'", s))?;
+
+        if line == 0 {
+            return Err("Line numbers start at 1, not 0".to_string());
+        }
+
+        Ok(LineRange {
+            start: line,
+            end: line,
+        })
+    }
+}
+
+/// Merge overlapping or adjacent ranges
+fn merge_ranges(mut ranges: Vec<LineRange>) -> Vec<LineRange> {
+    if ranges.is_empty() {
+        return ranges;
+    }
+
+    // Sort by start position
+    ranges.sort_by_key(|r| r.start);
+
+    let mut merged = Vec::new();
+    let mut current = ranges[0].clone();
+
+    for range in ranges.into_iter().skip(1) {
+        // Check if overlapping or adjacent (allowing for +1 to merge adjacent)
+        if range.start <= current.end.saturating_add(1) {
+            // Merge: extend current range
+            current.end = current.end.max(range.end);
+        } else {
+            // No overlap: push current and start new
+            merged.push(current);
+            current = range;
+        }
+    }
+    merged.push(current);
+
+    merged
+}
+
+/// Parse a line ranges specification like "10-50", "1-100,200-250", "50-"
+fn parse_line_ranges(s: &str) -> Result<Vec<LineRange>, String> {
+    let mut ranges = Vec::new();
+
+    for part in s.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        ranges.push(parse_single_range(part)?);
+    }
+
+    if ranges.is_empty() {
+        return Err("No valid ranges specified".to_string());
+    }
+
+    // Merge overlapping ranges
+    Ok(merge_ranges(ranges))
+}
+
+/// Split inputs written as `FILE:RANGES` (such as `doc.md:10-50,80-`) into
+/// the path and its own line ranges. An input that exists as given, or
+/// whose suffix is not a range list, is a plain path.
+fn split_input_ranges(inputs: &mut [PathBuf]) -> Result<BTreeMap<PathBuf, Vec<LineRange>>> {
+    let mut file_lines = BTreeMap::new();
+    for input in inputs.iter_mut() {
+        if input.exists() {
+            continue;
+        }
+        let Some((path, ranges)) = input.to_str().and_then(|s| s.rsplit_once(':')) else {
+            continue;
+        };
+        let is_range_list = !ranges.is_empty()
+            && ranges
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '-' | ','));
+        if path.is_empty() || !is_range_list {
+            continue;
+        }
+        let ranges = parse_line_ranges(ranges)
+            .map_err(|e| ArgError(format!("{}: {}", input.display(), e)))?;
+        *input = PathBuf::from(path);
+        file_lines.insert(input.clone(), ranges);
+    }
+    Ok(file_lines)
+}
+
+/// Check if a line number (1-indexed) falls within any of the given ranges
+fn line_in_ranges(line_num: usize, ranges: &[LineRange]) -> bool {
+    ranges
+        .iter()
+        .any(|r| line_num >= r.start && line_num <= r.end)
+}
+
+/// `ranges` renumbered for a slice of the file starting after `offset`
+/// lines, dropping the ranges that end before it
+fn shift_line_ranges(ranges: &[LineRange], offset: usize) -> Vec<LineRange> {
+    ranges
+        .iter()
+        .filter(|r| r.end > offset)
+        .map(|r| LineRange {
+            start: r.start.saturating_sub(offset).max(1),
+            end: if r.end == usize::MAX {
+                usize::MAX
+            } else {
+                r.end - offset
+            },
+        })
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CLI Arguments
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Preset {
+    /// Conservative: only high-confidence edits (0.8)
+    Strict,
+    /// Balanced: reasonable confidence threshold (0.5)
+    Normal,
+    /// Aggressive: accept lower-confidence edits (0.3)
+    Aggressive,
+    /// Accept almost any edit (0.1)
+    Relaxed,
+}
+
+impl Preset {
+    fn min_score(self) -> f64 {
+        match self {
+            Self::Strict => 0.8,
+            Self::Normal => 0.5,
+            Self::Aggressive => 0.3,
+            Self::Relaxed => 0.1,
+        }
+    }
+}
+
+/// How backup files are named
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum BackupMode {
+    /// A single `file.bak`, overwritten by each backup
+    #[default]
+    Simple,
+    /// `file.bak.1`, `file.bak.2`, ... (like GNU cp's numbered backups)
+    Numbered,
+}
+
+/// Line terminator for `--normalize-eol`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Eol {
+    /// Unix line endings (`\n`)
+    Lf,
+    /// Windows line endings (`\r\n`)
+    Crlf,
+}
+
+/// Text encoding of input files (`--encoding`); output is written back
+/// in the encoding the input was read in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    /// Detect from the BOM or content: UTF-8, then UTF-16, else Latin-1
+    Auto,
+    /// UTF-8 (invalid input is an error)
+    #[default]
+    Utf8,
+    /// ISO-8859-1
+    Latin1,
+    /// UTF-16 with the byte order of its BOM (little-endian without one)
+    Utf16,
+    /// UTF-16, little-endian
+    Utf16le,
+    /// UTF-16, big-endian
+    Utf16be,
+}
+
+/// How a cluster of right borders picks the column it is aligned to
+/// (`--target`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum TargetStrategy {
+    /// The rightmost border
+    #[default]
+    Rightmost,
+    /// The most common border column (the rightmost on a tie)
+    Mode,
+    /// The median border column (the upper one for an even count)
+    Median,
+    /// The column closing the first horizontal border row (`+---+`),
+    /// falling back to the rightmost border
+    TopBorder,
+}
+
+impl TargetStrategy {
+    /// Pick the target from borders given in line order as (column,
+    /// closes a horizontal border row). None when there are none.
+    fn pick(self, borders: &[(usize, bool)]) -> Option<usize> {
+        let rightmost = borders.iter().map(|&(column, _)| column).max()?;
+        Some(match self {
+            Self::Rightmost => rightmost,
+            Self::Mode => {
+                let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+                for &(column, _) in borders {
+                    *counts.entry(column).or_default() += 1;
+                }
+                // max_by_key keeps the last maximum: the rightmost column
+                counts
+                    .into_iter()
+                    .max_by_key(|&(_, count)| count)
+                    .map_or(rightmost, |(column, _)| column)
+            }
+            Self::Median => {
+                let mut columns: Vec<usize> = borders.iter().map(|&(column, _)| column).collect();
+                columns.sort_unstable();
+                columns[columns.len() / 2]
+            }
+            Self::TopBorder => borders
+                .iter()
+                .find(|&&(_, closes_row)| closes_row)
+                .map_or(rightmost, |&(column, _)| column),
+        })
+    }
+}
+
+impl Eol {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    /// The terminator most lines end with (LF on a tie or when no line
+    /// is terminated)
+    fn dominant(endings: &[Option<Eol>]) -> Self {
+        let crlf = endings.iter().filter(|&&e| e == Some(Self::Crlf)).count();
+        let lf = endings.iter().filter(|&&e| e == Some(Self::Lf)).count();
+        if crlf > lf { Self::Crlf } else { Self::Lf }
+    }
+}
+
+impl Encoding {
+    /// The encoding an `.editorconfig` `charset` names
+    fn from_charset(charset: &str) -> Option<Self> {
+        match charset {
+            "utf-8" | "utf-8-bom" => Some(Self::Utf8),
+            "latin1" => Some(Self::Latin1),
+            "utf-16le" => Some(Self::Utf16le),
+            "utf-16be" => Some(Self::Utf16be),
+            _ => None,
+        }
+    }
+
+    /// Guess the encoding of `bytes`: a BOM decides, ASCII-heavy UTF-16
+    /// shows as NULs in every other byte, and anything else that is not
+    /// valid UTF-8 (nor binary) is taken as Latin-1
+    fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(b"\xFF\xFE") {
+            return Self::Utf16le;
+        }
+        if bytes.starts_with(b"\xFE\xFF") {
+            return Self::Utf16be;
+        }
+        if bytes.starts_with(b"\xEF\xBB\xBF") {
+            return Self::Utf8;
+        }
+        if bytes.len() % 2 == 0 && !bytes.is_empty() {
+            let nuls = |parity: usize| {
+                bytes
+                    .iter()
+                    .skip(parity)
+                    .step_by(2)
+                    .filter(|&&b| b == 0)
+                    .count()
+            };
+            let half = bytes.len() / 2;
+            match (nuls(0), nuls(1)) {
+                (0, odd) if odd * 2 > half => return Self::Utf16le,
+                (even, 0) if even * 2 > half => return Self::Utf16be,
+                _ => {}
+            }
+        }
+        if !bytes.contains(&0) && std::str::from_utf8(bytes).is_err() {
+            return Self::Latin1;
+        }
+        Self::Utf8
+    }
+
+    /// Decode `bytes`, returning the text and the concrete encoding used
+    fn decode(self, bytes: Vec<u8>, source_label: &str) -> Result<(String, Self)> {
+        let binary = || ParseError(format!("Input appears to be binary: {}", source_label));
+        let encoding = match self {
+            Self::Auto => Self::detect(&bytes),
+            Self::Utf16 if bytes.starts_with(b"\xFE\xFF") => Self::Utf16be,
+            Self::Utf16 => Self::Utf16le,
+            other => other,
+        };
+        let text = match encoding {
+            Self::Latin1 => {
+                if bytes.contains(&0) {
+                    return Err(binary().into());
+                }
+                bytes.iter().map(|&b| char::from(b)).collect()
+            }
+            Self::Utf16le | Self::Utf16be => {
+                if bytes.len() % 2 != 0 {
+                    return Err(ParseError(format!(
+                        "Invalid UTF-16 (odd byte count) in {}",
+                        source_label
+                    ))
+                    .into());
+                }
+                let units: Vec<u16> = bytes
+                    .chunks_exact(2)
+                    .map(|pair| match encoding {
+                        Self::Utf16be => u16::from_be_bytes([pair[0], pair[1]]),
+                        _ => u16::from_le_bytes([pair[0], pair[1]]),
+                    })
+                    .collect();
+                let text = String::from_utf16(&units)
+                    .map_err(|_| ParseError(format!("Invalid UTF-16 in {}", source_label)))?;
+                if text.contains('\0') {
+                    return Err(binary().into());
+                }
+                text
+            }
+            _ => {
+                if bytes.contains(&0) {
+                    return Err(binary().into());
+                }
+                String::from_utf8(bytes).map_err(|err| {
+                    let utf8_err = err.utf8_error();
+                    let valid_up_to = utf8_err.valid_up_to();
+                    let byte = err.as_bytes().get(valid_up_to).copied();
+                    let detail = match byte {
+                        Some(b) => format!(
+                            "Invalid UTF-8 at byte position {} (byte value: 0x{:02X}) in {}",
+                            valid_up_to, b, source_label
+                        ),
+                        None => format!("Invalid UTF-8 in {}", source_label),
+                    };
+                    ParseError(detail)
+                })?
+            }
+        };
+        Ok((text, encoding))
+    }
+
+    /// Encode `text` for output, failing on a character the encoding
+    /// cannot represent
+    fn encode(self, text: &str) -> Result<Vec<u8>> {
+        Ok(match self {
+            Self::Latin1 => text
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| {
+                        anyhow::anyhow!("'{}' (U+{:04X}) cannot be encoded as Latin-1", c, c as u32)
+                    })
+                })
+                .collect::<Result<_>>()?,
+            Self::Utf16 | Self::Utf16le => text.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Self::Utf16be => text.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+            Self::Auto | Self::Utf8 => text.as_bytes().to_vec(),
+        })
+    }
+}
+
+/// How errors are written to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum ErrorFormat {
+    /// `Error: ...` text
+    #[default]
+    Text,
+    /// One JSON object per error (code, kind, path, message)
+    Json,
+}
+
+/// How results are written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Corrected content (or the diff, list or JSON the other options ask
+    /// for)
+    #[default]
+    Content,
+    /// A GitHub Actions `::error` annotation per block needing changes,
+    /// shown inline on pull request diffs
+    Github,
+    /// Test Anything Protocol (version 13): a test per file, with the
+    /// blocks needing changes as YAML diagnostics
+    Tap,
+    /// GNU-style `file:line:col: warning: message` per proposed revision,
+    /// for Vim/Emacs quickfix lists
+    Gnu,
+}
+
+/// How `--diff` lays out changes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum DiffFormat {
+    /// Unified diff: removed and added lines one after the other
+    #[default]
+    Unified,
+    /// Original and corrected lines in two panes, fitted to the terminal
+    SideBySide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ColorMode {
+    /// Auto-detect color support
+    Auto,
+    /// Always emit colors (even when not a TTY)
+    Always,
+    /// Never emit colors
+    Never,
+}
+
+/// ASCII Art Diagram Corrector: fixes misaligned right borders in ASCII diagrams
+#[derive(Parser, Debug)]
+#[command(
+    name = "aadc",
+    version,
+    about,
+    long_about = None,
+    group(clap::ArgGroup::new("null_paths").multiple(true).args(["files_from", "list_different"])),
+    after_help = "EXIT CODES:\n  0  Success\n  1  General error (file not found, permission denied, I/O error)\n  2  Invalid command-line arguments\n  3  Dry-run mode: changes would be made\n  4  Parse error (invalid UTF-8 or binary input)\n  5  Lint found problems at error severity\n  6  --require-diagrams: an input had no diagram blocks\n"
+)]
+struct Args {
+    /// Input file(s). Reads from stdin if not provided, or where "-" is
+    /// given. Multiple files can be specified, each optionally as
+    /// FILE:RANGES (e.g. doc.md:10-50) to process only those lines.
+    #[arg(value_name = "FILE")]
+    inputs: Vec<PathBuf>,
+
+    /// Also process the paths listed in FILE, one per line ("-" reads the
+    /// list from stdin)
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    /// Only process inputs (or files found by --recursive) that differ
+    /// from REF in git, such as origin/main in a pull request job
+    #[arg(long, value_name = "REF", conflicts_with_all = ["watch", "batch", "stream"])]
+    changed_since: Option<String>,
+
+    /// Path that stdin content stands for: .aadcrc is searched from its
+    /// directory, its extension picks the --embedded syntax, and it names
+    /// the input in diffs and reports
+    #[arg(long, value_name = "PATH")]
+    stdin_filename: Option<PathBuf>,
+
+    /// Path to config file (default: search for .aadcrc)
+    #[arg(long = "config", value_name = "FILE")]
+    config_file: Option<PathBuf>,
+
+    /// Ignore config files
+    #[arg(long = "no-config")]
+    no_config: bool,
+
+    /// Process files recursively in directories
+    #[arg(short = 'r', long)]
+    recursive: bool,
+
+    /// Glob pattern to match files when recursing (comma-separated)
+    #[arg(long, default_value = "*.txt,*.md", requires = "recursive")]
+    glob: String,
+
+    /// Do not respect .gitignore when recursing
+    #[arg(long = "no-gitignore", requires = "recursive")]
+    no_gitignore: bool,
+
+    /// Maximum directory depth (0 = unlimited)
+    #[arg(long, default_value = "0", requires = "recursive")]
+    max_depth: usize,
+
+    /// Descend into symlinked directories when recursing (loops are
+    /// detected and skipped)
+    #[arg(long, requires = "recursive")]
+    follow_symlinks: bool,
+
+    /// Number of files to process in parallel (default: number of CPUs)
+    #[arg(short = 'j', long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Skip files an earlier --cached run found clean, if neither their
+    /// content nor the correction options changed since
+    #[arg(long)]
+    cached: bool,
+
+    /// Cache file for --cached
+    #[arg(long, value_name = "PATH", default_value = CACHE_FILE, requires = "cached")]
+    cache_file: PathBuf,
+
+    /// Edit file(s) in place
+    #[arg(short = 'i', long)]
+    in_place: bool,
+
+    /// Confidence threshold preset (conflicts with --min-score)
+    #[arg(long, short = 'P', value_enum, conflicts_with = "min_score")]
+    preset: Option<Preset>,
+
+    /// Maximum iterations for correction loop
+    #[arg(short = 'm', long, default_value = "10")]
+    max_iters: usize,
+
+    /// Minimum score threshold for applying revisions (0.0-1.0)
+    #[arg(short = 's', long, default_value = "0.5")]
+    min_score: f64,
+
+    /// Tab width for expansion
+    #[arg(short = 't', long, default_value = "4")]
+    tab_width: usize,
+
+    /// Convert the leading indentation of tab-indented lines back to tabs
+    /// after correction (lines left unchanged keep their exact content)
+    #[arg(long)]
+    keep_tabs: bool,
+
+    /// Ignore .editorconfig files (by default their tab_width/indent_size
+    /// and charset apply to each input unless --tab-width is given)
+    #[arg(long = "no-editorconfig")]
+    no_editorconfig: bool,
+
+    /// Ignore Vim/Emacs modelines (by default a `ts=`/`tab-width:`
+    /// declared near the top or bottom of a file sets its tab width)
+    #[arg(long = "no-modelines")]
+    no_modelines: bool,
+
+    /// Encoding of input files, kept on output (default: utf8, or the
+    /// .editorconfig charset)
+    #[arg(long, value_enum)]
+    encoding: Option<Encoding>,
+
+    /// Lines longer than this many bytes are not analyzed (treated as
+    /// non-diagram text, with a warning); 0 disables the cap
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_LINE_BYTES)]
+    max_line_bytes: usize,
+
+    /// Refuse input files larger than this, in bytes or with a unit
+    /// (`500K`, `200MB`, `1.5GiB`; default 100MB, 0 = no limit)
+    #[arg(long, value_name = "SIZE")]
+    max_file_size: Option<String>,
+
+    /// Process all diagram-like blocks, not just confident ones
+    #[arg(short = 'a', long)]
+    all: bool,
+
+    /// Minimum detection confidence (0.0-1.0) for a block to be processed
+    /// [default: 0.3]
+    #[arg(long, value_name = "SCORE")]
+    block_confidence: Option<f64>,
+
+    /// Blank lines a diagram block may span before it ends [default: 1]
+    #[arg(long, value_name = "N")]
+    blank_gap: Option<usize>,
+
+    /// How far past a non-diagram line to look for more diagram lines
+    /// before ending a block there (0 ends blocks at any such line)
+    /// [default: 3]
+    #[arg(long, value_name = "N")]
+    lookahead: Option<usize>,
+
+    /// Correct diagrams inside string values: YAML/JSON strings, Python
+    /// docstrings, Go raw strings, and JS/TS template literals
+    #[arg(long)]
+    embedded: bool,
+
+    /// Also treat these characters as vertical borders (e.g., ":!" for
+    /// legacy ASCII art); supported: ':' and '!'
+    #[arg(long, value_name = "CHARS")]
+    alt_borders: Option<String>,
+
+    /// Fill characters whose leader runs (`name ..... value`) are extended
+    /// instead of inserting spaces [default: ".·…—"; "" disables]
+    #[arg(long, value_name = "CHARS")]
+    fill_chars: Option<String>,
+
+    /// Right-align mostly numeric table columns and left-align text columns
+    #[arg(long)]
+    align_numbers: bool,
+
+    /// Pad every right border in a block out to the widest one instead of
+    /// aligning each box (or column cluster outside boxes) to its own
+    /// target column
+    #[arg(long)]
+    single_target: bool,
+
+    /// Column each cluster of right borders is aligned to: the rightmost
+    /// border (default), the most common column, the median, or the top
+    /// border's. Borders past it are left alone (see --allow-shrink)
+    #[arg(long, value_enum, value_name = "STRATEGY")]
+    target: Option<TargetStrategy>,
+
+    /// Replace a vertical border closing a horizontal border row (`+----|`)
+    /// with the corner that row needs (`+----+`)
+    #[arg(long)]
+    repair_corners: bool,
+
+    /// Insert a bottom border under a box that has a top border and rows but
+    /// was never closed
+    #[arg(long)]
+    complete_boxes: bool,
+
+    /// Also pull in right borders that sit past the block's target by
+    /// deleting spaces just before them (never any other character)
+    #[arg(long)]
+    allow_shrink: bool,
+
+    /// Re-align Markdown pipe tables and tables pasted from MySQL or psql
+    /// output so their columns line up
+    #[arg(long)]
+    format_tables: bool,
+
+    /// Run a shell command after files are modified; changed paths are passed
+    /// as arguments and in AADC_CHANGED_FILES (newline-separated)
+    #[arg(long, value_name = "CMD")]
+    on_change: Option<String>,
+
+    /// Refuse any revision that would make a line wider than N columns
+    #[arg(long, value_name = "N")]
+    max_line_length: Option<usize>,
+
+    /// Protected column: lines with content past column N are never used as
+    /// the alignment target and never padded
+    #[arg(long, value_name = "N")]
+    no_pad_past: Option<usize>,
+
+    /// Skip any revision that would insert more than N spaces or fill
+    /// characters into a line (counted as skipped)
+    #[arg(long, value_name = "N")]
+    max_pad: Option<usize>,
+
+    /// Convert all line endings on output (shown in --diff, counted in stats)
+    #[arg(long, value_enum, value_name = "EOL")]
+    normalize_eol: Option<Eol>,
+
+    /// Only touch the block annotated `aadc:block name=NAME` (correction,
+    /// extract, inject, and lint)
+    #[arg(long, value_name = "NAME", global = true)]
+    block_name: Option<String>,
+
+    /// Process only specific line ranges (e.g., "10-50", "1-100,200-250", "50-", "-100")
+    #[arg(short = 'L', long, value_name = "RANGES")]
+    lines: Option<String>,
+
+    /// Only correct blocks that overlap lines changed since REF in git
+    /// (default: HEAD, i.e. uncommitted edits)
+    #[arg(
+        long,
+        value_name = "REF",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "HEAD",
+        conflicts_with_all = ["lines", "watch", "batch", "stream"]
+    )]
+    diff_only: Option<String>,
+
+    /// Apply only the revisions with these IDs (e.g., "1,4,7-9"), as listed
+    /// by `--dry-run --verbose` or in `--json` output for the same input
+    #[arg(long, value_name = "IDS")]
+    apply_ids: Option<String>,
+
+    /// Verbose output showing correction progress
+    #[arg(short = 'v', long)]
+    verbose: bool,
+
+    /// Color output: auto, always, or never
+    #[arg(long, value_enum, default_value = "auto")]
+    color: ColorMode,
+
+    /// Show unified diff of changes instead of full output
+    #[arg(short = 'd', long)]
+    diff: bool,
+
+    /// Render inserted padding in diffs as `·` so added spaces are visible
+    #[arg(long)]
+    show_padding: bool,
+
+    /// Diff layout: unified, or side-by-side panes fitted to the terminal
+    /// width (`COLUMNS` if set)
+    #[arg(long, value_enum, default_value = "unified", requires = "diff")]
+    diff_format: DiffFormat,
+
+    /// Preview changes without modifying files (exit 0=no changes, 3=would change)
+    #[arg(short = 'n', long, conflicts_with = "in_place")]
+    dry_run: bool,
+
+    /// Print only the paths of files that would change, one per line, and
+    /// exit 1 if there are any
+    #[arg(
+        short = 'l',
+        long,
+        conflicts_with_all = ["in_place", "diff", "dry_run", "json", "json_lines", "verbose", "watch"]
+    )]
+    list_different: bool,
+
+    /// Report each file that would change and a final count on stderr,
+    /// and exit 3 if there are any; nothing is written to stdout
+    #[arg(
+        long,
+        conflicts_with_all = ["in_place", "diff", "dry_run", "json", "json_lines", "verbose", "list_different", "watch", "batch", "stream"]
+    )]
+    check: bool,
+
+    /// Use NUL instead of newline between paths: in the --files-from list
+    /// (as from `git ls-files -z`), and after paths printed by
+    /// --list-different
+    #[arg(short = '0', long, requires = "null_paths")]
+    null: bool,
+
+    /// Exit 6 if any input contains no diagram blocks, to flag documents
+    /// that were expected to have diagrams
+    #[arg(long, conflicts_with = "watch")]
+    require_diagrams: bool,
+
+    /// Read several documents from stdin split on --separator, and write
+    /// each corrected document followed by a JSON trailer line and the
+    /// separator
+    #[arg(
+        long,
+        conflicts_with_all = ["in_place", "recursive", "diff", "dry_run", "json", "json_lines", "list_different", "watch"]
+    )]
+    batch: bool,
+
+    /// Correct stdin (or one file) to stdout a chunk at a time, holding
+    /// only the current diagram and a few lines of context in memory
+    #[arg(
+        long,
+        conflicts_with_all = ["in_place", "recursive", "diff", "dry_run", "json", "json_lines", "list_different", "watch", "batch"]
+    )]
+    stream: bool,
+
+    /// Document separator for --batch (escapes: \n, \t, \0, \xHH, \\)
+    #[arg(
+        long,
+        value_name = "SEP",
+        default_value = "\\x0c",
+        requires = "batch",
+        allow_hyphen_values = true
+    )]
+    separator: String,
+
+    /// Watch file for changes and auto-correct
+    #[arg(short = 'w', long, conflicts_with_all = ["in_place", "recursive", "diff", "dry_run", "json", "json_lines"])]
+    watch: bool,
+
+    /// Debounce interval in milliseconds (for --watch mode)
+    #[arg(long, default_value = "500", requires = "watch")]
+    debounce_ms: u64,
+
+    /// Create backup file before in-place editing
+    #[arg(long, requires = "in_place")]
+    backup: bool,
+
+    /// Extension for backup files (default: .bak)
+    #[arg(long, default_value = ".bak", requires = "backup")]
+    backup_ext: String,
+
+    /// Backup naming: simple (one file, overwritten) or numbered
+    #[arg(long, value_enum, default_value = "simple", requires = "backup")]
+    backup_mode: BackupMode,
+
+    /// Keep only the N newest numbered backups of each file
+    #[arg(long, value_name = "N", requires = "backup")]
+    backup_keep: Option<usize>,
+
+    /// Write files in place even if another process changed them after
+    /// aadc read them (their edits are lost)
+    #[arg(long)]
+    force_write: bool,
+
+    /// Keep the original permissions and access/modification times of
+    /// files rewritten in place
+    #[arg(long)]
+    preserve_metadata: bool,
+
+    /// Output results as JSON for programmatic processing
+    #[arg(long, conflicts_with_all = ["verbose", "diff"])]
+    json: bool,
+
+    /// Like --json, but print each file's result as one compact line as
+    /// soon as it is ready (NDJSON, for jq or log collectors)
+    #[arg(long, conflicts_with_all = ["verbose", "diff", "json"])]
+    json_lines: bool,
+
+    /// Report blocks needing changes in this format instead of writing
+    /// corrected content, and exit 3 if there are any
+    #[arg(
+        long,
+        value_enum,
+        default_value = "content",
+        conflicts_with_all = ["in_place", "diff", "dry_run", "json", "json_lines", "verbose", "list_different", "check", "watch", "batch", "stream"]
+    )]
+    output_format: OutputFormat,
+
+    /// Also write a report file, as FORMAT=PATH (formats: junit); can be
+    /// repeated
+    #[arg(long, value_name = "FORMAT=PATH")]
+    report: Vec<String>,
+
+    /// Error output format on stderr (json is implied by --json)
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    error_format: ErrorFormat,
+
+    /// Ignore CI detection (CI, GITHUB_ACTIONS, GITLAB_CI) and use the
+    /// interactive defaults
+    #[arg(long, global = true)]
+    no_ci: bool,
+
+    /// Subcommand (hook/config management, block extract/inject)
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Subcommands
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Available subcommands
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Manage git pre-commit hook
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+    /// Manage configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Manage the git clean filter that normalizes diagrams as files are
+    /// staged
+    Filter {
+        #[command(subcommand)]
+        action: FilterAction,
+    },
+    /// Git clean filter: correct stdin to stdout, passing input through
+    /// unchanged when it cannot be corrected (never fails)
+    FilterClean {
+        /// Path of the file being filtered, as passed by git (`%f`); used
+        /// like `--stdin-filename`
+        path: Option<PathBuf>,
+    },
+    /// Write each detected diagram block to its own file
+    Extract {
+        /// File to extract diagram blocks from
+        file: PathBuf,
+
+        /// Directory to write block files into (created if missing)
+        #[arg(long, value_name = "DIR")]
+        out_dir: PathBuf,
+
+        /// Also write manifest.json mapping block files to source line ranges
+        #[arg(long)]
+        manifest: bool,
+    },
+    /// Replace a detected diagram block with externally edited content
+    Inject {
+        /// File containing the block to replace (modified in place)
+        file: PathBuf,
+
+        /// 1-based index of the block to replace (as numbered by extract);
+        /// or select a named block with --block-name
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        block: Option<u64>,
+
+        /// File holding the new block content
+        #[arg(long, value_name = "PATH")]
+        from: PathBuf,
+    },
+    /// Apply the `edits` of earlier `--json` output to the files it names,
+    /// refusing files whose content has changed since
+    Apply {
+        /// `--json` or `--json-lines` output to apply (`-` for stdin)
+        edits: PathBuf,
+
+        /// Check that every edit applies cleanly without writing files
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the JSON Schema of `--json` output for this version
+    JsonSchema,
+    /// Serve the Language Server Protocol on stdin/stdout: document and
+    /// range formatting, and lint diagnostics as documents change
+    Lsp,
+    /// Serve the Model Context Protocol on stdin/stdout, with tools to fix,
+    /// detect and score diagrams in text
+    Mcp,
+    /// Redraw diagram borders in ASCII (`+-|`) or Unicode box drawing
+    /// (`┌─│┐`), then align them as usual
+    Convert {
+        /// Files to convert (reads stdin when none are given)
+        files: Vec<PathBuf>,
+
+        /// Border style to convert to
+        #[arg(long, value_enum)]
+        to: ConvertTarget,
+
+        /// Modify files in place instead of printing the result
+        #[arg(short, long)]
+        in_place: bool,
+    },
+    /// Pandoc JSON filter: correct diagrams in the CodeBlock and RawBlock
+    /// contents of the AST on stdin and write the AST to stdout
+    PandocFilter {
+        /// Target format, as passed by `pandoc --filter` (ignored)
+        #[arg(hide = true)]
+        format: Option<String>,
+    },
+    /// Verify or update golden-file fixtures: each `NAME.input.txt` is
+    /// corrected and compared with `NAME.expected.txt`
+    Fixtures {
+        #[command(subcommand)]
+        action: FixturesAction,
+    },
+    /// Post corrections to a GitHub pull request as suggested changes,
+    /// computed from the local checkout of its head
+    #[cfg(feature = "net")]
+    Suggest {
+        /// Repository as OWNER/NAME
+        #[arg(long, value_name = "OWNER/NAME")]
+        repo: String,
+
+        /// Pull request number
+        #[arg(long, value_name = "N")]
+        pr: u64,
+
+        /// Only suggest changes to these files (default: all files the pull
+        /// request changes)
+        files: Vec<PathBuf>,
+
+        /// Print the review as JSON instead of posting it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Report diagram problems as rule diagnostics without modifying files
+    Lint {
+        /// Files to lint
+        #[arg(required_unless_present = "list_rules")]
+        files: Vec<PathBuf>,
+
+        /// Diagnostic output format [default: github under GitHub Actions,
+        /// otherwise text]
+        #[arg(long, value_enum)]
+        format: Option<LintFormat>,
+
+        /// List lint rules with their effective severities and exit
+        #[arg(long)]
+        list_rules: bool,
+
+        /// Fix auto-fixable problems (trailing whitespace after borders)
+        /// in place; only problems left unfixed are reported
+        #[arg(long)]
+        fix: bool,
+    },
+}
+
+/// Output format for lint diagnostics
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum LintFormat {
+    /// One `file:line:col: severity[rule] name: message` line per problem
+    #[default]
+    Text,
+    /// A single JSON report
+    Json,
+    /// GitHub Actions workflow commands (`::error file=...::...`), shown
+    /// inline on pull request diffs
+    Github,
+}
+
+impl LintFormat {
+    /// Default format for the detected CI service
+    fn default_for(ci: Option<CiProvider>) -> Self {
+        if ci == Some(CiProvider::GithubActions) {
+            Self::Github
+        } else {
+            Self::Text
+        }
+    }
+}
+
+/// Config management actions
+#[derive(Subcommand, Debug)]
+enum ConfigAction {
+    /// Initialize a new .aadcrc config file
+    Init {
+        /// Create in home directory instead of current
+        #[arg(long)]
+        global: bool,
+    },
+    /// Show effective configuration (merged file + CLI)
+    Show,
+    /// Show path to active config file
+    Path,
+}
+
+/// Fixture actions
+#[derive(Subcommand, Debug)]
+enum FixturesAction {
+    /// Report fixtures whose corrected input differs from the expected file
+    /// (exits 3 if any differ or lack an expected file)
+    Check {
+        /// Fixture directories (searched recursively) or `*.input.txt` files
+        #[arg(default_value = "tests/fixtures")]
+        paths: Vec<PathBuf>,
+
+        /// Show a diff of expected vs. actual output for each failure
+        #[arg(long)]
+        diff: bool,
+    },
+    /// Rewrite each expected file with the corrected input
+    Update {
+        /// Fixture directories (searched recursively) or `*.input.txt` files
+        #[arg(default_value = "tests/fixtures")]
+        paths: Vec<PathBuf>,
+    },
+}
+
+/// Hook management actions
+#[derive(Subcommand, Debug)]
+enum HookAction {
+    /// Install pre-commit hook
+    Install {
+        /// Only check diagrams, don't auto-fix (blocks commits with issues)
+        #[arg(long)]
+        check_only: bool,
+
+        /// Auto-fix diagrams before commit
+        #[arg(long, conflicts_with = "check_only")]
+        auto_fix: bool,
+
+        /// File patterns to check (default: *.md *.txt)
+        #[arg(long, value_delimiter = ',')]
+        patterns: Option<Vec<String>>,
+    },
+    /// Uninstall pre-commit hook
+    Uninstall,
+    /// Show hook status
+    Status,
+    /// Check staged files now (what the installed hook runs)
+    Run {
+        /// Correct and re-stage files instead of failing the commit
+        #[arg(long)]
+        auto_fix: bool,
+
+        /// Patterns for the repo-relative paths to check, e.g. `docs/*.md`
+        /// (default: *.md *.txt)
+        #[arg(long, value_delimiter = ',')]
+        patterns: Option<Vec<String>>,
+    },
+}
+
+/// Git filter management actions
+#[derive(Subcommand, Debug)]
+enum FilterAction {
+    /// Configure the `aadc` filter in .git/config and assign it to
+    /// files in .gitattributes
+    Install {
+        /// File patterns to filter (default: *.md *.txt)
+        #[arg(long, value_delimiter = ',')]
+        patterns: Option<Vec<String>>,
+    },
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Configuration and Statistics
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Runtime configuration derived from CLI args
+#[derive(Debug, Clone)]
+pub struct Config {
+    max_iters: usize,
+    min_score: f64,
+    preset: Option<Preset>,
+    tab_width: usize,
+    keep_tabs: bool,
+    /// Largest input file read, in bytes (0 = no limit)
+    max_file_size: u64,
+    /// Apply each input's `.editorconfig` settings
+    editorconfig: bool,
+    /// Set when `--tab-width` was given, so `.editorconfig` does not
+    /// override it
+    tab_width_pinned: bool,
+    /// Emit a BOM even if the input has none (`charset = utf-8-bom`)
+    write_bom: bool,
+    /// Take each file's tab width from its Vim/Emacs modeline
+    modelines: bool,
+    /// Input encoding (None: UTF-8)
+    encoding: Option<Encoding>,
+    max_line_bytes: usize,
+    all_blocks: bool,
+    block_confidence: f64,
+    blank_gap: usize,
+    lookahead: usize,
+    embedded: bool,
+    alt_borders: Vec<char>,
+    fill_chars: Vec<char>,
+    align_numbers: bool,
+    single_target: bool,
+    target_strategy: TargetStrategy,
+    repair_corners: bool,
+    complete_boxes: bool,
+    allow_shrink: bool,
+    format_tables: bool,
+    on_change: Option<String>,
+    block_name: Option<String>,
+    stdin_filename: Option<PathBuf>,
+    max_line_length: Option<usize>,
+    no_pad_past: Option<usize>,
+    max_pad: Option<usize>,
+    normalize_eol: Option<Eol>,
+    /// Lint severity overrides by rule ID
+    lint_severities: BTreeMap<&'static str, Severity>,
+    /// Revision scoring weights (`[scoring]` table)
+    scoring: ScoringWeights,
+    lines: Option<Vec<LineRange>>,
+    /// Line ranges given with an input (`FILE:RANGES`), replacing `lines`
+    /// for that file
+    file_lines: BTreeMap<PathBuf, Vec<LineRange>>,
+    /// Git ref whose changes set `lines` per file (`--diff-only`)
+    diff_only: Option<String>,
+    /// Revision IDs selected by `--apply-ids` (None applies all)
+    apply_ids: Option<Vec<LineRange>>,
+    recursive: bool,
+    glob: String,
+    gitignore: bool,
+    max_depth: usize,
+    follow_symlinks: bool,
+    /// Worker threads for multi-file runs
+    jobs: usize,
+    /// Skip files recorded as clean in `cache_file`
+    cached: bool,
+    cache_file: PathBuf,
+    color: ColorMode,
+    verbose: bool,
+    diff: bool,
+    show_padding: bool,
+    diff_format: DiffFormat,
+    dry_run: bool,
+    list_different: bool,
+    check: bool,
+    null: bool,
+    require_diagrams: bool,
+    watch: bool,
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    debounce_ms: u64,
+    backup: bool,
+    backup_ext: String,
+    backup_mode: BackupMode,
+    backup_keep: Option<usize>,
+    force_write: bool,
+    preserve_metadata: bool,
+    json: bool,
+    /// `--json` output as one compact line per file
+    json_lines: bool,
+    output_format: OutputFormat,
+    /// Report files written after the run (`--report`)
+    reports: Vec<ReportTarget>,
+    error_format: ErrorFormat,
+    /// CI service detected from the environment (None with --no-ci)
+    ci: Option<CiProvider>,
+}
+
+impl From<&Args> for Config {
+    fn from(args: &Args) -> Self {
+        // Parse line ranges if provided
+        let lines = args.lines.as_ref().and_then(|s| parse_line_ranges(s).ok());
+        let apply_ids = args
+            .apply_ids
+            .as_ref()
+            .and_then(|s| parse_line_ranges(s).ok());
+        let alt_borders = args
+            .alt_borders
+            .as_ref()
+            .and_then(|s| parse_alt_borders(s).ok())
+            .unwrap_or_default();
+        let fill_chars = parse_fill_chars(args.fill_chars.as_deref().unwrap_or(DEFAULT_FILL_CHARS))
+            .unwrap_or_default();
+
+        Self {
+            max_iters: args.max_iters,
+            min_score: args.min_score,
+            preset: args.preset,
+            tab_width: args.tab_width,
+            keep_tabs: args.keep_tabs,
+            max_file_size: args
+                .max_file_size
+                .as_deref()
+                .and_then(|s| parse_byte_size(s).ok())
+                .unwrap_or(DEFAULT_MAX_FILE_SIZE),
+            editorconfig: !args.no_editorconfig,
+            tab_width_pinned: args.tab_width != 4,
+            write_bom: false,
+            modelines: !args.no_modelines,
+            encoding: args.encoding,
+            max_line_bytes: args.max_line_bytes,
+            all_blocks: args.all,
+            block_confidence: args.block_confidence.unwrap_or(DEFAULT_BLOCK_CONFIDENCE),
+            blank_gap: args.blank_gap.unwrap_or(DEFAULT_BLANK_GAP),
+            lookahead: args.lookahead.unwrap_or(DEFAULT_LOOKAHEAD),
+            embedded: args.embedded,
+            alt_borders,
+            fill_chars,
+            align_numbers: args.align_numbers,
+            single_target: args.single_target,
+            target_strategy: args.target.unwrap_or_default(),
+            repair_corners: args.repair_corners,
+            complete_boxes: args.complete_boxes,
+            allow_shrink: args.allow_shrink,
+            format_tables: args.format_tables,
+            on_change: args.on_change.clone(),
+            block_name: args.block_name.clone(),
+            stdin_filename: args.stdin_filename.clone(),
+            max_line_length: args.max_line_length,
+            no_pad_past: args.no_pad_past,
+            max_pad: args.max_pad,
+            normalize_eol: args.normalize_eol,
+            lint_severities: BTreeMap::new(),
+            scoring: ScoringWeights::default(),
+            lines,
+            file_lines: BTreeMap::new(),
+            diff_only: args.diff_only.clone(),
+            apply_ids,
+            recursive: args.recursive,
+            glob: args.glob.clone(),
+            gitignore: !args.no_gitignore,
+            max_depth: args.max_depth,
+            follow_symlinks: args.follow_symlinks,
+            jobs: args
+                .jobs
+                .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+            cached: args.cached,
+            cache_file: args.cache_file.clone(),
+            color: args.color,
+            verbose: args.verbose,
+            diff: args.diff,
+            show_padding: args.show_padding,
+            diff_format: args.diff_format,
+            dry_run: args.dry_run,
+            list_different: args.list_different,
+            check: args.check,
+            require_diagrams: args.require_diagrams,
+            null: args.null,
+            watch: args.watch,
+            debounce_ms: args.debounce_ms,
+            backup: args.backup,
+            backup_ext: args.backup_ext.clone(),
+            backup_mode: args.backup_mode,
+            backup_keep: args.backup_keep,
+            force_write: args.force_write,
+            preserve_metadata: args.preserve_metadata,
+            json: args.json || args.json_lines,
+            json_lines: args.json_lines,
+            output_format: args.output_format,
+            reports: args
+                .report
+                .iter()
+                .filter_map(|s| parse_report(s).ok())
+                .collect(),
+            error_format: args.error_format,
+            ci: if args.no_ci {
+                None
+            } else {
+                CiProvider::detect()
+            },
+        }
+    }
+}
+
+impl Default for Config {
+    /// The configuration of a plain `aadc` run: no options, no `.aadcrc`
+    fn default() -> Self {
+        Self::from(&Args::parse_from(["aadc"]))
+    }
+}
+
+impl Config {
+    /// Parse `.aadcrc`-style TOML (such as `preset = "safe"`) over the
+    /// defaults, validated as a config file would be
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let file_config: FileConfig = toml::from_str(text).context("Failed to parse config")?;
+        Self::from_file_config(file_config)
+    }
+
+    /// Parse aadc command-line options (without the program name), with
+    /// the `.aadcrc` they lead to, as an `aadc` run would
+    pub fn from_cli_args<I, T>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString>,
+    {
+        let argv = std::iter::once("aadc".into()).chain(args.into_iter().map(Into::into));
+        let args = Args::try_parse_from(argv).map_err(|err| ArgError(err.to_string()))?;
+        validate_args(&args)?;
+        create_config(&args)
+    }
+
+    /// `file_config` over the defaults, validated as a config file would be
+    fn from_file_config(file_config: FileConfig) -> Result<Self> {
+        validate_file_config(&file_config, Path::new("<config>"))?;
+        let args = Args::parse_from(["aadc"]);
+        let mut config = Self::from(&args);
+        apply_file_config(&mut config, &args, file_config);
+        Ok(config)
+    }
+}
+
+impl Config {
+    fn effective_min_score(&self) -> f64 {
+        match self.preset {
+            Some(preset) => preset.min_score(),
+            None => self.min_score,
+        }
+    }
+
+    /// Whether errors go to stderr as JSON (`--error-format json` or `--json`)
+    fn json_errors(&self) -> bool {
+        self.json || self.error_format == ErrorFormat::Json
+    }
+
+    /// Whether the run only reports, exiting 3 when files would change
+    /// (`--dry-run`, `--check`, or a report `--output-format`)
+    fn reports_changes(&self) -> bool {
+        self.dry_run || self.check || self.output_format != OutputFormat::Content
+    }
+}
+
+/// Where verbose output is printed: the terminal, through rich_rust, or
+/// nowhere on wasm32, which has no terminal
+struct Console {
+    #[cfg(not(target_arch = "wasm32"))]
+    terminal: rich_rust::Console,
+}
+
+impl Console {
+    fn new() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            terminal: rich_rust::Console::new(),
+        }
+    }
+
+    fn print(&self, markup: &str) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.terminal.print(markup);
+        #[cfg(target_arch = "wasm32")]
+        let _ = markup;
+    }
+}
+
+/// Console markup for verbose output
+struct VerboseStyle {
+    use_color: bool,
+}
+
+impl VerboseStyle {
+    /// Styles that add color markup only when `use_color` is set
+    fn new(use_color: bool) -> Self {
+        Self { use_color }
+    }
+
+    fn wrap(&self, tag: &str, text: impl fmt::Display) -> String {
+        if self.use_color {
+            format!("[{}]{}[/]", tag, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn header(&self, text: impl fmt::Display) -> String {
+        self.wrap("bold cyan", text)
+    }
+
+    fn block(&self, text: impl fmt::Display) -> String {
+        self.wrap("yellow", text)
+    }
+
+    fn success(&self, text: impl fmt::Display) -> String {
+        self.wrap("bold green", text)
+    }
+
+    fn dim(&self, text: impl fmt::Display) -> String {
+        self.wrap("dim", text)
+    }
+
+    fn bold(&self, text: impl fmt::Display) -> String {
+        self.wrap("bold", text)
+    }
+
+    fn stat_label(&self, text: impl fmt::Display) -> String {
+        self.wrap("bold blue", text)
+    }
+
+    fn separator(&self) -> String {
+        self.wrap("dim", "───")
+    }
+}
+
+/// Print a statistics summary to stderr
+fn print_stats_summary(
+    stats: &Stats,
+    files_processed: usize,
+    files_changed: usize,
+    errors: usize,
+    console: &Console,
+    styles: &VerboseStyle,
+) {
+    console.print("");
+    console.print(&format!(
+        "{} Summary {}",
+        styles.separator(),
+        styles.separator()
+    ));
+
+    // File statistics (for multiple files)
+    if files_processed > 1 {
+        console.print(&format!(
+            "  {} {} processed, {} modified, {} unchanged",
+            styles.stat_label("Files:"),
+            files_processed,
+            files_changed,
+            files_processed.saturating_sub(files_changed)
+        ));
+    }
+
+    // Block statistics
+    console.print(&format!(
+        "  {} {} found, {} processed, {} skipped",
+        styles.stat_label("Blocks:"),
+        stats.blocks_found,
+        stats.blocks_modified,
+        stats.blocks_skipped
+    ));
+
+    // Revision statistics
+    console.print(&format!(
+        "  {} {} applied, {} skipped",
+        styles.stat_label("Revisions:"),
+        stats.total_revisions,
+        stats.revisions_skipped
+    ));
+    if stats.revisions_refused > 0 {
+        console.print(&format!(
+            "  {} {} (would exceed --max-line-length)",
+            styles.stat_label("Refused:"),
+            stats.revisions_refused
+        ));
+    }
+    if stats.eol_changes > 0 {
+        console.print(&format!(
+            "  {} {} line ending(s) converted",
+            styles.stat_label("EOL:"),
+            stats.eol_changes
+        ));
+    }
+    if !stats.breakdown.by_kind.is_empty() {
+        let kinds = stats
+            .breakdown
+            .by_kind
+            .iter()
+            .map(|(kind, c)| format!("{} {}/{}", kind, c.applied, c.skipped))
+            .collect::<Vec<_>>()
+            .join(", ");
+        console.print(&format!(
+            "  {} {} (applied/skipped)",
+            styles.stat_label("By kind:"),
+            kinds
+        ));
+    }
+
+    // Performance statistics
+    let elapsed_ms = stats.elapsed.as_secs_f64() * 1000.0;
+    let lines_per_sec = stats.lines_per_second();
+    console.print(&format!(
+        "  {} {:.2}ms ({:.0} lines/sec)",
+        styles.stat_label("Time:"),
+        elapsed_ms,
+        lines_per_sec
+    ));
+
+    // Error count if any
+    if errors > 0 {
+        console.print(&format!(
+            "  {} {}",
+            styles.wrap("bold red", "Errors:"),
+            errors
+        ));
+    }
+
+    console.print("");
+}
+
+/// Build the console for `color`. In auto mode, CI services whose logs
+/// render ANSI colors get color even though output is not a TTY; other CI
+/// environments fall back to TTY detection.
+#[cfg(not(target_arch = "wasm32"))]
+fn build_console(color: ColorMode, ci: Option<CiProvider>) -> (Console, VerboseStyle) {
+    if color == ColorMode::Never {
+        return (Console::new(), VerboseStyle::new(false));
+    }
+
+    // Windows consoles render ANSI sequences only with virtual terminal
+    // processing on; elsewhere this reports None
+    let ansi_enabled = anstyle_query::windows::enable_ansi_colors() != Some(false);
+
+    match color {
+        ColorMode::Never => (Console::new(), VerboseStyle::new(false)),
+        ColorMode::Always => forced_color_console(),
+        ColorMode::Auto => {
+            if std::env::var("NO_COLOR").is_ok() || !ansi_enabled {
+                return (Console::new(), VerboseStyle::new(false));
+            }
+
+            if std::env::var("FORCE_COLOR").is_ok() || ci.is_some_and(CiProvider::renders_ansi) {
+                return forced_color_console();
+            }
+
+            let console = Console::new();
+            let use_color = console.terminal.is_color_enabled();
+            (console, VerboseStyle::new(use_color))
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn forced_color_console() -> (Console, VerboseStyle) {
+    let system = terminal::detect_color_system().unwrap_or(ColorSystem::Standard);
+    let terminal = rich_rust::Console::builder()
+        .force_terminal(true)
+        .color_system(system)
+        .build();
+    (Console { terminal }, VerboseStyle::new(true))
+}
+
+/// wasm32 has no terminal to color
+#[cfg(target_arch = "wasm32")]
+fn build_console(_color: ColorMode, _ci: Option<CiProvider>) -> (Console, VerboseStyle) {
+    (Console::new(), VerboseStyle::new(false))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CI Detection
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// CI service detected from the environment, used to pick defaults so
+/// pipelines don't need the same boilerplate flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CiProvider {
+    GithubActions,
+    GitlabCi,
+    /// Any other service that sets `CI`
+    Generic,
+}
+
+impl CiProvider {
+    /// Detect the CI service from the process environment
+    fn detect() -> Option<Self> {
+        Self::detect_from(|name| std::env::var(name).ok())
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let set = |name| var(name).is_some_and(|v| !matches!(v.as_str(), "" | "0" | "false"));
+        if set("GITHUB_ACTIONS") {
+            Some(Self::GithubActions)
+        } else if set("GITLAB_CI") {
+            Some(Self::GitlabCi)
+        } else if set("CI") {
+            Some(Self::Generic)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the service's log viewer renders ANSI colors
+    #[cfg(not(target_arch = "wasm32"))]
+    fn renders_ansi(self) -> bool {
+        matches!(self, Self::GithubActions | Self::GitlabCi)
+    }
+}
+
+impl fmt::Display for CiProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::GithubActions => "GitHub Actions",
+            Self::GitlabCi => "GitLab CI",
+            Self::Generic => "CI",
+        })
+    }
+}
+
+/// In CI with `--verbose`, print the effective configuration once to
+/// stderr so pipeline logs record what aadc actually ran with
+fn announce_ci(config: &Config) {
+    if let Some(ci) = config.ci.filter(|_| config.verbose) {
+        eprintln!(
+            "{} detected (disable with --no-ci); effective configuration:",
+            ci
+        );
+        print_effective_config(config);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Config File Support
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Config file names searched in order
+const CONFIG_FILENAMES: &[&str] = &[".aadcrc", ".aadcrc.toml", "aadcrc.toml"];
+
+/// Configuration loaded from a .aadcrc file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FileConfig {
+    /// Minimum confidence score (0.0-1.0)
+    min_score: Option<f64>,
+    /// Confidence threshold preset (overrides min_score)
+    preset: Option<Preset>,
+    /// Maximum correction iterations
+    max_iters: Option<usize>,
+    /// Tab expansion width
+    tab_width: Option<usize>,
+    /// Convert leading indentation back to tabs after correction
+    keep_tabs: Option<bool>,
+    /// Apply `.editorconfig` tab width and charset
+    editorconfig: Option<bool>,
+    /// Take tab width from Vim/Emacs modelines
+    modelines: Option<bool>,
+    /// Input encoding
+    encoding: Option<Encoding>,
+    /// Byte length past which lines are not analyzed (0 = no cap)
+    max_line_bytes: Option<usize>,
+    /// Largest input file read (bytes, or a string with a unit)
+    max_file_size: Option<ByteSize>,
+    /// Show verbose output
+    verbose: Option<bool>,
+    /// Color mode: auto, always, never
+    color: Option<ColorMode>,
+    /// Output as JSON
+    json: Option<bool>,
+    /// Output as JSON, one compact line per file
+    json_lines: Option<bool>,
+    /// Render inserted padding in diffs as `·`
+    show_padding: Option<bool>,
+    /// Diff layout: unified or side-by-side
+    diff_format: Option<DiffFormat>,
+    /// Create backup before in-place edit
+    backup: Option<bool>,
+    /// Backup file extension
+    backup_ext: Option<String>,
+    /// Backup naming: simple or numbered
+    backup_mode: Option<BackupMode>,
+    /// Number of numbered backups to keep per file
+    backup_keep: Option<usize>,
+    /// Keep permissions and times of files rewritten in place
+    preserve_metadata: Option<bool>,
+    /// Enable recursive mode
+    recursive: Option<bool>,
+    /// Glob patterns for recursive mode
+    glob: Option<String>,
+    /// Respect .gitignore
+    gitignore: Option<bool>,
+    /// Maximum directory depth
+    max_depth: Option<usize>,
+    /// Descend into symlinked directories
+    follow_symlinks: Option<bool>,
+    /// Skip files found clean by an earlier cached run
+    cached: Option<bool>,
+    /// Cache file for cached runs
+    cache_file: Option<PathBuf>,
+    /// Process all diagram-like blocks
+    all: Option<bool>,
+    /// Minimum detection confidence for a block to be processed
+    block_confidence: Option<f64>,
+    /// Blank lines a diagram block may span
+    blank_gap: Option<usize>,
+    /// Lines to look ahead past a non-diagram line inside a block
+    lookahead: Option<usize>,
+    /// Correct diagrams inside string values of structured/source files
+    embedded: Option<bool>,
+    /// Alternative vertical border characters (e.g., ":!")
+    alt_borders: Option<String>,
+    /// Leader fill characters extended instead of padding with spaces
+    fill_chars: Option<String>,
+    /// Right-align numeric table columns
+    align_numbers: Option<bool>,
+    /// Pad every right border in a block to the widest instead of aligning
+    /// each box to its own target column
+    single_target: Option<bool>,
+    /// Target column strategy (rightmost, mode, median, top-border)
+    target: Option<TargetStrategy>,
+    /// Replace vertical borders that should be corners
+    repair_corners: Option<bool>,
+    /// Insert missing bottom borders
+    complete_boxes: Option<bool>,
+    /// Remove spaces before right borders past the target
+    allow_shrink: Option<bool>,
+    /// Re-align Markdown pipe tables and MySQL/psql output tables
+    format_tables: Option<bool>,
+    /// Shell command run after files are modified
+    on_change: Option<String>,
+    /// Maximum line width revisions may produce
+    max_line_length: Option<usize>,
+    /// Protected column lines past which are left alone
+    no_pad_past: Option<usize>,
+    /// Most padding a single revision may insert
+    max_pad: Option<usize>,
+    /// Line endings to convert output to (lf or crlf)
+    normalize_eol: Option<Eol>,
+    /// Lint rule severities (`[lint]` table, keyed by rule ID or name)
+    lint: Option<BTreeMap<String, Severity>>,
+    /// Revision scoring weights (`[scoring]` table)
+    scoring: Option<ScoringWeights>,
+}
+
+/// Search for a config file starting from the given directory
+fn find_config_file(start_dir: &Path) -> Option<PathBuf> {
+    let mut current = start_dir.to_path_buf();
+
+    // Search up the directory tree
+    loop {
+        for filename in CONFIG_FILENAMES {
+            let config_path = current.join(filename);
+            if config_path.exists() {
+                return Some(config_path);
+            }
+        }
+
+        if !current.pop() {
+            break;
+        }
+    }
+
+    // Check home directory
+    if let Some(home) = dirs::home_dir() {
+        for filename in CONFIG_FILENAMES {
+            let config_path = home.join(filename);
+            if config_path.exists() {
+                return Some(config_path);
+            }
+        }
+    }
+
+    None
+}
+
+/// Load and parse a config file
+fn load_config_file(path: &Path) -> Result<FileConfig> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+    let parsed: FileConfig = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+    validate_file_config(&parsed, path)?;
+    Ok(parsed)
+}
+
+/// Reject obviously-invalid file-config values *before* they are merged
+/// into the runtime [`Config`]. This mirrors `validate_args`, which only
+/// runs on the CLI side; without it, a config file containing
+/// `tab_width = 0` slips past the CLI gate at `validate_args` and lands
+/// directly in `expand_tabs`, where the modular-arithmetic
+/// `tab_width - (col % tab_width)` then panics with a divide-by-zero.
+/// Out-of-range `min_score` and `max_iters = 0` are silent rather than
+/// fatal, but they likewise produce surprising behavior (every revision
+/// auto-rejected/auto-accepted, or zero correction passes), so we
+/// reject them at the same surface for symmetry with the CLI rules.
+fn validate_file_config(file_config: &FileConfig, path: &Path) -> Result<()> {
+    let location = || path.display().to_string();
+    if let Some(ref size) = file_config.max_file_size {
+        size.bytes()
+            .map_err(|e| anyhow::anyhow!("{}: max_file_size: {e}", location()))?;
+    }
+    if let Some(score) = file_config.min_score {
+        if !(0.0..=1.0).contains(&score) {
+            return Err(anyhow::anyhow!(
+                "{}: min_score must be between 0.0 and 1.0, got {score}",
+                location()
+            ));
+        }
+    }
+    if let Some(confidence) = file_config.block_confidence {
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err(anyhow::anyhow!(
+                "{}: block_confidence must be between 0.0 and 1.0, got {confidence}",
+                location()
+            ));
+        }
+    }
+    if let Some(iters) = file_config.max_iters {
+        if iters == 0 {
+            return Err(anyhow::anyhow!(
+                "{}: max_iters must be at least 1",
+                location()
+            ));
+        }
+    }
+    if let Some(width) = file_config.tab_width {
+        if width == 0 || width > 16 {
+            return Err(anyhow::anyhow!(
+                "{}: tab_width must be between 1 and 16, got {width}",
+                location()
+            ));
+        }
+    }
+    if let Some(ref chars) = file_config.alt_borders {
+        parse_alt_borders(chars)
+            .map_err(|e| anyhow::anyhow!("{}: alt_borders: {e}", location()))?;
+    }
+    if file_config.max_line_length == Some(0) {
+        return Err(anyhow::anyhow!(
+            "{}: max_line_length must be at least 1",
+            location()
+        ));
+    }
+    if file_config.no_pad_past == Some(0) {
+        return Err(anyhow::anyhow!(
+            "{}: no_pad_past must be at least 1",
+            location()
+        ));
+    }
+    if file_config.max_pad == Some(0) {
+        return Err(anyhow::anyhow!(
+            "{}: max_pad must be at least 1",
+            location()
+        ));
+    }
+    if file_config.backup_keep == Some(0) {
+        return Err(anyhow::anyhow!(
+            "{}: backup_keep must be at least 1",
+            location()
+        ));
+    }
+    if let Some(ref chars) = file_config.fill_chars {
+        parse_fill_chars(chars).map_err(|e| anyhow::anyhow!("{}: fill_chars: {e}", location()))?;
+    }
+    if let Some(ref table) = file_config.lint {
+        parse_lint_severities(table).map_err(|e| anyhow::anyhow!("{}: lint: {e}", location()))?;
+    }
+    if let Some(ref weights) = file_config.scoring {
+        weights
+            .validate()
+            .map_err(|e| anyhow::anyhow!("{}: scoring: {e}", location()))?;
+    }
+    Ok(())
+}
+
+/// Create Config by merging file config with CLI args (CLI wins)
+fn create_config(args: &Args) -> Result<Config> {
+    let mut config = Config::from(args);
+
+    // Skip config file loading if --no-config is set
+    if args.no_config {
+        return Ok(config);
+    }
+
+    // Find and load config file
+    let config_path = if let Some(ref path) = args.config_file {
+        // Explicit config file specified
+        if !path.exists() {
+            return Err(anyhow::anyhow!("Config file not found: {}", path.display()));
+        }
+        Some(path.clone())
+    } else {
+        // Search for config file from the first input, or from where
+        // stdin content claims to live
+        let anchor = match args.inputs.first() {
+            Some(p) if !is_stdin_path(p) => Some(p),
+            _ => args.stdin_filename.as_ref(),
+        };
+        let start_dir = anchor
+            .and_then(|p| {
+                if p.is_dir() {
+                    Some(p.clone())
+                } else {
+                    p.parent().map(|p| p.to_path_buf())
+                }
+            })
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+
+        find_config_file(&start_dir)
+    };
+
+    if let Some(path) = config_path {
+        let file_config = load_config_file(&path)?;
+        apply_file_config(&mut config, args, file_config);
+    }
+
+    Ok(config)
+}
+
+/// Merge `.aadcrc` values into `config`. The CLI wins: a file value is
+/// only used where the CLI option was left at its default.
+fn apply_file_config(config: &mut Config, args: &Args, file_config: FileConfig) {
+    // Merge file config with CLI config (CLI wins)
+    // Only apply file config values when CLI used defaults
+    if args.preset.is_none() {
+        if let Some(preset) = file_config.preset {
+            config.preset = Some(preset);
+        } else if let Some(score) = file_config.min_score {
+            // Only use min_score from file if no preset specified
+            if config.preset.is_none() {
+                config.min_score = score;
+            }
+        }
+    }
+
+    // max_iters: use file value if CLI used default (10)
+    if args.max_iters == 10 {
+        if let Some(iters) = file_config.max_iters {
+            config.max_iters = iters;
+        }
+    }
+
+    // tab_width: use file value if CLI used default (4)
+    if args.tab_width == 4 {
+        if let Some(width) = file_config.tab_width {
+            config.tab_width = width;
+        }
+    }
+
+    if !args.keep_tabs {
+        if let Some(k) = file_config.keep_tabs {
+            config.keep_tabs = k;
+        }
+    }
+
+    if !args.no_editorconfig {
+        if let Some(e) = file_config.editorconfig {
+            config.editorconfig = e;
+        }
+    }
+
+    if !args.no_modelines {
+        if let Some(m) = file_config.modelines {
+            config.modelines = m;
+        }
+    }
+
+    if args.encoding.is_none() {
+        if let Some(e) = file_config.encoding {
+            config.encoding = Some(e);
+        }
+    }
+
+    if args.max_file_size.is_none() {
+        if let Some(ref size) = file_config.max_file_size {
+            config.max_file_size = size.bytes().unwrap_or(DEFAULT_MAX_FILE_SIZE);
+        }
+    }
+
+    if args.max_line_bytes == DEFAULT_MAX_LINE_BYTES {
+        if let Some(bytes) = file_config.max_line_bytes {
+            config.max_line_bytes = bytes;
+        }
+    }
+
+    // Boolean flags: use file value if CLI flag wasn't set
+    if !args.verbose {
+        if let Some(v) = file_config.verbose {
+            config.verbose = v;
+        }
+    }
+
+    if args.color == ColorMode::Auto {
+        if let Some(c) = file_config.color {
+            config.color = c;
+        }
+    }
+
+    if !args.json && !args.json_lines {
+        if let Some(j) = file_config.json {
+            config.json = j;
+        }
+        if let Some(j) = file_config.json_lines {
+            config.json_lines = j;
+            config.json |= j;
+        }
+    }
+
+    if !args.show_padding {
+        if let Some(p) = file_config.show_padding {
+            config.show_padding = p;
+        }
+    }
+
+    if args.diff_format == DiffFormat::Unified {
+        if let Some(format) = file_config.diff_format {
+            config.diff_format = format;
+        }
+    }
+
+    if !args.backup {
+        if let Some(b) = file_config.backup {
+            config.backup = b;
+        }
+    }
+
+    // backup_ext: use file value if CLI used default
+    if args.backup_ext == ".bak" {
+        if let Some(ext) = file_config.backup_ext {
+            config.backup_ext = ext;
+        }
+    }
+
+    if args.backup_mode == BackupMode::Simple {
+        if let Some(mode) = file_config.backup_mode {
+            config.backup_mode = mode;
+        }
+    }
+
+    if args.backup_keep.is_none() {
+        config.backup_keep = file_config.backup_keep;
+    }
+
+    if !args.preserve_metadata {
+        if let Some(p) = file_config.preserve_metadata {
+            config.preserve_metadata = p;
+        }
+    }
+
+    // Recursive options
+    if !args.recursive {
+        if let Some(r) = file_config.recursive {
+            config.recursive = r;
+        }
+    }
+
+    if args.glob == "*.txt,*.md" {
+        if let Some(g) = file_config.glob {
+            config.glob = g;
+        }
+    }
+
+    if !args.no_gitignore {
+        if let Some(gi) = file_config.gitignore {
+            config.gitignore = gi;
+        }
+    }
+
+    if args.max_depth == 0 {
+        if let Some(d) = file_config.max_depth {
+            config.max_depth = d;
+        }
+    }
+
+    if !args.follow_symlinks {
+        if let Some(f) = file_config.follow_symlinks {
+            config.follow_symlinks = f;
+        }
+    }
+
+    if !args.cached {
+        if let Some(c) = file_config.cached {
+            config.cached = c;
+        }
+    }
+
+    if args.cache_file == Path::new(CACHE_FILE) {
+        if let Some(path) = file_config.cache_file {
+            config.cache_file = path;
+        }
+    }
+
+    if !args.all {
+        if let Some(a) = file_config.all {
+            config.all_blocks = a;
+        }
+    }
+
+    if args.block_confidence.is_none() {
+        if let Some(c) = file_config.block_confidence {
+            config.block_confidence = c;
+        }
+    }
+
+    if args.blank_gap.is_none() {
+        if let Some(g) = file_config.blank_gap {
+            config.blank_gap = g;
+        }
+    }
+
+    if args.lookahead.is_none() {
+        if let Some(l) = file_config.lookahead {
+            config.lookahead = l;
+        }
+    }
+
+    if !args.embedded {
+        if let Some(e) = file_config.embedded {
+            config.embedded = e;
+        }
+    }
+
+    if args.alt_borders.is_none() {
+        if let Some(chars) = file_config.alt_borders {
+            config.alt_borders = parse_alt_borders(&chars).unwrap_or_default();
+        }
+    }
+
+    if args.fill_chars.is_none() {
+        if let Some(chars) = file_config.fill_chars {
+            config.fill_chars = parse_fill_chars(&chars).unwrap_or_default();
+        }
+    }
+
+    if !args.align_numbers {
+        if let Some(a) = file_config.align_numbers {
+            config.align_numbers = a;
+        }
+    }
+
+    if !args.single_target {
+        if let Some(s) = file_config.single_target {
+            config.single_target = s;
+        }
+    }
+
+    if args.target.is_none() {
+        if let Some(t) = file_config.target {
+            config.target_strategy = t;
+        }
+    }
+
+    if !args.repair_corners {
+        if let Some(r) = file_config.repair_corners {
+            config.repair_corners = r;
+        }
+    }
+
+    if !args.complete_boxes {
+        if let Some(c) = file_config.complete_boxes {
+            config.complete_boxes = c;
+        }
+    }
+
+    if !args.allow_shrink {
+        if let Some(a) = file_config.allow_shrink {
+            config.allow_shrink = a;
+        }
+    }
+
+    if !args.format_tables {
+        if let Some(t) = file_config.format_tables {
+            config.format_tables = t;
+        }
+    }
+
+    if args.on_change.is_none() {
+        config.on_change = file_config.on_change;
+    }
+
+    if args.max_line_length.is_none() {
+        config.max_line_length = file_config.max_line_length;
+    }
+
+    if args.no_pad_past.is_none() {
+        config.no_pad_past = file_config.no_pad_past;
+    }
+
+    if args.max_pad.is_none() {
+        config.max_pad = file_config.max_pad;
+    }
+
+    if args.normalize_eol.is_none() {
+        config.normalize_eol = file_config.normalize_eol;
+    }
+
+    if let Some(table) = file_config.lint {
+        config.lint_severities = parse_lint_severities(&table).unwrap_or_default();
+    }
+
+    if let Some(weights) = file_config.scoring {
+        config.scoring = weights;
+    }
+}
+
+/// Default config file content
+const DEFAULT_CONFIG: &str = r#"# .aadcrc - aadc configuration file
+# https://github.com/Dicklesworthstone/aadc
+
+# Confidence threshold for applying edits
+# Use either min_score (0.0-1.0) or preset (strict|normal|aggressive|relaxed)
+# preset = "normal"
+min_score = 0.5
+
+# Maximum correction iterations per block
+max_iters = 10
+
+# Tab expansion width
+tab_width = 4
+
+# Convert leading indentation of tab-indented lines back to tabs
+# keep_tabs = false
+
+# Take tab width and charset from .editorconfig (overrides tab_width above)
+# editorconfig = true
+
+# Take a file's tab width from a Vim (vim: ts=8) or Emacs (tab-width: 8)
+# modeline in its first or last lines
+# modelines = true
+
+# Encoding of input files: "auto", "utf8", "latin1", "utf16", "utf16le" or
+# "utf16be" (output keeps the input's encoding)
+# encoding = "utf8"
+
+# Lines longer than this many bytes (e.g. pasted minified content) are not
+# analyzed; 0 disables the cap
+# max_line_bytes = 16384
+
+# Input files larger than this are refused (bytes, or "500K", "200MB",
+# "1.5GiB"; 0 = no limit)
+# max_file_size = "100MB"
+
+# Output options
+# verbose = false
+# color = "auto"
+# json = false
+# json_lines = false  # one compact JSON line per file (implies json)
+# show_padding = false  # render inserted padding in diffs as ·
+# diff_format = "unified"  # or "side-by-side" (two panes, fitted to the terminal)
+
+# Backup options (for --in-place)
+# backup = false
+# backup_ext = ".bak"
+# backup_mode = "simple"   # or "numbered" (file.bak.1, file.bak.2, ...)
+# backup_keep = 5          # numbered backups kept per file
+# preserve_metadata = false  # keep permissions and mtime of rewritten files
+
+# Recursive mode defaults
+# recursive = false
+# glob = "*.txt,*.md"
+# gitignore = true
+# max_depth = 0
+# follow_symlinks = false
+
+# Skip files an earlier cached run found clean (content and options unchanged)
+# cached = false
+# cache_file = ".aadc-cache"
+
+# Force processing of low-confidence blocks
+# all = false
+
+# Minimum detection confidence (0.0-1.0) for a block to be processed
+# block_confidence = 0.3
+
+# Block grouping: blank lines a block may span, and how many lines past a
+# non-diagram line to look for more diagram lines before ending the block
+# there (lower both to keep a diagram apart from an adjacent table)
+# blank_gap = 1
+# lookahead = 3
+
+# Correct diagrams inside string values (YAML/JSON strings, Python
+# docstrings, Go raw strings, JS/TS template literals)
+# embedded = false
+
+# Also treat these characters as vertical borders (legacy ASCII art)
+# alt_borders = ":!"
+
+# Leader fill characters extended instead of padding with spaces
+# ("" disables leader extension)
+# fill_chars = ".·…—"
+
+# Right-align mostly numeric table columns, left-align text columns
+# align_numbers = false
+
+# Pad every right border in a block out to the widest instead of aligning
+# each box (boxes of different widths stacked in one block), or each
+# column cluster of rows outside boxes, to its own target column
+# single_target = false
+
+# Column each cluster of right borders aligns to (rightmost|mode|median|
+# top-border); borders past it are left alone unless allow_shrink is set
+# target = "rightmost"
+
+# Replace a | closing a horizontal border row (+----|) with the corner the
+# row needs (+----+, ┌───┐); this changes a character rather than padding
+# repair_corners = false
+
+# Insert a bottom border under a box whose top border and rows are there
+# but which was never closed (adds a line)
+# complete_boxes = false
+
+# Pull in over-padded right borders (| x        | next to | longer |) by
+# deleting spaces just before them; aadc otherwise only inserts
+# allow_shrink = false
+
+# Re-align Markdown pipe tables (| a | b | over a |---|---| delimiter row)
+# and tables pasted from MySQL or psql output (+----+ separators or psql's
+# ----+---- header rule), so every column lines up
+# format_tables = false
+
+# Shell command run after files are modified (paths passed as arguments
+# and in AADC_CHANGED_FILES)
+# on_change = "make docs-preview"
+
+# Refuse revisions that would make a line wider than this
+# max_line_length = 120
+
+# Protected column: lines with content past it never set the alignment
+# target and are never padded
+# no_pad_past = 100
+
+# Skip revisions that would insert more padding than this into a line
+# (guards against bogus targets in false-positive blocks)
+# max_pad = 40
+
+# Convert line endings on output (lf|crlf)
+# normalize_eol = "lf"
+
+# Lint rule severities (off|warning|error), keyed by rule ID or name
+# [lint]
+# AD001 = "error"
+# unclosed-box = "warning"
+
+# Revision scoring weights (defaults shown). Padding scores
+# pad_base - min(spaces / pad_penalty_divisor, pad_penalty_cap)
+# + pad_strong_bonus (strong lines) - alt_border_penalty (alternative
+# borders); adding a border scores add_border_base plus a strong or weak
+# line bonus. Indenting a line to the block's left-border column scores
+# align_left_base + align_left_consensus_bonus * (share of left borders
+# already there) - align_left_column_penalty per column. Repairing a
+# corner (repair_corners) scores replace_corner_base; closing a box
+# (complete_boxes) scores complete_box_base * (share of closed rows), and
+# removing spaces before a border (allow_shrink) scores
+# shrink_base - min(spaces / pad_penalty_divisor, pad_penalty_cap)
+# + pad_strong_bonus (strong lines).
+# [scoring]
+# pad_base = 0.8
+# pad_penalty_divisor = 10.0
+# pad_penalty_cap = 0.5
+# pad_strong_bonus = 0.2
+# alt_border_penalty = 0.1
+# add_border_base = 0.5
+# add_border_strong_bonus = 0.2
+# add_border_weak_bonus = 0.1
+# align_left_base = 0.6
+# align_left_consensus_bonus = 0.3
+# align_left_column_penalty = 0.1
+# replace_corner_base = 0.7
+# complete_box_base = 0.6
+# shrink_base = 0.8
+"#;
+
+/// Handle the config subcommand
+fn run_config_command(action: &ConfigAction) -> Result<()> {
+    match action {
+        ConfigAction::Init { global } => {
+            let path = if *global {
+                dirs::home_dir()
+                    .ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?
+                    .join(".aadcrc")
+            } else {
+                PathBuf::from(".aadcrc")
+            };
+
+            if path.exists() {
+                return Err(anyhow::anyhow!(
+                    "Config file already exists: {}",
+                    path.display()
+                ));
+            }
+
+            fs::write(&path, DEFAULT_CONFIG)
+                .with_context(|| format!("Failed to create config file: {}", path.display()))?;
+
+            eprintln!("Created config file: {}", path.display());
+            Ok(())
+        }
+
+        ConfigAction::Show => {
+            // Parse minimal args to get effective config
+            let args = Args::parse_from(["aadc"]);
+            let config = create_config(&args)?;
+
+            eprintln!("Effective configuration:");
+            print_effective_config(&config);
+
+            // Show config file path if found
+            let start_dir = std::env::current_dir().unwrap_or_default();
+            if let Some(path) = find_config_file(&start_dir) {
+                eprintln!();
+                eprintln!("Config file: {}", path.display());
+            }
+
+            Ok(())
+        }
+
+        ConfigAction::Path => {
+            let start_dir = std::env::current_dir().unwrap_or_default();
+            if let Some(path) = find_config_file(&start_dir) {
+                println!("{}", path.display());
+                Ok(())
+            } else {
+                eprintln!("No config file found");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Print the effective configuration, one `key: value` per line, to stderr
+fn print_effective_config(config: &Config) {
+    eprintln!("  min_score: {}", config.effective_min_score());
+    if let Some(preset) = config.preset {
+        eprintln!("  preset: {:?}", preset);
+    }
+    eprintln!("  max_iters: {}", config.max_iters);
+    eprintln!("  tab_width: {}", config.tab_width);
+    eprintln!("  keep_tabs: {}", config.keep_tabs);
+    eprintln!("  editorconfig: {}", config.editorconfig);
+    eprintln!("  modelines: {}", config.modelines);
+    if let Some(encoding) = config.encoding {
+        eprintln!("  encoding: {:?}", encoding);
+    }
+    eprintln!("  max_line_bytes: {}", config.max_line_bytes);
+    eprintln!("  max_file_size: {}", config.max_file_size);
+    eprintln!("  verbose: {}", config.verbose);
+    eprintln!("  color: {:?}", config.color);
+    if let Some(ci) = config.ci {
+        eprintln!("  ci: {}", ci);
+    }
+    eprintln!("  json: {}", config.json);
+    eprintln!("  json_lines: {}", config.json_lines);
+    eprintln!("  show_padding: {}", config.show_padding);
+    eprintln!("  diff_format: {:?}", config.diff_format);
+    eprintln!("  backup: {}", config.backup);
+    eprintln!("  backup_ext: {}", config.backup_ext);
+    eprintln!("  backup_mode: {:?}", config.backup_mode);
+    if let Some(keep) = config.backup_keep {
+        eprintln!("  backup_keep: {}", keep);
+    }
+    eprintln!("  preserve_metadata: {}", config.preserve_metadata);
+    eprintln!("  recursive: {}", config.recursive);
+    eprintln!("  glob: {}", config.glob);
+    eprintln!("  gitignore: {}", config.gitignore);
+    eprintln!("  max_depth: {}", config.max_depth);
+    eprintln!("  follow_symlinks: {}", config.follow_symlinks);
+    eprintln!("  cached: {}", config.cached);
+    if config.cached {
+        eprintln!("  cache_file: {}", config.cache_file.display());
+    }
+    eprintln!("  jobs: {}", config.jobs);
+    eprintln!("  all_blocks: {}", config.all_blocks);
+    eprintln!("  block_confidence: {}", config.block_confidence);
+    eprintln!("  blank_gap: {}", config.blank_gap);
+    eprintln!("  lookahead: {}", config.lookahead);
+    eprintln!("  embedded: {}", config.embedded);
+    eprintln!(
+        "  alt_borders: {}",
+        config.alt_borders.iter().collect::<String>()
+    );
+    eprintln!(
+        "  fill_chars: {}",
+        config.fill_chars.iter().collect::<String>()
+    );
+    eprintln!("  align_numbers: {}", config.align_numbers);
+    eprintln!("  single_target: {}", config.single_target);
+    eprintln!("  target: {:?}", config.target_strategy);
+    eprintln!("  repair_corners: {}", config.repair_corners);
+    eprintln!("  complete_boxes: {}", config.complete_boxes);
+    eprintln!("  allow_shrink: {}", config.allow_shrink);
+    eprintln!("  format_tables: {}", config.format_tables);
+    if let Some(ref command) = config.on_change {
+        eprintln!("  on_change: {}", command);
+    }
+    if let Some(max) = config.max_line_length {
+        eprintln!("  max_line_length: {}", max);
+    }
+    if let Some(column) = config.no_pad_past {
+        eprintln!("  no_pad_past: {}", column);
+    }
+    if let Some(max) = config.max_pad {
+        eprintln!("  max_pad: {}", max);
+    }
+    if let Some(eol) = config.normalize_eol {
+        eprintln!("  normalize_eol: {:?}", eol);
+    }
+    if config.scoring != ScoringWeights::default() {
+        eprintln!("  scoring: {:?}", config.scoring);
+    }
+    for rule in LINT_RULES {
+        eprintln!(
+            "  lint.{} ({}): {}",
+            rule.id,
+            rule.name,
+            config.lint_severity(rule)
+        );
+    }
+}
+
+fn validate_args(args: &Args) -> Result<()> {
+    if !(0.0..=1.0).contains(&args.min_score) {
+        return Err(ArgError("--min-score must be between 0.0 and 1.0".to_string()).into());
+    }
+
+    if args
+        .block_confidence
+        .is_some_and(|c| !(0.0..=1.0).contains(&c))
+    {
+        return Err(ArgError("--block-confidence must be between 0.0 and 1.0".to_string()).into());
+    }
+
+    if args.max_iters == 0 {
+        return Err(ArgError("--max-iters must be at least 1".to_string()).into());
+    }
+
+    if args.tab_width == 0 || args.tab_width > 16 {
+        return Err(ArgError("--tab-width must be between 1 and 16".to_string()).into());
+    }
+
+    if args.in_place && args.inputs.is_empty() {
+        return Err(ArgError("--in-place requires at least one input file".to_string()).into());
+    }
+
+    let stdin_inputs = args.inputs.iter().filter(|p| is_stdin_path(p)).count();
+    if stdin_inputs > 1 {
+        return Err(ArgError("stdin (-) can only be given once".to_string()).into());
+    }
+    if args.changed_since.is_some() && (stdin_inputs > 0 || args.inputs.is_empty()) {
+        return Err(ArgError(
+            "--changed-since needs file or directory inputs, not stdin".to_string(),
+        )
+        .into());
+    }
+    if args.diff_only.is_some() && (stdin_inputs > 0 || args.inputs.is_empty()) {
+        return Err(
+            ArgError("--diff-only needs file or directory inputs, not stdin".to_string()).into(),
+        );
+    }
+    if stdin_inputs > 0 {
+        if args.in_place || args.watch {
+            return Err(ArgError(
+                "stdin (-) cannot be used with --in-place or --watch".to_string(),
+            )
+            .into());
+        }
+        if args.files_from.as_deref().is_some_and(is_stdin_path) {
+            return Err(ArgError(
+                "stdin (-) cannot be both an input and the --files-from list".to_string(),
+            )
+            .into());
+        }
+    }
+
+    if args.recursive && args.inputs.is_empty() {
+        return Err(ArgError("--recursive requires at least one input path".to_string()).into());
+    }
+
+    if args.stream {
+        if args.inputs.len() > 1 {
+            return Err(ArgError("--stream takes at most one input".to_string()).into());
+        }
+        if args.encoding.is_some_and(|e| e != Encoding::Utf8) {
+            return Err(ArgError("--stream only reads UTF-8 input".to_string()).into());
+        }
+    }
+
+    if args.batch {
+        if !args.inputs.is_empty() {
+            return Err(
+                ArgError("--batch reads from stdin and takes no input files".to_string()).into(),
+            );
+        }
+        parse_separator(&args.separator).map_err(|e| ArgError(format!("--separator: {e}")))?;
+    }
+
+    if let Some(ref chars) = args.alt_borders {
+        parse_alt_borders(chars).map_err(|e| ArgError(format!("--alt-borders: {e}")))?;
+    }
+
+    for report in &args.report {
+        parse_report(report).map_err(|e| ArgError(format!("--report: {e}")))?;
+    }
+
+    if let Some(ref chars) = args.fill_chars {
+        parse_fill_chars(chars).map_err(|e| ArgError(format!("--fill-chars: {e}")))?;
+    }
+
+    if let Some(ref size) = args.max_file_size {
+        parse_byte_size(size).map_err(|e| ArgError(format!("--max-file-size: {e}")))?;
+    }
+
+    if let Some(ref ids) = args.apply_ids {
+        parse_line_ranges(ids).map_err(|e| ArgError(format!("--apply-ids: {e}")))?;
+    }
+
+    if args.max_line_length == Some(0) {
+        return Err(ArgError("--max-line-length must be at least 1".to_string()).into());
+    }
+
+    if args.no_pad_past == Some(0) {
+        return Err(ArgError("--no-pad-past must be at least 1".to_string()).into());
+    }
+
+    if args.max_pad == Some(0) {
+        return Err(ArgError("--max-pad must be at least 1".to_string()).into());
+    }
+
+    if args.jobs == Some(0) {
+        return Err(ArgError("--jobs must be at least 1".to_string()).into());
+    }
+
+    if args.backup_keep == Some(0) {
+        return Err(ArgError("--backup-keep must be at least 1".to_string()).into());
+    }
+
+    Ok(())
+}
+
+/// Statistics collected during correction
+#[derive(Default, Clone)]
+pub struct Stats {
+    /// Number of diagram blocks detected
+    pub blocks_found: usize,
+    /// Number of blocks that received modifications
+    pub blocks_modified: usize,
+    /// Number of blocks skipped (low confidence or outside line ranges)
+    pub blocks_skipped: usize,
+    /// Total number of revisions applied
+    pub total_revisions: usize,
+    /// Number of lines whose revision was refused by `--max-line-length`
+    pub revisions_refused: usize,
+    /// Number of revisions skipped (below min_score threshold)
+    pub revisions_skipped: usize,
+    /// Total number of lines processed
+    pub total_lines: usize,
+    /// Processing elapsed time
+    pub elapsed: Duration,
+    /// Per-block details for a single input (not aggregated by `merge`)
+    blocks: Vec<BlockReport>,
+    /// Applied/skipped revisions by kind and score decile
+    breakdown: RevisionBreakdown,
+    /// Lines (0-based) refused by `--max-line-length`, for a single input
+    /// (not aggregated by `merge`)
+    refused_lines: Vec<usize>,
+    /// Number of line endings converted by `--normalize-eol`
+    eol_changes: usize,
+    /// Lines (0-based) past `--max-line-bytes`, left unanalyzed, for a
+    /// single input (not aggregated by `merge`)
+    long_lines: Vec<usize>,
+    /// Revisions in the order they were proposed, numbered for
+    /// `--apply-ids`, for a single input (not aggregated by `merge`)
+    pub revisions: Vec<RevisionRecord>,
+}
+
+/// A numbered revision from a correction run. IDs are stable for the same
+/// input and options, so a dry run's IDs select revisions in a later run.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RevisionRecord {
+    /// 1-based ID, in proposal order across the input
+    pub id: usize,
+    /// 1-based line number
+    pub line: usize,
+    /// Revision kind (see [`Revision::kind`])
+    pub kind: &'static str,
+    /// Confidence score (see [`Revision::score`])
+    pub score: f64,
+    /// What the revision does, e.g. "pad 2 column(s) before border"
+    pub description: String,
+    /// 1-based visual column of the misaligned character
+    #[serde(skip)]
+    pub column: usize,
+    /// What is wrong with the line, e.g. "right border 2 column(s) short
+    /// of target 40"
+    #[serde(skip)]
+    pub problem: String,
+    /// False when `--apply-ids` left it out
+    pub applied: bool,
+}
+
+/// A change to a single line: delete `delete` characters at `column`, then
+/// insert `insert_text` there. Columns count characters, not display
+/// columns, so multi-byte and wide characters are one column each.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineEdit {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based character column
+    pub column: usize,
+    /// Characters removed
+    pub delete: usize,
+    /// Text inserted in their place
+    pub insert_text: String,
+}
+
+impl LineEdit {
+    /// The smallest edit turning `before` into `after` on line `line_idx`
+    /// (0-based), or None if they are equal
+    fn between(line_idx: usize, before: &str, after: &str) -> Option<Self> {
+        if before == after {
+            return None;
+        }
+        let prefix = before
+            .chars()
+            .zip(after.chars())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let before_rest: Vec<char> = before.chars().skip(prefix).collect();
+        let after_rest: Vec<char> = after.chars().skip(prefix).collect();
+        let suffix = before_rest
+            .iter()
+            .rev()
+            .zip(after_rest.iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        Some(Self {
+            line: line_idx + 1,
+            column: prefix + 1,
+            delete: before_rest.len() - suffix,
+            insert_text: after_rest[..after_rest.len() - suffix].iter().collect(),
+        })
+    }
+
+    /// Apply to `lines`, failing if the edit does not fit its line
+    fn apply(&self, lines: &mut [String]) -> Result<()> {
+        let line = self
+            .line
+            .checked_sub(1)
+            .and_then(|idx| lines.get_mut(idx))
+            .ok_or_else(|| anyhow::anyhow!("line {} is past the end of the file", self.line))?;
+        let mut offsets = line.char_indices().map(|(i, _)| i).chain([line.len()]);
+        let start = self.column.checked_sub(1).and_then(|col| offsets.nth(col));
+        let end = match (start, self.delete) {
+            (Some(start), 0) => Some(start),
+            (Some(_), n) => offsets.nth(n - 1),
+            (None, _) => None,
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            anyhow::bail!(
+                "line {}: column {} (deleting {}) is past the end of the line",
+                self.line,
+                self.column,
+                self.delete
+            );
+        };
+        line.replace_range(start..end, &self.insert_text);
+        Ok(())
+    }
+}
+
+/// Applied and skipped revision counts for one bucket
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+struct RevisionCounts {
+    applied: usize,
+    skipped: usize,
+}
+
+/// Revision counts broken down by kind and by score decile.
+///
+/// Skipped revisions are counted once per iteration they were proposed in,
+/// matching `Stats::revisions_skipped`.
+#[derive(Debug, Default, Clone)]
+struct RevisionBreakdown {
+    /// Counts per revision kind (see [`Revision::kind`])
+    by_kind: BTreeMap<&'static str, RevisionCounts>,
+    /// Counts per score decile; index 0 is [0.0, 0.1), index 9 is [0.9, 1.0].
+    /// Only scored revisions are counted here.
+    by_decile: [RevisionCounts; 10],
+}
+
+impl RevisionBreakdown {
+    /// Record one scored revision
+    fn record(&mut self, kind: &'static str, score: f64, applied: bool) {
+        let decile = ((score * 10.0).floor().max(0.0) as usize).min(9);
+        for counts in [
+            self.by_kind.entry(kind).or_default(),
+            &mut self.by_decile[decile],
+        ] {
+            if applied {
+                counts.applied += 1;
+            } else {
+                counts.skipped += 1;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &RevisionBreakdown) {
+        for (kind, counts) in &other.by_kind {
+            let entry = self.by_kind.entry(kind).or_default();
+            entry.applied += counts.applied;
+            entry.skipped += counts.skipped;
+        }
+        for (mine, theirs) in self.by_decile.iter_mut().zip(&other.by_decile) {
+            mine.applied += theirs.applied;
+            mine.skipped += theirs.skipped;
+        }
+    }
+}
+
+/// Location and identity of a detected block after correction
+#[derive(Debug, Clone)]
+struct BlockReport {
+    /// Starting line index (0-based, inclusive)
+    start: usize,
+    /// Ending line index (exclusive)
+    end: usize,
+    /// Content fingerprint, see [`block_fingerprint`]
+    fingerprint: String,
+    /// Name from an `aadc:block name=...` annotation
+    name: Option<String>,
+    /// Detection confidence (0.0-1.0)
+    confidence: f64,
+    /// Boxes or a file tree
+    kind: BlockKind,
+    /// Dominant border style, if the block has recognizable borders
+    style: Option<BorderStyle>,
+    /// Right-border column the block was aligned to (1-based)
+    target_column: Option<usize>,
+    status: BlockStatus,
+    /// Numbered revisions proposed for this block (see `Stats::revisions`)
+    revisions: Vec<RevisionRecord>,
+    /// Revisions left unapplied for scoring below the threshold
+    skipped: Vec<SkippedRevision>,
+}
+
+/// What a correction run did with a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum BlockStatus {
+    /// At least one revision was applied
+    Modified,
+    /// Processed, nothing to change
+    Unchanged,
+    /// Outside `--lines` or not the `--block-name` block
+    Skipped,
+}
+
+/// A revision scored below the block's threshold. Reported from the last
+/// iteration, so each is listed once.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+struct SkippedRevision {
+    /// 1-based line number
+    line: usize,
+    /// Revision kind (see [`Revision::kind`])
+    kind: &'static str,
+    score: f64,
+    description: String,
+}
+
+impl Stats {
+    /// Merge another Stats into this one (for aggregating across files)
+    fn merge(&mut self, other: &Stats) {
+        self.blocks_found += other.blocks_found;
+        self.blocks_modified += other.blocks_modified;
+        self.blocks_skipped += other.blocks_skipped;
+        self.total_revisions += other.total_revisions;
+        self.revisions_refused += other.revisions_refused;
+        self.revisions_skipped += other.revisions_skipped;
+        self.eol_changes += other.eol_changes;
+        self.total_lines += other.total_lines;
+        self.elapsed += other.elapsed;
+        self.breakdown.merge(&other.breakdown);
+    }
+
+    /// Calculate lines processed per second
+    fn lines_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.total_lines as f64 / secs
+        } else {
+            self.total_lines as f64
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// EditorConfig
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Settings from `.editorconfig` files that apply to one file
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct EditorConfig {
+    /// `tab_width`, else a numeric `indent_size`
+    tab_width: Option<usize>,
+    /// `charset` (`utf-8`, `utf-8-bom`, `latin1`, ...)
+    charset: Option<String>,
+}
+
+/// Resolve the `.editorconfig` settings for `path`. Files are read from
+/// the file's directory upwards until one declares `root = true`; nearer
+/// files and later sections win. Unreadable files are ignored.
+fn resolve_editorconfig(path: &Path) -> EditorConfig {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut levels = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let Ok(content) = fs::read_to_string(dir.join(".editorconfig")) else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let (root, properties) = editorconfig_properties(&content, relative);
+        levels.push(properties);
+        if root {
+            break;
+        }
+    }
+
+    let mut properties = BTreeMap::new();
+    for level in levels.into_iter().rev() {
+        properties.extend(level);
+    }
+    let width = |key: &str| {
+        properties
+            .get(key)
+            .and_then(|v: &String| v.parse::<usize>().ok())
+            .filter(|w| (1..=16).contains(w))
+    };
+    EditorConfig {
+        tab_width: width("tab_width").or_else(|| width("indent_size")),
+        charset: properties.get("charset").cloned(),
+    }
+}
+
+/// Parse one `.editorconfig` file: whether it is the root, and the
+/// properties (lowercased, in file order) of the sections matching
+/// `relative`, the file's path relative to the `.editorconfig` directory
+fn editorconfig_properties(content: &str, relative: &Path) -> (bool, Vec<(String, String)>) {
+    let mut root = false;
+    let mut properties = Vec::new();
+    // None in the preamble, before the first section
+    let mut matching = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            matching = Some(editorconfig_section_matches(pattern, relative));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_lowercase();
+        match matching {
+            None if key == "root" => root = value == "true",
+            Some(true) => properties.push((key, value)),
+            _ => {}
+        }
+    }
+    (root, properties)
+}
+
+/// Check a section glob against a relative path: globs without a `/`
+/// match the file name in any directory, others are anchored at the
+/// `.editorconfig` directory
+fn editorconfig_section_matches(pattern: &str, relative: &Path) -> bool {
+    let pattern = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{}", pattern),
+    };
+    GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .is_ok_and(|glob| glob.compile_matcher().is_match(relative))
+}
+
+/// The configuration for one input: `config` with the input's
+/// `.editorconfig` tab width and charset (BOM, and encoding unless
+/// `--encoding` is given) applied ("-" stands for stdin,
+/// looked up as `--stdin-filename` if given)
+fn config_for_path<'a>(path: &Path, config: &'a Config) -> Cow<'a, Config> {
+    let path = if is_stdin_path(path) {
+        match config.stdin_filename {
+            Some(ref name) => name.as_path(),
+            None => return Cow::Borrowed(config),
+        }
+    } else {
+        path
+    };
+    if !config.editorconfig {
+        return Cow::Borrowed(config);
+    }
+
+    let editorconfig = resolve_editorconfig(path);
+    let tab_width = editorconfig
+        .tab_width
+        .filter(|&w| !config.tab_width_pinned && w != config.tab_width);
+    let write_bom = editorconfig.charset.as_deref() == Some("utf-8-bom");
+    let encoding = editorconfig
+        .charset
+        .as_deref()
+        .and_then(Encoding::from_charset)
+        .filter(|&e| config.encoding.is_none() && e != Encoding::Utf8);
+    if tab_width.is_none() && !write_bom && encoding.is_none() {
+        return Cow::Borrowed(config);
+    }
+    let mut file_config = config.clone();
+    if let Some(width) = tab_width {
+        file_config.tab_width = width;
+    }
+    if encoding.is_some() {
+        file_config.encoding = encoding;
+    }
+    file_config.write_bom = write_bom;
+    Cow::Owned(file_config)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Modelines
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Lines at each end of a file searched for a modeline (Vim's default)
+const MODELINE_LINES: usize = 5;
+
+/// Tab width declared by a Vim (`vim: ts=8`, `vim: set tabstop=8:`) or
+/// Emacs (`-*- tab-width: 8 -*-`, `tab-width: 8` in a Local Variables
+/// list) modeline in the first or last lines. The last one found wins.
+fn modeline_tab_width(lines: &[String]) -> Option<usize> {
+    let tail = lines.len().saturating_sub(MODELINE_LINES);
+    // Emacs reads Local Variables lists near the end of a file only
+    let local_variables = lines[lines.len().saturating_sub(MODELINE_LINES * 4)..]
+        .iter()
+        .any(|line| line.contains("Local Variables:"));
+
+    lines
+        .iter()
+        .enumerate()
+        .filter(|&(idx, _)| idx < MODELINE_LINES || idx >= tail)
+        .rev()
+        .find_map(|(idx, line)| {
+            vim_modeline_tab_width(line)
+                .or_else(|| emacs_modeline_tab_width(line))
+                .or_else(|| {
+                    (idx >= tail && local_variables)
+                        .then(|| emacs_variable_tab_width(line))
+                        .flatten()
+                })
+                .filter(|w| (1..=16).contains(w))
+        })
+}
+
+/// `ts=N` / `tabstop=N` from a `vi:`, `vim:` or `ex:` modeline
+fn vim_modeline_tab_width(line: &str) -> Option<usize> {
+    let options = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        let at = line.find(marker)?;
+        let preceded = line[..at].ends_with(char::is_whitespace);
+        (at == 0 || preceded).then(|| &line[at + marker.len()..])
+    })?;
+    options
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .rev()
+        .find_map(|option| {
+            let (name, value) = option.split_once('=')?;
+            matches!(name, "ts" | "tabstop").then(|| value.parse().ok())?
+        })
+}
+
+/// `tab-width: N` between `-*-` markers
+fn emacs_modeline_tab_width(line: &str) -> Option<usize> {
+    let (_, rest) = line.split_once("-*-")?;
+    let (variables, _) = rest.split_once("-*-")?;
+    variables.split(';').find_map(emacs_variable_tab_width)
+}
+
+/// `tab-width: N`, as written in an Emacs Local Variables list
+fn emacs_variable_tab_width(text: &str) -> Option<usize> {
+    let (_, value) = text.split_once("tab-width:")?;
+    value.split_whitespace().next()?.parse().ok()
+}
+
+/// The configuration for one file's content: `config` with the tab width
+/// its modeline declares (unless `--tab-width` was given)
+fn config_for_lines<'a>(lines: &[String], config: &'a Config) -> Cow<'a, Config> {
+    if !config.modelines || config.tab_width_pinned {
+        return Cow::Borrowed(config);
+    }
+    match modeline_tab_width(lines) {
+        Some(width) if width != config.tab_width => Cow::Owned(Config {
+            tab_width: width,
+            ..config.clone()
+        }),
+        _ => Cow::Borrowed(config),
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Quick Scan (Passthrough Optimization)
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Minimum fraction of lines that must contain box-drawing chars to run full processing.
+const QUICK_SCAN_THRESHOLD: f64 = 0.01; // 1%
+
+/// Maximum number of lines to scan when deciding whether to process.
+const QUICK_SCAN_LIMIT: usize = 1000;
+
+/// Default `--max-line-bytes`: far wider than any diagram, small enough
+/// that a pasted minified blob is never measured or padded
+const DEFAULT_MAX_LINE_BYTES: usize = 16 * 1024;
+
+/// Summary of a quick scan decision for diagram detection.
+#[derive(Debug)]
+struct QuickScanResult {
+    lines_scanned: usize,
+    lines_with_box_chars: usize,
+    ratio: f64,
+    likely_has_diagrams: bool,
+}
+
+/// Quickly scan input lines to decide whether full processing is necessary.
+fn quick_scan_for_diagrams(lines: &[String]) -> QuickScanResult {
+    let mut lines_scanned = 0;
+    let mut lines_with_box_chars = 0;
+
+    for line in lines.iter().take(QUICK_SCAN_LIMIT) {
+        lines_scanned += 1;
+        if line.chars().any(is_box_char) {
+            lines_with_box_chars += 1;
+        }
+    }
+
+    let ratio = if lines_scanned > 0 {
+        lines_with_box_chars as f64 / lines_scanned as f64
+    } else {
+        0.0
+    };
+
+    let likely_has_diagrams = ratio >= QUICK_SCAN_THRESHOLD;
+
+    QuickScanResult {
+        lines_scanned,
+        lines_with_box_chars,
+        ratio,
+        likely_has_diagrams,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// JSON Output Structures
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Version of the `--json` correction output. 2.0 added per-block detail
+/// (confidence, style, target column, status, revisions).
+const JSON_FORMAT_VERSION: &str = "2.0";
+
+/// `--json` output: one input's object, or `files` and a `summary` for
+/// several inputs. `--json-lines` prints input objects one per line.
+#[derive(Serialize, JsonSchema)]
+#[serde(untagged)]
+enum JsonDocument {
+    File(JsonFile),
+    Run(JsonRunOutput),
+}
+
+/// `--json` object for one input, told apart by `status`
+#[derive(Serialize, JsonSchema)]
+#[serde(untagged)]
+enum JsonFile {
+    Processed(Box<JsonOutput>),
+    Failed(JsonFailure),
+}
+
+/// `--json` object for an input that could not be read or corrected
+#[derive(Serialize, JsonSchema)]
+struct JsonFailure {
+    version: &'static str,
+    /// Always `error`
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    error: JsonError,
+}
+
+impl JsonFailure {
+    fn of(path: &Path, err: &anyhow::Error) -> Self {
+        Self {
+            version: JSON_FORMAT_VERSION,
+            status: "error",
+            file: (!is_stdin_path(path)).then(|| path.display().to_string()),
+            error: JsonError::of(err, None),
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonOutput {
+    version: &'static str,
+    /// `success`, `dry_run`, or `no_diagrams` with `--require-diagrams`
+    /// (`error` entries are failures instead)
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    input: InputStats,
+    processing: ProcessingStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<OutputStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    blocks: Vec<JsonBlock>,
+    /// Numbered revisions, selectable with `--apply-ids`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    revisions: Vec<RevisionRecord>,
+    /// Line edits turning the input into the corrected text, one per
+    /// changed line, positioned in the input
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    edits: Vec<JsonEdit>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonEdit {
+    line: usize,
+    column: usize,
+    delete: usize,
+    insert_text: String,
+    /// Kind of the first revision applied to the line (absent for changes
+    /// no revision makes, such as tab expansion)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<f64>,
+    /// ID of the revision (see `revisions`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revision: Option<usize>,
+}
+
+/// `JsonOutput::edits` for a result: the smallest change on each line
+/// edited in place, computed on the original text (as for LSP edits) so
+/// tab expansion is included. Added lines have no line edit.
+fn json_edits(result: &FileResult) -> Vec<JsonEdit> {
+    let (original, corrected) = (&result.original, &result.corrected);
+    let mut edits = Vec::new();
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, original, corrected) {
+        let (old, new) = (op.old_range(), op.new_range());
+        if op.tag() != DiffTag::Replace || old.len() != new.len() {
+            continue;
+        }
+        for (i, j) in old.zip(new) {
+            let Some(edit) = LineEdit::between(i, &original[i], &corrected[j]) else {
+                continue;
+            };
+            let rev = result
+                .stats
+                .revisions
+                .iter()
+                .find(|rev| rev.applied && rev.line == j + 1);
+            edits.push(JsonEdit {
+                line: edit.line,
+                column: edit.column,
+                delete: edit.delete,
+                insert_text: edit.insert_text,
+                revision_type: rev.map(|rev| rev.kind),
+                score: rev.map(|rev| rev.score),
+                revision: rev.map(|rev| rev.id),
+            });
+        }
+    }
+    edits
+}
+
+/// `--json` output for several inputs: the per-file objects in input
+/// order, then totals across them
+#[derive(Serialize, JsonSchema)]
+struct JsonRunOutput {
+    version: &'static str,
+    files: Vec<JsonFile>,
+    summary: JsonSummary,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonSummary {
+    files_processed: usize,
+    files_changed: usize,
+    /// Inputs that could not be processed (`error` entries in `files`)
+    files_failed: usize,
+    processing: ProcessingStats,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonBlock {
+    /// 1-based inclusive line range
+    start_line: usize,
+    end_line: usize,
+    fingerprint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    confidence: f64,
+    kind: BlockKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    style: Option<BorderStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_column: Option<usize>,
+    status: BlockStatus,
+    revisions: Vec<RevisionRecord>,
+    skipped: Vec<SkippedRevision>,
+}
+
+impl JsonBlock {
+    fn from_report(report: &BlockReport) -> Self {
+        Self {
+            start_line: report.start + 1,
+            end_line: report.end,
+            fingerprint: report.fingerprint.clone(),
+            name: report.name.clone(),
+            confidence: report.confidence,
+            kind: report.kind,
+            style: report.style,
+            target_column: report.target_column,
+            status: report.status,
+            revisions: report.revisions.clone(),
+            skipped: report.skipped.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct InputStats {
+    lines: usize,
+    bytes: usize,
+    /// FNV-1a hash of the file's bytes, checked by `aadc apply` (not set
+    /// for stdin)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ProcessingStats {
+    blocks_detected: usize,
+    blocks_modified: usize,
+    revisions_applied: usize,
+    revisions_skipped: usize,
+    revisions_refused: usize,
+    /// Line endings converted by `--normalize-eol`
+    eol_changes: usize,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    revision_kinds: BTreeMap<&'static str, RevisionCounts>,
+    /// The same counts keyed by the lint rule each revision fixes
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    revision_rules: BTreeMap<&'static str, RevisionCounts>,
+    /// Non-empty score deciles only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    score_deciles: Vec<JsonDecile>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct JsonDecile {
+    /// Score range, e.g. "0.8-0.9"
+    range: String,
+    applied: usize,
+    skipped: usize,
+}
+
+impl ProcessingStats {
+    fn from_stats(stats: &Stats) -> Self {
+        let mut revision_rules: BTreeMap<&'static str, RevisionCounts> = BTreeMap::new();
+        for (kind, counts) in &stats.breakdown.by_kind {
+            if let Some(rule) = rule_for_kind(kind) {
+                let entry = revision_rules.entry(rule.id).or_default();
+                entry.applied += counts.applied;
+                entry.skipped += counts.skipped;
+            }
+        }
+        Self {
+            blocks_detected: stats.blocks_found,
+            blocks_modified: stats.blocks_modified,
+            revisions_applied: stats.total_revisions,
+            revisions_skipped: stats.revisions_skipped,
+            revisions_refused: stats.revisions_refused,
+            eol_changes: stats.eol_changes,
+            revision_kinds: stats.breakdown.by_kind.clone(),
+            revision_rules,
+            score_deciles: stats
+                .breakdown
+                .by_decile
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| c.applied + c.skipped > 0)
+                .map(|(i, c)| JsonDecile {
+                    range: format!("{:.1}-{:.1}", i as f64 / 10.0, (i + 1) as f64 / 10.0),
+                    applied: c.applied,
+                    skipped: c.skipped,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
+struct OutputStats {
+    lines: usize,
+    bytes: usize,
+    changed: bool,
+}
+
+/// Print the JSON Schema of `--json` output, generated from the structs
+/// above so it cannot drift from what is written
+fn print_json_schema() -> Result<()> {
+    let mut schema = schemars::schema_for!(JsonDocument);
+    schema.insert(
+        "title".to_string(),
+        format!("aadc --json output {}", JSON_FORMAT_VERSION).into(),
+    );
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+/// Manifest written by `aadc extract --manifest`
+#[derive(Serialize)]
+struct ExtractManifest {
+    version: &'static str,
+    source: String,
+    blocks: Vec<ExtractedBlock>,
+}
+
+#[derive(Serialize)]
+struct ExtractedBlock {
+    /// 1-based block index, as used by `aadc inject --block`
+    index: usize,
+    file: String,
+    /// 1-based inclusive line range in the source file
+    start_line: usize,
+    end_line: usize,
+    confidence: f64,
+    fingerprint: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Line Classification
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Classification of a line's role in a diagram.
+///
+/// Lines are classified based on the presence and type of box-drawing
+/// characters. This classification drives revision generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// Empty or whitespace-only line.
+    ///
+    /// Blank lines may separate logical sections within a diagram.
+    Blank,
+
+    /// A line with no detected diagram structure.
+    ///
+    /// These lines are passed through unchanged.
+    None,
+
+    /// A line with vertical borders but no horizontal structure.
+    ///
+    /// Weak lines form the content rows of boxes:
+    /// ```text
+    /// | Content  |   ← Weak (vertical borders only)
+    /// │ データ   │   ← Weak (Unicode vertical)
+    /// ```
+    Weak,
+
+    /// A line with strong horizontal structure.
+    ///
+    /// Strong lines typically form the top/bottom borders of boxes:
+    /// ```text
+    /// +----------+   ← Strong (corners + horizontal runs)
+    /// ┌──────────┐   ← Strong (Unicode corners + horizontal)
+    /// ```
+    Strong,
+
+    /// An arrow between boxes rather than part of one.
+    ///
+    /// Connectors keep a diagram's boxes in one block but are never given
+    /// a right border:
+    /// ```text
+    /// A --> B         ← Connector (shaft and head)
+    /// <---+           ← Connector (the arrow runs into the corner)
+    /// ```
+    Connector,
+}
+
+impl LineKind {
+    fn is_boxy(self) -> bool {
+        matches!(self, Self::Weak | Self::Strong)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Box Drawing Character Detection
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Check if character is a corner piece (ASCII or Unicode)
+fn is_corner(c: char) -> bool {
+    matches!(
+        c,
+        '+' | '┌'
+            | '┐'
+            | '└'
+            | '┘'
+            | '╔'
+            | '╗'
+            | '╚'
+            | '╝'
+            | '╭'
+            | '╮'
+            | '╯'
+            | '╰'
+            | '┏'
+            | '┓'
+            | '┗'
+            | '┛'
+    )
+}
+
+/// Check if character is a horizontal fill (for borders)
+fn is_horizontal_fill(c: char) -> bool {
+    matches!(
+        c,
+        '-' | '─' | '━' | '═' | '╌' | '╍' | '┄' | '┅' | '┈' | '┉' | '~' | '='
+    )
+}
+
+/// Check if character is a vertical border
+fn is_vertical_border(c: char) -> bool {
+    matches!(c, '|' | '│' | '┃' | '║' | '╎' | '╏' | '┆' | '┇' | '┊' | '┋')
+}
+
+/// Check if character is a T-junction
+fn is_junction(c: char) -> bool {
+    matches!(
+        c,
+        '┬' | '┴'
+            | '├'
+            | '┤'
+            | '┼'
+            | '╦'
+            | '╩'
+            | '╠'
+            | '╣'
+            | '╬'
+            | '╤'
+            | '╧'
+            | '╟'
+            | '╢'
+            | '╫'
+            | '╪'
+    )
+}
+
+/// Check if character is an arrow head
+fn is_arrow_head(c: char) -> bool {
+    matches!(c, '>' | '<' | '▶' | '◀' | '►' | '◄' | '▷' | '◁')
+}
+
+/// Check if a trimmed line is an arrow between boxes: it has a head on a
+/// shaft of at least two horizontal fill characters (`-->`, `◀──`), and
+/// does not end in a border, unless that border is where its shaft starts
+/// (`<---+`). Box rows (`| a --> b |`) and border rows with an arrow
+/// between two boxes (`+--+ --> +--+`) are not connectors.
+fn is_connector(trimmed: &str) -> bool {
+    let chars: Vec<char> = trimmed.chars().collect();
+    let shaft_at = |from: usize, step: isize| {
+        (1..=2).all(|n| {
+            let idx = from as isize + step * n;
+            usize::try_from(idx)
+                .ok()
+                .and_then(|i| chars.get(i))
+                .is_some_and(|&c| is_horizontal_fill(c))
+        })
+    };
+    let has_arrow = chars
+        .iter()
+        .enumerate()
+        .any(|(i, &c)| is_arrow_head(c) && (shaft_at(i, -1) || shaft_at(i, 1)));
+    let (Some(&first), Some(&last)) = (chars.first(), chars.last()) else {
+        return false;
+    };
+    if !has_arrow || (is_vertical_border(first) && is_vertical_border(last)) {
+        return false;
+    }
+    if !is_border_char(last) {
+        return true;
+    }
+    // A closing border only belongs to the arrow if its shaft leads
+    // straight back to a head
+    let shaft_end = chars[..chars.len() - 1]
+        .iter()
+        .rposition(|&c| !is_horizontal_fill(c));
+    shaft_end.is_some_and(|i| i + 3 < chars.len() && is_arrow_head(chars[i]))
+}
+
+/// Check if character is a diagonal connector (branching flows, tree edges)
+fn is_diagonal(c: char) -> bool {
+    matches!(c, '/' | '\\' | '╱' | '╲' | '╳')
+}
+
+/// Check if character could be part of a box drawing
+fn is_box_char(c: char) -> bool {
+    is_corner(c) || is_horizontal_fill(c) || is_vertical_border(c) || is_junction(c)
+}
+
+/// Check if character can terminate a line border
+fn is_border_char(c: char) -> bool {
+    is_vertical_border(c) || is_corner(c) || is_junction(c)
+}
+
+/// Detect the most common vertical border character in a set of lines
+fn detect_vertical_border(lines: &[&str]) -> char {
+    let mut counts = std::collections::HashMap::new();
+
+    for line in lines {
+        for c in line.chars() {
+            if is_vertical_border(c) {
+                *counts.entry(c).or_insert(0) += 1;
+            }
+        }
+    }
+
+    // Default to ASCII pipe if no Unicode detected
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(c, _)| c)
+        .unwrap_or('|')
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Line Analysis
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Result of analyzing a single line for diagram structure.
+///
+/// Contains extracted properties used for revision generation:
+/// - The line's classification (Strong, Weak, Blank, None)
+/// - Visual width accounting for CJK and other wide characters
+/// - Suffix border position and character if detected
+#[derive(Debug)]
+pub struct AnalyzedLine {
+    /// The original line content (unmodified)
+    #[allow(dead_code)]
+    content: String,
+
+    /// Classification of the line based on box-drawing characters
+    kind: LineKind,
+
+    /// Visual width in terminal columns (CJK chars count as 2)
+    visual_width: usize,
+
+    /// Number of leading space characters
+    indent: usize,
+
+    /// Detected right-side border information, if any
+    suffix_border: Option<SuffixBorder>,
+
+    /// True if a diagonal (`/`, `\`) ends right at the border, or at the end
+    /// of a line without one; moving the border would detach it
+    diagonal_at_border: bool,
+}
+
+impl AnalyzedLine {
+    /// Classification of the line
+    pub fn kind(&self) -> LineKind {
+        self.kind
+    }
+
+    /// Visual width in terminal columns
+    pub fn visual_width(&self) -> usize {
+        self.visual_width
+    }
+
+    /// Number of leading spaces
+    pub fn indent(&self) -> usize {
+        self.indent
+    }
+
+    /// Visual column (0-based) and character of the right-hand border, if
+    /// the line has one
+    pub fn suffix_border(&self) -> Option<(usize, char)> {
+        self.suffix_border
+            .as_ref()
+            .map(|border| (border.column, border.char))
+    }
+
+    /// Column of the left border of a box row: the first character after
+    /// the indent, when it is a border and the line closes further right.
+    /// A lone `|` is a connector, not a box edge.
+    fn left_border_column(&self) -> Option<usize> {
+        let first = self.content[self.indent..].chars().next()?;
+        let closed = self
+            .suffix_border
+            .as_ref()
+            .is_some_and(|border| border.column > self.indent);
+        (is_border_char(first) && closed).then_some(self.indent)
+    }
+}
+
+/// Information about a detected right-side border character.
+///
+/// Used to determine the target column for alignment and to
+/// generate revisions that pad lines to match.
+#[derive(Debug, Clone)]
+struct SuffixBorder {
+    /// Visual column position where the border appears (0-indexed)
+    column: usize,
+
+    /// The actual border character (`|`, `│`, or an alternative border)
+    char: char,
+
+    /// True if this appears to be a closing border (end of content),
+    /// false if it's a mid-line separator
+    #[allow(dead_code)]
+    is_closing: bool,
+}
+
+/// Calculate the visual width of a single character in terminal columns,
+/// from the Unicode East Asian Width tables (`unicode-width`).
+///
+/// - Wide and fullwidth characters (CJK, most emoji): 2 columns
+/// - Combining marks, zero-width characters and controls: 0 columns
+/// - Everything else, including box drawing, arrows, halfwidth katakana
+///   and ambiguous-width characters: 1 column
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Visual width of one extended grapheme cluster. A multi-codepoint glyph
+/// (emoji ZWJ sequence, flag, keycap, base character with combining marks)
+/// is drawn as one glyph, so it is measured as one.
+fn grapheme_width(grapheme: &str) -> usize {
+    grapheme.width()
+}
+
+/// Length in bytes of the ANSI escape sequence at the start of `s`, if one
+/// starts there: CSI (`ESC [` ... final byte, e.g. SGR colors), OSC
+/// (`ESC ]` ... BEL or `ESC \`, e.g. hyperlinks) or a two-byte escape
+fn ansi_escape_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    if bytes.first() != Some(&0x1b) {
+        return None;
+    }
+    match *bytes.get(1)? {
+        b'[' => {
+            let end = 2 + bytes[2..].iter().position(|b| !(0x20..=0x3f).contains(b))?;
+            (0x40..=0x7e).contains(&bytes[end]).then_some(end + 1)
+        }
+        b']' => {
+            let mut i = 2;
+            while i < bytes.len() {
+                match bytes[i] {
+                    0x07 => return Some(i + 1),
+                    0x1b if bytes.get(i + 1) == Some(&b'\\') => return Some(i + 2),
+                    _ => i += 1,
+                }
+            }
+            None
+        }
+        0x40..=0x5f => Some(2),
+        _ => None,
+    }
+}
+
+/// Display units of a string as (byte offset, unit): ANSI escape sequences,
+/// which take no columns, and the grapheme clusters of the text around them
+fn display_units(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut pos = 0;
+    let mut text: Option<(usize, unicode_segmentation::GraphemeIndices<'_>)> = None;
+    std::iter::from_fn(move || {
+        loop {
+            if let Some((offset, graphemes)) = text.as_mut() {
+                if let Some((idx, grapheme)) = graphemes.next() {
+                    return Some((*offset + idx, grapheme));
+                }
+                text = None;
+            }
+            let rest = &s[pos..];
+            let first = rest.chars().next()?;
+            if let Some(len) = ansi_escape_len(rest) {
+                pos += len;
+                return Some((pos - len, &rest[..len]));
+            }
+            let len = rest[first.len_utf8()..]
+                .find('\x1b')
+                .map_or(rest.len(), |i| i + first.len_utf8());
+            text = Some((pos, rest[..len].grapheme_indices(true)));
+            pos += len;
+        }
+    })
+}
+
+/// Visual width of a display unit (escape sequences are invisible)
+fn unit_width(unit: &str) -> usize {
+    if unit.starts_with('\x1b') {
+        0
+    } else {
+        grapheme_width(unit)
+    }
+}
+
+/// Display units of a string as (byte offset, visual column, unit)
+fn grapheme_columns(s: &str) -> impl Iterator<Item = (usize, usize, &str)> {
+    let mut column = 0;
+    display_units(s).map(move |(idx, unit)| {
+        let start = column;
+        column += unit_width(unit);
+        (idx, start, unit)
+    })
+}
+
+/// A line with its ANSI escape sequences removed
+fn strip_ansi(line: &str) -> Cow<'_, str> {
+    if !line.contains('\x1b') {
+        return Cow::Borrowed(line);
+    }
+    Cow::Owned(
+        display_units(line)
+            .filter(|(_, unit)| !unit.starts_with('\x1b'))
+            .map(|(_, unit)| unit)
+            .collect(),
+    )
+}
+
+/// Split a line after its last visible character: the text through it,
+/// and the whitespace and ANSI escape sequences (a color reset after a
+/// right border) trailing it
+fn split_visible_end(line: &str) -> (&str, &str) {
+    let trimmed = line.trim_end();
+    if !trimmed.contains('\x1b') {
+        return line.split_at(trimmed.len());
+    }
+    let end = display_units(trimmed)
+        .filter(|(_, unit)| !unit.starts_with('\x1b') && !unit.trim().is_empty())
+        .last()
+        .map_or(0, |(idx, unit)| idx + unit.len());
+    line.split_at(end)
+}
+
+/// Calculate the visual width of a string in terminal columns.
+///
+/// Handles different character widths:
+/// - ASCII characters: 1 column each
+/// - CJK characters (Chinese, Japanese, Korean): 2 columns each
+/// - Emoji and other wide Unicode: 2 columns each
+/// - Multi-codepoint glyphs: measured once per grapheme cluster
+/// - ANSI escape sequences (terminal colors, hyperlinks): 0 columns
+///
+/// # Examples
+///
+/// ```text
+/// visual_width("Hello")     == 5   // ASCII only
+/// visual_width("你好")      == 4   // CJK (2 chars × 2 columns)
+/// visual_width("Hello世界") == 9   // 5 ASCII + 2 CJK chars
+/// visual_width("👨‍👩‍👧")       == 2   // one ZWJ sequence, five code points
+/// visual_width("\x1b[31mok\x1b[0m") == 2
+/// ```
+///
+/// This is critical for correct padding calculations in diagrams.
+fn visual_width(s: &str) -> usize {
+    display_units(s).map(|(_, unit)| unit_width(unit)).sum()
+}
+
+/// Classify a single line
+fn classify_line(line: &str) -> LineKind {
+    let trimmed = line.trim();
+
+    if trimmed.is_empty() {
+        return LineKind::Blank;
+    }
+
+    let box_chars: usize = trimmed.chars().filter(|&c| is_box_char(c)).count();
+    let total_chars = trimmed.chars().count();
+
+    if box_chars == 0 {
+        return LineKind::None;
+    }
+
+    if is_connector(trimmed) {
+        return LineKind::Connector;
+    }
+
+    // Check for strong indicators
+    let has_corner = trimmed.chars().any(is_corner);
+    let starts_with_border = trimmed.chars().next().is_some_and(is_border_char);
+    let ends_with_border = trimmed.chars().next_back().is_some_and(is_border_char);
+
+    // Strong: has corners, or starts AND ends with border chars, or high ratio
+    if has_corner || (starts_with_border && ends_with_border) || box_chars * 3 >= total_chars {
+        LineKind::Strong
+    } else if box_chars > 0 {
+        LineKind::Weak
+    } else {
+        LineKind::None
+    }
+}
+
+/// Characters that may be enabled as alternative vertical borders.
+///
+/// Older ASCII art draws vertical edges with `:` (dashed) or `!` (broken
+/// bar). Both are common punctuation, so they are opt-in and only count
+/// when they frame a line on both sides.
+const ALT_BORDER_CHARS: &[char] = &[':', '!'];
+
+/// Parse an alternative border list like ":!" (commas and spaces ignored)
+fn parse_alt_borders(s: &str) -> Result<Vec<char>, String> {
+    let mut chars = Vec::new();
+    for c in s.chars().filter(|c| !c.is_whitespace() && *c != ',') {
+        if !ALT_BORDER_CHARS.contains(&c) {
+            return Err(format!(
+                "unsupported border character '{c}' (supported: ':' and '!')"
+            ));
+        }
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    Ok(chars)
+}
+
+/// Return the alternative border that frames a line on both sides, if any.
+///
+/// `: text :` is framed by `:`; a lone trailing colon (`Note:`) is not.
+fn alt_border_frame(line: &str, alt_borders: &[char]) -> Option<char> {
+    let trimmed = line.trim();
+    let first = trimmed.chars().next()?;
+    let last = trimmed.chars().next_back()?;
+    let framed = first == last && trimmed.chars().count() >= 3;
+    (framed && alt_borders.contains(&first)).then_some(first)
+}
+
+/// Classify a line, also accepting enabled alternative borders.
+///
+/// A line framed by an alternative border is at most Weak, so such lines
+/// only join blocks anchored by real box-drawing lines.
+fn classify_line_with(line: &str, opts: &DetectOptions) -> LineKind {
+    // Annotation comments (`<!-- aadc:... -->`) are never diagram content,
+    // and overlong lines are not worth scanning
+    if opts.too_long(line) || parse_directive(line).is_some() {
+        return LineKind::None;
+    }
+    let line = &*strip_ansi(line);
+    let kind = classify_line(line);
+    if kind == LineKind::None && alt_border_frame(line, &opts.alt_borders).is_some() {
+        LineKind::Weak
+    } else {
+        kind
+    }
+}
+
+/// Analyze a line for correction
+pub fn analyze_line(line: &str, opts: &DetectOptions) -> AnalyzedLine {
+    let kind = classify_line_with(line, opts);
+    // Overlong lines are never padded, so their byte length stands in for
+    // the width rather than measuring them
+    let visual = if opts.too_long(line) {
+        line.len()
+    } else {
+        visual_width(line)
+    };
+    let indent = line.len() - line.trim_start().len();
+
+    // Detect suffix border
+    let suffix_border = if kind.is_boxy() {
+        detect_suffix_border(line).or_else(|| detect_alt_suffix_border(line, opts))
+    } else {
+        None
+    };
+
+    // Look at the character just inside the border (or the last character)
+    let mut tail = split_visible_end(line).0.chars().rev();
+    if suffix_border.is_some() {
+        tail.next();
+    }
+    let diagonal_at_border = tail.next().is_some_and(is_diagonal);
+
+    AnalyzedLine {
+        content: line.to_string(),
+        kind,
+        visual_width: visual,
+        indent,
+        suffix_border,
+        diagonal_at_border,
+    }
+}
+
+/// Detect a right-side border in a line
+fn detect_suffix_border(line: &str) -> Option<SuffixBorder> {
+    let (trimmed, _) = split_visible_end(line);
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let last_char = trimmed.chars().next_back()?;
+
+    if is_border_char(last_char) {
+        let prefix = &trimmed[..trimmed.len() - last_char.len_utf8()];
+        let column = visual_width(prefix);
+        Some(SuffixBorder {
+            column,
+            char: last_char,
+            is_closing: is_corner(last_char) || is_junction(last_char),
+        })
+    } else {
+        None
+    }
+}
+
+/// Default leader fill characters: dots, middle dots, ellipses, em dashes
+const DEFAULT_FILL_CHARS: &str = ".·…—";
+
+/// Minimum run length for a fill character to count as a leader
+const MIN_FILL_RUN: usize = 3;
+
+/// Parse a fill character list like ".·…" (spaces and commas ignored)
+fn parse_fill_chars(s: &str) -> Result<Vec<char>, String> {
+    let mut chars = Vec::new();
+    for c in s.chars().filter(|c| !c.is_whitespace() && *c != ',') {
+        if is_border_char(c) {
+            return Err(format!(
+                "unsupported fill character '{c}' (border characters cannot be fill)"
+            ));
+        }
+        if char_width(c) == 0 {
+            return Err(format!(
+                "unsupported fill character U+{:04X} (zero-width characters cannot be fill)",
+                c as u32
+            ));
+        }
+        if !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    Ok(chars)
+}
+
+/// Find the last leader run in a bordered row (`| name ..... value |`).
+///
+/// Returns the fill character and the byte offset just past the run. A run
+/// only counts as a leader when content follows it before the border, so
+/// trailing ellipses (`loading... |`) are left alone.
+fn find_fill_run(line: &str, fill_chars: &[char]) -> Option<(char, usize)> {
+    let (trimmed, _) = split_visible_end(line);
+    let last = trimmed.chars().next_back()?;
+    let body = &trimmed[..trimmed.len() - last.len_utf8()];
+
+    let mut found = None;
+    let mut run: Option<(char, usize)> = None;
+    for (idx, c) in body
+        .char_indices()
+        .chain(std::iter::once((body.len(), ' ')))
+    {
+        match run {
+            Some((fill, len)) if c == fill => run = Some((fill, len + 1)),
+            _ => {
+                if let Some((fill, len)) = run {
+                    if len >= MIN_FILL_RUN && !body[idx..].trim().is_empty() {
+                        found = Some((fill, idx));
+                    }
+                }
+                run = fill_chars.contains(&c).then_some((c, 1));
+            }
+        }
+    }
+    found
+}
+
+/// Detect an alternative right-side border on a line framed by it
+fn detect_alt_suffix_border(line: &str, opts: &DetectOptions) -> Option<SuffixBorder> {
+    let border = alt_border_frame(line, &opts.alt_borders)?;
+    let trimmed = line.trim_end();
+    let prefix = &trimmed[..trimmed.len() - border.len_utf8()];
+    Some(SuffixBorder {
+        column: visual_width(prefix),
+        char: border,
+        is_closing: false,
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Diagram Block Detection
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A detected ASCII diagram block within the input text.
+///
+/// Blocks are identified by consecutive lines containing box-drawing
+/// characters. Each block is processed independently by the correction
+/// algorithm.
+///
+/// # Confidence Scoring
+///
+/// The confidence score (0.0-1.0) indicates how likely this block is
+/// to be an actual diagram versus coincidental box characters:
+/// - 0.9-1.0: Very likely a diagram (multiple strong lines)
+/// - 0.5-0.9: Probably a diagram (mixed strong/weak lines)
+/// - 0.0-0.5: Uncertain (weak lines only, may be table or code)
+#[derive(Debug)]
+pub struct DiagramBlock {
+    /// Starting line index in the input (0-based, inclusive)
+    pub start: usize,
+
+    /// Ending line index in the input (exclusive)
+    pub end: usize,
+
+    /// Confidence that this is an actual diagram (0.0-1.0)
+    pub confidence: f64,
+
+    /// Whether the block is boxes or a file tree
+    pub kind: BlockKind,
+
+    /// Annotations written directly above the block
+    annotations: BlockAnnotations,
+}
+
+/// What kind of diagram a block holds, which decides how it is corrected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    /// Boxes, borders and connectors: right borders are aligned
+    Boxes,
+    /// A file tree (`├── name`, `│   └── name`): branches and guides are
+    /// aligned under their parents, and no borders are ever added
+    Tree,
+}
+
+/// Settings attached to a block by `aadc:` comments directly above it
+#[derive(Debug, Clone, Default)]
+struct BlockAnnotations {
+    /// Name from `aadc:block name=NAME`
+    name: Option<String>,
+
+    /// Locked box width from `aadc:width N`, in columns from the block's
+    /// left edge through the right border
+    width: Option<usize>,
+
+    /// Score threshold from `aadc:preset NAME` or `aadc:min-score X`
+    min_score: Option<f64>,
+}
+
+/// Parse an `aadc:` directive from a comment line.
+///
+/// Accepts the usual comment syntaxes (`<!-- aadc:block name=x -->`,
+/// `# aadc:...`, `// aadc:...`, `/* aadc:... */`, `-- aadc:...`,
+/// `; aadc:...`) and returns the directive keyword and its arguments.
+fn parse_directive(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim();
+    let trimmed = trimmed
+        .strip_suffix("-->")
+        .or_else(|| trimmed.strip_suffix("*/"))
+        .unwrap_or(trimmed);
+    let body = ["<!--", "/*", "//", "#", "--", ";"]
+        .iter()
+        .find_map(|prefix| trimmed.strip_prefix(prefix))?;
+    let rest = body.trim().strip_prefix("aadc:")?;
+    let (keyword, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((keyword, args.trim()))
+}
+
+/// Block names may only use letters, digits, `-`, `_` and `.` (they double
+/// as file names in `aadc extract`)
+fn is_valid_block_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Collect the annotations written above the block starting at `start`.
+///
+/// Walks upward over directive lines, skipping blank lines and Markdown
+/// code fences so annotations can sit outside a fenced diagram. The
+/// nearest annotation wins when a setting is repeated.
+fn block_annotations(lines: &[String], start: usize) -> BlockAnnotations {
+    let mut annotations = BlockAnnotations::default();
+    for line in lines[..start].iter().rev() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            continue;
+        }
+        let Some((keyword, args)) = parse_directive(line) else {
+            break;
+        };
+        match keyword {
+            "block" if annotations.name.is_none() => {
+                annotations.name = args
+                    .split_whitespace()
+                    .filter_map(|arg| arg.strip_prefix("name="))
+                    .find(|name| is_valid_block_name(name))
+                    .map(str::to_string);
+            }
+            "width" if annotations.width.is_none() => {
+                annotations.width = args.parse().ok().filter(|&w| w >= 2);
+            }
+            "preset" if annotations.min_score.is_none() => {
+                annotations.min_score = Preset::from_str(args, true).ok().map(Preset::min_score);
+            }
+            "min-score" if annotations.min_score.is_none() => {
+                annotations.min_score = args
+                    .parse()
+                    .ok()
+                    .filter(|score| (0.0..=1.0).contains(score));
+            }
+            _ => {}
+        }
+    }
+    annotations
+}
+
+/// Line ranges (0-based, end exclusive) that `aadc:off` / `aadc:on`
+/// comments exempt from correction, pragma lines included. An `aadc:off`
+/// without a matching `aadc:on` runs to the end of the input.
+fn disabled_ranges(lines: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut off_at = None;
+    for (idx, line) in lines.iter().enumerate() {
+        match parse_directive(line) {
+            Some(("off", _)) if off_at.is_none() => off_at = Some(idx),
+            Some(("on", _)) => {
+                if let Some(start) = off_at.take() {
+                    ranges.push((start, idx + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = off_at {
+        ranges.push((start, lines.len()));
+    }
+    ranges
+}
+
+/// Whether any line in `start..end` falls in one of `ranges`
+fn overlaps_disabled(ranges: &[(usize, usize)], start: usize, end: usize) -> bool {
+    ranges.iter().any(|&(s, e)| start < e && s < end)
+}
+
+/// Expand tabs in every line outside `aadc:off` regions, which pass through
+/// untouched
+fn expand_enabled_tabs(lines: Vec<String>, tab_width: usize) -> Vec<String> {
+    let disabled = disabled_ranges(&lines);
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            if overlaps_disabled(&disabled, idx, idx + 1) {
+                line
+            } else {
+                expand_tabs(&line, tab_width)
+            }
+        })
+        .collect()
+}
+
+/// Detection confidence a block needs to be processed unless
+/// `--block-confidence` says otherwise
+const DEFAULT_BLOCK_CONFIDENCE: f64 = 0.3;
+
+/// Blank lines a block may span unless `--blank-gap` says otherwise
+const DEFAULT_BLANK_GAP: usize = 1;
+
+/// Lines searched past a non-diagram line unless `--lookahead` says
+/// otherwise
+const DEFAULT_LOOKAHEAD: usize = 3;
+
+/// Options that influence how lines are classified and grouped into blocks.
+/// The defaults match a plain `aadc` run; [`Config::detect_options`]
+/// derives them from a configuration.
+#[derive(Debug, Clone)]
+pub struct DetectOptions {
+    /// Keep blocks regardless of confidence
+    all_blocks: bool,
+
+    /// Minimum confidence for a block to be kept
+    block_confidence: f64,
+
+    /// Blank lines a block may span
+    blank_gap: usize,
+
+    /// Lines (from a non-diagram line on) searched for more diagram lines
+    /// before the block is ended at that line
+    lookahead: usize,
+
+    /// Enabled alternative vertical border characters
+    alt_borders: Vec<char>,
+
+    /// Lines longer than this many bytes are classified None (0 = no cap)
+    max_line_bytes: usize,
+
+    /// Line ranges (0-based, end exclusive) that are never part of a block,
+    /// such as tables handled by `--format-tables`
+    skip_ranges: Vec<(usize, usize)>,
+}
+
+impl Default for DetectOptions {
+    fn default() -> Self {
+        Self {
+            all_blocks: false,
+            block_confidence: DEFAULT_BLOCK_CONFIDENCE,
+            blank_gap: DEFAULT_BLANK_GAP,
+            lookahead: DEFAULT_LOOKAHEAD,
+            alt_borders: Vec::new(),
+            max_line_bytes: 0,
+            skip_ranges: Vec::new(),
+        }
+    }
+}
+
+impl DetectOptions {
+    /// Check whether a line is past the `--max-line-bytes` cap
+    fn too_long(&self, line: &str) -> bool {
+        self.max_line_bytes > 0 && line.len() > self.max_line_bytes
+    }
+
+    /// Check whether a line index falls in `skip_ranges`
+    fn skips(&self, idx: usize) -> bool {
+        self.skip_ranges
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&idx))
+    }
+}
+
+impl Config {
+    /// Block detection options derived from this configuration
+    pub fn detect_options(&self) -> DetectOptions {
+        DetectOptions {
+            all_blocks: self.all_blocks,
+            block_confidence: self.block_confidence,
+            blank_gap: self.blank_gap,
+            lookahead: self.lookahead,
+            alt_borders: self.alt_borders.clone(),
+            max_line_bytes: self.max_line_bytes,
+            skip_ranges: Vec::new(),
+        }
+    }
+}
+
+/// Find diagram blocks in the input text.
+///
+/// Scans the input for consecutive lines containing box-drawing characters
+/// and groups them into blocks, bridging up to `blank_gap` blank lines and
+/// any non-diagram line followed by diagram lines within `lookahead` lines.
+/// Lines between `aadc:off` and `aadc:on` comments never join a block.
+pub fn find_diagram_blocks(lines: &[String], opts: &DetectOptions) -> Vec<DiagramBlock> {
+    let disabled = disabled_ranges(lines);
+    let skips = |idx: usize| opts.skips(idx) || overlaps_disabled(&disabled, idx, idx + 1);
+    let classify = |idx: usize| {
+        if skips(idx) {
+            LineKind::None
+        } else {
+            classify_line_with(&lines[idx], opts)
+        }
+    };
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        // Skip blank/non-boxy lines
+        let kind = classify(i);
+        if !kind.is_boxy() {
+            i += 1;
+            continue;
+        }
+
+        // Found potential start of a block
+        let start = i;
+        let mut end = i + 1;
+        let mut strong_count = if kind == LineKind::Strong { 1 } else { 0 };
+        let mut weak_count = if kind == LineKind::Weak { 1 } else { 0 };
+        let mut blank_gap = 0;
+
+        // Extend block (skipped lines always end it)
+        while end < lines.len() && !skips(end) {
+            let next_kind = classify(end);
+
+            match next_kind {
+                LineKind::Strong => {
+                    strong_count += 1;
+                    blank_gap = 0;
+                    end += 1;
+                }
+                LineKind::Weak => {
+                    weak_count += 1;
+                    blank_gap = 0;
+                    end += 1;
+                }
+                // Arrows join boxes but say nothing about confidence
+                LineKind::Connector => {
+                    blank_gap = 0;
+                    end += 1;
+                }
+                LineKind::Blank => {
+                    // Allow small gaps within diagrams
+                    blank_gap += 1;
+                    if blank_gap > opts.blank_gap {
+                        break;
+                    }
+                    end += 1;
+                }
+                LineKind::None => {
+                    // Check if next non-blank is boxy
+                    let lookahead =
+                        (end..lines.len().min(end + opts.lookahead)).any(|j| classify(j).is_boxy());
+                    if lookahead && blank_gap == 0 {
+                        end += 1;
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // Trim trailing blanks
+        while end > start && classify(end - 1) == LineKind::Blank {
+            end -= 1;
+        }
+
+        // Calculate confidence
+        let total = strong_count + weak_count;
+        let confidence = if total > 0 {
+            let strong_ratio = strong_count as f64 / total as f64;
+            let size_bonus = ((end - start) as f64 / 10.0).min(0.2);
+            (strong_ratio * 0.8 + size_bonus).min(1.0)
+        } else {
+            0.0
+        };
+
+        // Add block if confidence meets threshold
+        // Trees rarely reach the box threshold on corners alone, so they
+        // are scored by how many of their lines are tree lines
+        let tree_share = tree_share(&lines[start..end]);
+        let (kind, confidence) = match tree_share {
+            Some(share) => (BlockKind::Tree, share),
+            None => (BlockKind::Boxes, confidence),
+        };
+
+        if opts.all_blocks || confidence >= opts.block_confidence {
+            blocks.push(DiagramBlock {
+                start,
+                end,
+                confidence,
+                kind,
+                annotations: block_annotations(lines, start),
+            });
+        }
+
+        i = end;
+    }
+
+    blocks
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tree Diagrams
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A line of a file tree: vertical guides, then optionally a branch
+/// (`├── `, `└── `, `|-- `, `` `-- ``) and a name
+#[derive(Debug, PartialEq, Eq)]
+struct TreeLine {
+    /// Visual columns of the guides (`│`, `|`) before the branch
+    guides: Vec<usize>,
+    /// Visual column of the branch character and of the name after it;
+    /// None for a guide-only line
+    branch: Option<(usize, usize)>,
+}
+
+/// Check if character starts a tree branch (`├`, `└`, and ASCII `|`, `` ` ``,
+/// `+`, `\`)
+fn is_tree_branch(c: char) -> bool {
+    matches!(
+        c,
+        '├' | '└' | '┣' | '┗' | '╠' | '╚' | '|' | '`' | '+' | '\\'
+    )
+}
+
+/// Parse a line as a file-tree line. Guides must be followed by a space
+/// (or end the line); a branch is its character and a run of horizontal
+/// fill (two or more for ASCII), then a space or the end of the line.
+fn parse_tree_line(line: &str) -> Option<TreeLine> {
+    let chars: Vec<char> = line.trim_end().chars().collect();
+    let mut guides = Vec::new();
+    let mut column = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c == ' ' {
+            column += 1;
+            i += 1;
+        } else if is_tree_branch(c) && next.is_some_and(is_horizontal_fill) {
+            let run = chars[i + 1..]
+                .iter()
+                .take_while(|&&f| is_horizontal_fill(f))
+                .count();
+            let after = chars.get(i + 1 + run).copied();
+            if (c.is_ascii() && run < 2) || after.is_some_and(|a| a != ' ') {
+                return None;
+            }
+            // The name starts after the fill and one space
+            return Some(TreeLine {
+                guides,
+                branch: Some((column, column + run + 2)),
+            });
+        } else if is_vertical_border(c) && next.is_none_or(|n| n == ' ') {
+            guides.push(column);
+            column += 1;
+            i += 1;
+        } else {
+            return None;
+        }
+    }
+    (!guides.is_empty()).then_some(TreeLine {
+        guides,
+        branch: None,
+    })
+}
+
+/// Share of a block's non-blank lines that are tree lines, when the block
+/// is a file tree: every non-blank line after the first parses as a tree
+/// line (the first may be the root, like `src/`), with at least two
+/// branches among them
+fn tree_share(lines: &[String]) -> Option<f64> {
+    let non_blank: Vec<&String> = lines.iter().filter(|l| !l.trim().is_empty()).collect();
+    let parsed: Vec<Option<TreeLine>> = non_blank.iter().map(|l| parse_tree_line(l)).collect();
+    let branches = parsed
+        .iter()
+        .flatten()
+        .filter(|t| t.branch.is_some())
+        .count();
+    if branches < 2 || parsed.iter().skip(1).any(Option::is_none) {
+        return None;
+    }
+    let tree_lines = parsed.iter().flatten().count();
+    Some(tree_lines as f64 / non_blank.len() as f64)
+}
+
+/// An open branch of a tree while walking it: where its branch character
+/// was and where it (and its name) end up once aligned
+struct TreeAncestor {
+    column: usize,
+    aligned_column: usize,
+    aligned_name: usize,
+}
+
+/// Pads that align a tree block's branches and guides, per line, as
+/// (column, spaces to insert, target column), left to right.
+///
+/// A branch's parent is the nearest line above whose branch is at least two
+/// columns further left; the branch belongs under the parent's name, as
+/// `tree` prints it. Guides follow the branch they descend from. Edits are
+/// insert-only, so over-indented lines stay put.
+fn tree_pads(lines: &[String]) -> Vec<Vec<(usize, usize, usize)>> {
+    let mut stack: Vec<TreeAncestor> = Vec::new();
+    let mut pads = vec![Vec::new(); lines.len()];
+
+    for (i, line) in lines.iter().enumerate() {
+        let Some(tree_line) = parse_tree_line(line) else {
+            if !line.trim().is_empty() {
+                stack.clear();
+            }
+            continue;
+        };
+        if let Some((column, _)) = tree_line.branch {
+            while stack.last().is_some_and(|a| a.column + 2 > column) {
+                stack.pop();
+            }
+        }
+
+        // Columns to the right of an edit move with it, so targets never
+        // fall below what earlier edits on the line already imply
+        let mut shift = 0;
+        let mut pad = |column: usize, target: usize, shift: &mut usize| {
+            let target = target.max(column + *shift);
+            if target > column + *shift {
+                pads[i].push((column, target - column - *shift, target));
+                *shift = target - column;
+            }
+        };
+        for &guide in &tree_line.guides {
+            let target = stack
+                .iter()
+                .find(|a| a.column == guide)
+                .map_or(guide, |a| a.aligned_column);
+            pad(guide, target, &mut shift);
+        }
+        if let Some((column, name)) = tree_line.branch {
+            let target = stack.last().map_or(column, |parent| parent.aligned_name);
+            pad(column, target, &mut shift);
+            stack.push(TreeAncestor {
+                column,
+                aligned_column: column + shift,
+                aligned_name: name + shift,
+            });
+        }
+    }
+
+    pads
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Revision System
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Weights of the revision scoring model, set in the `[scoring]` table of
+/// `.aadcrc` so teams can calibrate without recompiling
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringWeights {
+    /// Base score for padding before an existing border
+    pad_base: f64,
+    /// Each `pad_penalty_divisor` columns of padding costs 1.0
+    pad_penalty_divisor: f64,
+    /// Maximum padding penalty
+    pad_penalty_cap: f64,
+    /// Bonus for padding a strong line
+    pad_strong_bonus: f64,
+    /// Penalty for padding before an alternative border (`:`, `!`)
+    alt_border_penalty: f64,
+    /// Base score for adding a missing border
+    add_border_base: f64,
+    /// Bonus for adding a border to a strong line
+    add_border_strong_bonus: f64,
+    /// Bonus for adding a border to a weak line
+    add_border_weak_bonus: f64,
+    /// Base score for indenting a line to align its left border
+    align_left_base: f64,
+    /// Bonus scaled by the share of left borders already at the target
+    align_left_consensus_bonus: f64,
+    /// Penalty per column of indentation added
+    align_left_column_penalty: f64,
+    /// Score for replacing a vertical border with a corner
+    /// (`--repair-corners`)
+    replace_corner_base: f64,
+    /// Score for closing an unterminated box (`--complete-boxes`), scaled
+    /// by the share of its rows that are closed on both sides
+    complete_box_base: f64,
+    /// Base score for removing spaces before a border past the target
+    /// (`--allow-shrink`)
+    shrink_base: f64,
+}
+
+impl Default for ScoringWeights {
+    fn default() -> Self {
+        Self {
+            pad_base: 0.8,
+            pad_penalty_divisor: 10.0,
+            pad_penalty_cap: 0.5,
+            pad_strong_bonus: 0.2,
+            alt_border_penalty: 0.1,
+            add_border_base: 0.5,
+            add_border_strong_bonus: 0.2,
+            add_border_weak_bonus: 0.1,
+            align_left_base: 0.6,
+            align_left_consensus_bonus: 0.3,
+            align_left_column_penalty: 0.1,
+            replace_corner_base: 0.7,
+            complete_box_base: 0.6,
+            shrink_base: 0.8,
+        }
+    }
+}
+
+impl ScoringWeights {
+    /// Check that every weight is between 0.0 and 1.0 (the divisor only
+    /// needs to be positive)
+    fn validate(&self) -> Result<(), String> {
+        let weights = [
+            ("pad_base", self.pad_base),
+            ("pad_penalty_cap", self.pad_penalty_cap),
+            ("pad_strong_bonus", self.pad_strong_bonus),
+            ("alt_border_penalty", self.alt_border_penalty),
+            ("add_border_base", self.add_border_base),
+            ("add_border_strong_bonus", self.add_border_strong_bonus),
+            ("add_border_weak_bonus", self.add_border_weak_bonus),
+            ("align_left_base", self.align_left_base),
+            (
+                "align_left_consensus_bonus",
+                self.align_left_consensus_bonus,
+            ),
+            ("align_left_column_penalty", self.align_left_column_penalty),
+            ("replace_corner_base", self.replace_corner_base),
+            ("complete_box_base", self.complete_box_base),
+            ("shrink_base", self.shrink_base),
+        ];
+        for (name, value) in weights {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("{name} must be between 0.0 and 1.0, got {value}"));
+            }
+        }
+        if !(self.pad_penalty_divisor.is_finite() && self.pad_penalty_divisor > 0.0) {
+            return Err(format!(
+                "pad_penalty_divisor must be positive, got {}",
+                self.pad_penalty_divisor
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A proposed modification to align a line's left or right border.
+///
+/// Revisions are generated during the correction loop and scored for
+/// confidence. Only revisions above the `--min-score` threshold are applied.
+///
+/// # Scoring
+///
+/// Each revision type has different base confidence scores:
+/// - `PadBeforeSuffixBorder`: Higher confidence (0.3-0.9), as we're just adding
+///   whitespace before an existing border
+/// - `ExtendFillRun`: Scored like `PadBeforeSuffixBorder`, but lengthens a dot
+///   leader instead of inserting spaces
+/// - `ExtendHorizontalBorder`: Scored like `PadBeforeSuffixBorder`, but keeps a
+///   `+----+` border solid by repeating its fill character
+/// - `AddSuffixBorder`: Lower confidence (0.3-0.6), as we're adding a character
+///   that wasn't there
+/// - `AlignLeftBorder`: Scored on how many left borders already agree on the
+///   target column, less a penalty per column of indentation
+/// - `PadBeforeSeparator`: Scored like `PadBeforeSuffixBorder`, for an
+///   interior column separator
+/// - `ReplaceCornerChar`: A fixed `replace_corner_base`
+/// - `PadTreeBranch`: Scored like `PadBeforeSuffixBorder` on a weak line
+/// - `TrimBeforeSuffixBorder`: Like `PadBeforeSuffixBorder`, from `shrink_base`
+///
+/// # Monotone Edits
+///
+/// All revision types but two are "monotone" (insert-only) - they never
+/// remove content from the line, making them safe to apply. The exceptions
+/// are opt-in: `ReplaceCornerChar` swaps one border character for another
+/// (`--repair-corners`), and `TrimBeforeSuffixBorder` deletes spaces, and
+/// only spaces, before a border (`--allow-shrink`).
+#[derive(Debug, Clone)]
+pub enum Revision {
+    /// Insert spaces before an existing suffix border to align it.
+    ///
+    /// This is the most common revision type and has higher confidence
+    /// since we're only adjusting whitespace.
+    PadBeforeSuffixBorder {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Number of space characters to insert
+        spaces_to_add: usize,
+        /// Target visual column for alignment
+        target_column: usize,
+    },
+
+    /// Lengthen a leader run (`name ..... value`) to push the border out.
+    ///
+    /// Keeps the diagram's visual style where plain padding would leave a
+    /// gap before the value.
+    ExtendFillRun {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Fill character to repeat
+        fill_char: char,
+        /// Number of columns to fill (a multiple of the fill character width)
+        spaces_to_add: usize,
+        /// Target visual column for alignment
+        target_column: usize,
+    },
+
+    /// Lengthen a horizontal border (`+---+`, `└──┘`) up to its closing
+    /// corner with the border's own fill character.
+    ///
+    /// Used for strong lines, where spaces would leave a gap in the border.
+    ExtendHorizontalBorder {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Horizontal fill character to repeat
+        fill_char: char,
+        /// Number of fill characters to insert
+        spaces_to_add: usize,
+        /// Target visual column for alignment
+        target_column: usize,
+    },
+
+    /// Add a border character at the target column.
+    ///
+    /// Used when a line has content but no closing border. Lower confidence
+    /// since we're adding structure that may not be intended.
+    AddSuffixBorder {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Border character to add (`|`, `│`, etc.)
+        border_char: char,
+        /// Target visual column for the new border
+        target_column: usize,
+    },
+
+    /// Widen a cell so the interior column separator after it lines up with
+    /// the same separator in neighbouring rows (`| a | b |`, `+---+---+`).
+    PadBeforeSeparator {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Current visual column of the separator
+        column: usize,
+        /// Character to insert: a space, or the horizontal fill of a
+        /// border row
+        fill_char: char,
+        /// Number of columns to insert
+        spaces_to_add: usize,
+        /// Target visual column for the separator
+        target_column: usize,
+        /// Visual width of the line once padded
+        new_width: usize,
+    },
+
+    /// Insert spaces before a tree branch or guide so it lines up under its
+    /// parent (tree blocks only; they get no other revisions).
+    PadTreeBranch {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Current visual column of the branch or guide
+        column: usize,
+        /// Number of spaces to insert
+        spaces_to_add: usize,
+        /// Target visual column
+        target_column: usize,
+        /// Visual width of the line once padded
+        new_width: usize,
+    },
+
+    /// Replace the vertical border closing a horizontal border row with the
+    /// corner that row needs (`+----|` → `+----+`, `└───│` → `└───┘`).
+    ///
+    /// The only revision that changes a character; off unless
+    /// `--repair-corners` is set.
+    ReplaceCornerChar {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Visual column of the border being replaced
+        column: usize,
+        /// Character currently closing the row
+        found: char,
+        /// Corner character to put in its place
+        corner_char: char,
+    },
+
+    /// Remove spaces before a suffix border that sits past the target
+    /// column, keeping at least one (`| x        |` → `| x      |`).
+    ///
+    /// Off unless `--allow-shrink` is set.
+    TrimBeforeSuffixBorder {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Number of spaces to remove
+        spaces_to_remove: usize,
+        /// Target visual column for alignment
+        target_column: usize,
+    },
+
+    /// Insert leading spaces so the line's left border lines up with the
+    /// block's dominant left-border column.
+    ///
+    /// Only under-indented lines are shifted; the line's right border moves
+    /// with it.
+    AlignLeftBorder {
+        /// Global line index (0-based)
+        line_idx: usize,
+        /// Number of leading spaces to insert
+        spaces_to_add: usize,
+        /// Target visual column for the left border
+        target_column: usize,
+        /// Visual width of the line once shifted
+        new_width: usize,
+    },
+}
+
+impl Revision {
+    /// Short name of this revision's kind, used in stats and JSON output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::PadBeforeSuffixBorder { .. } => "pad",
+            Self::ExtendFillRun { .. } => "extend_fill",
+            Self::ExtendHorizontalBorder { .. } => "extend_border",
+            Self::AddSuffixBorder { .. } => "add_border",
+            Self::PadBeforeSeparator { .. } => "align_separator",
+            Self::ReplaceCornerChar { .. } => "replace_corner",
+            Self::PadTreeBranch { .. } => "align_tree",
+            Self::AlignLeftBorder { .. } => "align_left",
+            Self::TrimBeforeSuffixBorder { .. } => "shrink",
+        }
+    }
+
+    /// Global index of the line this revision edits
+    pub fn line_idx(&self) -> usize {
+        match self {
+            Self::PadBeforeSuffixBorder { line_idx, .. }
+            | Self::ExtendFillRun { line_idx, .. }
+            | Self::ExtendHorizontalBorder { line_idx, .. }
+            | Self::AddSuffixBorder { line_idx, .. }
+            | Self::PadBeforeSeparator { line_idx, .. }
+            | Self::ReplaceCornerChar { line_idx, .. }
+            | Self::PadTreeBranch { line_idx, .. }
+            | Self::TrimBeforeSuffixBorder { line_idx, .. }
+            | Self::AlignLeftBorder { line_idx, .. } => *line_idx,
+        }
+    }
+
+    /// Human-readable summary of the edit
+    pub fn describe(&self) -> String {
+        match self {
+            Self::PadBeforeSuffixBorder {
+                spaces_to_add,
+                target_column,
+                ..
+            } => format!(
+                "pad {} column(s) before border (to column {})",
+                spaces_to_add,
+                target_column + 1
+            ),
+            Self::ExtendFillRun {
+                fill_char,
+                spaces_to_add,
+                ..
+            } => format!("extend '{}' run by {} column(s)", fill_char, spaces_to_add),
+            Self::ExtendHorizontalBorder {
+                fill_char,
+                spaces_to_add,
+                target_column,
+                ..
+            } => format!(
+                "extend '{}' border by {} column(s) (to column {})",
+                fill_char,
+                spaces_to_add,
+                target_column + 1
+            ),
+            Self::AddSuffixBorder {
+                border_char,
+                target_column,
+                ..
+            } => format!(
+                "add '{}' border at column {}",
+                border_char,
+                target_column + 1
+            ),
+            Self::PadBeforeSeparator {
+                column,
+                spaces_to_add,
+                target_column,
+                ..
+            } => format!(
+                "pad {} column(s) before separator at column {} (to column {})",
+                spaces_to_add,
+                column + 1,
+                target_column + 1
+            ),
+            Self::PadTreeBranch {
+                column,
+                spaces_to_add,
+                target_column,
+                ..
+            } => format!(
+                "pad {} column(s) before tree branch at column {} (to column {})",
+                spaces_to_add,
+                column + 1,
+                target_column + 1
+            ),
+            Self::ReplaceCornerChar {
+                column,
+                found,
+                corner_char,
+                ..
+            } => format!(
+                "replace '{}' with '{}' at column {}",
+                found,
+                corner_char,
+                column + 1
+            ),
+            Self::AlignLeftBorder {
+                spaces_to_add,
+                target_column,
+                ..
+            } => format!(
+                "indent {} column(s) to align left border (to column {})",
+                spaces_to_add,
+                target_column + 1
+            ),
+            Self::TrimBeforeSuffixBorder {
+                spaces_to_remove,
+                target_column,
+                ..
+            } => format!(
+                "remove {} space(s) before border (to column {})",
+                spaces_to_remove,
+                target_column + 1
+            ),
+        }
+    }
+
+    /// The 1-based visual column of the misaligned character and what is
+    /// wrong with it, as reported by `--output-format gnu`
+    pub fn problem(&self) -> (usize, String) {
+        match self {
+            Self::PadBeforeSuffixBorder {
+                spaces_to_add,
+                target_column,
+                ..
+            }
+            | Self::ExtendFillRun {
+                spaces_to_add,
+                target_column,
+                ..
+            }
+            | Self::ExtendHorizontalBorder {
+                spaces_to_add,
+                target_column,
+                ..
+            } => (
+                target_column - spaces_to_add + 1,
+                format!(
+                    "right border {} column(s) short of target {}",
+                    spaces_to_add,
+                    target_column + 1
+                ),
+            ),
+            Self::AddSuffixBorder { target_column, .. } => (
+                target_column + 1,
+                format!("missing right border at target {}", target_column + 1),
+            ),
+            Self::TrimBeforeSuffixBorder {
+                spaces_to_remove,
+                target_column,
+                ..
+            } => (
+                target_column + spaces_to_remove + 1,
+                format!(
+                    "right border {} column(s) past target {}",
+                    spaces_to_remove,
+                    target_column + 1
+                ),
+            ),
+            Self::PadBeforeSeparator {
+                column,
+                spaces_to_add,
+                target_column,
+                ..
+            } => (
+                column + 1,
+                format!(
+                    "column separator {} column(s) short of target {}",
+                    spaces_to_add,
+                    target_column + 1
+                ),
+            ),
+            Self::PadTreeBranch {
+                column,
+                spaces_to_add,
+                target_column,
+                ..
+            } => (
+                column + 1,
+                format!(
+                    "tree branch {} column(s) short of target {}",
+                    spaces_to_add,
+                    target_column + 1
+                ),
+            ),
+            Self::ReplaceCornerChar {
+                column,
+                found,
+                corner_char,
+                ..
+            } => (
+                column + 1,
+                format!("corner '{}' should be '{}'", found, corner_char),
+            ),
+            Self::AlignLeftBorder {
+                spaces_to_add,
+                target_column,
+                ..
+            } => (
+                target_column - spaces_to_add + 1,
+                format!(
+                    "left border {} column(s) short of target {}",
+                    spaces_to_add,
+                    target_column + 1
+                ),
+            ),
+        }
+    }
+
+    /// Spaces (or fill characters) this revision inserts into its line;
+    /// None for revisions that insert no padding
+    fn spaces_to_add(&self) -> Option<usize> {
+        match self {
+            Self::PadBeforeSuffixBorder { spaces_to_add, .. }
+            | Self::ExtendFillRun { spaces_to_add, .. }
+            | Self::ExtendHorizontalBorder { spaces_to_add, .. }
+            | Self::PadBeforeSeparator { spaces_to_add, .. }
+            | Self::PadTreeBranch { spaces_to_add, .. }
+            | Self::AlignLeftBorder { spaces_to_add, .. } => Some(*spaces_to_add),
+            Self::AddSuffixBorder { .. }
+            | Self::ReplaceCornerChar { .. }
+            | Self::TrimBeforeSuffixBorder { .. } => None,
+        }
+    }
+
+    /// Whether this revision inserts more padding than `--max-pad` allows
+    fn exceeds_max_pad(&self, config: &Config) -> bool {
+        matches!(
+            (self.spaces_to_add(), config.max_pad),
+            (Some(spaces), Some(max)) if spaces > max
+        )
+    }
+
+    /// Visual width of the edited line once this revision is applied
+    /// (a right border lands at `target_column` and is one column wide)
+    fn resulting_width(&self) -> usize {
+        match self {
+            Self::PadBeforeSuffixBorder { target_column, .. }
+            | Self::ExtendFillRun { target_column, .. }
+            | Self::ExtendHorizontalBorder { target_column, .. }
+            | Self::AddSuffixBorder { target_column, .. }
+            | Self::TrimBeforeSuffixBorder { target_column, .. } => target_column + 1,
+            Self::ReplaceCornerChar { column, .. } => column + 1,
+            Self::PadBeforeSeparator { new_width, .. }
+            | Self::PadTreeBranch { new_width, .. }
+            | Self::AlignLeftBorder { new_width, .. } => *new_width,
+        }
+    }
+
+    /// Score this revision (higher = more confident it's correct)
+    /// `block_start` is the offset of the block in the global lines array
+    pub fn score(
+        &self,
+        analyzed: &[AnalyzedLine],
+        block_start: usize,
+        weights: &ScoringWeights,
+    ) -> f64 {
+        match self {
+            Self::PadBeforeSuffixBorder {
+                line_idx,
+                spaces_to_add,
+                ..
+            }
+            | Self::ExtendFillRun {
+                line_idx,
+                spaces_to_add,
+                ..
+            }
+            | Self::ExtendHorizontalBorder {
+                line_idx,
+                spaces_to_add,
+                ..
+            } => {
+                let local_idx = line_idx - block_start;
+                let line = &analyzed[local_idx];
+                // Padding would split a diagonal from the border it meets
+                if line.diagonal_at_border {
+                    return 0.0;
+                }
+                // Prefer smaller adjustments
+                let adjustment_penalty = (*spaces_to_add as f64 / weights.pad_penalty_divisor)
+                    .min(weights.pad_penalty_cap);
+                // Prefer strong lines
+                let strength_bonus = if line.kind == LineKind::Strong {
+                    weights.pad_strong_bonus
+                } else {
+                    0.0
+                };
+                // Alternative borders double as punctuation; trust them less
+                let alt_penalty = if line
+                    .suffix_border
+                    .as_ref()
+                    .is_some_and(|b| !is_border_char(b.char))
+                {
+                    weights.alt_border_penalty
+                } else {
+                    0.0
+                };
+                weights.pad_base - adjustment_penalty + strength_bonus - alt_penalty
+            }
+            Self::AddSuffixBorder { line_idx, .. } => {
+                let local_idx = line_idx - block_start;
+                let line = &analyzed[local_idx];
+                // A trailing diagonal is a connector leaving the box, not a
+                // missing border
+                if line.diagonal_at_border {
+                    return 0.0;
+                }
+                // Adding borders is less confident
+                let strength_bonus = if line.kind == LineKind::Strong {
+                    weights.add_border_strong_bonus
+                } else {
+                    weights.add_border_weak_bonus
+                };
+                weights.add_border_base + strength_bonus
+            }
+            Self::PadBeforeSeparator {
+                line_idx,
+                spaces_to_add,
+                ..
+            } => {
+                let line = &analyzed[line_idx - block_start];
+                let adjustment_penalty = (*spaces_to_add as f64 / weights.pad_penalty_divisor)
+                    .min(weights.pad_penalty_cap);
+                let strength_bonus = if line.kind == LineKind::Strong {
+                    weights.pad_strong_bonus
+                } else {
+                    0.0
+                };
+                weights.pad_base - adjustment_penalty + strength_bonus
+            }
+            Self::ReplaceCornerChar { .. } => weights.replace_corner_base,
+            Self::TrimBeforeSuffixBorder {
+                line_idx,
+                spaces_to_remove,
+                ..
+            } => {
+                let line = &analyzed[line_idx - block_start];
+                if line.diagonal_at_border {
+                    return 0.0;
+                }
+                let adjustment_penalty = (*spaces_to_remove as f64 / weights.pad_penalty_divisor)
+                    .min(weights.pad_penalty_cap);
+                let strength_bonus = if line.kind == LineKind::Strong {
+                    weights.pad_strong_bonus
+                } else {
+                    0.0
+                };
+                weights.shrink_base - adjustment_penalty + strength_bonus
+            }
+            Self::PadTreeBranch { spaces_to_add, .. } => {
+                weights.pad_base
+                    - (*spaces_to_add as f64 / weights.pad_penalty_divisor)
+                        .min(weights.pad_penalty_cap)
+            }
+            Self::AlignLeftBorder {
+                spaces_to_add,
+                target_column,
+                ..
+            } => {
+                // Trust the target as far as the other left borders agree
+                let (agreeing, bordered) = analyzed
+                    .iter()
+                    .filter_map(|a| a.left_border_column())
+                    .fold((0, 0), |(agreeing, bordered), column| {
+                        (
+                            agreeing + usize::from(column == *target_column),
+                            bordered + 1,
+                        )
+                    });
+                let consensus = agreeing as f64 / bordered.max(1) as f64;
+                weights.align_left_base + weights.align_left_consensus_bonus * consensus
+                    - *spaces_to_add as f64 * weights.align_left_column_penalty
+            }
+        }
+    }
+
+    /// Apply this revision to the lines
+    fn apply(&self, lines: &mut [String]) {
+        match self {
+            Self::PadBeforeSuffixBorder {
+                line_idx,
+                spaces_to_add,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                let (trimmed, escapes) = split_visible_end(line);
+                if let Some(last_char) = trimmed.chars().next_back() {
+                    if is_border_char(last_char) || ALT_BORDER_CHARS.contains(&last_char) {
+                        // Insert spaces before the last character
+                        let prefix = &trimmed[..trimmed.len() - last_char.len_utf8()];
+                        *line = format!(
+                            "{}{}{}{}",
+                            prefix,
+                            " ".repeat(*spaces_to_add),
+                            last_char,
+                            escapes.trim_end()
+                        );
+                    }
+                }
+            }
+            Self::ExtendFillRun {
+                line_idx,
+                fill_char,
+                spaces_to_add,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                if let Some((_, run_end)) = find_fill_run(line, &[*fill_char]) {
+                    let count = spaces_to_add / char_width(*fill_char);
+                    line.insert_str(run_end, &fill_char.to_string().repeat(count));
+                }
+            }
+            Self::ExtendHorizontalBorder {
+                line_idx,
+                fill_char,
+                spaces_to_add,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                let (trimmed, escapes) = split_visible_end(line);
+                if let Some(last_char) = trimmed.chars().next_back() {
+                    let prefix = &trimmed[..trimmed.len() - last_char.len_utf8()];
+                    *line = format!(
+                        "{}{}{}{}",
+                        prefix,
+                        fill_char.to_string().repeat(*spaces_to_add),
+                        last_char,
+                        escapes.trim_end()
+                    );
+                }
+            }
+            Self::AddSuffixBorder {
+                line_idx,
+                border_char,
+                target_column,
+            } => {
+                let line = &mut lines[*line_idx];
+                let current_width = visual_width(line.trim_end());
+                let padding = target_column.saturating_sub(current_width);
+                *line = format!("{}{}{}", line.trim_end(), " ".repeat(padding), border_char);
+            }
+            Self::PadBeforeSeparator {
+                line_idx,
+                column,
+                fill_char,
+                spaces_to_add,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                if let Some(byte) = byte_at_column(line, *column) {
+                    line.insert_str(byte, &fill_char.to_string().repeat(*spaces_to_add));
+                }
+            }
+            Self::PadTreeBranch {
+                line_idx,
+                column,
+                spaces_to_add,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                if let Some(byte) = byte_at_column(line, *column) {
+                    line.insert_str(byte, &" ".repeat(*spaces_to_add));
+                }
+            }
+            Self::ReplaceCornerChar {
+                line_idx,
+                found,
+                corner_char,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                let (trimmed, escapes) = split_visible_end(line);
+                if trimmed.ends_with(*found) {
+                    let prefix = &trimmed[..trimmed.len() - found.len_utf8()];
+                    *line = format!("{}{}{}", prefix, corner_char, escapes.trim_end());
+                }
+            }
+            Self::AlignLeftBorder {
+                line_idx,
+                spaces_to_add,
+                ..
+            } => {
+                lines[*line_idx].insert_str(0, &" ".repeat(*spaces_to_add));
+            }
+            Self::TrimBeforeSuffixBorder {
+                line_idx,
+                spaces_to_remove,
+                ..
+            } => {
+                let line = &mut lines[*line_idx];
+                let (trimmed, escapes) = split_visible_end(line);
+                if let Some(last_char) = trimmed.chars().next_back() {
+                    let prefix = &trimmed[..trimmed.len() - last_char.len_utf8()];
+                    let kept = prefix.len().saturating_sub(*spaces_to_remove);
+                    // Only spaces go, and one always stays before the border
+                    if prefix.trim_end_matches(' ').len() < kept {
+                        *line = format!("{}{}{}", &prefix[..kept], last_char, escapes.trim_end());
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Block Correction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Result of correcting a single block
+struct BlockCorrectionResult {
+    /// Number of revisions applied
+    revisions_applied: usize,
+    /// Number of revisions skipped due to low score
+    revisions_skipped: usize,
+    /// Applied/skipped revisions by kind and score decile
+    breakdown: RevisionBreakdown,
+    /// Lines whose revision would have exceeded `--max-line-length`
+    refused_lines: BTreeSet<usize>,
+    /// Applied revisions with their scores, in order
+    applied: Vec<(Revision, f64)>,
+    /// Revisions below the threshold in the last iteration
+    skipped: Vec<(Revision, f64)>,
+    /// Right-border column of the first iteration (0-based)
+    target_column: Option<usize>,
+}
+
+/// Score threshold for a block: its `aadc:preset`/`aadc:min-score`
+/// annotation wins over the configured threshold
+fn block_min_score(block: &DiagramBlock, config: &Config) -> f64 {
+    block
+        .annotations
+        .min_score
+        .unwrap_or_else(|| config.effective_min_score())
+}
+
+/// Split a block's rows into boxes and the runs of rows between them, as
+/// (first row, last row, is a box) ranges covering the block in order. A
+/// box runs from a border row (`+---+`) to the border row closing it: the
+/// last one before a blank row, the block end, or a border row directly
+/// below it (the top of the next box). Rows between boxes form ranges of
+/// their own.
+fn box_row_ranges(analyzed: &[AnalyzedLine]) -> Vec<(usize, usize, bool)> {
+    let is_edge = |i: usize| horizontal_border_fill(&analyzed[i]).is_some();
+    let mut ranges = Vec::new();
+    let mut loose_start = 0;
+    let mut i = 0;
+    while i < analyzed.len() {
+        if !is_edge(i) {
+            i += 1;
+            continue;
+        }
+        let mut end = i;
+        for j in i + 1..analyzed.len() {
+            if analyzed[j].kind == LineKind::Blank {
+                break;
+            }
+            if is_edge(j) {
+                end = j;
+                if j + 1 < analyzed.len() && is_edge(j + 1) {
+                    break;
+                }
+            }
+        }
+        if end == i {
+            i += 1;
+            continue;
+        }
+        if loose_start < i {
+            ranges.push((loose_start, i - 1, false));
+        }
+        ranges.push((i, end, true));
+        loose_start = end + 1;
+        i = end + 1;
+    }
+    if loose_start < analyzed.len() {
+        ranges.push((loose_start, analyzed.len() - 1, false));
+    }
+    ranges
+}
+
+/// Minimum gap, in columns, between the right borders of rows outside any
+/// box that separates two column clusters
+const CLUSTER_GAP: usize = 8;
+
+/// Group right-border columns into clusters: sorted columns split wherever
+/// consecutive ones are more than `CLUSTER_GAP` apart. Returns each
+/// cluster's (leftmost, rightmost) column, left to right.
+fn column_clusters(columns: impl IntoIterator<Item = usize>) -> Vec<(usize, usize)> {
+    let columns: BTreeSet<usize> = columns.into_iter().collect();
+    let mut clusters: Vec<(usize, usize)> = Vec::new();
+    for column in columns {
+        match clusters.last_mut() {
+            Some((_, end)) if column - *end <= CLUSTER_GAP => *end = column,
+            _ => clusters.push((column, column)),
+        }
+    }
+    clusters
+}
+
+/// Whether a line has content past the `--no-pad-past` protected column,
+/// which keeps it out of target selection and padding
+fn past_protected_column(line: &AnalyzedLine, config: &Config) -> bool {
+    config
+        .no_pad_past
+        .is_some_and(|limit| visual_width(line.content.trim_end()) > limit)
+}
+
+/// Target columns for a block's rows, as (first row, last row, target):
+/// each box (see `box_row_ranges`) aligned to the column `--target` picks
+/// from its own borders, or the whole block aligned to `target` under
+/// `--single-target` or when the width is locked by annotation.
+///
+/// Rows outside any box (open-ended flow regions) have no edges to agree
+/// on, so their right borders are clustered by column (see
+/// `column_clusters`) and each row aligns to its own cluster's target.
+fn block_regions(
+    analyzed: &[AnalyzedLine],
+    block: &DiagramBlock,
+    target: usize,
+    config: &Config,
+) -> Vec<(usize, usize, usize)> {
+    let last_row = analyzed.len().saturating_sub(1);
+    if config.single_target || block.annotations.width.is_some() {
+        return vec![(0, last_row, target)];
+    }
+
+    let mut regions: Vec<(usize, usize, usize)> = Vec::new();
+    for (first, last, is_box) in box_row_ranges(analyzed) {
+        let borders: Vec<Option<(usize, bool)>> = analyzed[first..=last]
+            .iter()
+            .map(|a| {
+                let column = alignment_column(a, config)?;
+                (!past_protected_column(a, config))
+                    .then(|| (column, horizontal_border_fill(a).is_some()))
+            })
+            .collect();
+        let members: Vec<(usize, bool)> = borders.iter().flatten().copied().collect();
+        let range_target = config.target_strategy.pick(&members).unwrap_or(target);
+        if is_box {
+            regions.push((first, last, range_target));
+            continue;
+        }
+
+        let clusters: Vec<(usize, usize, usize)> =
+            column_clusters(members.iter().map(|&(column, _)| column))
+                .into_iter()
+                .map(|(start, end)| {
+                    let cluster: Vec<(usize, bool)> = members
+                        .iter()
+                        .copied()
+                        .filter(|&(column, _)| (start..=end).contains(&column))
+                        .collect();
+                    let cluster_target = config.target_strategy.pick(&cluster).unwrap_or(end);
+                    (start, end, cluster_target)
+                })
+                .collect();
+        // Rows without a border keep the target of the whole range
+        for (row, border) in (first..=last).zip(&borders) {
+            let row_target = border
+                .and_then(|(column, _)| {
+                    clusters
+                        .iter()
+                        .find(|&&(start, end, _)| (start..=end).contains(&column))
+                })
+                .map_or(range_target, |&(_, _, cluster_target)| cluster_target);
+            match regions.last_mut() {
+                Some((start, end, region_target))
+                    if *start >= first && *region_target == row_target =>
+                {
+                    *end = row;
+                }
+                _ => regions.push((row, row, row_target)),
+            }
+        }
+    }
+    regions
+}
+
+/// Fill character of a strong line whose horizontal border runs right up to
+/// its closing corner or junction (`+---+`, `└──┘`, `╠══╣`)
+fn horizontal_border_fill(line: &AnalyzedLine) -> Option<char> {
+    let border = line.suffix_border.as_ref()?;
+    if line.kind != LineKind::Strong || !(is_corner(border.char) || is_junction(border.char)) {
+        return None;
+    }
+    let (trimmed, _) = split_visible_end(&line.content);
+    let mut chars = trimmed.chars().rev();
+    chars.next();
+    chars.next().filter(|&c| is_horizontal_fill(c))
+}
+
+/// Closing piece matching the opening piece of a border row
+fn closing_corner(open: char) -> Option<char> {
+    Some(match open {
+        '+' => '+',
+        '┌' => '┐',
+        '└' => '┘',
+        '├' => '┤',
+        '╭' => '╮',
+        '╰' => '╯',
+        '╔' => '╗',
+        '╚' => '╝',
+        '╠' => '╣',
+        '┏' => '┓',
+        '┗' => '┛',
+        '┣' => '┫',
+        _ => return None,
+    })
+}
+
+/// A border row that opens with a corner but is closed by a vertical
+/// border after its horizontal run (`+----|`, `┌───│`): the corner it
+/// should close with, and the run's fill character
+fn misplaced_corner(line: &AnalyzedLine) -> Option<(char, char)> {
+    let border = line.suffix_border.as_ref()?;
+    if line.kind != LineKind::Strong || !is_vertical_border(border.char) {
+        return None;
+    }
+    let corner = closing_corner(line.content[line.indent..].chars().next()?)?;
+    let mut chars = split_visible_end(&line.content).0.chars().rev();
+    chars.next();
+    let fill = chars.next().filter(|&c| is_horizontal_fill(c))?;
+    Some((corner, fill))
+}
+
+/// Column a line's right border is aligned by: the border's own column, or
+/// under `--allow-shrink` the column it can be pulled in to by removing all
+/// but one of the spaces before it (a border with nothing but spaces before
+/// it stays put). None for lines without a right border.
+fn alignment_column(line: &AnalyzedLine, config: &Config) -> Option<usize> {
+    let border = line.suffix_border.as_ref()?;
+    if !config.allow_shrink {
+        return Some(border.column);
+    }
+    let (trimmed, _) = split_visible_end(&line.content);
+    let prefix = &trimmed[..trimmed.len() - border.char.len_utf8()];
+    let content = prefix.trim_end_matches(' ');
+    if content.trim().is_empty() {
+        return Some(border.column);
+    }
+    let spaces = prefix.len() - content.len();
+    Some(border.column - spaces.saturating_sub(1))
+}
+
+/// Find a block's target column: the border position `--target` picks
+/// (the rightmost by default), ignoring lines past the `--no-pad-past`
+/// column, unless locked by an `aadc:width` annotation (lines already past
+/// it are left alone unless they can be shrunk). Under `--allow-shrink`
+/// each border counts at the column it can be pulled in to. None when no
+/// line has a right border.
+fn block_target(
+    analyzed: &[AnalyzedLine],
+    block_lines: &[String],
+    block: &DiagramBlock,
+    config: &Config,
+) -> Option<usize> {
+    let borders: Vec<(usize, bool)> = analyzed
+        .iter()
+        .filter(|a| !past_protected_column(a, config))
+        .filter_map(|a| {
+            Some((
+                alignment_column(a, config)?,
+                horizontal_border_fill(a).is_some(),
+            ))
+        })
+        .collect();
+    let picked = config.target_strategy.pick(&borders)?;
+    Some(match block.annotations.width {
+        Some(width) => {
+            let left_edge = block_lines
+                .iter()
+                .filter(|l| !l.trim().is_empty())
+                .map(|l| l.len() - l.trim_start().len())
+                .min()
+                .unwrap_or(0);
+            left_edge + width - 1
+        }
+        None => picked,
+    })
+}
+
+/// Leading spaces each line needs for its left border to reach the block's
+/// dominant left-border column: the one most box rows start at, when it is
+/// shared by at least two rows and by more rows than any other column.
+/// Lines already at or past it are left alone (0), as are lines whose right
+/// border would be pushed past `target`.
+fn left_border_shifts(analyzed: &[AnalyzedLine], target: usize, config: &Config) -> Vec<usize> {
+    let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+    for line in analyzed
+        .iter()
+        .filter(|a| !past_protected_column(a, config))
+    {
+        if let Some(column) = line.left_border_column() {
+            *counts.entry(column).or_default() += 1;
+        }
+    }
+    let mut ranked: Vec<(usize, usize)> = counts.into_iter().collect();
+    ranked.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    let dominant = match ranked.as_slice() {
+        [(column, count), rest @ ..]
+            if *count >= 2 && rest.first().is_none_or(|r| r.1 < *count) =>
+        {
+            *column
+        }
+        _ => return vec![0; analyzed.len()],
+    };
+
+    analyzed
+        .iter()
+        .map(|line| {
+            let Some(column) = line.left_border_column() else {
+                return 0;
+            };
+            let shift = dominant.saturating_sub(column);
+            let fits = line
+                .suffix_border
+                .as_ref()
+                .is_some_and(|border| border.column + shift <= target);
+            if fits && !past_protected_column(line, config) {
+                shift
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+/// Byte offset of the grapheme cluster starting at a visual column, if one
+/// does
+fn byte_at_column(line: &str, column: usize) -> Option<usize> {
+    grapheme_columns(line)
+        .find(|&(_, start, _)| start >= column)
+        .filter(|&(_, start, _)| start == column)
+        .map(|(idx, _, _)| idx)
+}
+
+/// Interior column separators of a box row, as (visual column, preceding
+/// character): vertical borders and junctions between its left and right
+/// borders, plus corners joined to a horizontal run (a `+` between spaces
+/// is text). None for lines that are not closed box rows.
+fn interior_separators(line: &AnalyzedLine) -> Option<Vec<(usize, char)>> {
+    let left = line.left_border_column()?;
+    let right = line.suffix_border.as_ref()?.column;
+    let chars: Vec<(usize, char)> = grapheme_columns(&line.content)
+        .filter_map(|(_, column, grapheme)| Some((column, grapheme.chars().next()?)))
+        .collect();
+
+    let mut separators = Vec::new();
+    for (i, &(column, c)) in chars.iter().enumerate() {
+        if column <= left || column >= right {
+            continue;
+        }
+        let prev = chars[i - 1].1;
+        let next = chars.get(i + 1).map_or(' ', |&(_, c)| c);
+        let joined = is_horizontal_fill(prev) || is_horizontal_fill(next);
+        if is_vertical_border(c) || is_junction(c) || (is_corner(c) && joined) {
+            separators.push((column, prev));
+        }
+    }
+    Some(separators)
+}
+
+/// Pads that line up interior column separators, per line of the block, as
+/// (separator column, fill character, columns to insert, target column).
+///
+/// Consecutive box rows with the same number of separators form a table;
+/// each of its columns is widened to the widest cell in that column, so the
+/// k-th separators of all its rows line up. Border rows extend their
+/// horizontal run instead of inserting spaces.
+fn separator_pads(
+    analyzed: &[AnalyzedLine],
+    config: &Config,
+) -> Vec<Vec<(usize, char, usize, usize)>> {
+    let separators: Vec<Option<Vec<(usize, char)>>> = analyzed
+        .iter()
+        .map(|line| {
+            interior_separators(line)
+                .filter(|seps| !seps.is_empty() && !past_protected_column(line, config))
+        })
+        .collect();
+    let mut pads = vec![Vec::new(); analyzed.len()];
+
+    let mut start = 0;
+    while start < analyzed.len() {
+        let Some(count) = separators[start].as_ref().map(Vec::len) else {
+            start += 1;
+            continue;
+        };
+        let mut end = start + 1;
+        while separators
+            .get(end)
+            .is_some_and(|s| s.as_ref().is_some_and(|s| s.len() == count))
+        {
+            end += 1;
+        }
+
+        if end - start >= 2 {
+            // Cell widths: the distance from each separator to the previous
+            // one (or to the left border)
+            let cells: Vec<Vec<usize>> = (start..end)
+                .map(|i| {
+                    let mut previous = analyzed[i].indent;
+                    separators[i]
+                        .iter()
+                        .flatten()
+                        .map(|&(column, _)| {
+                            let width = column - previous;
+                            previous = column;
+                            width
+                        })
+                        .collect()
+                })
+                .collect();
+            let widest: Vec<usize> = (0..count)
+                .map(|k| cells.iter().map(|row| row[k]).max().unwrap_or(0))
+                .collect();
+
+            for (i, row) in (start..end).zip(&cells) {
+                let mut inserted = 0;
+                for (k, &(column, prev)) in separators[i].iter().flatten().enumerate() {
+                    let spaces = widest[k] - row[k];
+                    if spaces == 0 {
+                        continue;
+                    }
+                    inserted += spaces;
+                    let fill = if is_horizontal_fill(prev) { prev } else { ' ' };
+                    pads[i].push((column, fill, spaces, column + inserted));
+                }
+            }
+        }
+        start = end;
+    }
+
+    pads
+}
+
+/// Analyze a block's current state and propose revisions aligning its
+/// left and right borders and interior column separators.
+///
+/// Returns the analyzed block lines with the (unscored) candidates, or
+/// None when no line in the block has a right border.
+fn propose_revisions(
+    lines: &[String],
+    block: &DiagramBlock,
+    config: &Config,
+) -> Option<(Vec<AnalyzedLine>, Vec<Revision>)> {
+    let block_lines = &lines[block.start..block.end];
+    let detect = config.detect_options();
+    let analyzed: Vec<_> = block_lines
+        .iter()
+        .map(|l| analyze_line(l, &detect))
+        .collect();
+
+    // Trees only have their branches lined up; a tree line never ends in a
+    // border, so none is aligned or added. Pads go right to left so each
+    // applies at a column the others have not moved.
+    if block.kind == BlockKind::Tree {
+        let mut revisions = Vec::new();
+        for (i, line_pads) in tree_pads(block_lines).into_iter().enumerate() {
+            let width = visual_width(block_lines[i].trim_end());
+            let total: usize = line_pads.iter().map(|&(_, spaces, _)| spaces).sum();
+            for (column, spaces, target_column) in line_pads.into_iter().rev() {
+                revisions.push(Revision::PadTreeBranch {
+                    line_idx: block.start + i,
+                    column,
+                    spaces_to_add: spaces,
+                    target_column,
+                    new_width: width + total,
+                });
+            }
+        }
+        return Some((analyzed, revisions));
+    }
+
+    let target = block_target(&analyzed, block_lines, block, config)?;
+
+    // Each line aligns to the right edge of the box it is in; lines
+    // without a border get it too if their content fits
+    let regions = block_regions(&analyzed, block, target, config);
+    let region_target = |row: usize| {
+        regions
+            .iter()
+            .find(|&&(first, last, _)| (first..=last).contains(&row))
+            .map_or(target, |&(_, _, region_target)| region_target)
+    };
+
+    // Generate revision candidates
+    let mut revisions = Vec::new();
+    let border_char =
+        detect_vertical_border(&block_lines.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+
+    // A line widened at a separator or indented to its left border carries
+    // its right border along, so right-edge revisions only cover what
+    // remains of the gap. Separator pads are pushed right to left, then the
+    // indent, so each applies at columns the earlier ones have not moved,
+    // and all before a border is added. One scoring below the threshold is
+    // dropped here so the right edge closes the whole gap instead.
+    let pads = separator_pads(&analyzed, config);
+    let shifts = left_border_shifts(&analyzed, target, config);
+    let min_score = block_min_score(block, config);
+
+    for (i, analyzed_line) in analyzed.iter().enumerate() {
+        let global_idx = block.start + i;
+        if past_protected_column(analyzed_line, config) {
+            continue;
+        }
+
+        let width = visual_width(block_lines[i].trim_end());
+        let mut shift = 0;
+        for &(column, fill_char, spaces, target_column) in pads[i].iter().rev() {
+            let rev = Revision::PadBeforeSeparator {
+                line_idx: global_idx,
+                column,
+                fill_char,
+                spaces_to_add: spaces,
+                target_column,
+                new_width: width + spaces,
+            };
+            if rev.score(&analyzed, block.start, &config.scoring) >= min_score
+                && !rev.exceeds_max_pad(config)
+            {
+                revisions.push(rev);
+                shift += spaces;
+            }
+        }
+        if shifts[i] > 0 {
+            let rev = Revision::AlignLeftBorder {
+                line_idx: global_idx,
+                spaces_to_add: shifts[i],
+                target_column: analyzed_line.indent + shifts[i],
+                new_width: width + shifts[i],
+            };
+            if rev.score(&analyzed, block.start, &config.scoring) >= min_score
+                && !rev.exceeds_max_pad(config)
+            {
+                revisions.push(rev);
+                shift += shifts[i];
+            }
+        }
+
+        // A repaired corner closes a border run, which then extends with
+        // its fill like any other
+        let mut corner_fill = None;
+        if let (true, Some(border), Some((corner_char, fill))) = (
+            config.repair_corners,
+            &analyzed_line.suffix_border,
+            misplaced_corner(analyzed_line),
+        ) {
+            let rev = Revision::ReplaceCornerChar {
+                line_idx: global_idx,
+                column: border.column,
+                found: border.char,
+                corner_char,
+            };
+            if rev.score(&analyzed, block.start, &config.scoring) >= min_score {
+                revisions.push(rev);
+                corner_fill = Some(fill);
+            }
+        }
+
+        if let Some(ref border) = analyzed_line.suffix_border {
+            let column = border.column + shift;
+            // Equal to `column` unless the border can be pulled in
+            let aligned = alignment_column(analyzed_line, config).unwrap_or(border.column) + shift;
+            let target = region_target(i);
+            if column > target && target >= aligned {
+                revisions.push(Revision::TrimBeforeSuffixBorder {
+                    line_idx: global_idx,
+                    spaces_to_remove: column - target,
+                    target_column: target,
+                });
+            } else if column < target {
+                let spaces = target - column;
+                // Wide fill characters can only close gaps of whole widths
+                let fill = find_fill_run(&analyzed_line.content, &config.fill_chars)
+                    .filter(|(c, _)| spaces % char_width(*c) == 0);
+                if let Some(fill_char) = horizontal_border_fill(analyzed_line).or(corner_fill) {
+                    revisions.push(Revision::ExtendHorizontalBorder {
+                        line_idx: global_idx,
+                        fill_char,
+                        spaces_to_add: spaces,
+                        target_column: target,
+                    });
+                } else if let Some((fill_char, _)) = fill {
+                    revisions.push(Revision::ExtendFillRun {
+                        line_idx: global_idx,
+                        fill_char,
+                        spaces_to_add: spaces,
+                        target_column: target,
+                    });
+                } else {
+                    revisions.push(Revision::PadBeforeSuffixBorder {
+                        line_idx: global_idx,
+                        spaces_to_add: spaces,
+                        target_column: target,
+                    });
+                }
+            }
+        } else if analyzed_line.kind.is_boxy() {
+            // Consider adding a border
+            revisions.push(Revision::AddSuffixBorder {
+                line_idx: global_idx,
+                border_char,
+                target_column: Some(region_target(i))
+                    .filter(|&t| t >= visual_width(block_lines[i].trim_end()))
+                    .unwrap_or(target),
+            });
+        }
+    }
+
+    Some((analyzed, revisions))
+}
+
+/// Correct a single diagram block using iterative refinement.
+///
+/// This is the core correction algorithm. It runs a loop that:
+/// 1. Analyzes all lines in the block to find their border positions
+/// 2. Determines the target column (rightmost border position)
+/// 3. Generates candidate revisions to align other lines to the target
+/// 4. Scores each revision and filters by `min_score`
+/// 5. Applies valid revisions
+/// 6. Repeats until no more revisions needed or `max_iters` reached
+///
+/// # Arguments
+///
+/// * `lines` - Mutable slice of all input lines (block is modified in place)
+/// * `block` - The block to correct (defines which lines to process)
+/// * `config` - Configuration with thresholds and iteration limits
+/// * `console` - For verbose output
+///
+/// # Returns
+///
+/// A `BlockCorrectionResult` with counts of applied and skipped revisions.
+fn correct_block(
+    lines: &mut [String],
+    block: &DiagramBlock,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> BlockCorrectionResult {
+    if config.verbose {
+        if let Some(width) = block.annotations.width {
+            console.print(
+                &styles
+                    .dim(format!("    Width locked at {} column(s)", width))
+                    .to_string(),
+            );
+        }
+        if let Some(score) = block.annotations.min_score {
+            console.print(
+                &styles
+                    .dim(format!("    Min score for this block: {:.2}", score))
+                    .to_string(),
+            );
+        }
+    }
+    let min_score = block_min_score(block, config);
+    let mut total_revisions = 0;
+    let mut total_skipped = 0;
+    let mut breakdown = RevisionBreakdown::default();
+    let mut refused_lines = BTreeSet::new();
+    let mut applied = Vec::new();
+    let mut skipped = Vec::new();
+    let mut target_column = None;
+
+    for iteration in 0..config.max_iters {
+        let Some((analyzed, mut revisions)) = propose_revisions(lines, block, config) else {
+            // No borders found, nothing to align
+            break;
+        };
+        if iteration == 0 && block.kind == BlockKind::Boxes {
+            target_column = block_target(&analyzed, &lines[block.start..block.end], block, config);
+        }
+
+        if config.verbose && !config.single_target && iteration == 0 {
+            let regions = block_regions(&analyzed, block, 0, config);
+            if regions.len() > 1 {
+                let summary = regions
+                    .iter()
+                    .map(|(first, last, target)| {
+                        format!(
+                            "lines {}-{} → col {}",
+                            block.start + first + 1,
+                            block.start + last + 1,
+                            target
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                console.print(&styles.dim(format!("    Regions: {}", summary)).to_string());
+            }
+        }
+
+        // Refuse edits that would break the line length cap outright,
+        // whatever their score
+        if let Some(max) = config.max_line_length {
+            revisions.retain(|rev| {
+                let fits = rev.resulting_width() <= max;
+                if !fits {
+                    refused_lines.insert(rev.line_idx());
+                }
+                fits
+            });
+        }
+
+        // Filter by score and padding limit, and count skipped
+        let total_candidates = revisions.len();
+        let mut valid_revisions = Vec::new();
+        skipped.clear();
+        for rev in revisions {
+            let score = rev.score(&analyzed, block.start, &config.scoring);
+            let accepted = score >= min_score && !rev.exceeds_max_pad(config);
+            breakdown.record(rev.kind(), score, accepted);
+            if accepted {
+                valid_revisions.push((rev, score));
+            } else {
+                skipped.push((rev, score));
+            }
+        }
+        let skipped_this_iter = total_candidates - valid_revisions.len();
+        total_skipped += skipped_this_iter;
+
+        if valid_revisions.is_empty() {
+            // Converged
+            if config.verbose && iteration > 0 {
+                console.print(
+                    &styles
+                        .dim(format!("    Converged after {} iteration(s)", iteration))
+                        .to_string(),
+                );
+            }
+            break;
+        }
+
+        // Apply revisions
+        for (rev, _) in &valid_revisions {
+            rev.apply(lines);
+        }
+
+        total_revisions += valid_revisions.len();
+
+        if config.verbose {
+            console.print(
+                &styles
+                    .dim(format!(
+                        "    Iteration {}: applied {} revision(s)",
+                        iteration + 1,
+                        valid_revisions.len()
+                    ))
+                    .to_string(),
+            );
+        }
+        applied.extend(valid_revisions);
+    }
+
+    if config.verbose && !refused_lines.is_empty() {
+        console.print(
+            &styles
+                .dim(format!(
+                    "    Refused {} revision(s) exceeding {} columns",
+                    refused_lines.len(),
+                    config.max_line_length.unwrap_or_default()
+                ))
+                .to_string(),
+        );
+    }
+
+    BlockCorrectionResult {
+        revisions_applied: total_revisions,
+        revisions_skipped: total_skipped,
+        breakdown,
+        refused_lines,
+        applied,
+        skipped,
+        target_column,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Box Completion
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Bottom-border counterpart of a top-border piece (`┌` → `└`, `┬` → `┴`)
+fn bottom_piece(c: char) -> Option<char> {
+    Some(match c {
+        '+' => '+',
+        '┌' => '└',
+        '┐' => '┘',
+        '┬' => '┴',
+        '╭' => '╰',
+        '╮' => '╯',
+        '╔' => '╚',
+        '╗' => '╝',
+        '╦' => '╩',
+        '╤' => '╧',
+        '┏' => '┗',
+        '┓' => '┛',
+        '┳' => '┻',
+        _ => return None,
+    })
+}
+
+/// A box left open at the end of a block: its top border and the rows
+/// under it are there, but no bottom border follows
+struct OpenBox {
+    /// Line index the bottom border is inserted at (the block's end)
+    insert_at: usize,
+    /// The bottom border to insert
+    border: String,
+    /// Confidence score (`complete_box_base` × share of closed rows)
+    score: f64,
+}
+
+/// Find an open box in a block: the first line is a top border (a
+/// top-left corner through a closing corner) and every line after it is a
+/// box row with its left border under that corner. Blocks with any other
+/// border row (a bottom or a separator) are left alone.
+///
+/// The bottom border mirrors the top one, piece for piece; a title in the
+/// top border becomes plain fill.
+fn find_open_box(lines: &[String], block: &DiagramBlock, config: &Config) -> Option<OpenBox> {
+    let detect = config.detect_options();
+    let top = analyze_line(&lines[block.start], &detect);
+    let first = top.content[top.indent..].chars().next()?;
+    let closing = top.suffix_border.as_ref()?.char;
+    if !matches!(first, '+' | '┌' | '╭' | '╔' | '┏') || bottom_piece(closing).is_none() {
+        return None;
+    }
+
+    let rows: Vec<AnalyzedLine> = lines[block.start + 1..block.end]
+        .iter()
+        .map(|l| analyze_line(l, &detect))
+        .collect();
+    let opens_row = |line: &AnalyzedLine| {
+        line.indent == top.indent
+            && line.content[line.indent..]
+                .chars()
+                .next()
+                .is_some_and(is_vertical_border)
+    };
+    if rows.is_empty() || !rows.iter().all(opens_row) {
+        return None;
+    }
+    let closed = rows.iter().filter(|r| r.suffix_border.is_some()).count();
+
+    let trimmed = top.content.trim_end();
+    let fill = trimmed
+        .chars()
+        .find(|&c| is_horizontal_fill(c))
+        .unwrap_or('-');
+    let border = trimmed
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            if i < top.indent {
+                ' '
+            } else {
+                bottom_piece(c).unwrap_or(fill)
+            }
+        })
+        .collect();
+
+    Some(OpenBox {
+        insert_at: block.end,
+        border,
+        score: config.scoring.complete_box_base * closed as f64 / rows.len() as f64,
+    })
+}
+
+/// Insert bottom borders under the open boxes of `blocks` that score at
+/// least their block's threshold. Returns the insertion points, ascending,
+/// as indices into the original lines.
+fn complete_boxes(lines: &mut Vec<String>, blocks: &[DiagramBlock], config: &Config) -> Vec<usize> {
+    let open: Vec<OpenBox> = blocks
+        .iter()
+        .filter(|block| {
+            block_name_matches(block, config.block_name.as_deref())
+                && config
+                    .lines
+                    .as_ref()
+                    .is_none_or(|ranges| block_overlaps_ranges(block, ranges))
+        })
+        .filter_map(|block| {
+            find_open_box(lines, block, config)
+                .filter(|b| b.score >= block_min_score(block, config))
+        })
+        .collect();
+
+    for open_box in open.iter().rev() {
+        lines.insert(open_box.insert_at, open_box.border.clone());
+    }
+    open.iter().map(|b| b.insert_at).collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Numeric Column Alignment
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Check if a table cell holds a number (`42`, `-3.5`, `1,024`, `$9.99`, `87%`)
+fn is_numeric_cell(text: &str) -> bool {
+    let s = text
+        .trim_start_matches(['+', '-', '$', '€', '£'])
+        .trim_end_matches('%');
+    s.chars().any(|c| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, ',' | '.' | '_'))
+}
+
+/// Split a table row into the byte ranges of its cells.
+///
+/// Returns None unless the row starts and ends with the same vertical
+/// border; that border also separates the cells.
+fn split_table_row(line: &str) -> Option<Vec<(usize, usize)>> {
+    let trimmed = line.trim_end();
+    let body = trimmed.trim_start();
+    let border = body.chars().next().filter(|&c| is_vertical_border(c))?;
+    if body.chars().count() < 2 || !body.ends_with(border) {
+        return None;
+    }
+
+    let positions: Vec<usize> = trimmed
+        .char_indices()
+        .filter(|&(_, c)| c == border)
+        .map(|(i, _)| i)
+        .collect();
+    Some(
+        positions
+            .windows(2)
+            .map(|w| (w[0] + border.len_utf8(), w[1]))
+            .collect(),
+    )
+}
+
+/// Re-align cell contents in the tables of a block.
+///
+/// Rows whose borders sit at identical columns form a table. A column whose
+/// non-empty cells are mostly numeric is right-aligned (padding on the left
+/// of the number); other columns are left-aligned. Each cell keeps its
+/// width and the column's narrowest margin, so borders never move.
+///
+/// Returns the number of rows changed.
+fn align_numeric_columns(lines: &mut [String]) -> usize {
+    // Group rows by border layout, in order of first appearance
+    let mut tables: Vec<(Vec<usize>, Vec<usize>)> = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let Some(cells) = split_table_row(line) else {
+            continue;
+        };
+        let layout: Vec<usize> = cells
+            .iter()
+            .map(|&(_, end)| visual_width(&line[..end]))
+            .collect();
+        match tables.iter_mut().find(|(l, _)| *l == layout) {
+            Some((_, rows)) => rows.push(idx),
+            None => tables.push((layout, vec![idx])),
+        }
+    }
+
+    let mut changed = 0;
+    for (layout, rows) in tables.iter().filter(|(_, rows)| rows.len() >= 2) {
+        let mut new_rows: Vec<String> = rows.iter().map(|&r| lines[r].clone()).collect();
+
+        // Work right to left so earlier byte ranges stay valid
+        for col in (0..layout.len()).rev() {
+            let cells: Vec<(usize, usize)> = new_rows
+                .iter()
+                .map(|row| split_table_row(row).map_or((0, 0), |c| c[col]))
+                .collect();
+            let texts: Vec<&str> = new_rows
+                .iter()
+                .zip(&cells)
+                .map(|(row, &(s, e))| &row[s..e])
+                .filter(|t| !t.trim().is_empty())
+                .collect();
+            if texts.is_empty() {
+                continue;
+            }
+
+            let numeric = texts.iter().filter(|t| is_numeric_cell(t.trim())).count();
+            let right_align = numeric * 2 > texts.len();
+            // Both sides keep the narrowest margin found in the column
+            let margin = texts
+                .iter()
+                .map(|t| (t.len() - t.trim_start().len()).min(t.len() - t.trim_end().len()))
+                .min()
+                .unwrap_or(0);
+
+            for (row, &(start, end)) in new_rows.iter_mut().zip(&cells) {
+                let content = row[start..end].trim();
+                if content.is_empty() {
+                    continue;
+                }
+                let width = visual_width(&row[start..end]);
+                let Some(slack) = width.checked_sub(2 * margin + visual_width(content)) else {
+                    continue;
+                };
+                let (left, right) = if right_align {
+                    (margin + slack, margin)
+                } else {
+                    (margin, margin + slack)
+                };
+                let cell = format!("{}{}{}", " ".repeat(left), content, " ".repeat(right));
+                row.replace_range(start..end, &cell);
+            }
+        }
+
+        for (&r, new_row) in rows.iter().zip(new_rows) {
+            if lines[r] != new_row {
+                lines[r] = new_row;
+                changed += 1;
+            }
+        }
+    }
+
+    changed
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tables
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Layout of a table found in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputTableStyle {
+    /// MySQL (and psql `\pset border 2`): `+----+----+` separators and
+    /// `| a | b |` rows
+    Mysql,
+    /// psql: ` a | b ` rows under a `----+----` separator, no outer border
+    Psql,
+    /// GitHub-flavored Markdown pipe table: a header row, a `|---|:--:|`
+    /// delimiter row, and body rows
+    Markdown,
+}
+
+/// Horizontal alignment of a table cell's text
+#[derive(Debug, Clone, Copy)]
+enum CellAlign {
+    Left,
+    Right,
+    Center,
+}
+
+/// A table found in the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OutputTable {
+    /// Starting line index (0-based, inclusive)
+    start: usize,
+    /// Ending line index (exclusive)
+    end: usize,
+    style: OutputTableStyle,
+}
+
+/// Dash runs of a separator row: `+--+---+` (MySQL) or `---+----` (psql)
+fn separator_widths(line: &str, style: OutputTableStyle) -> Option<Vec<usize>> {
+    let trimmed = line.trim();
+    let inner = match style {
+        OutputTableStyle::Mysql => trimmed.strip_prefix('+')?.strip_suffix('+')?,
+        OutputTableStyle::Psql => trimmed,
+        OutputTableStyle::Markdown => return None,
+    };
+    let widths: Vec<usize> = inner.split('+').map(str::len).collect();
+    let dashes_only = inner.chars().all(|c| c == '-' || c == '+');
+    let valid = dashes_only && widths.iter().all(|&w| w > 0);
+    match style {
+        OutputTableStyle::Mysql => valid.then_some(widths),
+        // A plain `-----` rule is not a psql separator
+        OutputTableStyle::Psql | OutputTableStyle::Markdown => {
+            (valid && widths.len() >= 2).then_some(widths)
+        }
+    }
+}
+
+/// Cell texts of a table row with `columns` cells, untrimmed
+fn output_table_cells(line: &str, style: OutputTableStyle, columns: usize) -> Option<Vec<&str>> {
+    let inner = match style {
+        OutputTableStyle::Mysql => line.trim().strip_prefix('|')?.strip_suffix('|')?,
+        OutputTableStyle::Psql => line.trim_end(),
+        OutputTableStyle::Markdown => return None,
+    };
+    let cells: Vec<&str> = inner.split('|').collect();
+    (cells.len() == columns).then_some(cells)
+}
+
+/// Cells of a Markdown table row, trimmed. Outer pipes are optional and
+/// `\|` is an escaped pipe inside a cell. Lines without a pipe are not rows.
+fn markdown_cells(line: &str) -> Option<Vec<&str>> {
+    let trimmed = line.trim();
+    let mut pipes = Vec::new();
+    let mut escaped = false;
+    for (idx, c) in trimmed.char_indices() {
+        if c == '|' && !escaped {
+            pipes.push(idx);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    let (&first, &last) = (pipes.first()?, pipes.last()?);
+
+    let mut cells = Vec::new();
+    let mut from = 0;
+    for &pipe in &pipes {
+        cells.push(trimmed[from..pipe].trim());
+        from = pipe + 1;
+    }
+    cells.push(trimmed[from..].trim());
+    // Outer pipes leave empty pieces at the ends
+    if last == trimmed.len() - 1 {
+        cells.pop();
+    }
+    if first == 0 && !cells.is_empty() {
+        cells.remove(0);
+    }
+    Some(cells)
+}
+
+/// Column alignments of a Markdown delimiter row (`|---|:--|:-:|--:|`);
+/// `None` is a column without a colon
+fn markdown_delimiter(line: &str) -> Option<Vec<Option<CellAlign>>> {
+    let cells = markdown_cells(line)?;
+    if cells.is_empty() {
+        return None;
+    }
+    cells
+        .iter()
+        .map(|cell| {
+            let left = cell.starts_with(':');
+            let right = cell.len() > 1 && cell.ends_with(':');
+            let dashes = &cell[usize::from(left)..cell.len() - usize::from(right)];
+            if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+                return None;
+            }
+            Some(match (left, right) {
+                (true, true) => Some(CellAlign::Center),
+                (true, false) => Some(CellAlign::Left),
+                (false, true) => Some(CellAlign::Right),
+                (false, false) => None,
+            })
+        })
+        .collect()
+}
+
+/// Find tables. A MySQL table is a separator row followed by rows and
+/// separators with the same column count, ending at a separator; a psql
+/// table is a header row, a separator, and the rows below it. Tables whose
+/// rows don't all match the separator's column count (a cell containing
+/// `|`, say) are left alone. A Markdown table is a header row, a delimiter
+/// row with the same number of cells, and the rows up to the next line
+/// without a pipe.
+fn find_output_tables(lines: &[String]) -> Vec<OutputTable> {
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some(widths) = separator_widths(&lines[i], OutputTableStyle::Mysql) {
+            let columns = widths.len();
+            let mut end = i + 1;
+            let mut last_separator = i;
+            while end < lines.len() {
+                let line = &lines[end];
+                if separator_widths(line, OutputTableStyle::Mysql)
+                    .is_some_and(|w| w.len() == columns)
+                {
+                    last_separator = end;
+                } else if output_table_cells(line, OutputTableStyle::Mysql, columns).is_none() {
+                    break;
+                }
+                end += 1;
+            }
+            // The table must close with a separator right before whatever
+            // follows it, or it's part of something else (a diagram)
+            if last_separator > i + 1 && last_separator == end - 1 {
+                tables.push(OutputTable {
+                    start: i,
+                    end: last_separator + 1,
+                    style: OutputTableStyle::Mysql,
+                });
+                i = last_separator + 1;
+                continue;
+            }
+        }
+
+        if i > 0 {
+            if let Some(widths) = separator_widths(&lines[i], OutputTableStyle::Psql) {
+                let columns = widths.len();
+                let header = &lines[i - 1];
+                if !header.trim().is_empty()
+                    && output_table_cells(header, OutputTableStyle::Psql, columns).is_some()
+                {
+                    let mut end = i + 1;
+                    while end < lines.len()
+                        && !lines[end].trim().is_empty()
+                        && output_table_cells(&lines[end], OutputTableStyle::Psql, columns)
+                            .is_some()
+                    {
+                        end += 1;
+                    }
+                    if end > i + 1 {
+                        tables.push(OutputTable {
+                            start: i - 1,
+                            end,
+                            style: OutputTableStyle::Psql,
+                        });
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(aligns) = markdown_delimiter(&lines[i]) {
+                let columns = aligns.len();
+                if markdown_cells(&lines[i - 1]).is_some_and(|cells| cells.len() == columns) {
+                    let mut end = i + 1;
+                    while end < lines.len()
+                        && !lines[end].trim().is_empty()
+                        && markdown_cells(&lines[end]).is_some()
+                    {
+                        end += 1;
+                    }
+                    // Extra cells would be lost on reformatting
+                    let fits = lines[i + 1..end]
+                        .iter()
+                        .all(|line| markdown_cells(line).is_some_and(|c| c.len() <= columns));
+                    if fits {
+                        tables.push(OutputTable {
+                            start: i - 1,
+                            end,
+                            style: OutputTableStyle::Markdown,
+                        });
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+    tables
+}
+
+/// Re-align a tool-output table in place, using its separator rows as the
+/// schema: each column is as wide as its widest cell or its separator, cells
+/// get one space of margin, mostly numeric columns are right-aligned and
+/// others left-aligned (psql headers are centered, as psql prints them).
+///
+/// Returns the number of lines changed.
+fn format_output_table(lines: &mut [String], table: &OutputTable) -> usize {
+    if table.style == OutputTableStyle::Markdown {
+        return format_markdown_table(lines, table);
+    }
+    let rows = &lines[table.start..table.end];
+    let style = table.style;
+    let separator = |line: &str| separator_widths(line, style);
+    let Some((schema, indent)) = rows.iter().find_map(|line| {
+        separator(line).map(|widths| (widths, line.len() - line.trim_start().len()))
+    }) else {
+        return 0;
+    };
+    let columns = schema.len();
+
+    // Rows before the first separator with content (psql) or between the
+    // first two separators (MySQL) are headers
+    let header_end = match style {
+        OutputTableStyle::Psql | OutputTableStyle::Markdown => 1,
+        OutputTableStyle::Mysql => rows
+            .iter()
+            .skip(1)
+            .position(|line| separator(line).is_some())
+            .map_or(0, |p| p + 1),
+    };
+
+    let cells: Vec<Option<Vec<String>>> = rows
+        .iter()
+        .map(|line| {
+            if separator(line).is_some() {
+                return None;
+            }
+            let cells = output_table_cells(line, style, columns)?;
+            Some(cells.iter().map(|c| c.trim().to_string()).collect())
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = schema.iter().map(|w| w.saturating_sub(2)).collect();
+    let mut right_align = vec![false; columns];
+    for col in 0..columns {
+        let body: Vec<&str> = cells
+            .iter()
+            .enumerate()
+            .filter(|&(idx, _)| idx >= header_end)
+            .filter_map(|(_, row)| row.as_ref().map(|r| r[col].as_str()))
+            .filter(|text| !text.is_empty())
+            .collect();
+        let numeric = body.iter().filter(|t| is_numeric_cell(t)).count();
+        right_align[col] = !body.is_empty() && numeric * 2 > body.len();
+        for text in cells.iter().flatten().map(|row| &row[col]) {
+            widths[col] = widths[col].max(visual_width(text));
+        }
+    }
+
+    let pad = |text: &str, width: usize, align: CellAlign| {
+        let slack = width - visual_width(text);
+        let left = match align {
+            CellAlign::Left => 0,
+            CellAlign::Right => slack,
+            CellAlign::Center => slack / 2,
+        };
+        format!("{}{}{}", " ".repeat(left), text, " ".repeat(slack - left))
+    };
+
+    let mut changed = 0;
+    for (idx, row) in cells.iter().enumerate() {
+        let formatted = match row {
+            None => {
+                let dashes: Vec<String> = widths.iter().map(|w| "-".repeat(w + 2)).collect();
+                match style {
+                    OutputTableStyle::Mysql => format!("+{}+", dashes.join("+")),
+                    _ => dashes.join("+"),
+                }
+            }
+            Some(row) => {
+                let texts: Vec<String> = row
+                    .iter()
+                    .enumerate()
+                    .map(|(col, text)| {
+                        let align = if idx < header_end {
+                            match style {
+                                OutputTableStyle::Psql => CellAlign::Center,
+                                _ => CellAlign::Left,
+                            }
+                        } else if right_align[col] {
+                            CellAlign::Right
+                        } else {
+                            CellAlign::Left
+                        };
+                        format!(" {} ", pad(text, widths[col], align))
+                    })
+                    .collect();
+                match style {
+                    OutputTableStyle::Mysql => format!("|{}|", texts.join("|")),
+                    _ => texts.join("|").trim_end().to_string(),
+                }
+            }
+        };
+        let formatted = format!("{}{}", " ".repeat(indent), formatted);
+        let line = &mut lines[table.start + idx];
+        if *line != formatted {
+            *line = formatted;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Re-align a Markdown pipe table in place so every pipe lines up: each
+/// column is as wide as its widest cell (at least 3, the shortest
+/// delimiter), cells are padded per the delimiter's alignment, and the
+/// delimiter row is redrawn to the column width with its colons kept.
+/// Rows with missing cells get empty ones. Outer pipes are written when
+/// the header has them.
+///
+/// Returns the number of lines changed.
+fn format_markdown_table(lines: &mut [String], table: &OutputTable) -> usize {
+    let header = &lines[table.start];
+    let indent = header.len() - header.trim_start().len();
+    let outer = header.trim_start().starts_with('|');
+    let Some(aligns) = markdown_delimiter(&lines[table.start + 1]) else {
+        return 0;
+    };
+    let columns = aligns.len();
+
+    let rows: Vec<Vec<String>> = lines[table.start..table.end]
+        .iter()
+        .map(|line| {
+            let mut cells: Vec<String> = markdown_cells(line)
+                .unwrap_or_default()
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+            cells.resize(columns, String::new());
+            cells
+        })
+        .collect();
+
+    let mut widths = vec![3; columns];
+    for (idx, row) in rows.iter().enumerate() {
+        if idx == 1 {
+            continue;
+        }
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(visual_width(cell));
+        }
+    }
+
+    let mut changed = 0;
+    for (idx, row) in rows.iter().enumerate() {
+        let cells: Vec<String> = (0..columns)
+            .map(|col| {
+                let width = widths[col];
+                if idx == 1 {
+                    return match aligns[col] {
+                        None => "-".repeat(width),
+                        Some(CellAlign::Left) => format!(":{}", "-".repeat(width - 1)),
+                        Some(CellAlign::Right) => format!("{}:", "-".repeat(width - 1)),
+                        Some(CellAlign::Center) => format!(":{}:", "-".repeat(width - 2)),
+                    };
+                }
+                let text = &row[col];
+                let slack = width - visual_width(text);
+                let left = match aligns[col] {
+                    Some(CellAlign::Right) => slack,
+                    Some(CellAlign::Center) => slack / 2,
+                    _ => 0,
+                };
+                format!("{}{}{}", " ".repeat(left), text, " ".repeat(slack - left))
+            })
+            .collect();
+
+        let body = cells.join(" | ");
+        let formatted = if outer {
+            format!("{}| {} |", " ".repeat(indent), body)
+        } else {
+            format!("{}{}", " ".repeat(indent), body.trim_end())
+        };
+        let line = &mut lines[table.start + idx];
+        if *line != formatted {
+            *line = formatted;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Main Correction Logic
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Expand tabs to spaces, accounting for character visual width.
+///
+/// Tab stops are calculated based on visual columns, not character count.
+/// This ensures correct alignment when CJK or other wide characters are present.
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut col = 0;
+
+    for (_, unit) in display_units(line) {
+        if unit == "\t" {
+            let spaces = tab_width - (col % tab_width);
+            result.extend(std::iter::repeat_n(' ', spaces));
+            col += spaces;
+        } else {
+            result.push_str(unit);
+            col += unit_width(unit);
+        }
+    }
+
+    result
+}
+
+/// Re-indent a line with tabs: its leading spaces become as many tabs as
+/// fit, followed by the remaining spaces
+fn retab_indent(line: &str, tab_width: usize) -> String {
+    let body = line.trim_start_matches(' ');
+    let indent = line.len() - body.len();
+    let mut result = "\t".repeat(indent / tab_width);
+    result.extend(std::iter::repeat_n(' ', indent % tab_width));
+    result.push_str(body);
+    result
+}
+
+/// Undo tab expansion where it changed nothing else: lines outside the
+/// `processed` blocks (and, with `--keep-tabs`, any line) whose expanded
+/// content was not revised get their original text back. With
+/// `--keep-tabs`, revised lines that were tab-indented get their
+/// indentation converted back to tabs. Lines at `inserted` (closed boxes)
+/// have no original and are left alone.
+fn restore_tabs(
+    lines: &mut [String],
+    originals: &[String],
+    inserted: &[usize],
+    processed: &[(usize, usize)],
+    config: &Config,
+) {
+    for (idx, line) in lines.iter_mut().enumerate() {
+        if inserted.contains(&idx) {
+            continue;
+        }
+        let original = &originals[idx - inserted.partition_point(|&at| at < idx)];
+        if !original.contains('\t') || *line == *original {
+            continue;
+        }
+        let in_block = processed
+            .iter()
+            .any(|&(start, end)| (start..end).contains(&idx));
+        if (config.keep_tabs || !in_block) && *line == expand_tabs(original, config.tab_width) {
+            line.clone_from(original);
+        } else if config.keep_tabs
+            && original
+                .chars()
+                .take_while(|c| c.is_whitespace())
+                .any(|c| c == '\t')
+        {
+            *line = retab_indent(line, config.tab_width);
+        }
+    }
+}
+
+/// Check if a block overlaps with any of the given line ranges
+/// Block indices are 0-indexed, ranges are 1-indexed
+fn block_overlaps_ranges(block: &DiagramBlock, ranges: &[LineRange]) -> bool {
+    // Convert block to 1-indexed for comparison with ranges
+    let block_start = block.start + 1;
+    let block_end = block.end; // end is already exclusive, so it's effectively 1-indexed
+
+    ranges.iter().any(|r| {
+        // Check if block and range overlap
+        block_start <= r.end && block_end >= r.start
+    })
+}
+
+/// Check if a span of lines (0-indexed, end exclusive) overlaps any of the given ranges
+fn span_overlaps_ranges(start: usize, end: usize, ranges: &[LineRange]) -> bool {
+    ranges.iter().any(|r| start < r.end && end >= r.start)
+}
+
+/// Format line ranges for display
+fn format_line_ranges(ranges: &[LineRange], total_lines: usize) -> String {
+    let range_strs: Vec<String> = ranges
+        .iter()
+        .map(|r| {
+            if r.end == usize::MAX {
+                format!("{}-", r.start)
+            } else if r.start == r.end {
+                format!("{}", r.start)
+            } else {
+                format!("{}-{}", r.start, r.end)
+            }
+        })
+        .collect();
+
+    // Calculate how many lines are covered
+    let covered: usize = ranges
+        .iter()
+        .map(|r| {
+            let effective_end = r.end.min(total_lines);
+            if r.start <= effective_end {
+                effective_end - r.start + 1
+            } else {
+                0
+            }
+        })
+        .sum();
+
+    format!(
+        "{} ({} of {} lines)",
+        range_strs.join(", "),
+        covered,
+        total_lines
+    )
+}
+
+/// Correct the diagram blocks in `lines` with `config`, returning the
+/// corrected lines and [`Stats`]. Nothing is printed, even with `verbose`
+/// set in the configuration.
+pub fn correct(lines: Vec<String>, config: &Config) -> (Vec<String>, Stats) {
+    let mut config = config.clone();
+    config.verbose = false;
+    correct_lines(lines, &config, &Console::new(), &VerboseStyle::new(false))
+}
+
+/// Main correction entry point. Verbose progress (with `verbose` set in
+/// the configuration) is printed to `console`.
+pub(crate) fn correct_lines(
+    lines: Vec<String>,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> (Vec<String>, Stats) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = Instant::now();
+    let mut stats = Stats::default();
+    let total_lines = lines.len();
+    stats.total_lines = total_lines;
+
+    // Show line range info in verbose mode
+    if config.verbose {
+        if let Some(ref ranges) = config.lines {
+            console.print(
+                &styles
+                    .header(format!(
+                        "Line ranges: {}",
+                        format_line_ranges(ranges, total_lines)
+                    ))
+                    .to_string(),
+            );
+        }
+    }
+
+    if !config.all_blocks {
+        let scan = quick_scan_for_diagrams(&lines);
+        if !scan.likely_has_diagrams {
+            if config.verbose {
+                console.print(
+                    &styles
+                        .dim(format!(
+                            "Quick scan: no diagrams detected ({}/{} lines, {:.1}% box chars < {:.1}% threshold)",
+                            scan.lines_with_box_chars,
+                            scan.lines_scanned,
+                            scan.ratio * 100.0,
+                            QUICK_SCAN_THRESHOLD * 100.0
+                        ))
+                        .to_string(),
+                );
+                console.print(
+                    &styles.dim("Passing through unchanged (use --all to force processing)"),
+                );
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                stats.elapsed = start_time.elapsed();
+            }
+            return (lines, stats);
+        }
+    }
+
+    // Expand tabs (aadc:off regions pass through untouched)
+    if config.verbose {
+        for (start, end) in disabled_ranges(&lines) {
+            console.print(
+                &styles
+                    .dim(format!("aadc:off: lines {}-{} left as is", start + 1, end))
+                    .to_string(),
+            );
+        }
+    }
+    // Detection and revision work on the expanded lines; lines outside
+    // the processed blocks get their original text back at the end
+    let originals = lines
+        .iter()
+        .any(|line| line.contains('\t'))
+        .then(|| lines.clone());
+    let mut lines = expand_enabled_tabs(lines, config.tab_width);
+
+    // Tables are re-aligned as tables, and kept out of diagram
+    // blocks. They have no revision IDs or block names, so --apply-ids and
+    // --block-name skip them.
+    let mut detect = config.detect_options();
+    if config.format_tables && config.apply_ids.is_none() && config.block_name.is_none() {
+        let disabled = disabled_ranges(&lines);
+        for table in find_output_tables(&lines) {
+            detect.skip_ranges.push((table.start, table.end));
+            if overlaps_disabled(&disabled, table.start, table.end) {
+                continue;
+            }
+            if let Some(ref ranges) = config.lines {
+                if !(table.start + 1..=table.end).any(|n| line_in_ranges(n, ranges)) {
+                    continue;
+                }
+            }
+            let changed = format_output_table(&mut lines, &table);
+            if config.verbose {
+                console.print(
+                    &styles
+                        .dim(format!(
+                            "Table: lines {}-{} ({:?}), re-aligned {} line(s)",
+                            table.start + 1,
+                            table.end,
+                            table.style,
+                            changed
+                        ))
+                        .to_string(),
+                );
+            }
+            if changed > 0 {
+                stats.total_revisions += changed;
+                stats
+                    .breakdown
+                    .by_kind
+                    .entry("align_table")
+                    .or_default()
+                    .applied += changed;
+            }
+        }
+    }
+
+    // Find diagram blocks, closing open boxes first so that every line
+    // number from here on refers to the output. Bottom borders have no
+    // revision IDs, so --apply-ids skips them.
+    let mut blocks = find_diagram_blocks(&lines, &detect);
+    let mut completed = Vec::new();
+    if config.complete_boxes && config.apply_ids.is_none() {
+        let inserted = complete_boxes(&mut lines, &blocks, config);
+        if !inserted.is_empty() {
+            for range in &mut detect.skip_ranges {
+                let before = inserted.iter().filter(|&&at| at <= range.0).count();
+                *range = (range.0 + before, range.1 + before);
+            }
+            // Each insertion shifts the ones after it down a line
+            completed = inserted.iter().enumerate().map(|(k, at)| at + k).collect();
+            blocks = find_diagram_blocks(&lines, &detect);
+            stats.total_lines = lines.len();
+            stats.total_revisions += completed.len();
+            stats
+                .breakdown
+                .by_kind
+                .entry("complete_box")
+                .or_default()
+                .applied += completed.len();
+            if config.verbose {
+                console.print(
+                    &styles
+                        .dim(format!("Closed {} open box(es)", completed.len()))
+                        .to_string(),
+                );
+            }
+        }
+    }
+    stats.long_lines = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| detect.too_long(line))
+        .map(|(i, _)| i)
+        .collect();
+    stats.blocks_found = blocks.len();
+
+    if config.verbose {
+        console.print(
+            &styles
+                .header(format!("Found {} diagram block(s)", blocks.len()))
+                .to_string(),
+        );
+    }
+
+    // Blocks are reported as skipped until they are processed; fingerprints
+    // and styles are filled in from the final lines
+    let mut reports: Vec<BlockReport> = blocks
+        .iter()
+        .map(|block| BlockReport {
+            kind: block.kind,
+            start: block.start,
+            end: block.end,
+            // Hashed before correction, like `extract`, so a block keeps
+            // its fingerprint whether or not it gets aligned
+            fingerprint: block_fingerprint(&lines[block.start..block.end]),
+            name: block.annotations.name.clone(),
+            confidence: block.confidence,
+            style: None,
+            target_column: None,
+            status: BlockStatus::Skipped,
+            revisions: Vec::new(),
+            skipped: Vec::new(),
+        })
+        .collect();
+
+    // Correct each block
+    for (i, block) in blocks.iter().enumerate() {
+        // Check if block overlaps with line ranges (if specified)
+        if let Some(ref ranges) = config.lines {
+            if !block_overlaps_ranges(block, ranges) {
+                if config.verbose {
+                    console.print(
+                        &styles
+                            .dim(format!(
+                                "  Block {}: lines {}-{} (skipped: outside line ranges)",
+                                i + 1,
+                                block.start + 1,
+                                block.end
+                            ))
+                            .to_string(),
+                    );
+                }
+                stats.blocks_skipped += 1;
+                continue;
+            }
+        }
+
+        if !block_name_matches(block, config.block_name.as_deref()) {
+            if config.verbose {
+                console.print(
+                    &styles
+                        .dim(format!(
+                            "  Block {}: lines {}-{} (skipped: not the named block)",
+                            i + 1,
+                            block.start + 1,
+                            block.end
+                        ))
+                        .to_string(),
+                );
+            }
+            stats.blocks_skipped += 1;
+            continue;
+        }
+
+        if config.verbose {
+            console.print(
+                &styles
+                    .block(format!(
+                        "  Block {}: lines {}-{} (confidence: {:.0}%)",
+                        i + 1,
+                        block.start + 1,
+                        block.end,
+                        block.confidence * 100.0
+                    ))
+                    .to_string(),
+            );
+        }
+
+        let original_block = config
+            .apply_ids
+            .is_some()
+            .then(|| lines[block.start..block.end].to_vec());
+        let result = correct_block(&mut lines, block, config, console, styles);
+        let mut revisions_applied = result.revisions_applied;
+
+        // Number the revisions; with --apply-ids, redo the block applying
+        // only the selected ones (revisions edit single lines, so a subset
+        // replays cleanly on the original block)
+        if let Some(original) = original_block {
+            lines[block.start..block.end].clone_from_slice(&original);
+            revisions_applied = 0;
+        }
+        let first_record = stats.revisions.len();
+        for (rev, score) in &result.applied {
+            let id = stats.revisions.len() + 1;
+            let selected = config
+                .apply_ids
+                .as_ref()
+                .is_none_or(|ids| line_in_ranges(id, ids));
+            if config.apply_ids.is_some() && selected {
+                rev.apply(&mut lines);
+                revisions_applied += 1;
+            }
+            if config.verbose && config.dry_run {
+                console.print(
+                    &styles
+                        .dim(format!(
+                            "    #{} line {}: {} (score {:.2}{})",
+                            id,
+                            rev.line_idx() + 1,
+                            rev.describe(),
+                            score,
+                            if selected { "" } else { ", not selected" }
+                        ))
+                        .to_string(),
+                );
+            }
+            let (column, problem) = rev.problem();
+            stats.revisions.push(RevisionRecord {
+                id,
+                line: rev.line_idx() + 1,
+                kind: rev.kind(),
+                score: *score,
+                description: rev.describe(),
+                column,
+                problem,
+                applied: selected,
+            });
+        }
+
+        // Cell re-alignment has no revision IDs, so --apply-ids skips it
+        if config.align_numbers && config.apply_ids.is_none() {
+            let aligned = align_numeric_columns(&mut lines[block.start..block.end]);
+            if config.verbose && aligned > 0 {
+                console.print(
+                    &styles
+                        .dim(format!("    Re-aligned cells in {} row(s)", aligned))
+                        .to_string(),
+                );
+            }
+            revisions_applied += aligned;
+            // Cell re-alignment is unscored, so it has no decile
+            if aligned > 0 {
+                stats
+                    .breakdown
+                    .by_kind
+                    .entry("align_cells")
+                    .or_default()
+                    .applied += aligned;
+            }
+        }
+        let closed = completed
+            .iter()
+            .filter(|&&at| (block.start..block.end).contains(&at))
+            .count();
+        let report = &mut reports[i];
+        report.status = if revisions_applied > 0 || closed > 0 {
+            BlockStatus::Modified
+        } else {
+            BlockStatus::Unchanged
+        };
+        report.target_column = result.target_column.map(|column| column + 1);
+        report.revisions = stats.revisions[first_record..].to_vec();
+        report.skipped = result
+            .skipped
+            .iter()
+            .map(|(rev, score)| SkippedRevision {
+                line: rev.line_idx() + 1,
+                kind: rev.kind(),
+                score: *score,
+                description: rev.describe(),
+            })
+            .collect();
+
+        if revisions_applied > 0 || closed > 0 {
+            stats.blocks_modified += 1;
+            stats.total_revisions += revisions_applied;
+        }
+        stats.revisions_skipped += result.revisions_skipped;
+        stats.breakdown.merge(&result.breakdown);
+        stats.revisions_refused += result.refused_lines.len();
+        stats.refused_lines.extend(result.refused_lines);
+    }
+
+    // Revisions never add or remove lines (bottom borders were inserted
+    // before detection), so block ranges are still valid
+    for (report, block) in reports.iter_mut().zip(&blocks) {
+        report.style = block_style(&lines, block);
+    }
+    stats.blocks = reports;
+
+    if let Some(originals) = originals {
+        let processed: Vec<(usize, usize)> = stats
+            .blocks
+            .iter()
+            .filter(|report| report.status != BlockStatus::Skipped)
+            .map(|report| (report.start, report.end))
+            .collect();
+        restore_tabs(&mut lines, &originals, &completed, &processed, config);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        stats.elapsed = start_time.elapsed();
+    }
+    (lines, stats)
+}
+
+/// Check a block against `--block-name` (no name selected matches all)
+fn block_name_matches(block: &DiagramBlock, name: Option<&str>) -> bool {
+    name.is_none_or(|name| block.annotations.name.as_deref() == Some(name))
+}
+
+/// Compute a stable content fingerprint for a diagram block.
+///
+/// All whitespace is dropped and blank lines skipped before hashing (with
+/// [`fnv1a`]), so the fingerprint survives line shifts, re-indentation and
+/// alignment padding.
+fn block_fingerprint(lines: &[String]) -> String {
+    let normalized = lines
+        .iter()
+        .map(|l| l.chars().filter(|c| !c.is_whitespace()).collect::<String>())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!("{:016x}", fnv1a(normalized.as_bytes()))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Embedded Diagrams
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// File syntaxes whose string values can carry embedded diagrams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmbedSyntax {
+    /// YAML: literal block scalars (`key: |`) and double-quoted scalars
+    Yaml,
+    /// JSON: string values containing `\n` escapes
+    Json,
+    /// Python: triple-quoted docstrings
+    Python,
+    /// Go: backtick raw strings
+    Go,
+    /// JavaScript/TypeScript: template literals (`${...}` kept opaque)
+    JavaScript,
+}
+
+impl EmbedSyntax {
+    /// Pick the embedded syntax from a file name's extension
+    fn from_path(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "yaml" | "yml" => Some(Self::Yaml),
+            "json" => Some(Self::Json),
+            "py" | "pyi" => Some(Self::Python),
+            "go" => Some(Self::Go),
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "mts" | "cts" | "tsx" => Some(Self::JavaScript),
+            _ => None,
+        }
+    }
+
+    /// Human-readable name of the multi-line regions this syntax provides
+    fn region_label(self) -> &'static str {
+        match self {
+            Self::Yaml | Self::Json => "Block scalar",
+            Self::Python => "Docstring",
+            Self::Go => "Raw string",
+            Self::JavaScript => "Template literal",
+        }
+    }
+
+    /// Whether backtick-delimited raw strings carry embedded diagrams
+    fn has_backtick_strings(self) -> bool {
+        matches!(self, Self::Go | Self::JavaScript)
+    }
+
+    /// Whether single-line `\n`-escaped strings are corrected too
+    fn has_escaped_strings(self) -> bool {
+        matches!(self, Self::Yaml | Self::Json)
+    }
+}
+
+/// Multi-line text region embedded in a file (block scalar, docstring).
+///
+/// The region is corrected as if it were a standalone document after
+/// stripping its common indentation.
+#[derive(Debug, PartialEq, Eq)]
+struct IndentedRegion {
+    /// First content line (0-based, inclusive)
+    start: usize,
+    /// Last content line (0-based, exclusive)
+    end: usize,
+    /// Indentation stripped from every content line
+    indent: usize,
+}
+
+/// Number of leading space characters
+fn leading_spaces(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Parse a literal block scalar header (`key: |`, `- |-`, `key: |2+`).
+///
+/// Returns `Some(explicit_indent)` when the line opens a literal block,
+/// where `explicit_indent` is the optional indentation indicator.
+fn yaml_literal_header(line: &str) -> Option<Option<usize>> {
+    let trimmed = line.trim_end();
+    let code = match trimmed.find(" #") {
+        Some(pos) => trimmed[..pos].trim_end(),
+        None => trimmed,
+    };
+
+    let (prefix, token) = match code.rfind(char::is_whitespace) {
+        Some(pos) => (code[..pos].trim_end(), &code[pos + 1..]),
+        None => ("", code),
+    };
+    if !(prefix.ends_with(':') || prefix.ends_with('-')) {
+        return None;
+    }
+
+    let indicators = token.strip_prefix('|')?;
+    if indicators.len() > 2 {
+        return None;
+    }
+    let mut explicit = None;
+    for c in indicators.chars() {
+        match c {
+            '+' | '-' => {}
+            '1'..='9' => explicit = c.to_digit(10).map(|d| d as usize),
+            _ => return None,
+        }
+    }
+    Some(explicit)
+}
+
+/// Find literal block scalars in a YAML document
+fn find_yaml_block_scalars(lines: &[String]) -> Vec<IndentedRegion> {
+    let mut scalars = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let Some(explicit) = yaml_literal_header(&lines[i]) else {
+            i += 1;
+            continue;
+        };
+
+        let header_indent = leading_spaces(&lines[i]);
+        let indent = match explicit {
+            Some(n) => header_indent + n,
+            None => match lines[i + 1..].iter().find(|l| !l.trim().is_empty()) {
+                Some(first) => leading_spaces(first),
+                None => break,
+            },
+        };
+        if indent <= header_indent {
+            i += 1;
+            continue;
+        }
+
+        // Content runs until the first non-blank line indented less than the block
+        let mut last_content = None;
+        let mut j = i + 1;
+        while j < lines.len() {
+            let line = &lines[j];
+            if !line.trim().is_empty() {
+                if leading_spaces(line) < indent {
+                    break;
+                }
+                last_content = Some(j);
+            }
+            j += 1;
+        }
+
+        match last_content {
+            Some(last) => {
+                scalars.push(IndentedRegion {
+                    start: i + 1,
+                    end: last + 1,
+                    indent,
+                });
+                i = last + 1;
+            }
+            None => i += 1,
+        }
+    }
+
+    scalars
+}
+
+/// Find multi-line triple-quoted docstrings in Python source.
+///
+/// Every triple-quoted literal is tracked from its opening to its closing
+/// quotes (skipping comments and one-line strings), so the closing quotes
+/// of `SQL = """...` are never taken for an opening. Only literals that
+/// open a statement (docstring position) become regions, covering the
+/// lines strictly between the opening and closing quotes.
+fn find_python_docstrings(lines: &[String]) -> Vec<IndentedRegion> {
+    let mut regions = Vec::new();
+    // Quote, opening line and docstring position of the open literal
+    let mut open: Option<(&[u8], usize, bool)> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let bytes = line.as_bytes();
+        let mut j = 0;
+        loop {
+            if let Some((quote, start, docstring)) = open {
+                let Some(close) = find_triple_quote(bytes, j, quote) else {
+                    break;
+                };
+                j = close + 3;
+                open = None;
+                // One-line docstrings have nothing to align
+                if docstring && i > start {
+                    let indent = lines[start + 1..i]
+                        .iter()
+                        .filter(|l| !l.trim().is_empty())
+                        .map(|l| leading_spaces(l))
+                        .min();
+                    if let Some(indent) = indent {
+                        regions.push(IndentedRegion {
+                            start: start + 1,
+                            end: i,
+                            indent,
+                        });
+                    }
+                }
+                continue;
+            }
+
+            match bytes.get(j) {
+                None | Some(b'#') => break,
+                Some(b'"' | b'\'') => {
+                    let quote = &bytes[j..(j + 3).min(bytes.len())];
+                    if quote == b"\"\"\"" || quote == b"\'\'\'" {
+                        let prefix = line[..j].trim_start();
+                        let docstring =
+                            prefix.is_empty() || matches!(prefix, "r" | "R" | "u" | "U");
+                        open = Some((quote, i, docstring));
+                        j += 3;
+                    } else {
+                        j = skip_quoted(bytes, j);
+                    }
+                }
+                Some(_) => j += 1,
+            }
+        }
+    }
+
+    regions
+}
+
+/// Byte offset of the first unescaped `quote` (a triple quote) in `bytes`
+/// at or after `from`
+fn find_triple_quote(bytes: &[u8], from: usize, quote: &[u8]) -> Option<usize> {
+    let mut k = from;
+    while k < bytes.len() {
+        if bytes[k] == b'\\' {
+            k += 2;
+        } else if bytes[k..].starts_with(quote) {
+            return Some(k);
+        } else {
+            k += 1;
+        }
+    }
+    None
+}
+
+/// A backtick-delimited raw string spanning several lines.
+///
+/// Byte offsets delimit the string value (backticks excluded) within the
+/// first and last lines; lines in between belong entirely to the value.
+#[derive(Debug, PartialEq, Eq)]
+struct RawStringSpan {
+    /// Line containing the opening backtick (0-based)
+    start_line: usize,
+    /// Byte offset just past the opening backtick
+    start_byte: usize,
+    /// Line containing the closing backtick (0-based)
+    end_line: usize,
+    /// Byte offset of the closing backtick
+    end_byte: usize,
+}
+
+impl RawStringSpan {
+    /// Byte range of the string value within line `idx`
+    fn segment(&self, idx: usize, line: &str) -> (usize, usize) {
+        let start = if idx == self.start_line {
+            self.start_byte
+        } else {
+            0
+        };
+        let end = if idx == self.end_line {
+            self.end_byte
+        } else {
+            line.len()
+        };
+        (start, end)
+    }
+}
+
+/// Skip a `'...'` or `"..."` literal starting at its opening quote.
+/// Returns the index just past the closing quote (or the end of the line).
+fn skip_quoted(bytes: &[u8], i: usize) -> usize {
+    let quote = bytes[i];
+    let mut j = i + 1;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' => j += 2,
+            c if c == quote => return j + 1,
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Skip a `${...}` placeholder starting at its `$`, honoring nested braces.
+/// Returns the index just past the closing brace (or the end of the line).
+fn skip_placeholder(bytes: &[u8], i: usize) -> usize {
+    let mut depth = 0;
+    let mut j = i + 1;
+    while j < bytes.len() {
+        match bytes[j] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return j + 1;
+                }
+            }
+            _ => {}
+        }
+        j += 1;
+    }
+    bytes.len()
+}
+
+/// Find multi-line backtick strings (Go raw strings, JS template literals).
+///
+/// Quoted literals and `//` comments are skipped so stray backticks inside
+/// them don't open a string. Single-line raw strings are ignored.
+fn find_backtick_strings(lines: &[String], syntax: EmbedSyntax) -> Vec<RawStringSpan> {
+    let template = syntax == EmbedSyntax::JavaScript;
+    let mut spans = Vec::new();
+    let mut open: Option<(usize, usize)> = None;
+
+    for (idx, line) in lines.iter().enumerate() {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if let Some((start_line, start_byte)) = open {
+                match bytes[i] {
+                    b'\\' if template => i += 2,
+                    b'$' if template && bytes.get(i + 1) == Some(&b'{') => {
+                        i = skip_placeholder(bytes, i);
+                    }
+                    b'`' => {
+                        if idx > start_line {
+                            spans.push(RawStringSpan {
+                                start_line,
+                                start_byte,
+                                end_line: idx,
+                                end_byte: i,
+                            });
+                        }
+                        open = None;
+                        i += 1;
+                    }
+                    _ => i += 1,
+                }
+            } else {
+                match bytes[i] {
+                    b'/' if bytes.get(i + 1) == Some(&b'/') => break,
+                    b'"' | b'\'' => i = skip_quoted(bytes, i),
+                    b'`' => {
+                        open = Some((idx, i + 1));
+                        i += 1;
+                    }
+                    _ => i += 1,
+                }
+            }
+        }
+    }
+
+    spans
+}
+
+/// Stand-in for one column of a masked `${...}` placeholder: one column
+/// wide, not whitespace, and never a box-drawing character (a private-use
+/// code point, which no real template contains).
+const PLACEHOLDER_MASK: char = '\u{E000}';
+
+/// Replace `${...}` placeholders with mask runs of the same visual width so
+/// the corrector treats them as opaque fixed-width tokens.
+///
+/// Returns the masked text and the original placeholders in order.
+fn mask_placeholders(text: &str) -> (String, Vec<String>) {
+    let bytes = text.as_bytes();
+    let mut masked = String::with_capacity(text.len());
+    let mut placeholders = Vec::new();
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                let end = skip_placeholder(bytes, i);
+                let placeholder = &text[i..end];
+                masked.push_str(&text[last..i]);
+                masked.extend(std::iter::repeat_n(
+                    PLACEHOLDER_MASK,
+                    visual_width(placeholder),
+                ));
+                placeholders.push(placeholder.to_string());
+                last = end;
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+
+    masked.push_str(&text[last..]);
+    (masked, placeholders)
+}
+
+/// Restore placeholders masked by [`mask_placeholders`]
+fn unmask_placeholders(text: &str, placeholders: &[String]) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut pending = placeholders.iter();
+    let mut skip = 0;
+
+    for c in text.chars() {
+        if c == PLACEHOLDER_MASK {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            if let Some(placeholder) = pending.next() {
+                output.push_str(placeholder);
+                skip = visual_width(placeholder).saturating_sub(1);
+                continue;
+            }
+        }
+        output.push(c);
+    }
+
+    output
+}
+
+/// Byte spans of double-quoted string literals on a line (quotes included)
+fn find_quoted_strings(line: &str) -> Vec<(usize, usize)> {
+    let bytes = line.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'"' {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        i += 1;
+        let mut closed = false;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'"' => {
+                    closed = true;
+                    break;
+                }
+                _ => i += 1,
+            }
+        }
+
+        if !closed {
+            break;
+        }
+        spans.push((start, i + 1));
+        i += 1;
+    }
+
+    spans
+}
+
+/// Correct diagrams inside escaped (`\n`-separated) string literals on one line
+fn correct_escaped_strings(
+    line: &str,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> (String, Stats) {
+    let mut stats = Stats::default();
+    let mut output = String::with_capacity(line.len());
+    let mut last = 0;
+
+    for (start, end) in find_quoted_strings(line) {
+        let literal = &line[start..end];
+        if !literal.contains("\\n") {
+            continue;
+        }
+        let Ok(value) = serde_json::from_str::<String>(literal) else {
+            continue;
+        };
+
+        let value_lines: Vec<String> = value.split('\n').map(String::from).collect();
+        let (corrected, value_stats) = correct_lines(value_lines, config, console, styles);
+        stats.merge(&value_stats);
+
+        let joined = corrected.join("\n");
+        if joined != value {
+            if let Ok(encoded) = serde_json::to_string(&joined) {
+                output.push_str(&line[last..start]);
+                output.push_str(&encoded);
+                last = end;
+            }
+        }
+    }
+
+    output.push_str(&line[last..]);
+    (output, stats)
+}
+
+/// Correct diagrams embedded in the string values of a YAML/JSON document,
+/// the docstrings of a Python module, or Go/JS raw string literals.
+///
+/// Only string contents are touched: multi-line regions (block scalars,
+/// docstrings) are dedented, corrected, and re-indented; raw strings are
+/// corrected as the string value they denote; escaped strings are
+/// decoded, corrected, and re-encoded in place. Everything outside string
+/// values passes through, as do strings touching an `aadc:off` region.
+fn correct_embedded(
+    lines: Vec<String>,
+    syntax: EmbedSyntax,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> (Vec<String>, Stats) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let start_time = Instant::now();
+    let mut stats = Stats::default();
+    let total_lines = lines.len();
+    let mut lines = lines;
+
+    // Line ranges refer to the whole document, not to individual strings
+    // Regions are written back line for line, so no lines may be added
+    let region_config = Config {
+        lines: None,
+        complete_boxes: false,
+        ..config.clone()
+    };
+
+    let regions = match syntax {
+        EmbedSyntax::Yaml => find_yaml_block_scalars(&lines),
+        EmbedSyntax::Python => find_python_docstrings(&lines),
+        EmbedSyntax::Json | EmbedSyntax::Go | EmbedSyntax::JavaScript => Vec::new(),
+    };
+
+    let disabled = disabled_ranges(&lines);
+    let mut in_region = vec![false; lines.len()];
+    for region in regions {
+        in_region[region.start..region.end].fill(true);
+        if overlaps_disabled(&disabled, region.start, region.end) {
+            continue;
+        }
+
+        if let Some(ref ranges) = config.lines {
+            if !span_overlaps_ranges(region.start, region.end, ranges) {
+                continue;
+            }
+        }
+
+        if config.verbose {
+            console.print(
+                &styles
+                    .block(format!(
+                        "{}: lines {}-{}",
+                        syntax.region_label(),
+                        region.start + 1,
+                        region.end
+                    ))
+                    .to_string(),
+            );
+        }
+
+        let dedented: Vec<String> = lines[region.start..region.end]
+            .iter()
+            .map(|l| l.get(region.indent..).unwrap_or("").to_string())
+            .collect();
+        let (corrected, region_stats) =
+            correct_lines(dedented.clone(), &region_config, console, styles);
+        stats.merge(&region_stats);
+
+        for (offset, (before, after)) in dedented.iter().zip(corrected).enumerate() {
+            if *before != after {
+                lines[region.start + offset] = format!("{}{}", " ".repeat(region.indent), after);
+            }
+        }
+    }
+
+    if syntax.has_backtick_strings() {
+        let template = syntax == EmbedSyntax::JavaScript;
+        for span in find_backtick_strings(&lines, syntax) {
+            if overlaps_disabled(&disabled, span.start_line, span.end_line + 1) {
+                continue;
+            }
+            if let Some(ref ranges) = config.lines {
+                if !span_overlaps_ranges(span.start_line, span.end_line + 1, ranges) {
+                    continue;
+                }
+            }
+
+            if config.verbose {
+                console.print(
+                    &styles
+                        .block(format!(
+                            "{}: lines {}-{}",
+                            syntax.region_label(),
+                            span.start_line + 1,
+                            span.end_line + 1
+                        ))
+                        .to_string(),
+                );
+            }
+
+            let mut placeholders = Vec::new();
+            let segments: Vec<String> = (span.start_line..=span.end_line)
+                .map(|idx| {
+                    let (start, end) = span.segment(idx, &lines[idx]);
+                    let value = &lines[idx][start..end];
+                    let (masked, found) = if template {
+                        mask_placeholders(value)
+                    } else {
+                        (value.to_string(), Vec::new())
+                    };
+                    placeholders.push(found);
+                    masked
+                })
+                .collect();
+            let (corrected, span_stats) =
+                correct_lines(segments.clone(), &region_config, console, styles);
+            stats.merge(&span_stats);
+
+            for (offset, (before, after)) in segments.iter().zip(corrected).enumerate() {
+                if *before == after {
+                    continue;
+                }
+                let idx = span.start_line + offset;
+                let (start, end) = span.segment(idx, &lines[idx]);
+                let value = unmask_placeholders(&after, &placeholders[offset]);
+                lines[idx] = format!("{}{}{}", &lines[idx][..start], value, &lines[idx][end..]);
+            }
+        }
+    }
+
+    if syntax.has_escaped_strings() {
+        for (idx, line) in lines.iter_mut().enumerate() {
+            if in_region[idx] || !line.contains("\\n") || overlaps_disabled(&disabled, idx, idx + 1)
+            {
+                continue;
+            }
+            if let Some(ref ranges) = config.lines {
+                if !line_in_ranges(idx + 1, ranges) {
+                    continue;
+                }
+            }
+
+            let (corrected, line_stats) =
+                correct_escaped_strings(line, &region_config, console, styles);
+            stats.merge(&line_stats);
+            *line = corrected;
+        }
+    }
+
+    stats.total_lines = total_lines;
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        stats.elapsed = start_time.elapsed();
+    }
+    (lines, stats)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Recursive File Discovery
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Strip the Windows verbatim prefix from `\\?\C:\...` and
+/// `\\?\UNC\server\share\...` paths (as produced by `canonicalize`).
+/// Walking a verbatim root makes every discovered path verbatim, which
+/// breaks .gitignore matching and lists files twice when the same tree is
+/// also given as `C:\...`.
+fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    let text = path.to_string_lossy();
+    if let Some(rest) = text.strip_prefix(r"\\?\UNC\") {
+        return PathBuf::from(format!(r"\\{rest}"));
+    }
+    if let Some(rest) = text.strip_prefix(r"\\?\") {
+        let bytes = rest.as_bytes();
+        if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            return PathBuf::from(rest);
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Read the path list named by `--files-from` ("-" for stdin): one path
+/// per line, or per NUL-terminated entry with `--null`. Empty entries are
+/// skipped; paths are taken literally (no glob expansion).
+fn read_files_from(list: &Path, null: bool) -> Result<Vec<PathBuf>> {
+    let bytes = if list == Path::new("-") {
+        let mut buf = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buf)
+            .context("Failed to read file list from stdin")?;
+        buf
+    } else {
+        fs::read(list).with_context(|| format!("Failed to read file list {}", list.display()))?
+    };
+
+    let separator = if null { b'\0' } else { b'\n' };
+    bytes
+        .split(|&b| b == separator)
+        .map(|entry| {
+            if null {
+                entry
+            } else {
+                entry.strip_suffix(b"\r").unwrap_or(entry)
+            }
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| path_from_bytes(entry.to_vec(), list))
+        .collect()
+}
+
+/// A path from raw bytes: any bytes on Unix, UTF-8 elsewhere
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>, _list: &Path) -> Result<PathBuf> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
+}
+
+/// A path from raw bytes: any bytes on Unix, UTF-8 elsewhere
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>, list: &Path) -> Result<PathBuf> {
+    String::from_utf8(bytes).map(PathBuf::from).map_err(|_| {
+        ParseError(format!(
+            "Invalid UTF-8 path in file list {}",
+            list.display()
+        ))
+        .into()
+    })
+}
+
+/// Characters that make an input argument a glob pattern
+const GLOB_META: &[char] = &['*', '?', '['];
+
+/// Expand glob patterns in input arguments the way Unix shells do before
+/// aadc sees them; cmd and PowerShell pass `*.md` through literally.
+/// Arguments that name an existing path, or match nothing, are kept as
+/// given so the usual "not found" error still names them.
+fn expand_input_globs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        if input.exists() || !input.to_string_lossy().contains(GLOB_META) {
+            expanded.push(input.clone());
+            continue;
+        }
+        let matches = glob_matches(input)?;
+        if matches.is_empty() {
+            expanded.push(input.clone());
+        } else {
+            expanded.extend(matches);
+        }
+    }
+    Ok(expanded)
+}
+
+/// Files matching a glob pattern argument, sorted. The walk starts at the
+/// pattern's literal directory prefix; like shells, hidden files only
+/// match patterns that spell out the leading dot.
+fn glob_matches(pattern: &Path) -> Result<Vec<PathBuf>> {
+    let mut base = PathBuf::new();
+    let mut rest: Vec<String> = Vec::new();
+    for component in pattern.components() {
+        let part = component.as_os_str().to_string_lossy();
+        if rest.is_empty() && !part.contains(GLOB_META) {
+            base.push(component);
+        } else {
+            rest.push(part.into_owned());
+        }
+    }
+
+    let joined = rest.join("/");
+    let matcher = GlobBuilder::new(&joined)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| ArgError(format!("Invalid glob pattern '{}': {}", joined, e)))?
+        .compile_matcher();
+    let root = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base.as_path()
+    };
+
+    let mut walker = WalkBuilder::new(root);
+    walker.standard_filters(false);
+    walker.hidden(!joined.split('/').any(|part| part.starts_with('.')));
+    if !joined.contains("**") {
+        walker.max_depth(Some(rest.len()));
+    }
+
+    let mut matches = Vec::new();
+    for entry in walker.build().flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        if matcher.is_match(relative) {
+            matches.push(base.join(relative));
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
+
+fn build_globset(patterns: &str) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    let mut added = 0;
+
+    for raw in patterns.split(',') {
+        let pattern = raw.trim();
+        if pattern.is_empty() {
+            continue;
+        }
+
+        let glob = Glob::new(pattern)
+            .map_err(|err| ArgError(format!("Invalid glob pattern '{}': {}", pattern, err)))?;
+        builder.add(glob);
+        added += 1;
+    }
+
+    if added == 0 {
+        return Err(ArgError("--glob must include at least one pattern".to_string()).into());
+    }
+
+    builder
+        .build()
+        .map_err(|err| ArgError(format!("Invalid glob set: {}", err)).into())
+}
+
+fn discover_recursive_files(
+    paths: &[PathBuf],
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<Vec<PathBuf>> {
+    let globs = build_globset(&config.glob)?;
+    let mut files = std::collections::BTreeSet::new();
+    // Followed links can reach one file by several paths; keep the first
+    let mut seen = BTreeSet::new();
+
+    for path in paths {
+        let path = &strip_verbatim_prefix(path);
+        if path.is_file() || is_stdin_path(path) {
+            files.insert(path.clone());
+            continue;
+        }
+
+        if !path.is_dir() {
+            if config.verbose {
+                console.print(
+                    &styles
+                        .dim(format!("Warning: path does not exist: {}", path.display()))
+                        .to_string(),
+                );
+            }
+            continue;
+        }
+
+        let mut walker = WalkBuilder::new(path);
+        walker.git_ignore(config.gitignore);
+        walker.git_exclude(config.gitignore);
+        walker.git_global(config.gitignore);
+        walker.ignore(config.gitignore);
+        walker.hidden(false);
+        // The walker checks each followed link against its ancestors and
+        // reports a loop as an error instead of descending
+        walker.follow_links(config.follow_symlinks);
+
+        if config.max_depth > 0 {
+            walker.max_depth(Some(config.max_depth));
+        }
+
+        for entry in walker.build() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    if config.verbose {
+                        console.print(&styles.dim(format!("Warning: {}", err)));
+                    }
+                    continue;
+                }
+            };
+
+            let entry_path = entry.path();
+            if entry_path.is_file() {
+                if let Some(name) = entry_path.file_name() {
+                    let first_visit = !config.follow_symlinks
+                        || seen.insert(
+                            fs::canonicalize(entry_path)
+                                .unwrap_or_else(|_| entry_path.to_path_buf()),
+                        );
+                    if globs.is_match(name) && first_visit {
+                        files.insert(entry_path.to_path_buf());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(files.into_iter().collect())
+}
+
+/// Keep the `files` that git reports as added, copied, modified or renamed
+/// since `base` (committed or not)
+fn changed_since(files: Vec<PathBuf>, base: &str) -> Result<Vec<PathBuf>> {
+    let top_level = PathBuf::from(git_output(&["rev-parse", "--show-toplevel"])?.trim_end());
+    let changed = git_output(&[
+        "diff",
+        "--name-only",
+        "--diff-filter=ACMR",
+        "-z",
+        base,
+        "--",
+    ])?;
+    let changed = changed
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(|name| fs::canonicalize(top_level.join(name)))
+        .filter_map(Result::ok)
+        .collect::<BTreeSet<_>>();
+    Ok(files
+        .into_iter()
+        .filter(|file| fs::canonicalize(file).is_ok_and(|file| changed.contains(&file)))
+        .collect())
+}
+
+/// Lines of `path` added or modified since `base`, or None (every line)
+/// when git does not track the file yet
+fn changed_lines(path: &Path, base: &str) -> Result<Option<Vec<LineRange>>> {
+    let path = path.to_string_lossy();
+    let diff = git_output(&[
+        "diff",
+        "--no-ext-diff",
+        "--no-color",
+        "-U0",
+        base,
+        "--",
+        &path,
+    ])?;
+    if diff.is_empty() && git_output(&["ls-files", "--", &path])?.is_empty() {
+        return Ok(None);
+    }
+    let ranges = patch_new_ranges(&diff)
+        .into_iter()
+        .map(|(start, end)| LineRange { start, end })
+        .collect();
+    Ok(Some(ranges))
+}
+
+/// `config` with `lines` set to the ranges given with `path`
+/// (`FILE:RANGES`), or else limited to the lines of `path` changed since
+/// the `--diff-only` ref
+fn config_for_changes<'a>(path: &Path, config: &'a Config) -> Result<Cow<'a, Config>> {
+    if let Some(ranges) = config.file_lines.get(path) {
+        let mut file_config = config.clone();
+        file_config.lines = Some(ranges.clone());
+        return Ok(Cow::Owned(file_config));
+    }
+    let Some(ref base) = config.diff_only else {
+        return Ok(Cow::Borrowed(config));
+    };
+    if is_stdin_path(path) {
+        return Ok(Cow::Borrowed(config));
+    }
+    let mut file_config = config.clone();
+    file_config.lines = changed_lines(path, base)?;
+    Ok(Cow::Owned(file_config))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Backup
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Creates a backup of the file by appending the extension to the filename.
+/// For example: "file.txt" with extension ".bak" becomes "file.txt.bak"
+///
+/// `content` is the file's current content (read by the caller, which may
+/// hold the file's lock); the backup target is locked while it is written.
+fn create_backup(path: &Path, ext: &str, content: &[u8]) -> Result<PathBuf> {
+    let mut backup_name = path.as_os_str().to_owned();
+    backup_name.push(ext);
+    let backup_path = PathBuf::from(backup_name);
+
+    write_backup(path, &backup_path, content)?;
+    Ok(backup_path)
+}
+
+/// Creates the next numbered backup ("file.txt.bak.1", ".2", ...), then
+/// removes the oldest ones beyond `keep`
+fn create_numbered_backup(
+    path: &Path,
+    ext: &str,
+    content: &[u8],
+    keep: Option<usize>,
+) -> Result<PathBuf> {
+    let mut existing = numbered_backups(path, ext)?;
+    let next = existing.last().map_or(1, |(n, _)| n + 1);
+
+    let mut backup_name = path.as_os_str().to_owned();
+    backup_name.push(format!("{}.{}", ext, next));
+    let backup_path = PathBuf::from(backup_name);
+    write_backup(path, &backup_path, content)?;
+    existing.push((next, backup_path.clone()));
+
+    if let Some(keep) = keep {
+        let excess = existing.len().saturating_sub(keep);
+        for (_, old) in existing.drain(..excess) {
+            fs::remove_file(&old)
+                .with_context(|| format!("Failed to remove old backup: {}", old.display()))?;
+        }
+    }
+
+    Ok(backup_path)
+}
+
+/// Existing numbered backups of `path`, sorted by number
+fn numbered_backups(path: &Path, ext: &str) -> Result<Vec<(usize, PathBuf)>> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}{}.", file_name, ext);
+
+    let mut backups = Vec::new();
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to list backups in {}", dir.display()))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let number = name
+            .to_str()
+            .and_then(|n| n.strip_prefix(&prefix))
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n > 0);
+        if let Some(number) = number {
+            backups.push((number, entry.path()));
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+/// Write `content` to a backup file, locked while it is written, with the
+/// permissions of the original file
+fn write_backup(path: &Path, backup_path: &Path, content: &[u8]) -> Result<()> {
+    let write = || -> io::Result<()> {
+        let mut backup = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(backup_path)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        backup.lock_exclusive()?;
+        backup.set_len(0)?;
+        backup.write_all(content)?;
+        fs::set_permissions(backup_path, fs::metadata(path)?.permissions())
+    };
+    write().with_context(|| format!("Failed to create backup at {}", backup_path.display()))
+}
+
+/// Write `content` to `path` in place while holding an exclusive advisory
+/// lock on it, so concurrent aadc runs (e.g. watch mode plus a manual
+/// invocation) cannot interleave writes.
+///
+/// Under the lock, the file is checked against `snapshot` and backed up
+/// with `backup_ext` if given. Files whose bytes already equal `content`
+/// are left untouched (no write, no backup, no fresh mtime), so pipelines
+/// keyed on timestamps don't rebuild. All I/O goes through the locked
+/// handle, since Windows locks are mandatory. Returns the backup path, if any.
+pub fn write_in_place(
+    path: &Path,
+    content: impl AsRef<[u8]>,
+    snapshot: Option<&FileSnapshot>,
+    backup_ext: Option<&str>,
+    config: &Config,
+) -> Result<Option<PathBuf>> {
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to open file for writing: {}", path.display()))?;
+    // wasm32 has no other processes to lock out
+    #[cfg(not(target_arch = "wasm32"))]
+    file.lock_exclusive()
+        .with_context(|| format!("Failed to lock file: {}", path.display()))?;
+
+    let mut current = Vec::new();
+    file.read_to_end(&mut current)
+        .with_context(|| format!("Failed to re-read file before writing: {}", path.display()))?;
+    ensure_unchanged(path, &current, snapshot, config)?;
+    let content = content.as_ref();
+    if current == content {
+        return Ok(None);
+    }
+
+    let backup = backup_ext
+        .map(|ext| match config.backup_mode {
+            BackupMode::Simple => create_backup(path, ext, &current),
+            BackupMode::Numbered => create_numbered_backup(path, ext, &current, config.backup_keep),
+        })
+        .transpose()?;
+
+    let metadata = if config.preserve_metadata {
+        Some(
+            file.metadata()
+                .with_context(|| format!("Failed to read metadata: {}", path.display()))?,
+        )
+    } else {
+        None
+    };
+
+    file.rewind()
+        .and_then(|()| file.set_len(0))
+        .and_then(|()| file.write_all(content))
+        .with_context(|| format!("Failed to write to file: {}", path.display()))?;
+
+    if let Some(metadata) = metadata {
+        restore_metadata(&file, &metadata)
+            .with_context(|| format!("Failed to restore metadata: {}", path.display()))?;
+    }
+
+    // The lock is released when `file` is dropped
+    Ok(backup)
+}
+
+/// Put back the permissions and access/modification times a file had
+/// before it was rewritten (`--preserve-metadata`)
+fn restore_metadata(file: &fs::File, metadata: &fs::Metadata) -> io::Result<()> {
+    file.set_permissions(metadata.permissions())?;
+    let mut times = fs::FileTimes::new().set_modified(metadata.modified()?);
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    file.set_times(times)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Concurrent Modification Detection
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A file's content as aadc read it, checked again right before an
+/// in-place write so edits made meanwhile (editor autosave, generators)
+/// are not clobbered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSnapshot {
+    len: u64,
+    hash: u64,
+}
+
+impl FileSnapshot {
+    /// Snapshot of a file's content as read
+    pub fn of(bytes: &[u8]) -> Self {
+        Self {
+            len: bytes.len() as u64,
+            hash: fnv1a(bytes),
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across Rust releases (unlike std's
+/// `DefaultHasher`), so it can be persisted
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    bytes.iter().fold(FNV_OFFSET, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Fail if `current` (the content of `path` right before writing) no
+/// longer matches `snapshot`, unless `--force-write` was given
+fn ensure_unchanged(
+    path: &Path,
+    current: &[u8],
+    snapshot: Option<&FileSnapshot>,
+    config: &Config,
+) -> Result<()> {
+    let Some(snapshot) = snapshot else {
+        return Ok(());
+    };
+    if config.force_write {
+        return Ok(());
+    }
+    if FileSnapshot::of(current) != *snapshot {
+        return Err(anyhow::anyhow!(
+            "{} was modified by another process after it was read; not overwriting (use --force-write to override)",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Default `--max-file-size` (100 MB): larger files are rejected to
+/// prevent memory issues
+const DEFAULT_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// A size in a config file: a byte count or a string with a unit
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum ByteSize {
+    Bytes(u64),
+    Text(String),
+}
+
+impl ByteSize {
+    fn bytes(&self) -> Result<u64, String> {
+        match self {
+            Self::Bytes(n) => Ok(*n),
+            Self::Text(s) => parse_byte_size(s),
+        }
+    }
+}
+
+/// Parse a size like "1048576", "500K", "200MB" or "1.5GiB". Units are
+/// binary (K = 1024) and case-insensitive; a trailing "B"/"iB" is optional.
+fn parse_byte_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{s}' (expected e.g. 500K, 200MB or 1048576)"))?;
+    let unit = unit.trim().to_ascii_lowercase();
+    let unit = unit
+        .strip_suffix("ib")
+        .or_else(|| unit.strip_suffix('b'))
+        .unwrap_or(&unit);
+    let power = match unit {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        _ => return Err(format!("unknown size unit in '{s}' (use K, M, G or T)")),
+    };
+    Ok((value * 1024f64.powi(power)).round() as u64)
+}
+
+/// Format a byte count for messages: "100 MB", "512 KB" or "300 bytes"
+fn format_byte_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = None;
+    for name in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = Some(name);
+    }
+    match unit {
+        Some(unit) if value.fract() == 0.0 => format!("{value} {unit}"),
+        Some(unit) => format!("{value:.1} {unit}"),
+        None => format!("{bytes} bytes"),
+    }
+}
+
+/// Byte order mark (U+FEFF), stripped from input and re-emitted on output
+const UTF8_BOM: &str = "\u{feff}";
+
+/// Decoded input lines along with each line's original terminator
+#[derive(Debug, Clone, Default)]
+struct SourceText {
+    lines: Vec<String>,
+    /// Terminator of each line (None for a final line without one)
+    endings: Vec<Option<Eol>>,
+    /// Whether the input started with a byte order mark (not part of
+    /// `lines`)
+    bom: bool,
+    /// Encoding the input was decoded from
+    encoding: Encoding,
+}
+
+/// Read content from a file path and return lines
+fn read_file(path: &Path, config: &Config) -> Result<Vec<String>> {
+    read_file_with_snapshot(path, config).map(|(source, _)| source.lines)
+}
+
+/// Read a file's lines along with a snapshot of its content, for files
+/// that may be written back in place
+fn read_file_with_snapshot(path: &Path, config: &Config) -> Result<(SourceText, FileSnapshot)> {
+    // Check file size before reading
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("Failed to read file metadata: {}", path.display()))?;
+
+    if config.max_file_size > 0 && metadata.len() > config.max_file_size {
+        return Err(ParseError(format!(
+            "File too large: {} ({}). Maximum size is {} (see --max-file-size).",
+            path.display(),
+            format_byte_size(metadata.len()),
+            format_byte_size(config.max_file_size)
+        ))
+        .into());
+    }
+
+    let source_label = path.display().to_string();
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read input file: {}", path.display()))?;
+
+    let snapshot = FileSnapshot::of(&bytes);
+    let source = decode_to_lines(bytes, &source_label, config.encoding.unwrap_or_default())?;
+    Ok((source, snapshot))
+}
+
+/// Read content from stdin and return lines
+fn read_stdin_content(config: &Config) -> Result<SourceText> {
+    let mut buf = Vec::new();
+    io::stdin()
+        .read_to_end(&mut buf)
+        .context("Failed to read stdin")?;
+    decode_to_lines(buf, "stdin", config.encoding.unwrap_or_default())
+}
+
+/// Convert raw UTF-8 bytes to lines, checking for binary content and
+/// valid UTF-8
+fn parse_bytes_to_lines(bytes: Vec<u8>, source_label: &str) -> Result<SourceText> {
+    decode_to_lines(bytes, source_label, Encoding::Utf8)
+}
+
+/// Decode raw bytes and split them into lines, checking for binary
+/// content and valid input in `encoding`
+fn decode_to_lines(bytes: Vec<u8>, source_label: &str, encoding: Encoding) -> Result<SourceText> {
+    let (content, encoding) = encoding.decode(bytes, source_label)?;
+    let (content, bom) = match content.strip_prefix(UTF8_BOM) {
+        Some(rest) => (rest, true),
+        None => (content.as_str(), false),
+    };
+
+    let endings = content
+        .split_inclusive('\n')
+        .map(|line| {
+            if line.ends_with("\r\n") {
+                Some(Eol::Crlf)
+            } else if line.ends_with('\n') {
+                Some(Eol::Lf)
+            } else {
+                None
+            }
+        })
+        .collect();
+    Ok(SourceText {
+        lines: content.lines().map(String::from).collect(),
+        endings,
+        bom,
+        encoding,
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Result Cache
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Default `--cache-file`
+const CACHE_FILE: &str = ".aadc-cache";
+
+/// Format version of the cache file; other versions are discarded
+const CACHE_VERSION: u32 = 1;
+
+/// A file found clean: its content and the options it was checked with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    /// FNV-1a hash of the raw file bytes
+    content: String,
+    /// `config_fingerprint` of the options
+    config: String,
+    /// Diagram blocks found, for `--require-diagrams`
+    blocks: usize,
+}
+
+/// On-disk form of the cache file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheContents {
+    version: u32,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/// Clean files by path, shared by worker threads and saved at the end of
+/// a `--cached` run
+#[derive(Debug)]
+struct ResultCache {
+    path: PathBuf,
+    entries: Mutex<BTreeMap<String, CacheEntry>>,
+    changed: AtomicBool,
+}
+
+impl ResultCache {
+    /// Load `path`; a missing, unreadable or outdated cache starts empty
+    fn load(path: &Path) -> Self {
+        let entries = fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<CacheContents>(&bytes).ok())
+            .filter(|contents| contents.version == CACHE_VERSION)
+            .map(|contents| contents.entries)
+            .unwrap_or_default();
+        Self {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, CacheEntry>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Blocks found in `filename` when it was last clean, if its content
+    /// and options are still the same
+    fn lookup(&self, filename: &str, snapshot: &FileSnapshot, config: &str) -> Option<usize> {
+        self.entries()
+            .get(filename)
+            .filter(|entry| {
+                entry.size == snapshot.len
+                    && entry.content == format!("{:016x}", snapshot.hash)
+                    && entry.config == config
+            })
+            .map(|entry| entry.blocks)
+    }
+
+    /// Record a processed file: clean results are cached, anything else
+    /// drops the file's entry
+    fn record(&self, result: &FileResult, snapshot: &FileSnapshot, config: String) {
+        let mut entries = self.entries();
+        let changed = if result.would_change {
+            entries.remove(&result.filename).is_some()
+        } else {
+            let entry = CacheEntry {
+                size: snapshot.len,
+                content: format!("{:016x}", snapshot.hash),
+                config,
+                blocks: result.stats.blocks_found,
+            };
+            entries.insert(result.filename.clone(), entry.clone()) != Some(entry)
+        };
+        if changed {
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Write the cache back if anything changed (via a temporary file, so
+    /// an interrupted run never leaves a truncated cache)
+    fn save(&self) -> Result<()> {
+        if !self.changed.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let contents = CacheContents {
+            version: CACHE_VERSION,
+            entries: self.entries().clone(),
+        };
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_vec(&contents)?)
+            .and_then(|()| fs::rename(&tmp, &self.path))
+            .with_context(|| format!("Failed to write cache file: {}", self.path.display()))
+    }
+}
+
+/// Fingerprint of the options that decide how a file is corrected, with
+/// output modes left out so `--dry-run`, `-i` and `--json` runs share
+/// cache entries. Includes the aadc version, as corrections can change
+/// between releases.
+fn config_fingerprint(config: &Config) -> String {
+    let relevant = Config {
+        on_change: None,
+        recursive: false,
+        glob: String::new(),
+        gitignore: false,
+        max_depth: 0,
+        follow_symlinks: false,
+        jobs: 0,
+        cached: false,
+        cache_file: PathBuf::new(),
+        // Already applied to `lines`
+        file_lines: BTreeMap::new(),
+        color: ColorMode::Auto,
+        verbose: false,
+        diff: false,
+        show_padding: false,
+        diff_format: DiffFormat::Unified,
+        dry_run: false,
+        list_different: false,
+        check: false,
+        null: false,
+        watch: false,
+        debounce_ms: 0,
+        backup: false,
+        backup_ext: String::new(),
+        backup_mode: BackupMode::Simple,
+        backup_keep: None,
+        force_write: false,
+        preserve_metadata: false,
+        json: false,
+        json_lines: false,
+        output_format: OutputFormat::Content,
+        reports: Vec::new(),
+        error_format: ErrorFormat::default(),
+        ci: None,
+        ..config.clone()
+    };
+    let text = format!("{} {:?}", env!("CARGO_PKG_VERSION"), relevant);
+    format!("{:016x}", fnv1a(text.as_bytes()))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Post-Change Command
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Environment variable listing the files changed, one per line
+const ON_CHANGE_ENV: &str = "AADC_CHANGED_FILES";
+
+/// Whether the current output mode rewrites input files.
+///
+/// JSON output writes alongside its report whenever `--in-place` is set;
+/// otherwise `--dry-run` and `--diff` leave files untouched.
+fn writes_in_place(args: &Args, config: &Config) -> bool {
+    args.in_place && (config.json || !(config.dry_run || config.diff))
+}
+
+/// Run the `--on-change` command, if any, after files were modified.
+///
+/// The command runs through the shell with the changed paths as positional
+/// arguments (`$@`) and in `AADC_CHANGED_FILES`. Nothing runs when no file
+/// changed. A failing command is reported as an error.
+fn run_on_change(config: &Config, changed: &[PathBuf]) -> Result<()> {
+    let Some(ref command) = config.on_change else {
+        return Ok(());
+    };
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    let files: Vec<String> = changed.iter().map(|p| p.display().to_string()).collect();
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = std::process::Command::new("sh");
+        // The argument after the script becomes $0; files follow as $@
+        cmd.arg("-c").arg(command).arg("aadc");
+        cmd
+    };
+    let status = cmd
+        .args(&files)
+        .env(ON_CHANGE_ENV, files.join("\n"))
+        .status()
+        .with_context(|| format!("Failed to run --on-change command: {command}"))?;
+
+    if !status.success() {
+        anyhow::bail!("--on-change command failed ({status}): {command}");
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Hook Management
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Marker comment identifying aadc-generated hooks
+const HOOK_MARKER: &str = "# aadc pre-commit hook";
+
+/// Default file patterns for hook
+const DEFAULT_PATTERNS: &[&str] = &["*.md", "*.txt"];
+
+/// Run a subcommand
+fn run_command(command: &Commands, args: &Args) -> Result<i32> {
+    let result = match command {
+        Commands::Hook { action } => return run_hook_command(action, args),
+        Commands::Config { action } => run_config_command(action),
+        Commands::Filter {
+            action: FilterAction::Install { patterns },
+        } => filter_install(patterns.as_deref()),
+        Commands::FilterClean { path } => run_filter_clean(path.as_deref(), args),
+        Commands::Extract {
+            file,
+            out_dir,
+            manifest,
+        } => run_extract_command(file, out_dir, *manifest, &create_config(args)?),
+        Commands::Inject { file, block, from } => {
+            run_inject_command(file, block.map(|b| b as usize), from, &create_config(args)?)
+        }
+        Commands::JsonSchema => print_json_schema(),
+        Commands::Lsp => return run_lsp(&create_config(args)?),
+        Commands::Mcp => run_mcp(&create_config(args)?),
+        Commands::Apply { edits, dry_run } => {
+            run_apply_command(edits, *dry_run, &create_config(args)?)
+        }
+        Commands::Convert {
+            files,
+            to,
+            in_place,
+        } => run_convert_command(files, *to, *in_place, &create_config(args)?),
+        Commands::PandocFilter { format: _ } => run_pandoc_filter(&create_config(args)?),
+        Commands::Fixtures { action } => {
+            return run_fixtures_command(action, &create_config(args)?);
+        }
+        #[cfg(feature = "net")]
+        Commands::Suggest {
+            repo,
+            pr,
+            files,
+            dry_run,
+        } => run_suggest_command(repo, *pr, files, *dry_run, &create_config(args)?),
+        Commands::Lint {
+            files,
+            format,
+            list_rules,
+            fix,
+        } => {
+            let config = create_config(args)?;
+            if *list_rules {
+                list_lint_rules(&config);
+                Ok(())
+            } else {
+                announce_ci(&config);
+                let format = match *format {
+                    Some(format) if args.json && format != LintFormat::Json => {
+                        return Err(ArgError(
+                            "--json conflicts with lint --format text/github".to_string(),
+                        )
+                        .into());
+                    }
+                    Some(format) => format,
+                    None if config.json => LintFormat::Json,
+                    None => LintFormat::default_for(config.ci),
+                };
+                return run_lint_command(files, format, *fix, &config);
+            }
+        }
+    };
+    result.map(|()| exit_codes::SUCCESS)
+}
+
+/// Run a hook subcommand
+fn run_hook_command(action: &HookAction, args: &Args) -> Result<i32> {
+    let result = match action {
+        HookAction::Install {
+            check_only,
+            auto_fix,
+            patterns,
+        } => hook_install(*check_only, *auto_fix, patterns.as_deref()),
+        HookAction::Uninstall => hook_uninstall(),
+        HookAction::Status => hook_status(),
+        HookAction::Run { auto_fix, patterns } => {
+            return hook_run(*auto_fix, patterns.as_deref(), &create_config(args)?);
+        }
+    };
+    result.map(|()| exit_codes::SUCCESS)
+}
+
+/// Find the .git directory, searching upward from current directory
+fn find_git_dir() -> Result<PathBuf> {
+    let mut current = std::env::current_dir().context("Failed to get current directory")?;
+
+    loop {
+        let git_dir = current.join(".git");
+        if git_dir.is_dir() {
+            return Ok(git_dir);
+        }
+        if !current.pop() {
+            return Err(anyhow::anyhow!(
+                "Not in a git repository (or any parent up to the filesystem root)"
+            ));
+        }
+    }
+}
+
+/// Quote a hook pattern for a POSIX shell command line
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Generate a hook script that hands off to `aadc hook run`. Only a POSIX
+/// `sh` is needed, which git provides on every platform (Git for Windows
+/// runs hooks with its bundled shell), so the staged-file logic lives in
+/// aadc itself rather than in bash.
+fn generate_hook_script(patterns: &[&str], auto_fix: bool) -> String {
+    let (mode, flag, summary) = if auto_fix {
+        (
+            "auto-fix",
+            "--auto-fix",
+            "Automatically fixes diagram alignment before commit",
+        )
+    } else {
+        (
+            "check",
+            "--check-only",
+            "Blocks commits if diagrams have misaligned borders",
+        )
+    };
+    let run_flag = if auto_fix { " --auto-fix" } else { "" };
+    let patterns: Vec<String> = patterns.iter().map(|p| shell_quote(p)).collect();
+    format!(
+        r#"#!/bin/sh
+{marker} ({mode} mode)
+# Generated by: aadc hook install {flag}
+# {summary}
+
+exec aadc hook run{run_flag} --patterns {patterns}
+"#,
+        marker = HOOK_MARKER,
+        patterns = patterns.join(",")
+    )
+}
+
+/// Generate the check-mode hook script
+fn generate_check_hook(patterns: &[&str]) -> String {
+    generate_hook_script(patterns, false)
+}
+
+/// Generate the auto-fix mode hook script
+fn generate_autofix_hook(patterns: &[&str]) -> String {
+    generate_hook_script(patterns, true)
+}
+
+/// Generate the default (check) hook script
+fn generate_default_hook(patterns: &[&str]) -> String {
+    generate_check_hook(patterns)
+}
+
+/// Install the pre-commit hook
+fn hook_install(check_only: bool, auto_fix: bool, patterns: Option<&[String]>) -> Result<()> {
+    let git_dir = find_git_dir()?;
+    let hooks_dir = git_dir.join("hooks");
+    let hook_path = hooks_dir.join("pre-commit");
+
+    // Create hooks directory if it doesn't exist
+    if !hooks_dir.exists() {
+        fs::create_dir_all(&hooks_dir).with_context(|| {
+            format!("Failed to create hooks directory: {}", hooks_dir.display())
+        })?;
+    }
+
+    // Check for existing hook
+    if hook_path.exists() {
+        let content = fs::read_to_string(&hook_path)
+            .with_context(|| format!("Failed to read existing hook: {}", hook_path.display()))?;
+
+        if content.contains(HOOK_MARKER) {
+            // Our hook already installed - update it
+            println!("Updating existing aadc hook...");
+        } else {
+            // Different hook present - backup before overwriting
+            let backup_path = hook_path.with_extension("pre-aadc");
+            fs::rename(&hook_path, &backup_path).with_context(|| {
+                format!(
+                    "Failed to backup existing hook to: {}",
+                    backup_path.display()
+                )
+            })?;
+            println!("Backed up existing hook to: {}", backup_path.display());
+        }
+    }
+
+    // Determine patterns to use
+    let pattern_refs: Vec<&str> = match patterns {
+        Some(p) => p.iter().map(|s| s.as_str()).collect(),
+        None => DEFAULT_PATTERNS.to_vec(),
+    };
+
+    // Generate hook script based on mode
+    let script = if auto_fix {
+        generate_autofix_hook(&pattern_refs)
+    } else if check_only {
+        generate_check_hook(&pattern_refs)
+    } else {
+        // Default to check mode
+        generate_default_hook(&pattern_refs)
+    };
+
+    // Write hook
+    fs::write(&hook_path, &script)
+        .with_context(|| format!("Failed to write hook: {}", hook_path.display()))?;
+
+    // Make executable on Unix
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(&hook_path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&hook_path, perms)
+            .with_context(|| format!("Failed to make hook executable: {}", hook_path.display()))?;
+    }
+
+    let mode = if auto_fix { "auto-fix" } else { "check" };
+    println!(
+        "Installed aadc pre-commit hook ({} mode): {}",
+        mode,
+        hook_path.display()
+    );
+    println!("Patterns: {}", pattern_refs.join(", "));
+
+    Ok(())
+}
+
+/// Uninstall the pre-commit hook
+fn hook_uninstall() -> Result<()> {
+    let git_dir = find_git_dir()?;
+    let hook_path = git_dir.join("hooks").join("pre-commit");
+
+    if !hook_path.exists() {
+        println!("No pre-commit hook installed");
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&hook_path)
+        .with_context(|| format!("Failed to read hook: {}", hook_path.display()))?;
+
+    if !content.contains(HOOK_MARKER) {
+        return Err(anyhow::anyhow!(
+            "Pre-commit hook exists but was not installed by aadc. Remove manually if desired."
+        ));
+    }
+
+    fs::remove_file(&hook_path)
+        .with_context(|| format!("Failed to remove hook: {}", hook_path.display()))?;
+
+    println!("Removed aadc pre-commit hook");
+
+    // Check for backup to restore
+    let backup_path = hook_path.with_extension("pre-aadc");
+    if backup_path.exists() {
+        println!(
+            "Note: Previous hook backup exists at: {}",
+            backup_path.display()
+        );
+        println!(
+            "Restore it manually with: mv {} {}",
+            backup_path.display(),
+            hook_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Show hook status
+fn hook_status() -> Result<()> {
+    let git_dir = find_git_dir()?;
+    let hook_path = git_dir.join("hooks").join("pre-commit");
+
+    if !hook_path.exists() {
+        println!("Status: No pre-commit hook installed");
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&hook_path)
+        .with_context(|| format!("Failed to read hook: {}", hook_path.display()))?;
+
+    if content.contains(&format!("{} (check mode)", HOOK_MARKER)) {
+        println!("Status: aadc hook installed (check mode)");
+        println!("Path: {}", hook_path.display());
+    } else if content.contains(&format!("{} (auto-fix mode)", HOOK_MARKER)) {
+        println!("Status: aadc hook installed (auto-fix mode)");
+        println!("Path: {}", hook_path.display());
+    } else if content.contains(HOOK_MARKER) {
+        println!("Status: aadc hook installed (unknown mode)");
+        println!("Path: {}", hook_path.display());
+    } else {
+        println!("Status: Non-aadc pre-commit hook present");
+        println!("Path: {}", hook_path.display());
+    }
+
+    // Check for backup
+    let backup_path = hook_path.with_extension("pre-aadc");
+    if backup_path.exists() {
+        println!("Backup: {}", backup_path.display());
+    }
+
+    Ok(())
+}
+
+/// Run git with `args`, returning its stdout
+fn git_output(args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    String::from_utf8(output.stdout).context("git produced invalid UTF-8")
+}
+
+/// Check the staged files matching `patterns`, or with `auto_fix` correct
+/// and re-stage them. Exits 1 in check mode when any file needs fixing.
+fn hook_run(auto_fix: bool, patterns: Option<&[String]>, config: &Config) -> Result<i32> {
+    let patterns = match patterns {
+        Some(p) => p.join(","),
+        None => DEFAULT_PATTERNS.join(","),
+    };
+    let globs = build_globset(&patterns)?;
+    let top_level = PathBuf::from(git_output(&["rev-parse", "--show-toplevel"])?.trim_end());
+    let staged = git_output(&["diff", "--cached", "--name-only", "--diff-filter=ACM", "-z"])?;
+    let (console, styles) = build_console(ColorMode::Never, None);
+
+    let mut flagged = 0;
+    let mut fixed = Vec::new();
+    for name in staged.split('\0').filter(|name| !name.is_empty()) {
+        // Patterns match the repo-relative path; `*` crosses directories
+        if !globs.is_match(name) {
+            continue;
+        }
+        let path = top_level.join(name);
+        let (source, snapshot) = match read_file_with_snapshot(&path, config) {
+            Ok(read) => read,
+            Err(err) => {
+                eprintln!("aadc: Skipping {}: {:#}", name, err);
+                continue;
+            }
+        };
+        let result = process_input(source, name.to_string(), config, &console, &styles);
+        if !result.would_change {
+            continue;
+        }
+
+        flagged += 1;
+        if auto_fix {
+            println!("aadc: Auto-fixing diagrams: {}", name);
+            write_in_place(&path, result.output_bytes()?, Some(&snapshot), None, config)?;
+            let top_level = top_level.to_string_lossy();
+            git_output(&["-C", &top_level, "add", "--", name])?;
+            fixed.push(path);
+        } else {
+            println!("aadc: Diagram alignment needed: {}", name);
+        }
+    }
+
+    run_on_change(config, &fixed)?;
+
+    if flagged == 0 {
+        return Ok(exit_codes::SUCCESS);
+    }
+    if auto_fix {
+        println!("aadc: Auto-fixed {} file(s)", flagged);
+        Ok(exit_codes::SUCCESS)
+    } else {
+        println!();
+        println!("Run 'aadc -i <file>' to fix, or 'git commit --no-verify' to skip");
+        Ok(exit_codes::ERROR)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Git Clean Filter
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Name of the filter driver in .git/config and .gitattributes
+const FILTER_NAME: &str = "aadc";
+
+/// Clean command git runs for each filtered file
+const FILTER_CLEAN_COMMAND: &str = "aadc filter-clean %f";
+
+/// Correct stdin to stdout for git. Anything that stops the correction
+/// (an invalid `.aadcrc`, binary or undecodable input) is reported on
+/// stderr and the input written back unchanged, so `git add` never fails
+/// because of aadc.
+fn run_filter_clean(path: Option<&Path>, args: &Args) -> Result<()> {
+    let mut input = Vec::new();
+    io::stdin()
+        .read_to_end(&mut input)
+        .context("Failed to read stdin")?;
+
+    let output = match create_config(args).and_then(|config| filter_clean(&input, path, &config)) {
+        Ok(Some(output)) => output,
+        Ok(None) => input,
+        Err(e) => {
+            let label = path.map_or_else(|| "stdin".to_string(), |p| p.display().to_string());
+            eprintln!("aadc: {}: left unchanged: {:#}", label, e);
+            input
+        }
+    };
+
+    let mut stdout = io::stdout().lock();
+    stdout
+        .write_all(&output)
+        .context("Failed to write stdout")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// The cleaned content of a file git is staging, or `None` if it needs
+/// no correction
+fn filter_clean(input: &[u8], path: Option<&Path>, config: &Config) -> Result<Option<Vec<u8>>> {
+    let config = Config {
+        verbose: false,
+        lines: None,
+        stdin_filename: path
+            .map(Path::to_path_buf)
+            .or(config.stdin_filename.clone()),
+        ..config.clone()
+    };
+    let config = config_for_path(Path::new("-"), &config);
+    let label = stdin_label(&config);
+    let source = decode_to_lines(input.to_vec(), &label, config.encoding.unwrap_or_default())?;
+
+    let (console, styles) = build_console(ColorMode::Never, None);
+    let result = process_input(source, label, &config, &console, &styles);
+    if !result.would_change {
+        return Ok(None);
+    }
+    result.output_bytes().map(Some)
+}
+
+/// Register the clean filter in .git/config and assign it to `patterns`
+/// in the repository's .gitattributes, keeping any lines already there
+fn filter_install(patterns: Option<&[String]>) -> Result<()> {
+    let top_level = PathBuf::from(git_output(&["rev-parse", "--show-toplevel"])?.trim_end());
+    git_output(&[
+        "config",
+        &format!("filter.{}.clean", FILTER_NAME),
+        FILTER_CLEAN_COMMAND,
+    ])?;
+    println!(
+        "Configured filter.{}.clean = {}",
+        FILTER_NAME, FILTER_CLEAN_COMMAND
+    );
+
+    let pattern_refs: Vec<&str> = match patterns {
+        Some(p) => p.iter().map(|s| s.as_str()).collect(),
+        None => DEFAULT_PATTERNS.to_vec(),
+    };
+    let attributes_path = top_level.join(".gitattributes");
+    let existing = match fs::read_to_string(&attributes_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", attributes_path.display()));
+        }
+    };
+    let (content, added) = add_filter_attributes(&existing, &pattern_refs);
+    if added.is_empty() {
+        println!("{} already assigns the filter", attributes_path.display());
+    } else {
+        fs::write(&attributes_path, content)
+            .with_context(|| format!("Failed to write {}", attributes_path.display()))?;
+        println!(
+            "Assigned the filter in {}: {}",
+            attributes_path.display(),
+            added.join(", ")
+        );
+    }
+    println!("Run `git add --renormalize .` to clean files already in the index.");
+
+    Ok(())
+}
+
+/// `existing` .gitattributes content with a `PATTERN filter=aadc` line
+/// appended for each pattern that lacks one, and the patterns added
+fn add_filter_attributes<'a>(existing: &str, patterns: &[&'a str]) -> (String, Vec<&'a str>) {
+    let attribute = format!("filter={}", FILTER_NAME);
+    let mut content = existing.to_string();
+    let mut added = Vec::new();
+    for &pattern in patterns {
+        let present = content.lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some(pattern) && fields.any(|f| f == attribute)
+        });
+        if present {
+            continue;
+        }
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&format!("{} {}\n", pattern, attribute));
+        added.push(pattern);
+    }
+    (content, added)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Block Extraction
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Read a file and detect its diagram blocks.
+///
+/// Blocks are detected on tab-expanded lines (as during correction); the
+/// returned source holds the file's original content, and the snapshot
+/// guards writing it back.
+fn detect_file_blocks(
+    path: &Path,
+    config: &Config,
+) -> Result<(SourceText, FileSnapshot, Vec<DiagramBlock>)> {
+    let (source, snapshot) = read_file_with_snapshot(path, config)?;
+    let expanded: Vec<String> = source
+        .lines
+        .iter()
+        .map(|l| expand_tabs(l, config.tab_width))
+        .collect();
+    let blocks = find_diagram_blocks(&expanded, &config.detect_options());
+    Ok((source, snapshot, blocks))
+}
+
+/// File name for an extracted block: its annotated name, else its index
+fn block_file_name(index: usize, block: &DiagramBlock) -> String {
+    match block.annotations.name {
+        Some(ref name) => format!("{}.txt", name),
+        None => format!("block-{:03}.txt", index),
+    }
+}
+
+/// Handle the extract subcommand
+fn run_extract_command(file: &Path, out_dir: &Path, manifest: bool, config: &Config) -> Result<()> {
+    let (SourceText { lines, .. }, _, blocks) = detect_file_blocks(file, config)?;
+
+    // Keep original numbering so indices still match `inject --block`
+    let selected: Vec<(usize, &DiagramBlock)> = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| (i + 1, block))
+        .filter(|(_, block)| block_name_matches(block, config.block_name.as_deref()))
+        .collect();
+
+    if selected.is_empty() {
+        match config.block_name {
+            Some(ref name) => eprintln!("No block named '{}' in {}", name, file.display()),
+            None => eprintln!("No diagram blocks found in {}", file.display()),
+        }
+        return Ok(());
+    }
+
+    // Blocks sharing a name (or named like `block-001`) would overwrite
+    // each other's file; names are compared ignoring case for
+    // case-insensitive file systems
+    let mut seen = BTreeMap::new();
+    for &(index, block) in &selected {
+        let name = block_file_name(index, block);
+        if let Some(other) = seen.insert(name.to_lowercase(), index) {
+            anyhow::bail!(
+                "Blocks {} and {} of {} would both be extracted to {}; rename one of them",
+                other,
+                index,
+                file.display(),
+                name
+            );
+        }
+    }
+
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create directory: {}", out_dir.display()))?;
+
+    let mut extracted = Vec::new();
+    for &(index, block) in &selected {
+        let name = block_file_name(index, block);
+        let path = out_dir.join(&name);
+        let mut content = lines[block.start..block.end].join("\n");
+        content.push('\n');
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write block file: {}", path.display()))?;
+
+        extracted.push(ExtractedBlock {
+            index,
+            file: name,
+            start_line: block.start + 1,
+            end_line: block.end,
+            confidence: block.confidence,
+            fingerprint: block_fingerprint(&lines[block.start..block.end]),
+            name: block.annotations.name.clone(),
+        });
+    }
+
+    if manifest {
+        let manifest = ExtractManifest {
+            version: "1.0",
+            source: file.display().to_string(),
+            blocks: extracted,
+        };
+        let path = out_dir.join("manifest.json");
+        let json =
+            serde_json::to_string_pretty(&manifest).context("Failed to serialize manifest")?;
+        fs::write(&path, json + "\n")
+            .with_context(|| format!("Failed to write manifest: {}", path.display()))?;
+    }
+
+    eprintln!(
+        "Extracted {} block(s) from {} to {}",
+        selected.len(),
+        file.display(),
+        out_dir.display()
+    );
+    Ok(())
+}
+
+/// Handle the inject subcommand.
+///
+/// The replacement content is corrected before it is spliced in, so an
+/// externally edited diagram comes back aligned.
+fn run_inject_command(
+    file: &Path,
+    index: Option<usize>,
+    from: &Path,
+    config: &Config,
+) -> Result<()> {
+    let (source, snapshot, blocks) = detect_file_blocks(file, config)?;
+    let mut lines = source.lines.clone();
+    let (index, block) = match (index, config.block_name.as_deref()) {
+        (Some(index), _) => {
+            let block = blocks.get(index - 1).ok_or_else(|| {
+                ArgError(format!(
+                    "--block {index} is out of range: {} has {} diagram block(s)",
+                    file.display(),
+                    blocks.len()
+                ))
+            })?;
+            (index, block)
+        }
+        (None, Some(name)) => blocks
+            .iter()
+            .enumerate()
+            .find(|(_, block)| block.annotations.name.as_deref() == Some(name))
+            .map(|(i, block)| (i + 1, block))
+            .ok_or_else(|| ArgError(format!("No block named '{name}' in {}", file.display())))?,
+        (None, None) => {
+            return Err(
+                ArgError("inject requires --block N or --block-name NAME".to_string()).into(),
+            );
+        }
+    };
+
+    let replacement = read_file(from, config)?;
+    let inject_config = Config {
+        lines: None,
+        verbose: false,
+        ..config.clone()
+    };
+    let (console, styles) = build_console(ColorMode::Never, None);
+    let (corrected, stats) = correct_lines(replacement, &inject_config, &console, &styles);
+
+    let (start, end) = (block.start, block.end);
+    lines.splice(start..end, corrected.iter().cloned());
+
+    let output = FileResult::revised(source, lines, file.display().to_string()).output_bytes()?;
+    write_in_place(file, &output, Some(&snapshot), None, config)?;
+
+    eprintln!(
+        "Injected {} line(s) into block {} of {} (was lines {}-{}; {} revision(s) applied)",
+        corrected.len(),
+        index,
+        file.display(),
+        start + 1,
+        end,
+        stats.total_revisions
+    );
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Applying Exported Edits
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// A file's entry in `--json` output, as far as `aadc apply` reads it
+#[derive(Debug, Deserialize)]
+struct EditSet {
+    file: Option<String>,
+    /// Absent from `error` entries
+    input: Option<EditSetInput>,
+    #[serde(default)]
+    edits: Vec<LineEdit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EditSetInput {
+    hash: Option<String>,
+}
+
+/// `--json` output for one file or several, or `--json-lines` output
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EditDocument {
+    Run { files: Vec<EditSet> },
+    File(EditSet),
+}
+
+/// Read the edit sets of `--json` (one file or several) or `--json-lines`
+/// output
+fn parse_edit_sets(text: &str) -> Result<Vec<EditSet>> {
+    match serde_json::from_str::<EditDocument>(text) {
+        Ok(EditDocument::Run { files }) => return Ok(files),
+        Ok(EditDocument::File(set)) => return Ok(vec![set]),
+        Err(_) => {}
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|err| {
+                ParseError(format!(
+                    "Not aadc --json or --json-lines output (line {}: {})",
+                    i + 1,
+                    err
+                ))
+                .into()
+            })
+        })
+        .collect()
+}
+
+/// Apply exported edits, checking every file before writing any, so a
+/// stale or mismatched file leaves all of them untouched
+fn run_apply_command(edits: &Path, dry_run: bool, config: &Config) -> Result<()> {
+    let text = if edits == Path::new("-") {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read edits from stdin")?;
+        text
+    } else {
+        fs::read_to_string(edits)
+            .with_context(|| format!("Failed to read edits: {}", edits.display()))?
+    };
+
+    let mut pending = Vec::new();
+    for set in parse_edit_sets(&text)? {
+        if set.edits.is_empty() {
+            continue;
+        }
+        let file = set.file.ok_or_else(|| {
+            ParseError("Edits for stdin input name no file to apply them to".to_string())
+        })?;
+        let path = PathBuf::from(file);
+        let (mut source, snapshot) = read_file_with_snapshot(&path, config)?;
+        let expected = set.input.and_then(|input| input.hash).ok_or_else(|| {
+            ParseError(format!("No content hash recorded for {}", path.display()))
+        })?;
+        if format!("{:016x}", snapshot.hash) != expected {
+            anyhow::bail!(
+                "{} has changed since the edits were generated",
+                path.display()
+            );
+        }
+        for edit in &set.edits {
+            edit.apply(&mut source.lines)
+                .with_context(|| format!("Edit does not apply to {}", path.display()))?;
+        }
+
+        let mut output = String::new();
+        if source.bom {
+            output.push_str(UTF8_BOM);
+        }
+        for (line, ending) in source.lines.iter().zip(&source.endings) {
+            output.push_str(line);
+            output.push_str(ending.map_or("", Eol::as_str));
+        }
+        let bytes = source.encoding.encode(&output)?;
+        pending.push((path, bytes, snapshot, set.edits.len()));
+    }
+
+    for (path, bytes, snapshot, count) in pending {
+        if dry_run {
+            eprintln!("Would apply {} edit(s) to {}", count, path.display());
+            continue;
+        }
+        let backup_ext = config.backup.then_some(config.backup_ext.as_str());
+        write_in_place(&path, bytes, Some(&snapshot), backup_ext, config)?;
+        eprintln!("Applied {} edit(s) to {}", count, path.display());
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Border Style Conversion
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Border style written by `aadc convert`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum ConvertTarget {
+    /// Light box drawing: `┌─┬─┐`, `│`, `├─┼─┤`, `└─┴─┘`, arrows `──▶`
+    Unicode,
+    /// ASCII: `+-+`, `|`, arrows `-->`
+    Ascii,
+}
+
+/// ASCII spelling of a Unicode border character or arrow head. Double
+/// horizontals become `=`; every other line becomes `-` or `|`, and every
+/// corner or junction `+`.
+fn ascii_border(c: char) -> Option<char> {
+    if c.is_ascii() {
+        return None;
+    }
+    Some(match c {
+        '═' => '=',
+        '▶' | '►' | '▷' => '>',
+        '◀' | '◄' | '◁' => '<',
+        c if is_horizontal_fill(c) => '-',
+        c if is_vertical_border(c) => '|',
+        c if is_box_char(c) || BorderStyle::of_unicode(c).is_some() => '+',
+        _ => return None,
+    })
+}
+
+/// Light Unicode piece for an ASCII `+`, from which of its neighbours (up,
+/// down, left, right) connect to it. None for a `+` that joins nothing,
+/// which is text.
+fn unicode_junction(up: bool, down: bool, left: bool, right: bool) -> Option<char> {
+    Some(match (up, down, left, right) {
+        (false, true, false, true) => '┌',
+        (false, true, true, false) => '┐',
+        (true, false, false, true) => '└',
+        (true, false, true, false) => '┘',
+        (true, true, false, true) => '├',
+        (true, true, true, false) => '┤',
+        (false, true, true, true) => '┬',
+        (true, false, true, true) => '┴',
+        (true, true, true, true) => '┼',
+        (false, false, true, true) => '─',
+        (true, true, false, false) => '│',
+        _ => return None,
+    })
+}
+
+/// Unicode spellings for the ASCII borders of a block's lines, as (line,
+/// byte offset, character).
+///
+/// ASCII borders are told from text by context: a `+` by which neighbours
+/// connect to it (`-` beside it, `|` or `+` above and below), a run of `-` by touching a `+` or `|` (or, two or more
+/// long, an arrow head, which converts with it), and a `|` by standing
+/// between blanks, line ends, horizontal borders or arrow heads.
+fn unicode_borders(lines: &[String]) -> Vec<(usize, usize, char)> {
+    let rows: Vec<Vec<(usize, usize, char)>> = lines
+        .iter()
+        .map(|line| {
+            grapheme_columns(line)
+                .filter_map(|(idx, column, unit)| Some((idx, column, unit.chars().next()?)))
+                .collect()
+        })
+        .collect();
+    let at = |row: usize, column: usize| {
+        rows.get(row)?
+            .iter()
+            .find(|&&(_, c, _)| c == column)
+            .map(|&(_, _, ch)| ch)
+    };
+    let vertical = |c: Option<char>| matches!(c, Some('|' | '+'));
+
+    let mut edits = Vec::new();
+    for (r, cells) in rows.iter().enumerate() {
+        let ch = |i: usize| cells.get(i).map(|&(_, _, c)| c);
+        let mut i = 0;
+        while i < cells.len() {
+            let (byte, column, c) = cells[i];
+            match c {
+                '+' => {
+                    let up = r > 0 && vertical(at(r - 1, column));
+                    let down = vertical(at(r + 1, column));
+                    let left = i > 0 && ch(i - 1) == Some('-');
+                    let right = ch(i + 1) == Some('-');
+                    if let Some(piece) = unicode_junction(up, down, left, right) {
+                        edits.push((r, byte, piece));
+                    }
+                }
+                '-' => {
+                    let end = i + cells[i..].iter().take_while(|&&(_, _, c)| c == '-').count();
+                    let before = i.checked_sub(1).and_then(ch);
+                    let after = ch(end);
+                    let border = |c: Option<char>| matches!(c, Some('+' | '|'));
+                    let arrow = end - i >= 2 && (after == Some('>') || before == Some('<'));
+                    if border(before) || border(after) || arrow {
+                        edits.extend(cells[i..end].iter().map(|&(b, _, _)| (r, b, '─')));
+                        if after == Some('>') {
+                            edits.push((r, cells[end].0, '▶'));
+                        }
+                        if before == Some('<') {
+                            edits.push((r, cells[i - 1].0, '◀'));
+                        }
+                    }
+                    i = end;
+                    continue;
+                }
+                '|' => {
+                    let open = |c: Option<char>| {
+                        c.is_none_or(|c| c.is_whitespace() || matches!(c, '-' | '+' | '<' | '>'))
+                    };
+                    if (i == 0 || open(ch(i - 1))) && open(ch(i + 1)) {
+                        edits.push((r, byte, '│'));
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+    edits
+}
+
+/// Redraw the borders of a file's box diagrams in the target style (tabs
+/// are expanded first). Tree diagrams, `aadc:off` regions and text outside
+/// diagrams are left alone.
+fn convert_lines(lines: Vec<String>, target: ConvertTarget, config: &Config) -> Vec<String> {
+    let mut lines = expand_enabled_tabs(lines, config.tab_width);
+    let blocks = find_diagram_blocks(&lines, &config.detect_options());
+    for block in blocks.iter().filter(|b| b.kind == BlockKind::Boxes) {
+        let block_lines = &mut lines[block.start..block.end];
+        match target {
+            ConvertTarget::Ascii => {
+                for line in block_lines.iter_mut() {
+                    *line = line.chars().map(|c| ascii_border(c).unwrap_or(c)).collect();
+                }
+            }
+            ConvertTarget::Unicode => {
+                // Edits run right to left within a line, so byte offsets
+                // stay valid
+                let mut edits = unicode_borders(block_lines);
+                edits.sort_by_key(|&(line, byte, _)| std::cmp::Reverse((line, byte)));
+                for (line, byte, piece) in edits {
+                    let line = &mut block_lines[line];
+                    let len = line[byte..].chars().next().map_or(0, char::len_utf8);
+                    line.replace_range(byte..byte + len, piece.encode_utf8(&mut [0; 4]));
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Handle the convert subcommand: correct as usual, then redraw borders
+/// (which keeps every width, so the result stays aligned). Borders are
+/// converted after alignment because a `+` is read from the borders above
+/// and below it.
+fn run_convert_command(
+    files: &[PathBuf],
+    target: ConvertTarget,
+    in_place: bool,
+    config: &Config,
+) -> Result<()> {
+    let (console, styles) = build_console(ColorMode::Never, None);
+    let convert = |source: SourceText, filename: String| {
+        let (corrected, _stats) = correct_lines(source.lines.clone(), config, &console, &styles);
+        let converted = convert_lines(corrected, target, config);
+        FileResult::revised(source, converted, filename).output_bytes()
+    };
+
+    if files.is_empty() {
+        if in_place {
+            return Err(
+                ArgError("convert --in-place requires at least one file".to_string()).into(),
+            );
+        }
+        let output = convert(read_stdin_content(config)?, stdin_label(config))?;
+        io::stdout().lock().write_all(&output)?;
+        return Ok(());
+    }
+
+    for path in files {
+        let (source, snapshot) = read_file_with_snapshot(path, config)?;
+        let output = convert(source, path.display().to_string())?;
+        if in_place {
+            write_in_place(path, &output, Some(&snapshot), None, config)?;
+            eprintln!("Converted {}", path.display());
+        } else {
+            io::stdout().lock().write_all(&output)?;
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Pandoc Filter
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Read a Pandoc JSON AST from stdin, correct its diagrams, and write it
+/// back to stdout
+fn run_pandoc_filter(config: &Config) -> Result<()> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .context("Failed to read Pandoc JSON from stdin")?;
+    let mut ast: serde_json::Value = serde_json::from_str(&input)
+        .map_err(|e| ParseError(format!("Invalid Pandoc JSON on stdin: {}", e)))?;
+
+    let filter_config = Config {
+        lines: None,
+        verbose: false,
+        ..config.clone()
+    };
+    let changed = correct_pandoc_blocks(&mut ast, &filter_config);
+    if config.verbose {
+        eprintln!("aadc: corrected {} code/raw block(s)", changed);
+    }
+
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, &ast).context("Failed to write Pandoc JSON")?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Correct the text of every CodeBlock and RawBlock in a Pandoc AST,
+/// returning how many blocks changed. Both carry their text as the second
+/// element of `c` (after the attributes or the raw format).
+fn correct_pandoc_blocks(value: &mut serde_json::Value, config: &Config) -> usize {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            let is_code = matches!(
+                map.get("t").and_then(Value::as_str),
+                Some("CodeBlock" | "RawBlock")
+            );
+            if is_code {
+                if let Some(Value::String(text)) = map.get_mut("c").and_then(|c| c.get_mut(1)) {
+                    return match correct_block_text(text, config) {
+                        Some(corrected) => {
+                            *text = corrected;
+                            1
+                        }
+                        None => 0,
+                    };
+                }
+            }
+            map.values_mut()
+                .map(|v| correct_pandoc_blocks(v, config))
+                .sum()
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .map(|v| correct_pandoc_blocks(v, config))
+            .sum(),
+        _ => 0,
+    }
+}
+
+/// Correct diagrams in a block of text, or None if no revision applied
+/// (so blocks without diagrams keep their tabs and exact content)
+fn correct_block_text(text: &str, config: &Config) -> Option<String> {
+    let source = parse_bytes_to_lines(text.as_bytes().to_vec(), "pandoc block").ok()?;
+    let (console, styles) = build_console(ColorMode::Never, None);
+    let (corrected, stats) = correct_lines(source.lines.clone(), config, &console, &styles);
+    if stats.total_revisions == 0 {
+        return None;
+    }
+    Some(FileResult::revised(source, corrected, String::new()).output_text())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Fixtures
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Suffix of fixture input files
+const FIXTURE_INPUT_SUFFIX: &str = ".input.txt";
+
+/// Suffix of fixture expected-output files
+const FIXTURE_EXPECTED_SUFFIX: &str = ".expected.txt";
+
+/// Find fixture inputs under `paths`: directories are searched recursively
+/// and files not named `*.input.txt` are ignored, so shell globs like
+/// `tests/fixtures/**` work. Returns sorted (input, expected) path pairs.
+fn find_fixtures(paths: &[PathBuf]) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let is_input = |path: &Path| {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(FIXTURE_INPUT_SUFFIX))
+    };
+
+    let mut inputs = BTreeSet::new();
+    for path in paths {
+        if path.is_file() {
+            if is_input(path) {
+                inputs.insert(path.clone());
+            }
+            continue;
+        }
+        if !path.is_dir() {
+            anyhow::bail!("Fixture path not found: {}", path.display());
+        }
+        for entry in WalkBuilder::new(path).hidden(false).build() {
+            let entry = entry?;
+            if entry.path().is_file() && is_input(entry.path()) {
+                inputs.insert(entry.path().to_path_buf());
+            }
+        }
+    }
+
+    Ok(inputs
+        .into_iter()
+        .map(|input| {
+            let name = input
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let stem = &name[..name.len() - FIXTURE_INPUT_SUFFIX.len()];
+            let expected = input.with_file_name(format!("{}{}", stem, FIXTURE_EXPECTED_SUFFIX));
+            (input, expected)
+        })
+        .collect())
+}
+
+/// Correct a fixture input the way `aadc FILE` would print it
+fn correct_fixture(input: &Path, config: &Config) -> Result<String> {
+    let (source, _) = read_file_with_snapshot(input, config)?;
+    let (console, styles) = build_console(ColorMode::Never, None);
+    let result = process_input(
+        source,
+        input.display().to_string(),
+        config,
+        &console,
+        &styles,
+    );
+    Ok(result.output_text())
+}
+
+/// Handle the fixtures subcommand
+fn run_fixtures_command(action: &FixturesAction, config: &Config) -> Result<i32> {
+    let config = Config {
+        verbose: false,
+        ..config.clone()
+    };
+
+    match action {
+        FixturesAction::Check { paths, diff } => {
+            let fixtures = find_fixtures(paths)?;
+            let mut failed = 0;
+            for (input, expected_path) in &fixtures {
+                let actual = correct_fixture(input, &config)?;
+                let Ok(expected) = fs::read_to_string(expected_path) else {
+                    println!("MISSING {}", expected_path.display());
+                    failed += 1;
+                    continue;
+                };
+                if actual == expected {
+                    println!("ok      {}", input.display());
+                } else {
+                    println!("FAIL    {}", input.display());
+                    failed += 1;
+                    if *diff {
+                        let label = expected_path.display().to_string();
+                        write_unified_diff(
+                            &mut io::stdout().lock(),
+                            &label,
+                            &format!("{} (actual)", label),
+                            &expected,
+                            &actual,
+                            config.show_padding,
+                            diff_color(&config),
+                        )?;
+                    }
+                }
+            }
+            eprintln!(
+                "{} fixture(s): {} passed, {} failed",
+                fixtures.len(),
+                fixtures.len() - failed,
+                failed
+            );
+            Ok(if failed > 0 {
+                exit_codes::WOULD_CHANGE
+            } else {
+                exit_codes::SUCCESS
+            })
+        }
+        FixturesAction::Update { paths } => {
+            let fixtures = find_fixtures(paths)?;
+            let mut updated = 0;
+            for (input, expected_path) in &fixtures {
+                let actual = correct_fixture(input, &config)?;
+                if fs::read_to_string(expected_path).ok().as_deref() == Some(actual.as_str()) {
+                    continue;
+                }
+                fs::write(expected_path, &actual)
+                    .with_context(|| format!("Failed to write {}", expected_path.display()))?;
+                println!("updated {}", expected_path.display());
+                updated += 1;
+            }
+            eprintln!("{} fixture(s): {} updated", fixtures.len(), updated);
+            Ok(exit_codes::SUCCESS)
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// GitHub Suggestions
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// The subset of a GitHub pull request used here
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+struct PullRequest {
+    head: PullRequestHead,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+struct PullRequestHead {
+    sha: String,
+}
+
+/// A file changed by a pull request
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+struct PullRequestFile {
+    filename: String,
+    status: String,
+    /// Unified diff of the file (absent for binary or very large diffs)
+    patch: Option<String>,
+}
+
+/// A pull request review, as posted to the GitHub API
+#[cfg(feature = "net")]
+#[derive(Serialize)]
+struct Review {
+    commit_id: String,
+    body: String,
+    event: &'static str,
+    comments: Vec<ReviewComment>,
+}
+
+/// A review comment on lines of the pull request head
+#[cfg(feature = "net")]
+#[derive(Debug, Serialize)]
+struct ReviewComment {
+    path: String,
+    /// First line of a multi-line comment (1-based)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_line: Option<usize>,
+    /// Last line of the comment (1-based)
+    line: usize,
+    side: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_side: Option<&'static str>,
+    body: String,
+}
+
+/// Page size for listing pull request files (the API maximum)
+#[cfg(feature = "net")]
+const PR_FILES_PER_PAGE: usize = 100;
+
+/// Line ranges (1-based, inclusive) on the new side of a unified diff's
+/// hunks. GitHub only accepts review comments on these lines, and
+/// `--diff-only` corrects only blocks that overlap them.
+fn patch_new_ranges(patch: &str) -> Vec<(usize, usize)> {
+    patch
+        .lines()
+        .filter_map(|line| {
+            // @@ -a,b +c,d @@
+            let new = line
+                .strip_prefix("@@ ")?
+                .split(' ')
+                .nth(1)?
+                .strip_prefix('+')?;
+            let (start, len) = match new.split_once(',') {
+                Some((start, len)) => (start.parse::<usize>().ok()?, len.parse::<usize>().ok()?),
+                None => (new.parse::<usize>().ok()?, 1),
+            };
+            (len > 0).then(|| (start, start + len - 1))
+        })
+        .collect()
+}
+
+/// Turn the differences between a file's lines and its correction into
+/// suggestion comments. Changes outside `ranges` can't be commented on;
+/// their line ranges are returned separately.
+#[cfg(feature = "net")]
+fn suggestion_comments(
+    path: &str,
+    original: &[String],
+    corrected: &[String],
+    ranges: &[(usize, usize)],
+) -> (Vec<ReviewComment>, Vec<(usize, usize)>) {
+    let mut comments = Vec::new();
+    let mut outside = Vec::new();
+
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, original, corrected) {
+        let old = op.old_range();
+        let new = op.new_range();
+        if op.tag() == similar::DiffTag::Equal || old.is_empty() {
+            continue;
+        }
+        let (start, end) = (old.start + 1, old.end);
+        if !ranges.iter().any(|&(lo, hi)| lo <= start && end <= hi) {
+            outside.push((start, end));
+            continue;
+        }
+
+        let multi_line = start < end;
+        comments.push(ReviewComment {
+            path: path.to_string(),
+            start_line: multi_line.then_some(start),
+            line: end,
+            side: "RIGHT",
+            start_side: multi_line.then_some("RIGHT"),
+            body: format!("```suggestion\n{}\n```", corrected[new].join("\n")),
+        });
+    }
+    (comments, outside)
+}
+
+/// Send a GitHub API request, returning the response body as JSON
+#[cfg(feature = "net")]
+fn github_request(
+    method: &str,
+    url: &str,
+    token: Option<&str>,
+    body: Option<&serde_json::Value>,
+) -> Result<serde_json::Value> {
+    let mut request = ureq::request(method, url)
+        .set("Accept", "application/vnd.github+json")
+        .set("User-Agent", concat!("aadc/", env!("CARGO_PKG_VERSION")));
+    if let Some(token) = token {
+        request = request.set("Authorization", &format!("Bearer {}", token));
+    }
+    let response = match body {
+        Some(body) => request.send_json(body),
+        None => request.call(),
+    };
+    match response {
+        Ok(response) => response
+            .into_json()
+            .with_context(|| format!("Invalid JSON from {}", url)),
+        Err(ureq::Error::Status(code, response)) => {
+            let message = response.into_string().unwrap_or_default();
+            anyhow::bail!("{} {} failed ({}): {}", method, url, code, message.trim())
+        }
+        Err(err) => Err(err).with_context(|| format!("{} {} failed", method, url)),
+    }
+}
+
+/// Check that the working tree is checked out at the pull request head
+/// `sha`: suggestions are computed from local files but posted against
+/// the head, so a stale checkout would suggest the wrong lines. Returns
+/// the top level of the working tree, which PR file names are relative to.
+#[cfg(feature = "net")]
+fn checkout_at_head(sha: &str) -> Result<String> {
+    let head = git_output(&["rev-parse", "HEAD"])?;
+    if head.trim_end() != sha {
+        anyhow::bail!(
+            "the checkout is at {} but the pull request head is {}; check out the head first",
+            head.trim_end(),
+            sha
+        );
+    }
+    Ok(git_output(&["rev-parse", "--show-toplevel"])?
+        .trim_end()
+        .to_string())
+}
+
+/// Handle the suggest subcommand
+#[cfg(feature = "net")]
+fn run_suggest_command(
+    repo: &str,
+    pr: u64,
+    files: &[PathBuf],
+    dry_run: bool,
+    config: &Config,
+) -> Result<()> {
+    if repo.split('/').filter(|part| !part.is_empty()).count() != 2 {
+        return Err(ArgError(format!("--repo must be OWNER/NAME, got '{}'", repo)).into());
+    }
+    let token = std::env::var("GITHUB_TOKEN")
+        .or_else(|_| std::env::var("GH_TOKEN"))
+        .ok();
+    if token.is_none() && !dry_run {
+        anyhow::bail!("GITHUB_TOKEN (or GH_TOKEN) must be set to post a review");
+    }
+    let token = token.as_deref();
+    let api = std::env::var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com".into());
+    let pull_url = format!("{}/repos/{}/pulls/{}", api.trim_end_matches('/'), repo, pr);
+
+    let pull: PullRequest = serde_json::from_value(github_request("GET", &pull_url, token, None)?)
+        .context("Unexpected pull request JSON")?;
+    let top_level = checkout_at_head(&pull.head.sha)?;
+    let mut pr_files: Vec<PullRequestFile> = Vec::new();
+    for page in 1.. {
+        let url = format!(
+            "{}/files?per_page={}&page={}",
+            pull_url, PR_FILES_PER_PAGE, page
+        );
+        let batch: Vec<PullRequestFile> =
+            serde_json::from_value(github_request("GET", &url, token, None)?)
+                .context("Unexpected pull request files JSON")?;
+        let done = batch.len() < PR_FILES_PER_PAGE;
+        pr_files.extend(batch);
+        if done {
+            break;
+        }
+    }
+
+    let config = Config {
+        verbose: false,
+        ..config.clone()
+    };
+    let (console, styles) = build_console(ColorMode::Never, None);
+    let mut comments = Vec::new();
+    for file in &pr_files {
+        let path = Path::new(&file.filename);
+        let wanted = files.is_empty() || files.iter().any(|f| f == path);
+        if file.status == "removed" || !wanted {
+            continue;
+        }
+        let Some(ref patch) = file.patch else {
+            continue;
+        };
+        let status = git_output(&[
+            "-C",
+            &top_level,
+            "status",
+            "--porcelain",
+            "--",
+            &file.filename,
+        ])?;
+        if !status.is_empty() {
+            anyhow::bail!(
+                "{} has uncommitted changes, so it does not match the pull request head",
+                file.filename
+            );
+        }
+        // Binary or unreadable files have no diagrams to suggest
+        let path = Path::new(&top_level).join(path);
+        let Ok((source, _)) = read_file_with_snapshot(&path, &config) else {
+            continue;
+        };
+        let result = process_input(source, file.filename.clone(), &config, &console, &styles);
+        if !result.would_change {
+            continue;
+        }
+
+        let (file_comments, outside) = suggestion_comments(
+            &file.filename,
+            &result.original,
+            &result.corrected,
+            &patch_new_ranges(patch),
+        );
+        for (start, end) in outside {
+            eprintln!(
+                "Warning: {}:{}-{}: outside the pull request diff, not suggested",
+                file.filename, start, end
+            );
+        }
+        comments.extend(file_comments);
+    }
+
+    if comments.is_empty() {
+        eprintln!("No diagram suggestions for {}#{}", repo, pr);
+        return Ok(());
+    }
+    let count = comments.len();
+    let review = Review {
+        commit_id: pull.head.sha,
+        body: format!("aadc: {} diagram alignment suggestion(s)", count),
+        event: "COMMENT",
+        comments,
+    };
+    let review = serde_json::to_value(&review).context("Failed to serialize review")?;
+
+    if dry_run {
+        println!("{}", serde_json::to_string_pretty(&review)?);
+    } else {
+        github_request(
+            "POST",
+            &format!("{}/reviews", pull_url),
+            token,
+            Some(&review),
+        )?;
+        eprintln!(
+            "Posted {} suggestion(s) to https://github.com/{}/pull/{}",
+            count, repo, pr
+        );
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Lint
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Severity of a lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    /// Rule disabled
+    Off,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Off => "off",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A named lint rule
+#[derive(Debug)]
+struct LintRule {
+    id: &'static str,
+    name: &'static str,
+    default_severity: Severity,
+    description: &'static str,
+}
+
+const MISALIGNED_RIGHT_BORDER: LintRule = LintRule {
+    id: "AD001",
+    name: "misaligned-right-border",
+    default_severity: Severity::Error,
+    description: "Right border is left of the block's border column",
+};
+
+const UNCLOSED_BOX: LintRule = LintRule {
+    id: "AD002",
+    name: "unclosed-box",
+    default_severity: Severity::Warning,
+    description: "Box line is missing its right border",
+};
+
+const MIXED_BORDER_STYLE: LintRule = LintRule {
+    id: "AD003",
+    name: "mixed-border-style",
+    default_severity: Severity::Warning,
+    description: "Block mixes ASCII, light, heavy, or double borders",
+};
+
+const TRAILING_WHITESPACE: LintRule = LintRule {
+    id: "AD004",
+    name: "trailing-whitespace",
+    default_severity: Severity::Warning,
+    description: "Whitespace after a diagram line's closing border (fixable)",
+};
+
+const MISALIGNED_LEFT_BORDER: LintRule = LintRule {
+    id: "AD005",
+    name: "misaligned-left-border",
+    default_severity: Severity::Warning,
+    description: "Left border is left of the block's left-border column",
+};
+
+const MISALIGNED_SEPARATOR: LintRule = LintRule {
+    id: "AD006",
+    name: "misaligned-separator",
+    default_severity: Severity::Warning,
+    description: "Interior column separator is out of line with the rows around it",
+};
+
+/// All lint rules, in ID order
+const LINT_RULES: &[LintRule] = &[
+    MISALIGNED_RIGHT_BORDER,
+    UNCLOSED_BOX,
+    MIXED_BORDER_STYLE,
+    TRAILING_WHITESPACE,
+    MISALIGNED_LEFT_BORDER,
+    MISALIGNED_SEPARATOR,
+];
+
+/// Look up a lint rule by ID (case-insensitive) or name
+fn find_lint_rule(key: &str) -> Option<&'static LintRule> {
+    LINT_RULES
+        .iter()
+        .find(|rule| rule.id.eq_ignore_ascii_case(key) || rule.name == key)
+}
+
+/// Lint rule reported for a revision kind, if any
+fn rule_for_kind(kind: &str) -> Option<&'static LintRule> {
+    match kind {
+        "pad" | "extend_fill" | "extend_border" | "shrink" => Some(&MISALIGNED_RIGHT_BORDER),
+        "add_border" => Some(&UNCLOSED_BOX),
+        "align_left" => Some(&MISALIGNED_LEFT_BORDER),
+        "align_separator" => Some(&MISALIGNED_SEPARATOR),
+        _ => None,
+    }
+}
+
+/// Drawing style of a border character
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum BorderStyle {
+    Ascii,
+    Light,
+    Heavy,
+    Double,
+}
+
+impl BorderStyle {
+    /// Style of a Unicode box-drawing character. Mixed-weight pieces
+    /// (e.g. `╤`) belong to no single style.
+    fn of_unicode(c: char) -> Option<Self> {
+        match c {
+            '─' | '│' | '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' | '╭' | '╮' | '╯'
+            | '╰' | '╌' | '╎' | '┄' | '┆' | '┈' | '┊' => Some(Self::Light),
+            '━' | '┃' | '┏' | '┓' | '┗' | '┛' | '┣' | '┫' | '┳' | '┻' | '╋' | '╍' | '╏' | '┅'
+            | '┇' | '┉' | '┋' => Some(Self::Heavy),
+            '═' | '║' | '╔' | '╗' | '╚' | '╝' | '╠' | '╣' | '╦' | '╩' | '╬' => {
+                Some(Self::Double)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BorderStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ascii => "ASCII",
+            Self::Light => "light",
+            Self::Heavy => "heavy",
+            Self::Double => "double",
+        })
+    }
+}
+
+/// Border characters of a block with their styles, as (line index, byte
+/// index, character, style).
+///
+/// Unicode box characters count anywhere; ASCII `+`/`|` only as a line's
+/// first or last non-blank character, since they are common in labels.
+fn block_borders(lines: &[String], block: &DiagramBlock) -> Vec<(usize, usize, char, BorderStyle)> {
+    let mut borders = Vec::new();
+    for (line_idx, line) in lines.iter().enumerate().take(block.end).skip(block.start) {
+        let first = line.find(|c: char| !c.is_whitespace());
+        let last = line.trim_end().char_indices().last().map(|(i, _)| i);
+        for (byte_idx, c) in line.char_indices() {
+            let style = if matches!(c, '+' | '|') {
+                (Some(byte_idx) == first || Some(byte_idx) == last).then_some(BorderStyle::Ascii)
+            } else {
+                BorderStyle::of_unicode(c)
+            };
+            if let Some(style) = style {
+                borders.push((line_idx, byte_idx, c, style));
+            }
+        }
+    }
+    borders
+}
+
+/// Dominant border style of a block by character count; ties go to the
+/// first seen
+fn block_style(lines: &[String], block: &DiagramBlock) -> Option<BorderStyle> {
+    dominant_style(&block_borders(lines, block))
+}
+
+fn dominant_style(borders: &[(usize, usize, char, BorderStyle)]) -> Option<BorderStyle> {
+    let mut counts: Vec<(BorderStyle, usize)> = Vec::new();
+    for &(_, _, _, style) in borders {
+        match counts.iter_mut().find(|(s, _)| *s == style) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((style, 1)),
+        }
+    }
+    counts
+        .iter()
+        .fold(
+            None,
+            |best: Option<(BorderStyle, usize)>, &(style, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((style, count)),
+            },
+        )
+        .map(|(style, _)| style)
+}
+
+/// Find lines of a block drawn in a border style other than the block's
+/// dominant one, as (line index, 1-based column, message)
+fn mixed_style_lines(lines: &[String], block: &DiagramBlock) -> Vec<(usize, usize, String)> {
+    let borders = block_borders(lines, block);
+    let Some(dominant) = dominant_style(&borders) else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    let mut last_line = None;
+    for &(line_idx, byte_idx, c, style) in &borders {
+        if style == dominant || last_line == Some(line_idx) {
+            continue;
+        }
+        last_line = Some(line_idx);
+        found.push((
+            line_idx,
+            visual_width(&lines[line_idx][..byte_idx]) + 1,
+            format!(
+                "{} border '{}' in a block drawn with {} borders",
+                style, c, dominant
+            ),
+        ));
+    }
+    found
+}
+
+/// Resolve a `[lint]` config table to severities keyed by rule ID
+fn parse_lint_severities(
+    table: &BTreeMap<String, Severity>,
+) -> Result<BTreeMap<&'static str, Severity>, String> {
+    table
+        .iter()
+        .map(|(key, &severity)| match find_lint_rule(key) {
+            Some(rule) => Ok((rule.id, severity)),
+            None => Err(format!("unknown lint rule '{}'", key)),
+        })
+        .collect()
+}
+
+impl Config {
+    /// Effective severity of a rule (config override, else its default)
+    fn lint_severity(&self, rule: &LintRule) -> Severity {
+        self.lint_severities
+            .get(rule.id)
+            .copied()
+            .unwrap_or(rule.default_severity)
+    }
+}
+
+/// A single lint finding (1-based line and column)
+#[derive(Debug, Clone, Serialize)]
+struct LintDiagnostic {
+    rule: &'static str,
+    name: &'static str,
+    severity: Severity,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+/// JSON report written by `aadc lint --format json`
+#[derive(Serialize)]
+struct LintReport {
+    version: &'static str,
+    files: Vec<LintFileReport>,
+    errors: usize,
+    warnings: usize,
+}
+
+#[derive(Serialize)]
+struct LintFileReport {
+    file: String,
+    diagnostics: Vec<LintDiagnostic>,
+}
+
+/// Find block lines ending in a border followed by whitespace, as (line
+/// index, 1-based column of the first trailing whitespace character).
+/// Lines not ending in a border are left to other tools.
+fn trailing_whitespace_lines(
+    lines: &[String],
+    block: &DiagramBlock,
+    alt_borders: &[char],
+) -> Vec<(usize, usize)> {
+    (block.start..block.end)
+        .filter_map(|line_idx| {
+            let line = &lines[line_idx];
+            let trimmed = line.trim_end();
+            let ends_in_border = trimmed
+                .chars()
+                .last()
+                .is_some_and(|c| is_border_char(c) || alt_borders.contains(&c));
+            (ends_in_border && trimmed.len() < line.len())
+                .then(|| (line_idx, visual_width(trimmed) + 1))
+        })
+        .collect()
+}
+
+/// Lint lines, reporting each revision correction would apply as a
+/// diagnostic of its rule, plus blocks mixing border styles and trailing
+/// whitespace after borders. Rules turned off are not reported.
+fn lint_lines(lines: &[String], config: &Config) -> Vec<LintDiagnostic> {
+    if !config.all_blocks && !quick_scan_for_diagrams(lines).likely_has_diagrams {
+        return Vec::new();
+    }
+
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|l| expand_tabs(l, config.tab_width))
+        .collect();
+    let mut diagnostics = Vec::new();
+    let mut report = |rule: &'static LintRule, line_idx: usize, column, message| {
+        let severity = config.lint_severity(rule);
+        if severity != Severity::Off {
+            diagnostics.push(LintDiagnostic {
+                rule: rule.id,
+                name: rule.name,
+                severity,
+                line: line_idx + 1,
+                column,
+                message,
+            });
+        }
+    };
+
+    for block in find_diagram_blocks(&lines, &config.detect_options()) {
+        if let Some(ref ranges) = config.lines {
+            if !block_overlaps_ranges(&block, ranges) {
+                continue;
+            }
+        }
+        if !block_name_matches(&block, config.block_name.as_deref()) {
+            continue;
+        }
+
+        for (line_idx, column, message) in mixed_style_lines(&lines, &block) {
+            report(&MIXED_BORDER_STYLE, line_idx, column, message);
+        }
+        for (line_idx, column) in trailing_whitespace_lines(&lines, &block, &config.alt_borders) {
+            report(
+                &TRAILING_WHITESPACE,
+                line_idx,
+                column,
+                "trailing whitespace after right border".to_string(),
+            );
+        }
+
+        let Some((analyzed, revisions)) = propose_revisions(&lines, &block, config) else {
+            continue;
+        };
+        let min_score = block_min_score(&block, config);
+
+        for rev in revisions {
+            if rev.score(&analyzed, block.start, &config.scoring) < min_score
+                || rev.exceeds_max_pad(config)
+            {
+                continue;
+            }
+            let Some(rule) = rule_for_kind(rev.kind()) else {
+                continue;
+            };
+
+            let line_idx = rev.line_idx();
+            let target = rev.resulting_width();
+            let line = &analyzed[line_idx - block.start];
+            let (column, message) = match (&rev, &line.suffix_border) {
+                (
+                    Revision::AlignLeftBorder {
+                        target_column: left,
+                        ..
+                    },
+                    _,
+                ) => (
+                    line.indent + 1,
+                    format!(
+                        "left border at column {}, expected column {}",
+                        line.indent + 1,
+                        left + 1
+                    ),
+                ),
+                (
+                    Revision::PadBeforeSeparator {
+                        column,
+                        target_column,
+                        ..
+                    },
+                    _,
+                ) => (
+                    column + 1,
+                    format!(
+                        "column separator at column {}, expected column {}",
+                        column + 1,
+                        target_column + 1
+                    ),
+                ),
+                (_, Some(border)) => (
+                    border.column + 1,
+                    format!(
+                        "right border at column {}, expected column {}",
+                        border.column + 1,
+                        target
+                    ),
+                ),
+                (_, None) => (
+                    visual_width(lines[line_idx].trim_end()) + 1,
+                    format!("missing right border at column {}", target),
+                ),
+            };
+            report(rule, line_idx, column, message);
+        }
+    }
+
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    diagnostics
+}
+
+/// Print the rule table for `aadc lint --list-rules`
+fn list_lint_rules(config: &Config) {
+    for rule in LINT_RULES {
+        println!(
+            "{}  {:<24} {:<8} {}",
+            rule.id,
+            rule.name,
+            config.lint_severity(rule),
+            rule.description
+        );
+    }
+}
+
+/// Apply fixes for fixable diagnostics, returning the ones left unfixed.
+///
+/// Only trailing whitespace is fixable; removal is limited to whitespace
+/// after the final border, so no content is ever dropped.
+fn fix_lint_diagnostics(
+    lines: &mut [String],
+    diagnostics: Vec<LintDiagnostic>,
+) -> (Vec<LintDiagnostic>, usize) {
+    let mut fixed = 0;
+    let remaining = diagnostics
+        .into_iter()
+        .filter(|d| {
+            if d.rule != TRAILING_WHITESPACE.id {
+                return true;
+            }
+            let line = &mut lines[d.line - 1];
+            line.truncate(line.trim_end().len());
+            fixed += 1;
+            false
+        })
+        .collect();
+    (remaining, fixed)
+}
+
+/// Escape a GitHub Actions workflow command message
+fn github_escape_data(s: &str) -> String {
+    s.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a GitHub Actions workflow command property value
+fn github_escape_property(s: &str) -> String {
+    github_escape_data(s)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Handle the lint subcommand, returning the process exit code
+fn run_lint_command(
+    files: &[PathBuf],
+    format: LintFormat,
+    fix: bool,
+    config: &Config,
+) -> Result<i32> {
+    let mut reports = Vec::new();
+    for path in files {
+        let (source, snapshot) = read_file_with_snapshot(path, config)?;
+        let mut diagnostics = lint_lines(&source.lines, config);
+
+        if fix {
+            let mut lines = source.lines.clone();
+            let fixed;
+            (diagnostics, fixed) = fix_lint_diagnostics(&mut lines, diagnostics);
+            if fixed > 0 {
+                let output = FileResult::revised(source, lines, path.display().to_string())
+                    .output_bytes()?;
+                write_in_place(path, &output, Some(&snapshot), None, config)?;
+                eprintln!("Fixed {} problem(s) in {}", fixed, path.display());
+            }
+        }
+
+        reports.push(LintFileReport {
+            file: path.display().to_string(),
+            diagnostics,
+        });
+    }
+
+    let count = |severity| {
+        reports
+            .iter()
+            .flat_map(|r| &r.diagnostics)
+            .filter(|d| d.severity == severity)
+            .count()
+    };
+    let errors = count(Severity::Error);
+    let warnings = count(Severity::Warning);
+
+    match format {
+        LintFormat::Text | LintFormat::Github => {
+            let mut stdout = io::stdout().lock();
+            for report in &reports {
+                for d in &report.diagnostics {
+                    if format == LintFormat::Github {
+                        writeln!(
+                            stdout,
+                            "::{} file={},line={},col={},title={}::{}",
+                            d.severity,
+                            github_escape_property(&report.file),
+                            d.line,
+                            d.column,
+                            github_escape_property(&format!("{} {}", d.rule, d.name)),
+                            github_escape_data(&d.message)
+                        )?;
+                    } else {
+                        writeln!(
+                            stdout,
+                            "{}:{}:{}: {}[{}] {}: {}",
+                            report.file, d.line, d.column, d.severity, d.rule, d.name, d.message
+                        )?;
+                    }
+                }
+            }
+            if errors + warnings > 0 {
+                eprintln!(
+                    "{} problem(s) ({} error(s), {} warning(s))",
+                    errors + warnings,
+                    errors,
+                    warnings
+                );
+            }
+        }
+        LintFormat::Json => {
+            let report = LintReport {
+                version: "1.0",
+                files: reports,
+                errors,
+                warnings,
+            };
+            let json =
+                serde_json::to_string_pretty(&report).context("Failed to serialize lint report")?;
+            println!("{}", json);
+        }
+    }
+
+    Ok(if errors > 0 {
+        exit_codes::LINT_ERRORS
+    } else {
+        exit_codes::SUCCESS
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Entry Point
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Result of processing a single file or stdin
+struct FileResult {
+    filename: String,
+    original: Vec<String>,
+    corrected: Vec<String>,
+    stats: Stats,
+    would_change: bool,
+    /// Content snapshot of the source file (None for stdin)
+    snapshot: Option<FileSnapshot>,
+    /// Original line terminators, shown in diffs when `--normalize-eol`
+    /// converts them
+    original_endings: Vec<Option<Eol>>,
+    /// Line terminator for revised lines (and every line under
+    /// `--normalize-eol`): the one most of the original lines end with
+    eol: Eol,
+    /// Whether the input started with a byte order mark, re-emitted on
+    /// output
+    bom: bool,
+    /// Encoding the input was read in, used for output
+    encoding: Encoding,
+}
+
+impl FileResult {
+    /// Original content for diffing: with its real line endings when they
+    /// are being normalized, so the conversion shows up
+    fn original_text(&self) -> String {
+        if self.stats.eol_changes == 0 {
+            return self.original.join(self.eol.as_str());
+        }
+        join_with_endings(&self.original, &self.original_endings)
+    }
+
+    /// Corrected content joined with the output line terminator (with a
+    /// trailing one whenever `original_text` keeps real endings)
+    fn corrected_text(&self) -> String {
+        if self.stats.eol_changes > 0 {
+            return self.output_text();
+        }
+        self.corrected.join(self.eol.as_str())
+    }
+
+    /// Content written to disk or stdout: untouched lines keep their own
+    /// terminators, the last line keeps (or lacks) its final newline, and
+    /// the input's BOM is re-emitted
+    fn output_text(&self) -> String {
+        let endings = output_endings(
+            &self.original,
+            &self.original_endings,
+            &self.corrected,
+            self.eol,
+            self.stats.eol_changes > 0,
+        );
+        let mut text = join_with_endings(&self.corrected, &endings);
+        if self.bom {
+            text.insert_str(0, UTF8_BOM);
+        }
+        text
+    }
+
+    /// Result for a file `--cached` knows is clean, without correcting it
+    fn unchanged(
+        source: SourceText,
+        filename: String,
+        blocks_found: usize,
+        config: &Config,
+    ) -> Self {
+        let eol = config
+            .normalize_eol
+            .unwrap_or_else(|| Eol::dominant(&source.endings));
+        Self {
+            filename,
+            corrected: source.lines.clone(),
+            original: source.lines,
+            stats: Stats {
+                blocks_found,
+                ..Stats::default()
+            },
+            would_change: false,
+            snapshot: None,
+            original_endings: source.endings,
+            eol,
+            bom: source.bom || config.write_bom,
+            encoding: source.encoding,
+        }
+    }
+
+    /// Result for lines another pass (lint fixes, inject, convert)
+    /// derived from `source`, written back with the source's line
+    /// endings, final newline, BOM and encoding
+    fn revised(source: SourceText, corrected: Vec<String>, filename: String) -> Self {
+        let eol = Eol::dominant(&source.endings);
+        Self {
+            filename,
+            would_change: corrected != source.lines,
+            corrected,
+            original: source.lines,
+            stats: Stats::default(),
+            snapshot: None,
+            original_endings: source.endings,
+            eol,
+            bom: source.bom,
+            encoding: source.encoding,
+        }
+    }
+
+    /// `output_text` encoded in the input's encoding
+    fn output_bytes(&self) -> Result<Vec<u8>> {
+        self.encoding
+            .encode(&self.output_text())
+            .with_context(|| format!("Failed to encode output for {}", self.filename))
+    }
+}
+
+/// Entry point of the `aadc` binary: parse the command line, run, and
+/// exit with the matching code
+#[doc(hidden)]
+pub fn cli_main() -> ! {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(err) => {
+            let code = match err.kind() {
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => exit_codes::SUCCESS,
+                _ => exit_codes::INVALID_ARGS,
+            };
+            let _ = err.print();
+            std::process::exit(code);
+        }
+    };
+
+    let json_errors = Config::from(&args).json_errors();
+
+    // Handle subcommands first
+    if let Some(command) = &args.command {
+        let exit_code = match run_command(command, &args) {
+            Ok(code) => code,
+            Err(err) => {
+                report_error(&err, None, json_errors);
+                exit_code_for_error(&err)
+            }
+        };
+        std::process::exit(exit_code);
+    }
+
+    let exit_code = match run(args) {
+        Ok(outcome) => {
+            if outcome.missing_diagrams {
+                exit_codes::NO_DIAGRAMS
+            } else if outcome.dry_run && outcome.would_change {
+                exit_codes::WOULD_CHANGE
+            } else if outcome.list_different && outcome.would_change {
+                exit_codes::LIST_DIFFERENT
+            } else {
+                exit_codes::SUCCESS
+            }
+        }
+        Err(err) => {
+            report_error(&err, None, json_errors);
+            exit_code_for_error(&err)
+        }
+    };
+
+    std::process::exit(exit_code);
+}
+
+/// Process a single input (file or stdin) and return the result
+fn process_input(
+    source: SourceText,
+    filename: String,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> FileResult {
+    if config.verbose {
+        console.print(
+            &styles
+                .bold(format!(
+                    "Processing {} ({} lines)...",
+                    filename,
+                    source.lines.len()
+                ))
+                .to_string(),
+        );
+    }
+
+    let SourceText {
+        lines,
+        endings,
+        bom,
+        encoding,
+    } = source;
+    let config = &config_for_lines(&lines, config);
+    let original = lines.clone();
+    let embed_syntax = EmbedSyntax::from_path(&filename).filter(|_| config.embedded);
+    let (corrected, mut stats) = match embed_syntax {
+        Some(syntax) => correct_embedded(lines, syntax, config, console, styles),
+        None => correct_lines(lines, config, console, styles),
+    };
+
+    for line_idx in &stats.long_lines {
+        eprintln!(
+            "Warning: {}:{}: line longer than {} bytes, not analyzed (see --max-line-bytes)",
+            filename,
+            line_idx + 1,
+            config.max_line_bytes
+        );
+    }
+
+    if let Some(max) = config.max_line_length {
+        for line_idx in &stats.refused_lines {
+            eprintln!(
+                "Warning: {}:{}: alignment refused, line would exceed {} columns",
+                filename,
+                line_idx + 1,
+                max
+            );
+        }
+    }
+
+    let eol = config
+        .normalize_eol
+        .unwrap_or_else(|| Eol::dominant(&endings));
+    if config.normalize_eol.is_some() {
+        stats.eol_changes = endings
+            .iter()
+            .filter(|ending| ending.is_some_and(|e| e != eol))
+            .count();
+    }
+
+    let original_text = original.join("\n");
+    let corrected_text = corrected.join("\n");
+    let would_change = original_text != corrected_text || stats.eol_changes > 0;
+
+    FileResult {
+        filename,
+        original,
+        corrected,
+        stats,
+        would_change,
+        snapshot: None,
+        original_endings: endings,
+        eol,
+        bom: bom || config.write_bom,
+        encoding,
+    }
+}
+
+/// Line terminators for `corrected`: lines carried over unchanged from
+/// `original` keep their own (converted to `eol` when `normalize`), while
+/// revised and inserted lines get `eol`. Only the last line may lack one,
+/// and only if the original's last line did.
+fn output_endings(
+    original: &[String],
+    endings: &[Option<Eol>],
+    corrected: &[String],
+    eol: Eol,
+    normalize: bool,
+) -> Vec<Option<Eol>> {
+    let mut output = vec![Some(eol); corrected.len()];
+    if !normalize {
+        for op in similar::capture_diff_slices(similar::Algorithm::Myers, original, corrected) {
+            let (tag, old, new) = op.as_tag_tuple();
+            if tag == DiffTag::Equal {
+                for (old, new) in old.zip(new) {
+                    output[new] = endings[old].or(Some(eol));
+                }
+            }
+        }
+    }
+    if let (Some(last), Some(None)) = (output.last_mut(), endings.last()) {
+        *last = None;
+    }
+    output
+}
+
+/// `lines` each followed by its terminator
+fn join_with_endings(lines: &[String], endings: &[Option<Eol>]) -> String {
+    let mut text = String::new();
+    for (line, ending) in lines.iter().zip(endings) {
+        text.push_str(line);
+        text.push_str(ending.map_or("", Eol::as_str));
+    }
+    text
+}
+
+/// Output the diff of a file result, in the `--diff-format` layout
+fn output_diff(result: &FileResult, proposed: bool, config: &Config) -> Result<()> {
+    if !result.would_change {
+        return Ok(());
+    }
+    let out = &mut io::stdout().lock();
+    let color = diff_color(config);
+    match config.diff_format {
+        DiffFormat::Unified => write_result_diff(out, result, proposed, config, color),
+        DiffFormat::SideBySide => {
+            let (old_label, new_label) = diff_labels(result, proposed);
+            let layout = SideBySide {
+                width: side_by_side_width(),
+                color,
+                show_padding: config.show_padding,
+                tab_width: config.tab_width,
+            };
+            write_side_by_side_diff(
+                out,
+                &old_label,
+                &new_label,
+                &result.original_text(),
+                &result.corrected_text(),
+                &layout,
+            )
+        }
+    }
+}
+
+/// Whether diffs on stdout are colored, decided by `--color` as for
+/// console output
+fn diff_color(config: &Config) -> bool {
+    build_console(config.color, config.ci).1.use_color
+}
+
+/// The `a/` and `b/` labels of a file result's diff
+fn diff_labels(result: &FileResult, proposed: bool) -> (String, String) {
+    let new_label = if proposed {
+        format!("b/{} (proposed)", result.filename)
+    } else {
+        format!("b/{}", result.filename)
+    };
+    (format!("a/{}", result.filename), new_label)
+}
+
+/// Write the unified diff of a file result to `out`
+fn write_result_diff(
+    out: &mut impl Write,
+    result: &FileResult,
+    proposed: bool,
+    config: &Config,
+    color: bool,
+) -> Result<()> {
+    let (old_label, new_label) = diff_labels(result, proposed);
+    write_unified_diff(
+        out,
+        &old_label,
+        &new_label,
+        &result.original_text(),
+        &result.corrected_text(),
+        config.show_padding,
+        color,
+    )
+}
+
+/// Placeholder for inserted padding under `--show-padding`
+const PADDING_MARKER: char = '·';
+
+/// ANSI styles of colored diffs
+const DIFF_REMOVED: &str = "\x1b[31m";
+const DIFF_ADDED: &str = "\x1b[32m";
+const DIFF_HUNK: &str = "\x1b[36m";
+const DIFF_RESET: &str = "\x1b[0m";
+
+/// Reverse video on and off, for the characters changed within a line
+const DIFF_EMPHASIS: &str = "\x1b[7m";
+const DIFF_EMPHASIS_OFF: &str = "\x1b[27m";
+
+/// Render `new` with the spaces inserted relative to `old` replaced by
+/// `PADDING_MARKER`
+fn mark_padding(old: &str, new: &str) -> String {
+    TextDiff::from_chars(old, new)
+        .iter_all_changes()
+        .filter(|change| change.tag() != ChangeTag::Delete)
+        .map(|change| match (change.tag(), change.value()) {
+            (ChangeTag::Insert, " ") => PADDING_MARKER.to_string(),
+            (_, value) => value.to_string(),
+        })
+        .collect()
+}
+
+/// A line as runs of `(changed, text)`
+type DiffRuns = Vec<(bool, String)>;
+
+/// `old` and `new` compared character by character, as runs in which the
+/// characters deleted from `old` and those inserted into `new` are
+/// changed. With `show_padding`, inserted spaces become `PADDING_MARKER`.
+fn changed_runs(old: &str, new: &str, show_padding: bool) -> (DiffRuns, DiffRuns) {
+    fn push(runs: &mut DiffRuns, changed: bool, text: &str) {
+        match runs.last_mut() {
+            Some((last_changed, last)) if *last_changed == changed => last.push_str(text),
+            _ => runs.push((changed, text.to_string())),
+        }
+    }
+
+    let (mut old_runs, mut new_runs) = (Vec::new(), Vec::new());
+    for change in TextDiff::from_chars(old, new).iter_all_changes() {
+        match (change.tag(), change.value()) {
+            (ChangeTag::Equal, value) => {
+                push(&mut old_runs, false, value);
+                push(&mut new_runs, false, value);
+            }
+            (ChangeTag::Delete, value) => push(&mut old_runs, true, value),
+            (ChangeTag::Insert, " ") if show_padding => {
+                push(&mut new_runs, true, &PADDING_MARKER.to_string());
+            }
+            (ChangeTag::Insert, value) => push(&mut new_runs, true, value),
+        }
+    }
+    (old_runs, new_runs)
+}
+
+/// `runs` in `style`, with their changed runs emphasized
+fn paint_runs(runs: &[(bool, String)], style: &str) -> String {
+    let mut painted = style.to_string();
+    for (changed, text) in runs {
+        if *changed {
+            painted.push_str(DIFF_EMPHASIS);
+            painted.push_str(text);
+            painted.push_str(DIFF_EMPHASIS_OFF);
+        } else {
+            painted.push_str(text);
+        }
+    }
+    painted.push_str(DIFF_RESET);
+    painted
+}
+
+/// Write a unified diff between two texts to `out`. With `show_padding`,
+/// lines replaced one-for-one show their inserted spaces as `PADDING_MARKER`;
+/// with `color`, removed lines are red and added lines green, and the
+/// characters that differ within lines replaced one-for-one are emphasized.
+fn write_unified_diff(
+    out: &mut impl Write,
+    old_label: &str,
+    new_label: &str,
+    old: &str,
+    new: &str,
+    show_padding: bool,
+    color: bool,
+) -> Result<()> {
+    let diff = TextDiff::from_lines(old, new);
+
+    // New line index -> old line index it replaces, and the reverse
+    let mut replaced = BTreeMap::new();
+    let mut replacing = BTreeMap::new();
+    if show_padding || color {
+        for op in diff.ops() {
+            if let similar::DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } = *op
+            {
+                if old_len == new_len {
+                    replaced.extend((0..new_len).map(|k| (new_index + k, old_index + k)));
+                    replacing.extend((0..old_len).map(|k| (old_index + k, new_index + k)));
+                }
+            }
+        }
+    }
+
+    writeln!(out, "--- {}", old_label)?;
+    writeln!(out, "+++ {}", new_label)?;
+
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        if color {
+            writeln!(out, "{}{}{}", DIFF_HUNK, hunk.header(), DIFF_RESET)?;
+        } else {
+            writeln!(out, "{}", hunk.header())?;
+        }
+        for change in hunk.iter_changes() {
+            let sign = match change.tag() {
+                ChangeTag::Delete => "-",
+                ChangeTag::Insert => "+",
+                ChangeTag::Equal => " ",
+            };
+            if color && change.tag() != ChangeTag::Equal {
+                let line = change.value().trim_end_matches(['\n', '\r']);
+                let paired = |lines: &[&str], index: Option<&usize>| {
+                    index.map(|&i| lines[i].trim_end_matches(['\n', '\r']).to_string())
+                };
+                let (style, runs) = match change.tag() {
+                    ChangeTag::Delete => {
+                        let new_line = paired(
+                            diff.new_slices(),
+                            change.old_index().and_then(|i| replacing.get(&i)),
+                        );
+                        let runs = match new_line {
+                            Some(new_line) => changed_runs(line, &new_line, false).0,
+                            None => vec![(false, line.to_string())],
+                        };
+                        (DIFF_REMOVED, runs)
+                    }
+                    _ => {
+                        let old_line = paired(
+                            diff.old_slices(),
+                            change.new_index().and_then(|i| replaced.get(&i)),
+                        );
+                        let runs = match old_line {
+                            Some(old_line) => changed_runs(&old_line, line, show_padding).1,
+                            None => vec![(false, line.to_string())],
+                        };
+                        (DIFF_ADDED, runs)
+                    }
+                };
+                writeln!(out, "{}", paint_runs(&runs, &format!("{}{}", style, sign)))?;
+                continue;
+            }
+            let marked = change
+                .new_index()
+                .filter(|_| show_padding && change.tag() == ChangeTag::Insert)
+                .and_then(|index| replaced.get(&index))
+                .map(|&old_index| mark_padding(diff.old_slices()[old_index], change.value()));
+            let line = marked.as_deref().unwrap_or(change.value());
+            if line.ends_with('\n') {
+                write!(out, "{}{}", sign, line)?;
+            } else {
+                writeln!(out, "{}{}", sign, line)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Total width of side-by-side diffs that are not written to a terminal
+/// (as for `diff --side-by-side`)
+const SIDE_BY_SIDE_WIDTH: usize = 130;
+
+/// Narrowest pane of a side-by-side diff, however narrow the terminal
+const MIN_PANE_WIDTH: usize = 12;
+
+/// Layout of a side-by-side diff
+struct SideBySide {
+    /// Total width of each row, in columns
+    width: usize,
+    /// Color removed lines red and added lines green, emphasizing the
+    /// characters that differ between the two sides of a row
+    color: bool,
+    /// Render inserted padding as `PADDING_MARKER`
+    show_padding: bool,
+    /// Tab stops for expanding tabs, so panes stay aligned
+    tab_width: usize,
+}
+
+/// Width of side-by-side diffs: `COLUMNS` if set, else that of the
+/// terminal on stdout, else `SIDE_BY_SIDE_WIDTH`
+fn side_by_side_width() -> usize {
+    let columns = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse::<usize>().ok())
+        .filter(|&c| c > 0);
+    if let Some(columns) = columns {
+        return columns;
+    }
+    if std::io::IsTerminal::is_terminal(&io::stdout()) {
+        if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+            return usize::from(width);
+        }
+    }
+    SIDE_BY_SIDE_WIDTH
+}
+
+/// Write the changes between two texts to `out` as two panes, old lines
+/// on the left and new on the right, in the hunks of a unified diff. The
+/// gutter marks changed (`|`), removed (`<`) and added (`>`) lines, as
+/// `diff --side-by-side` does; lines too long for their pane end in `…`.
+/// Lines are tab-expanded before comparison, so changed characters line
+/// up across the panes.
+fn write_side_by_side_diff(
+    out: &mut impl Write,
+    old_label: &str,
+    new_label: &str,
+    old: &str,
+    new: &str,
+    layout: &SideBySide,
+) -> Result<()> {
+    let diff = TextDiff::from_lines(old, new);
+    let (old_lines, new_lines) = (diff.old_slices(), diff.new_slices());
+    let number_width = old_lines.len().max(new_lines.len()).to_string().len();
+    let pane = (layout.width.saturating_sub(2 * (number_width + 1) + 3) / 2).max(MIN_PANE_WIDTH);
+
+    let cell = |index: Option<usize>, runs: DiffRuns, style: &str| -> String {
+        let number = index.map_or_else(String::new, |i| (i + 1).to_string());
+        let runs = fit_runs(runs, pane);
+        let width: usize = runs.iter().map(|(_, text)| visual_width(text)).sum();
+        let padding = " ".repeat(pane - width);
+        if layout.color && !style.is_empty() && width > 0 {
+            let text = paint_runs(&runs, style);
+            format!("{number:>number_width$} {text}{padding}")
+        } else {
+            let text: String = runs.into_iter().map(|(_, text)| text).collect();
+            format!("{number:>number_width$} {text}{padding}")
+        }
+    };
+    let line = |lines: &[&str], index: usize| {
+        let text = lines[index].trim_end_matches(['\n', '\r']);
+        expand_tabs(&strip_ansi(text), layout.tab_width)
+    };
+
+    writeln!(out, "--- {}", old_label)?;
+    writeln!(out, "+++ {}", new_label)?;
+
+    for hunk in diff.unified_diff().context_radius(3).iter_hunks() {
+        if layout.color {
+            writeln!(out, "{}{}{}", DIFF_HUNK, hunk.header(), DIFF_RESET)?;
+        } else {
+            writeln!(out, "{}", hunk.header())?;
+        }
+        for op in hunk.ops() {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            for k in 0..old_range.len().max(new_range.len()) {
+                let old_index = (k < old_range.len()).then(|| old_range.start + k);
+                let new_index = (k < new_range.len()).then(|| new_range.start + k);
+                let marker = match (tag, old_index, new_index) {
+                    (DiffTag::Equal, _, _) => ' ',
+                    (_, Some(_), Some(_)) => '|',
+                    (_, Some(_), None) => '<',
+                    _ => '>',
+                };
+                let old_text = old_index.map(|i| line(old_lines, i)).unwrap_or_default();
+                let new_text = new_index.map(|i| line(new_lines, i)).unwrap_or_default();
+                let (old_runs, new_runs) = if marker == '|' {
+                    changed_runs(&old_text, &new_text, layout.show_padding)
+                } else {
+                    (vec![(false, old_text)], vec![(false, new_text)])
+                };
+                let (old_style, new_style) = match marker {
+                    ' ' => ("", ""),
+                    _ => (DIFF_REMOVED, DIFF_ADDED),
+                };
+                let row = format!(
+                    "{} {} {}",
+                    cell(old_index, old_runs, old_style),
+                    marker,
+                    cell(new_index, new_runs, new_style)
+                );
+                writeln!(out, "{}", row.trim_end())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `runs` cut to at most `width` display columns, ending in an unchanged
+/// `…` if cut
+fn fit_runs(runs: DiffRuns, width: usize) -> DiffRuns {
+    if runs
+        .iter()
+        .map(|(_, text)| visual_width(text))
+        .sum::<usize>()
+        <= width
+    {
+        return runs;
+    }
+    let mut fitted = Vec::new();
+    let mut used = 0;
+    'runs: for (changed, text) in runs {
+        let mut kept = String::new();
+        for c in text.chars() {
+            let w = UnicodeWidthChar::width(c).unwrap_or(0);
+            if used + w + 1 > width {
+                if !kept.is_empty() {
+                    fitted.push((changed, kept));
+                }
+                break 'runs;
+            }
+            kept.push(c);
+            used += w;
+        }
+        fitted.push((changed, kept));
+    }
+    fitted.push((false, '…'.to_string()));
+    fitted
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Watch Mode
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Watch mode needs file change notifications, which `wasm32` lacks
+#[cfg(target_arch = "wasm32")]
+fn watch_and_correct(
+    path: &Path,
+    _config: &Config,
+    _console: &Console,
+    _styles: &VerboseStyle,
+) -> Result<RunOutcome> {
+    anyhow::bail!(
+        "--watch is not supported on this platform: {}",
+        path.display()
+    )
+}
+
+/// Watch a file for changes and auto-correct on each save
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_and_correct(
+    path: &Path,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<RunOutcome> {
+    // Validate that the file exists and is readable
+    if !path.exists() {
+        anyhow::bail!("File not found: {}", path.display());
+    }
+    if !path.is_file() {
+        anyhow::bail!(
+            "--watch requires a file, not a directory: {}",
+            path.display()
+        );
+    }
+
+    // Set up Ctrl+C handler
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })
+    .context("Failed to set Ctrl+C handler")?;
+
+    // Set up file watcher
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: Result<Event, notify::Error>| {
+            if let Ok(event) = res {
+                let _ = tx.send(event);
+            }
+        },
+        notify::Config::default(),
+    )
+    .context("Failed to create file watcher")?;
+
+    watcher
+        .watch(path, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch file: {}", path.display()))?;
+
+    let debounce = Duration::from_millis(config.debounce_ms);
+    let mut last_event = Instant::now() - debounce; // Allow immediate first run
+
+    eprintln!(
+        "Watching {} for changes (Ctrl+C to stop)...",
+        path.display()
+    );
+
+    let mut any_changes = false;
+
+    while running.load(Ordering::SeqCst) {
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(event) => {
+                // Only process file modification events
+                if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                    let now = Instant::now();
+                    if now.duration_since(last_event) >= debounce {
+                        last_event = now;
+
+                        // Re-read and process the file
+                        let file_config = config_for_path(path, config);
+                        match read_file_with_snapshot(path, &file_config) {
+                            Ok((source, snapshot)) => {
+                                let result = process_input(
+                                    source,
+                                    path.display().to_string(),
+                                    &file_config,
+                                    console,
+                                    styles,
+                                );
+
+                                if result.would_change {
+                                    // Write the corrected content back
+                                    let written = result.output_bytes().and_then(|output| {
+                                        write_in_place(path, output, Some(&snapshot), None, config)
+                                    });
+                                    match written {
+                                        Ok(_) => {
+                                            eprintln!(
+                                                "✓ Applied {} revision(s)",
+                                                result.stats.total_revisions
+                                            );
+                                            any_changes = true;
+                                            if let Err(e) =
+                                                run_on_change(config, &[path.to_path_buf()])
+                                            {
+                                                eprintln!("✗ {:#}", e);
+                                            }
+                                        }
+                                        Err(e) => {
+                                            eprintln!("✗ {:#}", e);
+                                        }
+                                    }
+                                } else {
+                                    eprintln!("✓ No changes needed");
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("✗ Error reading file: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                // Just continue waiting
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                // Watcher disconnected, exit
+                break;
+            }
+        }
+    }
+
+    eprintln!("\nWatch mode stopped.");
+
+    Ok(RunOutcome {
+        dry_run: false,
+        list_different: false,
+        would_change: any_changes,
+        missing_diagrams: false,
+    })
+}
+
+fn run(mut args: Args) -> Result<RunOutcome> {
+    if cfg!(windows) {
+        args.inputs = expand_input_globs(&args.inputs)?;
+    }
+    if let Some(ref list) = args.files_from {
+        let listed = read_files_from(list, args.null)?;
+        // An empty list means nothing to do, not "read stdin"
+        if listed.is_empty() && args.inputs.is_empty() {
+            return Ok(RunOutcome {
+                dry_run: args.dry_run,
+                list_different: args.list_different,
+                would_change: false,
+                missing_diagrams: false,
+            });
+        }
+        args.inputs.extend(listed);
+    }
+    let file_lines = split_input_ranges(&mut args.inputs)?;
+    validate_args(&args)?;
+
+    // Warn about very high max_iters values that may slow processing
+    if args.max_iters > 100 {
+        eprintln!(
+            "Warning: --max-iters {} is very high; this may slow processing",
+            args.max_iters
+        );
+    }
+
+    let mut config = create_config(&args)?;
+    config.file_lines = file_lines;
+    if config.embedded && config.apply_ids.is_some() {
+        return Err(ArgError("--apply-ids cannot be used with embedded mode".to_string()).into());
+    }
+    announce_ci(&config);
+    let (console, styles) = build_console(config.color, config.ci);
+
+    // Handle watch mode - must have exactly one file input
+    if config.watch {
+        if args.inputs.len() != 1 {
+            anyhow::bail!("--watch requires exactly one input file");
+        }
+        let path = &args.inputs[0];
+        return watch_and_correct(path, &config, &console, &styles);
+    }
+
+    if config.verbose {
+        if let Some(preset) = config.preset {
+            console.print(
+                &styles
+                    .dim(format!(
+                        "Using preset: {:?} (min_score = {:.1})",
+                        preset,
+                        config.effective_min_score()
+                    ))
+                    .to_string(),
+            );
+        }
+    }
+
+    if args.batch {
+        return run_batch(&args, &config, &console, &styles);
+    }
+
+    if args.stream {
+        return run_stream(&args, &config, &console, &styles);
+    }
+
+    let cache = config.cached.then(|| ResultCache::load(&config.cache_file));
+    let outcome = run_files(&args, &config, cache.as_ref(), &console, &styles);
+    if let Some(cache) = &cache {
+        // Keep what was learned even if some files failed
+        if let Err(err) = cache.save() {
+            eprintln!("Warning: {:#}", err);
+        }
+    }
+    outcome
+}
+
+/// Read, correct and output the input files (or stdin)
+fn run_files(
+    args: &Args,
+    config: &Config,
+    cache: Option<&ResultCache>,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<RunOutcome> {
+    let mut files = if config.recursive {
+        let files = discover_recursive_files(&args.inputs, config, console, styles)?;
+        if files.is_empty() {
+            let message = format!(
+                "Warning: No files matched pattern '{}' in provided paths",
+                config.glob
+            );
+            if config.verbose {
+                console.print(&styles.dim(message));
+            } else {
+                eprintln!("{}", message);
+            }
+            return Ok(RunOutcome {
+                dry_run: config.dry_run,
+                list_different: config.list_different,
+                would_change: false,
+                missing_diagrams: false,
+            });
+        }
+        files
+    } else {
+        args.inputs.clone()
+    };
+
+    if let Some(base) = &args.changed_since {
+        let total = files.len();
+        files = changed_since(files, base)?;
+        if config.verbose {
+            console.print(&styles.dim(format!(
+                "Changed since {}: {} of {} file(s)",
+                base,
+                files.len(),
+                total
+            )));
+        }
+        if files.is_empty() {
+            return Ok(RunOutcome {
+                dry_run: config.dry_run,
+                list_different: config.list_different,
+                would_change: false,
+                missing_diagrams: false,
+            });
+        }
+    }
+
+    if config.recursive {
+        return output_multiple_results(args, config, cache, console, styles, &files);
+    }
+
+    // Determine if we're processing stdin or files
+    if files.is_empty() || files.iter().all(|p| is_stdin_path(p)) {
+        // Stdin mode - single input
+        let result =
+            process_path(Path::new("-"), config, None, console, styles).inspect_err(|e| {
+                if config.json {
+                    let failure = JsonFailure::of(Path::new("-"), e);
+                    let _ = print_json(config, &JsonDocument::File(JsonFile::Failed(failure)));
+                }
+            })?;
+        output_single_result(args, config, console, styles, result)
+    } else if files.len() == 1 {
+        // Single file mode - same behavior as before
+        let result = process_path(&files[0], config, cache, console, styles).inspect_err(|e| {
+            // Best effort: the processing error is what gets reported
+            if config.json {
+                let failure = JsonFailure::of(&files[0], e);
+                let _ = print_json(config, &JsonDocument::File(JsonFile::Failed(failure)));
+            }
+            if !config.reports.is_empty() {
+                let _ = write_reports(config, &[ReportCase::error(&files[0], e)]);
+            }
+            if config.output_format != OutputFormat::Content {
+                let _ = output_report_header(config, 1)
+                    .and_then(|()| output_report_error(config, &files[0], e, 1));
+            }
+        })?;
+        output_single_result(args, config, console, styles, result)
+    } else {
+        // Multiple file mode
+        output_multiple_results(args, config, cache, console, styles, &files)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Batch Mode
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Summary line written after each document in `--batch` mode
+#[derive(Debug, Serialize)]
+struct BatchTrailer {
+    /// 1-based position of the document in the batch
+    document: usize,
+    changed: bool,
+    blocks: usize,
+    revisions: usize,
+    /// Whether a newline was written after a document that lacked a final
+    /// one, so the trailer starts on its own line
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    newline_added: bool,
+    /// Why the document was passed through unchanged
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Parse a `--separator` value, expanding `\n`, `\t`, `\0`, `\xHH` and `\\`
+fn parse_separator(s: &str) -> Result<Vec<u8>, String> {
+    let mut separator = Vec::new();
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'\\' {
+            separator.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => separator.push(b'\n'),
+            Some(b't') => separator.push(b'\t'),
+            Some(b'0') => separator.push(0),
+            Some(b'\\') => separator.push(b'\\'),
+            Some(b'x') => {
+                let hex: Vec<u8> = bytes.by_ref().take(2).collect();
+                let value = std::str::from_utf8(&hex)
+                    .ok()
+                    .filter(|h| h.len() == 2)
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| {
+                        format!("invalid escape '\\x{}'", String::from_utf8_lossy(&hex))
+                    })?;
+                separator.push(value);
+            }
+            Some(other) => return Err(format!("unknown escape '\\{}'", other as char)),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+    if separator.is_empty() {
+        return Err("separator cannot be empty".to_string());
+    }
+    Ok(separator)
+}
+
+/// Split batch input into documents. A separator at the very end (alone or
+/// followed by a newline) closes the last document instead of opening an
+/// empty one.
+fn split_batch<'a>(input: &'a [u8], separator: &[u8]) -> Vec<&'a [u8]> {
+    let mut documents = Vec::new();
+    let mut rest = input;
+    while let Some(pos) = rest
+        .windows(separator.len())
+        .position(|window| window == separator)
+    {
+        documents.push(&rest[..pos]);
+        rest = &rest[pos + separator.len()..];
+    }
+    if !matches!(rest, b"" | b"\n" | b"\r\n") {
+        documents.push(rest);
+    }
+    documents
+}
+
+/// Correct every document in a `--batch` stream. Documents that cannot be
+/// parsed (binary, invalid UTF-8) are passed through with an error in
+/// their trailer rather than aborting the batch.
+fn run_batch(
+    args: &Args,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<RunOutcome> {
+    let separator =
+        parse_separator(&args.separator).map_err(|e| ArgError(format!("--separator: {e}")))?;
+    let mut input = Vec::new();
+    io::stdin()
+        .read_to_end(&mut input)
+        .context("Failed to read stdin")?;
+
+    let mut stdout = io::stdout().lock();
+    let mut would_change = false;
+    let mut missing_diagrams = false;
+    for (idx, document) in split_batch(&input, &separator).into_iter().enumerate() {
+        let label = format!("stdin document {}", idx + 1);
+        let mut trailer = BatchTrailer {
+            document: idx + 1,
+            changed: false,
+            blocks: 0,
+            revisions: 0,
+            newline_added: false,
+            error: None,
+        };
+
+        let output = match parse_bytes_to_lines(document.to_vec(), &label) {
+            Ok(source) => {
+                let result = process_input(source, label, config, console, styles);
+                missing_diagrams |= lacks_required_diagrams(&result, config);
+                would_change |= result.would_change;
+                trailer.changed = result.would_change;
+                trailer.blocks = result.stats.blocks_found;
+                trailer.revisions = result.stats.total_revisions;
+                result.output_text().into_bytes()
+            }
+            Err(err) => {
+                trailer.error = Some(format!("{:#}", err));
+                document.to_vec()
+            }
+        };
+        stdout.write_all(&output)?;
+        if !output.is_empty() && !output.ends_with(b"\n") {
+            stdout.write_all(b"\n")?;
+            trailer.newline_added = true;
+        }
+
+        serde_json::to_writer(&mut stdout, &trailer).context("Failed to serialize trailer")?;
+        stdout.write_all(b"\n")?;
+        stdout.write_all(&separator)?;
+    }
+    stdout.flush()?;
+
+    Ok(RunOutcome {
+        dry_run: false,
+        list_different: false,
+        would_change,
+        missing_diagrams,
+    })
+}
+
+/// Check a result against `--require-diagrams`, warning on stderr when it
+/// has no diagram blocks
+fn lacks_required_diagrams(result: &FileResult, config: &Config) -> bool {
+    let missing = config.require_diagrams && result.stats.blocks_found == 0;
+    if missing && !config.json {
+        eprintln!("No diagrams found: {}", result.filename);
+    }
+    missing
+}
+
+/// Handle output for a single file/stdin result
+fn output_single_result(
+    args: &Args,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+    result: FileResult,
+) -> Result<RunOutcome> {
+    let would_change = result.would_change;
+    let missing_diagrams = lacks_required_diagrams(&result, config);
+    if !config.reports.is_empty() {
+        write_reports(config, &[ReportCase::of(&result, config)?])?;
+    }
+
+    if config.json {
+        output_json_single(args, config, &result)?;
+    } else if config.list_different {
+        if would_change {
+            print_listed_path(&result.filename, config)?;
+        }
+    } else if config.check {
+        if would_change {
+            eprintln!("Would correct: {}", result.filename);
+        }
+        print_check_summary(usize::from(would_change), 1);
+    } else if config.output_format != OutputFormat::Content {
+        output_report_header(config, 1)?;
+        output_report(config, &result, 1)?;
+    } else if config.dry_run {
+        output_dry_run_single(config, console, styles, &result)?;
+    } else if config.diff {
+        output_diff(&result, false, config)?;
+    } else if args.in_place {
+        // Must have a file path for in-place
+        let path = args
+            .inputs
+            .first()
+            .ok_or_else(|| ArgError("--in-place requires an input file".to_string()))?;
+
+        let output = result.output_bytes()?;
+        let backup_ext = config.backup.then_some(config.backup_ext.as_str());
+        let backup = write_in_place(path, &output, result.snapshot.as_ref(), backup_ext, config)?;
+        if let (Some(backup_path), true) = (backup, config.verbose) {
+            console.print(
+                &styles
+                    .dim(format!("Created backup: {}", backup_path.display()))
+                    .to_string(),
+            );
+        }
+    } else {
+        // Stdout mode
+        io::stdout().lock().write_all(&result.output_bytes()?)?;
+    }
+
+    if would_change && writes_in_place(args, config) {
+        run_on_change(config, &args.inputs[..1])?;
+    }
+
+    // Print summary in verbose mode for single file
+    if config.verbose {
+        print_stats_summary(
+            &result.stats,
+            1,
+            if would_change { 1 } else { 0 },
+            0,
+            console,
+            styles,
+        );
+    }
+
+    Ok(RunOutcome {
+        dry_run: config.reports_changes(),
+        list_different: config.list_different,
+        would_change,
+        missing_diagrams,
+    })
+}
+
+/// Output JSON for a single file result
+fn output_json_single(args: &Args, config: &Config, result: &FileResult) -> Result<()> {
+    print_json(
+        config,
+        &JsonDocument::File(JsonFile::Processed(Box::new(json_output(
+            args, config, result,
+        )))),
+    )?;
+
+    // If in-place mode with JSON, still write the file
+    if args.in_place {
+        if let Some(path) = args.inputs.first() {
+            let backup_ext = config.backup.then_some(config.backup_ext.as_str());
+            write_in_place(
+                path,
+                result.output_bytes()?,
+                result.snapshot.as_ref(),
+                backup_ext,
+                config,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a `--json` object, on one line with `--json-lines`
+fn print_json(config: &Config, value: &JsonDocument) -> Result<()> {
+    let json = if config.json_lines {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
+    };
+    println!("{}", json.context("Failed to serialize JSON output")?);
+    Ok(())
+}
+
+/// Build the `--json` object for one result
+fn json_output(args: &Args, config: &Config, result: &FileResult) -> JsonOutput {
+    let original_text = result.original_text();
+    let corrected_text = result.corrected_text();
+
+    JsonOutput {
+        version: JSON_FORMAT_VERSION,
+        status: if config.require_diagrams && result.stats.blocks_found == 0 {
+            "no_diagrams".to_string()
+        } else if config.dry_run {
+            "dry_run".to_string()
+        } else {
+            "success".to_string()
+        },
+        file: Some(result.filename.clone()),
+        input: InputStats {
+            lines: result.original.len(),
+            bytes: original_text.len(),
+            hash: result
+                .snapshot
+                .as_ref()
+                .map(|snapshot| format!("{:016x}", snapshot.hash)),
+        },
+        processing: ProcessingStats::from_stats(&result.stats),
+        output: Some(OutputStats {
+            lines: result.corrected.len(),
+            bytes: corrected_text.len(),
+            changed: result.would_change,
+        }),
+        content: if !config.dry_run && !args.in_place {
+            Some(corrected_text.clone())
+        } else {
+            None
+        },
+        blocks: result
+            .stats
+            .blocks
+            .iter()
+            .map(JsonBlock::from_report)
+            .collect(),
+        revisions: result.stats.revisions.clone(),
+        edits: json_edits(result),
+    }
+}
+
+/// Final line of `--check` output
+fn print_check_summary(changed: usize, total: usize) {
+    if changed == 0 {
+        eprintln!("All {} file(s) correct", total);
+    } else {
+        eprintln!("{} of {} file(s) would be corrected", changed, total);
+    }
+}
+
+/// Start `--output-format` output for `count` inputs (the TAP header
+/// and plan)
+fn output_report_header(config: &Config, count: usize) -> Result<()> {
+    if config.output_format == OutputFormat::Tap {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "TAP version 13")?;
+        writeln!(stdout, "1..{}", count)?;
+    }
+    Ok(())
+}
+
+/// Escape a TAP test description (`#` would start a directive)
+fn tap_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('#', "\\#")
+}
+
+/// Report a file's blocks needing changes in the `--output-format`;
+/// `number` is the file's 1-based position among the inputs
+fn output_report(config: &Config, result: &FileResult, number: usize) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    let blocks = result
+        .stats
+        .blocks
+        .iter()
+        .filter(|block| block.status == BlockStatus::Modified)
+        .map(|block| {
+            let applied = block.revisions.iter().filter(|r| r.applied).count();
+            (block.start + 1, block.end, applied)
+        })
+        .collect::<Vec<_>>();
+    match config.output_format {
+        OutputFormat::Content => {}
+        OutputFormat::Github if !result.would_change => {}
+        OutputFormat::Github if blocks.is_empty() => {
+            // Changes outside diagram blocks (tables, line endings)
+            writeln!(
+                stdout,
+                "::error file={},title=aadc::File needs correction",
+                github_escape_property(&result.filename)
+            )?;
+        }
+        OutputFormat::Github => {
+            for (start, end, applied) in blocks {
+                writeln!(
+                    stdout,
+                    "::error file={},line={},endLine={},title=aadc::{}",
+                    github_escape_property(&result.filename),
+                    start,
+                    end,
+                    github_escape_data(&format!(
+                        "Misaligned diagram block ({} revision(s))",
+                        applied
+                    ))
+                )?;
+            }
+        }
+        OutputFormat::Tap if !result.would_change => {
+            writeln!(stdout, "ok {} - {}", number, tap_escape(&result.filename))?;
+        }
+        OutputFormat::Tap => {
+            writeln!(
+                stdout,
+                "not ok {} - {}",
+                number,
+                tap_escape(&result.filename)
+            )?;
+            writeln!(stdout, "  ---")?;
+            let message = match blocks.len() {
+                0 => "File needs correction".to_string(),
+                n => format!("{} block(s) need correction", n),
+            };
+            writeln!(stdout, "  message: {}", serde_json::to_string(&message)?)?;
+            if !blocks.is_empty() {
+                writeln!(stdout, "  blocks:")?;
+                for (start, end, applied) in blocks {
+                    writeln!(stdout, "    - lines: {}-{}", start, end)?;
+                    writeln!(stdout, "      revisions: {}", applied)?;
+                }
+            }
+            writeln!(stdout, "  ...")?;
+        }
+        OutputFormat::Gnu if !result.would_change => {}
+        OutputFormat::Gnu if result.stats.revisions.is_empty() => {
+            writeln!(
+                stdout,
+                "{}:1:1: warning: file needs correction",
+                result.filename
+            )?;
+        }
+        OutputFormat::Gnu => {
+            for rev in result.stats.revisions.iter().filter(|r| r.applied) {
+                writeln!(
+                    stdout,
+                    "{}:{}:{}: warning: {}",
+                    result.filename, rev.line, rev.column, rev.problem
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Report an input that could not be processed in the `--output-format`
+fn output_report_error(
+    config: &Config,
+    path: &Path,
+    err: &anyhow::Error,
+    number: usize,
+) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    let message = format!("{:#}", err);
+    match config.output_format {
+        OutputFormat::Content => {}
+        OutputFormat::Github => {
+            writeln!(
+                stdout,
+                "::error file={},title=aadc::{}",
+                github_escape_property(&path.display().to_string()),
+                github_escape_data(&message)
+            )?;
+        }
+        OutputFormat::Tap => {
+            writeln!(
+                stdout,
+                "not ok {} - {}",
+                number,
+                tap_escape(&path.display().to_string())
+            )?;
+            writeln!(stdout, "  ---")?;
+            writeln!(stdout, "  message: {}", serde_json::to_string(&message)?)?;
+            writeln!(stdout, "  ...")?;
+        }
+        OutputFormat::Gnu => {
+            writeln!(stdout, "{}: error: {}", path.display(), message)?;
+        }
+    }
+    Ok(())
+}
+
+/// Print a path for --list-different, terminated by NUL with --null
+fn print_listed_path(path: &str, config: &Config) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(path.as_bytes())?;
+    stdout.write_all(if config.null { b"\0" } else { b"\n" })?;
+    Ok(())
+}
+
+/// Output dry-run info for a single file
+fn output_dry_run_single(
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+    result: &FileResult,
+) -> Result<()> {
+    if config.diff && result.would_change {
+        output_diff(result, true, config)?;
+    }
+
+    if config.verbose {
+        if result.would_change {
+            console.print(
+                &styles
+                    .block(format!("Would modify: {}", result.filename))
+                    .to_string(),
+            );
+            console.print(
+                &styles
+                    .dim(format!(
+                        "  {} block(s), {} revision(s), {} line ending(s)",
+                        result.stats.blocks_modified,
+                        result.stats.total_revisions,
+                        result.stats.eol_changes
+                    ))
+                    .to_string(),
+            );
+        } else {
+            console.print(
+                &styles
+                    .success(format!("No changes needed: {}", result.filename))
+                    .to_string(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether an input path is the "-" stdin placeholder
+fn is_stdin_path(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// Name stdin content goes by: `--stdin-filename`, else "stdin"
+fn stdin_label(config: &Config) -> String {
+    config
+        .stdin_filename
+        .as_ref()
+        .map_or_else(|| "stdin".to_string(), |p| p.display().to_string())
+}
+
+/// Read and correct one file of a multi-file run ("-" reads stdin). With
+/// a `cache`, files it records as clean are passed through uncorrected.
+fn process_path(
+    path: &Path,
+    config: &Config,
+    cache: Option<&ResultCache>,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<FileResult> {
+    let config = &config_for_path(path, config);
+    let config = &config_for_changes(path, config)?;
+    if is_stdin_path(path) {
+        let source = read_stdin_content(config)?;
+        return Ok(process_input(
+            source,
+            stdin_label(config),
+            config,
+            console,
+            styles,
+        ));
+    }
+    let (source, snapshot) = read_file_with_snapshot(path, config)?;
+    let filename = path.display().to_string();
+    let cache = cache.map(|cache| (cache, config_fingerprint(config)));
+    let hit = cache
+        .as_ref()
+        .and_then(|(cache, fingerprint)| cache.lookup(&filename, &snapshot, fingerprint));
+    let mut result = match hit {
+        Some(blocks_found) => {
+            if config.verbose {
+                console.print(&styles.dim(format!("{}: clean in cache, skipped", filename)));
+            }
+            FileResult::unchanged(source, filename, blocks_found, config)
+        }
+        None => {
+            let result = process_input(source, filename, config, console, styles);
+            if let Some((cache, fingerprint)) = cache {
+                cache.record(&result, &snapshot, fingerprint);
+            }
+            result
+        }
+    };
+    result.snapshot = Some(snapshot);
+    Ok(result)
+}
+
+/// Read and correct `paths` on `jobs` worker threads, passing each result
+/// to `handle` in input order as soon as it and all earlier ones are ready.
+///
+/// `handle` runs on the calling thread, so output, in-place writes and
+/// stats aggregation stay sequential and deterministic. Verbose runs use a
+/// single job so per-file logs do not interleave.
+fn for_each_processed(
+    paths: &[PathBuf],
+    jobs: usize,
+    config: &Config,
+    cache: Option<&ResultCache>,
+    console: &Console,
+    styles: &VerboseStyle,
+    mut handle: impl FnMut(&PathBuf, Result<FileResult>) -> Result<()>,
+) -> Result<()> {
+    let jobs = if config.verbose {
+        1
+    } else {
+        jobs.min(paths.len())
+    };
+    if jobs <= 1 {
+        for path in paths {
+            handle(path, process_path(path, config, cache, console, styles))?;
+        }
+        return Ok(());
+    }
+
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            let tx = tx.clone();
+            let next = &next;
+            scope.spawn(move || {
+                let console = Console::new();
+                let styles = VerboseStyle::new(false);
+                loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(idx) else {
+                        break;
+                    };
+                    let result = process_path(path, config, cache, &console, &styles);
+                    if tx.send((idx, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        // Results arrive in completion order; hold them until every
+        // earlier path has been handled
+        let mut pending = BTreeMap::new();
+        let mut handled = 0;
+        for (idx, result) in rx {
+            pending.insert(idx, result);
+            while let Some(result) = pending.remove(&handled) {
+                handle(&paths[handled], result)?;
+                handled += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Handle output for multiple files
+fn output_multiple_results(
+    args: &Args,
+    config: &Config,
+    cache: Option<&ResultCache>,
+    console: &Console,
+    styles: &VerboseStyle,
+    paths: &[PathBuf],
+) -> Result<RunOutcome> {
+    let mut total_files_processed = 0;
+    let mut total_files_changed = 0;
+    let mut aggregated_stats = Stats::default();
+    let mut any_would_change = false;
+    let mut any_missing_diagrams = false;
+    let mut changed_paths = Vec::new();
+    let mut errors: Vec<(PathBuf, anyhow::Error)> = Vec::new();
+    let mut report_cases = Vec::new();
+    let mut report_number = 0;
+    let mut json_files = Vec::new();
+
+    let show_file_headers =
+        !args.in_place && !config.diff && !config.json && !config.list_different && paths.len() > 1;
+    output_report_header(config, paths.len())?;
+
+    for_each_processed(
+        paths,
+        config.jobs,
+        config,
+        cache,
+        console,
+        styles,
+        |path, processed| {
+            report_number += 1;
+            match processed {
+                Ok(result) => {
+                    if result.would_change {
+                        any_would_change = true;
+                        total_files_changed += 1;
+                        if writes_in_place(args, config) {
+                            changed_paths.push(path.clone());
+                        }
+                    }
+                    total_files_processed += 1;
+                    aggregated_stats.merge(&result.stats);
+                    any_missing_diagrams |= lacks_required_diagrams(&result, config);
+                    if !config.reports.is_empty() {
+                        report_cases.push(ReportCase::of(&result, config)?);
+                    }
+
+                    // Handle output based on mode
+                    if config.json {
+                        // Printed together once every file is done, unless
+                        // each gets its own line
+                        let entry =
+                            JsonFile::Processed(Box::new(json_output(args, config, &result)));
+                        if config.json_lines {
+                            print_json(config, &JsonDocument::File(entry))?;
+                        } else {
+                            json_files.push(entry);
+                        }
+                        if args.in_place {
+                            let backup_ext = config.backup.then_some(config.backup_ext.as_str());
+                            write_in_place(
+                                path,
+                                result.output_bytes()?,
+                                result.snapshot.as_ref(),
+                                backup_ext,
+                                config,
+                            )?;
+                        }
+                    } else if config.list_different {
+                        if result.would_change {
+                            print_listed_path(&result.filename, config)?;
+                        }
+                    } else if config.check {
+                        if result.would_change {
+                            eprintln!("Would correct: {}", result.filename);
+                        }
+                    } else if config.output_format != OutputFormat::Content {
+                        output_report(config, &result, report_number)?;
+                    } else if config.dry_run {
+                        output_dry_run_single(config, console, styles, &result)?;
+                    } else if config.diff {
+                        output_diff(&result, false, config)?;
+                    } else if args.in_place {
+                        // Write file in-place
+                        let backup_ext = config.backup.then_some(config.backup_ext.as_str());
+                        let backup = write_in_place(
+                            path,
+                            result.output_bytes()?,
+                            result.snapshot.as_ref(),
+                            backup_ext,
+                            config,
+                        )?;
+                        if let (Some(backup_path), true) = (backup, config.verbose) {
+                            console.print(
+                                &styles
+                                    .dim(format!("Created backup: {}", backup_path.display()))
+                                    .to_string(),
+                            );
+                        }
+
+                        if config.verbose {
+                            if result.would_change {
+                                console.print(
+                                    &styles
+                                        .success(format!(
+                                            "{}: {} block(s), {} revision(s) applied",
+                                            path.display(),
+                                            result.stats.blocks_modified,
+                                            result.stats.total_revisions
+                                        ))
+                                        .to_string(),
+                                );
+                            } else {
+                                console.print(
+                                    &styles.dim(format!("{}: No changes needed", path.display())),
+                                );
+                            }
+                        }
+                    } else {
+                        // Stdout mode - concatenate output with file headers
+                        let mut stdout = io::stdout().lock();
+
+                        if show_file_headers {
+                            writeln!(stdout, "==> {} <==", result.filename)?;
+                        }
+
+                        stdout.write_all(&result.output_bytes()?)?;
+
+                        if show_file_headers {
+                            writeln!(stdout)?; // Blank line between files
+                        }
+                    }
+                }
+                Err(e) => {
+                    report_error(&e, Some(path), config.json_errors());
+                    output_report_error(config, path, &e, report_number)?;
+                    if config.json {
+                        let entry = JsonFile::Failed(JsonFailure::of(path, &e));
+                        if config.json_lines {
+                            print_json(config, &JsonDocument::File(entry))?;
+                        } else {
+                            json_files.push(entry);
+                        }
+                    }
+                    if !config.reports.is_empty() {
+                        report_cases.push(ReportCase::error(path, &e));
+                    }
+                    errors.push((path.clone(), e));
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    if config.json && !config.json_lines {
+        print_json(
+            config,
+            &JsonDocument::Run(JsonRunOutput {
+                version: JSON_FORMAT_VERSION,
+                files: json_files,
+                summary: JsonSummary {
+                    files_processed: total_files_processed,
+                    files_changed: total_files_changed,
+                    files_failed: errors.len(),
+                    processing: ProcessingStats::from_stats(&aggregated_stats),
+                },
+            }),
+        )?;
+    }
+
+    if !config.reports.is_empty() {
+        write_reports(config, &report_cases)?;
+    }
+
+    run_on_change(config, &changed_paths)?;
+
+    if config.check {
+        print_check_summary(total_files_changed, total_files_processed);
+    }
+
+    // Print summary in verbose mode
+    if config.verbose {
+        print_stats_summary(
+            &aggregated_stats,
+            total_files_processed,
+            total_files_changed,
+            errors.len(),
+            console,
+            styles,
+        );
+    }
+
+    // If any files had errors, report them
+    if !errors.is_empty() {
+        let files = errors
+            .iter()
+            .map(|(p, _)| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let has_parse_error = errors
+            .iter()
+            .any(|(_, err)| error_chain_has::<ParseError>(err));
+
+        if has_parse_error {
+            return Err(ParseError(format!(
+                "{} file(s) had parse errors: {}",
+                errors.len(),
+                files
+            ))
+            .into());
+        }
+
+        anyhow::bail!("{} file(s) had errors: {}", errors.len(), files);
+    }
+
+    Ok(RunOutcome {
+        dry_run: config.reports_changes(),
+        list_different: config.list_different,
+        would_change: any_would_change,
+        missing_diagrams: any_missing_diagrams,
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Reports
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// File format of a `--report`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    /// JUnit XML: a test case per file, failed when it would change
+    Junit,
+}
+
+/// A report file requested with `--report FORMAT=PATH`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReportTarget {
+    format: ReportFormat,
+    path: PathBuf,
+}
+
+/// Parse a `--report` value such as `junit=aadc.xml`
+fn parse_report(s: &str) -> Result<ReportTarget, String> {
+    let (format, path) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FORMAT=PATH, got '{}'", s))?;
+    let format = match format {
+        "junit" => ReportFormat::Junit,
+        other => return Err(format!("unknown format '{}' (expected junit)", other)),
+    };
+    if path.is_empty() {
+        return Err("missing report path".to_string());
+    }
+    Ok(ReportTarget {
+        format,
+        path: PathBuf::from(path),
+    })
+}
+
+/// The outcome for one input, as reported
+#[derive(Debug)]
+struct ReportCase {
+    name: String,
+    elapsed: Duration,
+    /// Failure message and diff, when the file would change
+    failure: Option<(String, String)>,
+    /// Error message, when the file could not be processed
+    error: Option<String>,
+}
+
+impl ReportCase {
+    fn of(result: &FileResult, config: &Config) -> Result<Self> {
+        let failure = if result.would_change {
+            let message = match result.stats.blocks_modified {
+                0 => "File needs correction".to_string(),
+                n => format!("{} block(s) need correction", n),
+            };
+            let mut diff = Vec::new();
+            write_result_diff(&mut diff, result, false, config, false)?;
+            Some((message, String::from_utf8_lossy(&diff).into_owned()))
+        } else {
+            None
+        };
+        Ok(Self {
+            name: result.filename.clone(),
+            elapsed: result.stats.elapsed,
+            failure,
+            error: None,
+        })
+    }
+
+    fn error(path: &Path, err: &anyhow::Error) -> Self {
+        Self {
+            name: path.display().to_string(),
+            elapsed: Duration::ZERO,
+            failure: None,
+            error: Some(format!("{:#}", err)),
+        }
+    }
+}
+
+/// Write every `--report` file for the run's cases
+fn write_reports(config: &Config, cases: &[ReportCase]) -> Result<()> {
+    for target in &config.reports {
+        let content = match target.format {
+            ReportFormat::Junit => junit_report(cases),
+        };
+        fs::write(&target.path, content)
+            .with_context(|| format!("Failed to write report: {}", target.path.display()))?;
+    }
+    Ok(())
+}
+
+/// Escape text for an XML attribute or element, dropping characters XML
+/// 1.0 does not allow (such as form feeds)
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' || c == '\u{fffe}' || c == '\u{ffff}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// JUnit XML with one test case per input
+fn junit_report(cases: &[ReportCase]) -> String {
+    let failures = cases.iter().filter(|c| c.failure.is_some()).count();
+    let errors = cases.iter().filter(|c| c.error.is_some()).count();
+    let time: f64 = cases.iter().map(|c| c.elapsed.as_secs_f64()).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"aadc\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        cases.len(),
+        failures,
+        errors,
+        time
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"aadc\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+        cases.len(),
+        failures,
+        errors,
+        time
+    ));
+    for case in cases {
+        xml.push_str(&format!(
+            "    <testcase classname=\"aadc\" name=\"{}\" time=\"{:.3}\"",
+            xml_escape(&case.name),
+            case.elapsed.as_secs_f64()
+        ));
+        if let Some((message, diff)) = &case.failure {
+            xml.push_str(&format!(
+                ">\n      <failure type=\"misaligned\" message=\"{}\">{}</failure>\n    </testcase>\n",
+                xml_escape(message),
+                xml_escape(diff)
+            ));
+        } else if let Some(message) = &case.error {
+            xml.push_str(&format!(
+                ">\n      <error message=\"{}\"/>\n    </testcase>\n",
+                xml_escape(message)
+            ));
+        } else {
+            xml.push_str("/>\n");
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Streaming
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// Lines buffered before a chunk is corrected even without a safe cut
+/// point, so memory stays bounded on input that never pauses between
+/// diagrams (a diagram straddling such a cut is corrected in two parts)
+const STREAM_MAX_LINES: usize = 10_000;
+
+/// Lines read in `--stream` mode and not yet corrected
+#[derive(Debug, Default)]
+struct StreamChunk {
+    lines: Vec<String>,
+    endings: Vec<Option<Eol>>,
+    /// Trailing lines that are not boxy
+    quiet: usize,
+    /// Inside an `aadc:off` region
+    disabled: bool,
+}
+
+impl StreamChunk {
+    fn push(&mut self, line: String, ending: Option<Eol>, opts: &DetectOptions) {
+        match parse_directive(&line) {
+            Some(("off", _)) => self.disabled = true,
+            Some(("on", _)) => self.disabled = false,
+            _ => {}
+        }
+        if classify_line_with(&line, opts).is_boxy() {
+            self.quiet = 0;
+        } else {
+            self.quiet += 1;
+        }
+        self.lines.push(line);
+        self.endings.push(ending);
+    }
+
+    /// Whether the lines so far can be corrected without the ones still
+    /// to come: no block can extend past `window` non-boxy lines, and a
+    /// plain text line ends the directive lookback of the next block
+    fn at_safe_cut(&self, window: usize) -> bool {
+        let Some(last) = self.lines.last() else {
+            return false;
+        };
+        let plain = !last.trim().is_empty() && parse_directive(last).is_none();
+        (self.quiet >= window && plain && !self.disabled) || self.lines.len() >= STREAM_MAX_LINES
+    }
+}
+
+/// Handle `--stream`: correct stdin or the single input file to stdout
+fn run_stream(
+    args: &Args,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<RunOutcome> {
+    let path = args.inputs.first().map_or(Path::new("-"), PathBuf::as_path);
+    let config = &config_for_path(path, config);
+    let config = &config_for_changes(path, config)?;
+    let (input, label): (Box<dyn io::BufRead>, String) = if is_stdin_path(path) {
+        (Box::new(io::stdin().lock()), stdin_label(config))
+    } else {
+        let file = fs::File::open(path)
+            .with_context(|| format!("Failed to open input file: {}", path.display()))?;
+        (
+            Box::new(io::BufReader::new(file)),
+            path.display().to_string(),
+        )
+    };
+
+    let mut stdout = io::stdout().lock();
+    let (stats, would_change) =
+        stream_correct(input, &mut stdout, &label, config, console, styles)?;
+    stdout.flush()?;
+
+    let missing_diagrams = config.require_diagrams && stats.blocks_found == 0;
+    if missing_diagrams {
+        eprintln!("No diagrams found: {}", label);
+    }
+    Ok(RunOutcome {
+        dry_run: false,
+        list_different: false,
+        would_change,
+        missing_diagrams,
+    })
+}
+
+/// Correct `input` chunk by chunk, writing each chunk to `output` as soon
+/// as no later line can change it. Returns the merged stats and whether
+/// anything changed.
+///
+/// Chunks are cut after enough non-boxy lines that no block can span the
+/// cut, so the output matches a whole-file run except that the quick scan
+/// and line numbers in verbose output apply per chunk, and modelines and
+/// embedded-diagram detection are not used. Line ranges are shifted to
+/// each chunk, so they still select lines of the whole input.
+fn stream_correct(
+    mut input: impl io::BufRead,
+    output: &mut impl Write,
+    label: &str,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+) -> Result<(Stats, bool)> {
+    let detect = config.detect_options();
+    let window = detect.lookahead.max(detect.blank_gap + 1);
+    let mut chunk = StreamChunk::default();
+    let mut stats = Stats::default();
+    let mut would_change = false;
+    let mut line_number = 0;
+
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = input
+            .read_until(b'\n', &mut buf)
+            .with_context(|| format!("Failed to read {}", label))?;
+        if read == 0 {
+            break;
+        }
+        line_number += 1;
+        if line_number == 1 && buf.starts_with(UTF8_BOM.as_bytes()) {
+            output.write_all(UTF8_BOM.as_bytes())?;
+            buf.drain(..UTF8_BOM.len());
+        }
+        let ending = if buf.ends_with(b"\r\n") {
+            buf.truncate(buf.len() - 2);
+            Some(Eol::Crlf)
+        } else if buf.ends_with(b"\n") {
+            buf.pop();
+            Some(Eol::Lf)
+        } else {
+            None
+        };
+        if buf.contains(&0) {
+            return Err(ParseError(format!("Input appears to be binary: {}", label)).into());
+        }
+        let line = String::from_utf8(std::mem::take(&mut buf)).map_err(|_| {
+            ParseError(format!(
+                "Invalid UTF-8 on line {} of {}",
+                line_number, label
+            ))
+        })?;
+
+        chunk.push(line, ending, &detect);
+        if chunk.at_safe_cut(window) {
+            let offset = line_number - chunk.lines.len();
+            would_change |= flush_chunk(
+                &mut chunk, offset, output, config, console, styles, &mut stats,
+            )?;
+        }
+    }
+    if !chunk.lines.is_empty() {
+        let offset = line_number - chunk.lines.len();
+        would_change |= flush_chunk(
+            &mut chunk, offset, output, config, console, styles, &mut stats,
+        )?;
+    }
+    Ok((stats, would_change))
+}
+
+/// Correct and write the buffered lines, emptying the chunk. `offset` is
+/// the number of input lines before the chunk. Returns whether the output
+/// differs from the input.
+fn flush_chunk(
+    chunk: &mut StreamChunk,
+    offset: usize,
+    output: &mut impl Write,
+    config: &Config,
+    console: &Console,
+    styles: &VerboseStyle,
+    stats: &mut Stats,
+) -> Result<bool> {
+    let lines = std::mem::take(&mut chunk.lines);
+    let endings = std::mem::take(&mut chunk.endings);
+    chunk.quiet = 0;
+
+    let chunk_config;
+    let config = match config.lines {
+        Some(ref ranges) if offset > 0 => {
+            chunk_config = Config {
+                lines: Some(shift_line_ranges(ranges, offset)),
+                ..config.clone()
+            };
+            &chunk_config
+        }
+        _ => config,
+    };
+
+    let (corrected, chunk_stats) = correct_lines(lines.clone(), config, console, styles);
+    stats.merge(&chunk_stats);
+
+    let eol = config
+        .normalize_eol
+        .unwrap_or_else(|| Eol::dominant(&endings));
+    let eol_changes = config.normalize_eol.is_some()
+        && endings
+            .iter()
+            .any(|ending| ending.is_some_and(|e| e != eol));
+    let corrected_endings = output_endings(&lines, &endings, &corrected, eol, eol_changes);
+    output.write_all(join_with_endings(&corrected, &corrected_endings).as_bytes())?;
+    output.flush()?;
+    Ok(corrected != lines || eol_changes)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Language Server
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// JSON-RPC error code for a method the server does not implement
+const LSP_METHOD_NOT_FOUND: i64 = -32601;
+/// LSP error code for a request that failed
+const LSP_REQUEST_FAILED: i64 = -32803;
+
+/// An `aadc lsp` session: the text of each open document, by URI
+struct LspServer {
+    config: Config,
+    documents: BTreeMap<String, String>,
+    shutdown: bool,
+}
+
+impl LspServer {
+    fn new(config: &Config) -> Self {
+        Self {
+            config: Config {
+                verbose: false,
+                lines: None,
+                diff_only: None,
+                normalize_eol: None,
+                ..config.clone()
+            },
+            documents: BTreeMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Handle one message from the client, returning the messages to send
+    /// back (a response for requests, diagnostics for document changes)
+    fn handle(&mut self, message: &serde_json::Value) -> Vec<serde_json::Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let result = match method {
+            "initialize" => Ok(serde_json::json!({
+                "capabilities": {
+                    "textDocumentSync": { "openClose": true, "change": 1 },
+                    "documentFormattingProvider": true,
+                    "documentRangeFormattingProvider": true,
+                },
+                "serverInfo": { "name": "aadc", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(serde_json::Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()?["text"].as_str()) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                return vec![self.diagnostics(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![lsp_publish_diagnostics(&uri, Vec::new())];
+            }
+            "textDocument/formatting" => self.format(&uri, None),
+            "textDocument/rangeFormatting" => self.format(&uri, Some(&params["range"])),
+            _ => Err((LSP_METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+        };
+
+        // Notifications (no ID) get no response
+        let Some(id) = message.get("id") else {
+            return Vec::new();
+        };
+        vec![match result {
+            Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        }]
+    }
+
+    /// TextEdits correcting a document, or only the blocks overlapping
+    /// `range`
+    fn format(
+        &self,
+        uri: &str,
+        range: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value, (i64, String)> {
+        let text = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (LSP_REQUEST_FAILED, format!("Document is not open: {}", uri)))?;
+        let path = lsp_uri_to_path(uri);
+        let mut config = config_for_path(&path, &self.config).into_owned();
+        if let Some(range) = range {
+            let line = |pos: &str| range[pos]["line"].as_u64().unwrap_or_default() as usize + 1;
+            config.lines = Some(vec![LineRange {
+                start: line("start"),
+                end: line("end"),
+            }]);
+        }
+
+        let result = correct_text(text, &path.display().to_string(), &config)
+            .map_err(|err| (LSP_REQUEST_FAILED, format!("{:#}", err)))?;
+        Ok(serde_json::Value::Array(lsp_text_edits(
+            &result.original,
+            &result.corrected,
+            result.eol,
+        )))
+    }
+
+    /// `publishDiagnostics` notification with the lint problems of an open
+    /// document
+    fn diagnostics(&self, uri: &str) -> serde_json::Value {
+        let lines: Vec<String> = self
+            .documents
+            .get(uri)
+            .map(|text| text.lines().map(String::from).collect())
+            .unwrap_or_default();
+        let config = config_for_path(&lsp_uri_to_path(uri), &self.config);
+        let diagnostics = lint_lines(&lines, &config)
+            .into_iter()
+            .map(|d| {
+                // Lint columns are visual columns of the tab-expanded line
+                let line = expand_tabs(&lines[d.line - 1], config.tab_width);
+                let start = grapheme_columns(&line)
+                    .find(|&(_, column, _)| column + 1 >= d.column)
+                    .map_or(line.len(), |(idx, _, _)| idx);
+                let character = line[..start].encode_utf16().count();
+                let position = serde_json::json!({ "line": d.line - 1, "character": character });
+                serde_json::json!({
+                    "range": { "start": position, "end": position },
+                    "severity": if d.severity == Severity::Error { 1 } else { 2 },
+                    "code": d.rule,
+                    "source": "aadc",
+                    "message": format!("{}: {}", d.name, d.message),
+                })
+            })
+            .collect();
+        lsp_publish_diagnostics(uri, diagnostics)
+    }
+}
+
+fn lsp_publish_diagnostics(uri: &str, diagnostics: Vec<serde_json::Value>) -> serde_json::Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// Local path of a `file://` URI (percent-decoded), used to find the
+/// document's `.editorconfig` and embedded syntax
+fn lsp_uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let decoded = (b == b'%')
+            .then(|| tail.get(..2))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match decoded {
+            Some(byte) => {
+                bytes.push(byte);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// LSP TextEdits turning `original` into `corrected`: the smallest change
+/// on each line where lines are edited in place, whole lines where lines
+/// are added or removed
+fn lsp_text_edits(original: &[String], corrected: &[String], eol: Eol) -> Vec<serde_json::Value> {
+    let position =
+        |line: usize, character: usize| serde_json::json!({ "line": line, "character": character });
+    let mut edits = Vec::new();
+    for op in similar::capture_diff_slices(similar::Algorithm::Myers, original, corrected) {
+        let (old, new) = (op.old_range(), op.new_range());
+        match op.tag() {
+            DiffTag::Equal => {}
+            DiffTag::Replace if old.len() == new.len() => {
+                for (i, j) in old.zip(new) {
+                    let line = &original[i];
+                    let Some(edit) = LineEdit::between(i, line, &corrected[j]) else {
+                        continue;
+                    };
+                    // LSP positions count UTF-16 code units
+                    let utf16 = |chars: usize| line.chars().take(chars).map(char::len_utf16).sum();
+                    edits.push(serde_json::json!({
+                        "range": {
+                            "start": position(i, utf16(edit.column - 1)),
+                            "end": position(i, utf16(edit.column - 1 + edit.delete)),
+                        },
+                        "newText": edit.insert_text,
+                    }));
+                }
+            }
+            _ => {
+                let text: String = corrected[new]
+                    .iter()
+                    .flat_map(|line| [line.as_str(), eol.as_str()])
+                    .collect();
+                edits.push(serde_json::json!({
+                    "range": { "start": position(old.start, 0), "end": position(old.end, 0) },
+                    "newText": text,
+                }));
+            }
+        }
+    }
+    edits
+}
+
+/// Read one LSP message (a `Content-Length` header block, then a JSON
+/// body); None at end of input
+fn read_lsp_message(input: &mut impl io::BufRead) -> Result<Option<serde_json::Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| {
+                    ParseError(format!("Invalid LSP Content-Length: {}", value.trim()))
+                })?);
+            }
+        }
+    }
+    let length =
+        length.ok_or_else(|| ParseError("LSP message without Content-Length".to_string()))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let message = serde_json::from_slice(&body)
+        .map_err(|err| ParseError(format!("Invalid LSP message: {}", err)))?;
+    Ok(Some(message))
+}
+
+fn write_lsp_message(output: &mut impl Write, message: &serde_json::Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Serve LSP over stdin/stdout until the client sends `exit`. Exits 0
+/// after a `shutdown` request, 1 otherwise, as the protocol asks.
+fn run_lsp(config: &Config) -> Result<i32> {
+    let mut server = LspServer::new(config);
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    while let Some(message) = read_lsp_message(&mut input)? {
+        if message["method"] == "exit" {
+            break;
+        }
+        for reply in server.handle(&message) {
+            write_lsp_message(&mut output, &reply)?;
+        }
+    }
+    Ok(if server.shutdown {
+        exit_codes::SUCCESS
+    } else {
+        exit_codes::ERROR
+    })
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// MCP Server
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// MCP protocol revision answered when the client asks for none
+const MCP_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Correct `text` as if read from a file named `label`
+fn correct_text(text: &str, label: &str, config: &Config) -> Result<FileResult> {
+    let source = parse_bytes_to_lines(text.as_bytes().to_vec(), label)?;
+    let (console, styles) = build_console(ColorMode::Never, None);
+    Ok(process_input(
+        source,
+        label.to_string(),
+        config,
+        &console,
+        &styles,
+    ))
+}
+
+/// Alignment of one diagram block, as reported by the MCP `score` tool
+#[derive(Serialize)]
+struct BlockScore {
+    /// 1-based inclusive line range
+    start_line: usize,
+    end_line: usize,
+    /// Lines that at least one revision would change
+    misaligned_lines: usize,
+    /// Share of the block's lines already aligned (0.0-1.0)
+    score: f64,
+}
+
+impl BlockScore {
+    fn of(report: &BlockReport) -> Self {
+        let lines = report.end - report.start;
+        let misaligned = report
+            .revisions
+            .iter()
+            .map(|rev| rev.line)
+            .chain(report.skipped.iter().map(|rev| rev.line))
+            .collect::<BTreeSet<_>>()
+            .len();
+        Self {
+            start_line: report.start + 1,
+            end_line: report.end,
+            misaligned_lines: misaligned,
+            score: 1.0 - misaligned as f64 / lines.max(1) as f64,
+        }
+    }
+}
+
+/// Tools offered by `aadc mcp`, as listed by `tools/list`
+fn mcp_tools() -> serde_json::Value {
+    let text_input = |description: &str| {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": { "type": "string", "description": description },
+            },
+            "required": ["text"],
+        })
+    };
+    serde_json::json!([
+        {
+            "name": "fix_diagram",
+            "description": "Align the right borders, separators and tree branches of the ASCII/Unicode diagrams in a text. Returns the corrected text; other text is left as is.",
+            "inputSchema": text_input("Text or Markdown containing diagrams"),
+        },
+        {
+            "name": "detect_blocks",
+            "description": "List the diagram blocks aadc detects in a text, with line ranges, detection confidence, kind and border style.",
+            "inputSchema": text_input("Text or Markdown to scan"),
+        },
+        {
+            "name": "score",
+            "description": "Score how well aligned the diagrams in a text are: per block and overall, the share of diagram lines that need no correction (1.0 is fully aligned).",
+            "inputSchema": text_input("Text or Markdown containing diagrams"),
+        },
+    ])
+}
+
+/// Run an MCP tool on `arguments`, returning its text and structured
+/// results
+fn mcp_call_tool(
+    name: &str,
+    arguments: &serde_json::Value,
+    config: &Config,
+) -> Result<(String, serde_json::Value)> {
+    let text = arguments["text"]
+        .as_str()
+        .ok_or_else(|| ArgError("Missing string argument: text".to_string()))?;
+    let result = correct_text(text, "text", config)?;
+    match name {
+        "fix_diagram" => {
+            let corrected = result.output_text();
+            let structured = serde_json::json!({
+                "text": corrected,
+                "changed": result.would_change,
+                "blocks": result.stats.blocks_found,
+                "revisions": result.stats.total_revisions,
+            });
+            Ok((corrected, structured))
+        }
+        "detect_blocks" => {
+            let blocks: Vec<JsonBlock> = result
+                .stats
+                .blocks
+                .iter()
+                .map(JsonBlock::from_report)
+                .collect();
+            let structured = serde_json::json!({ "blocks": blocks });
+            Ok((serde_json::to_string_pretty(&structured)?, structured))
+        }
+        "score" => {
+            let blocks: Vec<BlockScore> = result.stats.blocks.iter().map(BlockScore::of).collect();
+            let lines: usize = result.stats.blocks.iter().map(|b| b.end - b.start).sum();
+            let misaligned: usize = blocks.iter().map(|b| b.misaligned_lines).sum();
+            let structured = serde_json::json!({
+                "score": 1.0 - misaligned as f64 / lines.max(1) as f64,
+                "blocks": blocks,
+            });
+            Ok((serde_json::to_string_pretty(&structured)?, structured))
+        }
+        _ => Err(ArgError(format!("Unknown tool: {}", name)).into()),
+    }
+}
+
+/// Handle one MCP message, returning the response for requests
+fn mcp_handle(message: &serde_json::Value, config: &Config) -> Option<serde_json::Value> {
+    let id = message.get("id")?;
+    let params = &message["params"];
+    let result = match message["method"].as_str().unwrap_or_default() {
+        "initialize" => serde_json::json!({
+            "protocolVersion": params["protocolVersion"]
+                .as_str()
+                .unwrap_or(MCP_PROTOCOL_VERSION),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "aadc", "version": env!("CARGO_PKG_VERSION") },
+        }),
+        "ping" => serde_json::json!({}),
+        "tools/list" => serde_json::json!({ "tools": mcp_tools() }),
+        "tools/call" => {
+            let name = params["name"].as_str().unwrap_or_default();
+            // Tool failures are results the model can see, not protocol
+            // errors
+            match mcp_call_tool(name, &params["arguments"], config) {
+                Ok((text, structured)) => serde_json::json!({
+                    "content": [{ "type": "text", "text": text }],
+                    "structuredContent": structured,
+                }),
+                Err(err) => serde_json::json!({
+                    "content": [{ "type": "text", "text": format!("{:#}", err) }],
+                    "isError": true,
+                }),
+            }
+        }
+        method => {
+            return Some(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": LSP_METHOD_NOT_FOUND,
+                    "message": format!("Unknown method: {}", method),
+                },
+            }));
+        }
+    };
+    Some(serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// Serve MCP over stdio (one JSON-RPC message per line) until stdin closes
+fn run_mcp(config: &Config) -> Result<()> {
+    let config = Config {
+        verbose: false,
+        lines: None,
+        diff_only: None,
+        ..config.clone()
+    };
+    let mut output = io::stdout().lock();
+    for line in io::BufRead::lines(io::stdin().lock()) {
+        let line = line.context("Failed to read MCP message")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(message) => mcp_handle(&message, &config),
+            Err(err) => Some(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": -32700, "message": format!("Parse error: {}", err) },
+            })),
+        };
+        if let Some(reply) = reply {
+            writeln!(output, "{}", serde_json::to_string(&reply)?)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// WebAssembly
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// JavaScript bindings, built with `cargo rustc --lib --crate-type cdylib
+/// --target wasm32-unknown-unknown --features wasm`
+#[cfg(feature = "wasm")]
+pub mod wasm {
+    use super::{Config, FileConfig, ProcessingStats, correct_text};
+    use anyhow::Context;
+    use wasm_bindgen::prelude::*;
+
+    /// Correct the diagrams in `text`. `options` holds `.aadcrc` settings
+    /// (such as `{ preset: "strict", tab_width: 8 }`), or is `undefined` for
+    /// the defaults. Returns `{ text, stats }`, with `stats` shaped like
+    /// `processing` in `--json` output.
+    #[wasm_bindgen]
+    pub fn correct(text: &str, options: JsValue) -> Result<JsValue, JsError> {
+        let options = if options.is_undefined() || options.is_null() {
+            "{}".to_string()
+        } else {
+            js_sys::JSON::stringify(&options)
+                .map_err(|_| JsError::new("options must be a plain object"))?
+                .into()
+        };
+        let corrected =
+            correct_json(text, &options).map_err(|e| JsError::new(&format!("{:#}", e)))?;
+        js_sys::JSON::parse(&corrected).map_err(|_| JsError::new("Failed to build result"))
+    }
+
+    /// [`correct`] with `options` and the result as JSON text
+    pub(crate) fn correct_json(text: &str, options: &str) -> anyhow::Result<String> {
+        let file_config: FileConfig = serde_json::from_str(options).context("Invalid options")?;
+        let config = Config::from_file_config(file_config)?;
+        let result = correct_text(text, "<input>", &config)?;
+        let output = serde_json::json!({
+            "text": result.output_text(),
+            "stats": ProcessingStats::from_stats(&result.stats),
+        });
+        Ok(output.to_string())
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tests
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // ─────────────────────────────────────────────────────────────────────
+    // File-config validation
+    // ─────────────────────────────────────────────────────────────────────
+
+    /// Helper: write `content` to a fresh tempfile and return the path
+    /// guard. Using `fs::write` instead of the `Write` trait sidesteps a
+    /// `clippy::unused_imports` false positive that complained about the
+    /// macro-only `use std::io::Write as _;` on Rust 2024.
+    fn write_temp_config(content: &str) -> tempfile::NamedTempFile {
+        let tmp = tempfile::NamedTempFile::new().expect("tempfile");
+        std::fs::write(tmp.path(), content).expect("write");
+        tmp
+    }
+
+    /// Regression: a config file with `tab_width = 0` used to slip past
+    /// the CLI gate (which only runs on `Args`) and land in
+    /// `expand_tabs`, where the modular-arithmetic
+    /// `tab_width - (col % tab_width)` panicked with divide-by-zero.
+    /// The error path now returns a clean parse error instead.
+    #[test]
+    fn config_file_rejects_zero_tab_width() {
+        let tmp = write_temp_config("tab_width = 0\n");
+        let err = load_config_file(tmp.path()).expect_err("zero tab_width must be rejected");
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("tab_width must be between 1 and 16"),
+            "expected tab_width validation error, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn config_file_rejects_oversized_tab_width() {
+        let tmp = write_temp_config("tab_width = 99\n");
+        let err = load_config_file(tmp.path()).expect_err("oversized tab_width must be rejected");
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("tab_width must be between 1 and 16"),
+            "expected tab_width validation error, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn config_file_rejects_zero_max_iters() {
+        let tmp = write_temp_config("max_iters = 0\n");
+        let err = load_config_file(tmp.path()).expect_err("max_iters=0 must be rejected");
+        let msg = format!("{err:#}");
+        assert!(
+            msg.contains("max_iters must be at least 1"),
+            "expected max_iters validation error, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn config_file_rejects_min_score_out_of_range() {
+        for bad in ["-0.1", "1.5", "100.0"] {
+            let tmp = write_temp_config(&format!("min_score = {bad}\n"));
+            let err =
+                load_config_file(tmp.path()).expect_err("out-of-range min_score must be rejected");
+            let msg = format!("{err:#}");
+            assert!(
+                msg.contains("min_score must be between 0.0 and 1.0"),
+                "expected min_score validation error for {bad}, got: {msg}"
+            );
+        }
+    }
+
+    #[test]
+    fn config_file_accepts_valid_values() {
+        // Sanity check: valid values round-trip cleanly.
+        let tmp = write_temp_config("tab_width = 8\nmax_iters = 25\nmin_score = 0.65\n");
+        let cfg = load_config_file(tmp.path()).expect("valid config must parse");
+        assert_eq!(cfg.tab_width, Some(8));
+        assert_eq!(cfg.max_iters, Some(25));
+        assert_eq!(cfg.min_score, Some(0.65));
+    }
+
+    /// Mutex to serialize tests that change the current working directory.
+    /// These tests cannot run in parallel because std::env::set_current_dir
+    /// affects global process state.
+    static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+    /// Acquire CWD_LOCK, recovering from poisoned state if a previous test panicked.
+    /// This prevents cascading test failures when one test holding the lock panics.
+    fn acquire_cwd_lock() -> std::sync::MutexGuard<'static, ()> {
+        CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// RAII guard for safely saving and restoring the working directory in tests.
+    /// On macOS CI (GitHub Actions), the original working directory may not be
+    /// accessible (deleted or permission issues), causing `std::env::current_dir()`
+    /// to fail. This struct handles that case by using a temp directory as fallback.
+    struct SafeOriginalDir {
+        /// The path to restore to when dropped. Either the real original dir
+        /// or a temp directory if the original was inaccessible.
+        restore_path: std::path::PathBuf,
+        /// If we had to create a fallback temp dir, keep it alive here.
+        /// When this is dropped, the temp dir is cleaned up.
+        _fallback_temp: Option<tempfile::TempDir>,
+    }
+
+    impl SafeOriginalDir {
+        /// Create a new SafeOriginalDir, capturing the current directory or
+        /// creating a temp directory as fallback if current_dir() fails.
+        fn new() -> Self {
+            match std::env::current_dir() {
+                Ok(path) => SafeOriginalDir {
+                    restore_path: path,
+                    _fallback_temp: None,
+                },
+                Err(_) => {
+                    // Current dir is inaccessible (common on macOS CI).
+                    // Create a temp directory as our fallback restore point.
+                    let temp = tempfile::tempdir().expect("Failed to create fallback temp dir");
+                    let path = temp.path().to_path_buf();
+                    SafeOriginalDir {
+                        restore_path: path,
+                        _fallback_temp: Some(temp),
+                    }
+                }
+            }
+        }
+    }
+
+    impl Drop for SafeOriginalDir {
+        fn drop(&mut self) {
+            // Attempt to restore the working directory. Ignore errors since:
+            // 1. The test's temp dir might have been cleaned up already
+            // 2. The original dir might still be inaccessible
+            // 3. We're in cleanup - not much we can do about failures
+            let _ = std::env::set_current_dir(&self.restore_path);
+        }
+    }
+
+    fn make_args() -> Args {
+        Args {
+            inputs: vec![],
+            files_from: None,
+            changed_since: None,
+            report: Vec::new(),
+            config_file: None,
+            no_config: false,
+            recursive: false,
+            glob: "*.txt,*.md".to_string(),
+            no_gitignore: false,
+            max_depth: 0,
+            follow_symlinks: false,
+            jobs: None,
+            cached: false,
+            cache_file: PathBuf::from(CACHE_FILE),
+            in_place: false,
+            preset: None,
+            max_iters: 10,
+            min_score: 0.5,
+            tab_width: 4,
+            keep_tabs: false,
+            max_file_size: None,
+            no_editorconfig: false,
+            no_modelines: false,
+            encoding: None,
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            all: false,
+            block_confidence: None,
+            blank_gap: None,
+            lookahead: None,
+            embedded: false,
+            alt_borders: None,
+            fill_chars: None,
+            align_numbers: false,
+            single_target: false,
+            target: None,
+            repair_corners: false,
+            complete_boxes: false,
+            allow_shrink: false,
+            format_tables: false,
+            on_change: None,
+            block_name: None,
+            stdin_filename: None,
+            max_line_length: None,
+            no_pad_past: None,
+            max_pad: None,
+            normalize_eol: None,
+            lines: None, // String, not Vec<LineRange>
+            diff_only: None,
+            apply_ids: None,
+            verbose: false,
+            color: ColorMode::Auto,
+            diff: false,
+            show_padding: false,
+            diff_format: DiffFormat::Unified,
+            dry_run: false,
+            list_different: false,
+            check: false,
+            null: false,
+            require_diagrams: false,
+            batch: false,
+            stream: false,
+            separator: "\\x0c".to_string(),
+            watch: false,
+            debounce_ms: 500,
+            backup: false,
+            backup_ext: ".bak".to_string(),
+            backup_mode: BackupMode::Simple,
+            backup_keep: None,
+            preserve_metadata: false,
+            force_write: false,
+            json: false,
+            json_lines: false,
+            output_format: OutputFormat::Content,
+            error_format: ErrorFormat::Text,
+            no_ci: false,
+            command: None,
+        }
+    }
+
+    /// Create a default Config for tests
+    fn make_test_config() -> Config {
+        Config {
+            max_iters: 10,
+            min_score: 0.5,
+            preset: None,
+            tab_width: 4,
+            keep_tabs: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            editorconfig: true,
+            tab_width_pinned: false,
+            write_bom: false,
+            modelines: true,
+            encoding: None,
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            all_blocks: false,
+            block_confidence: 0.3,
+            blank_gap: 1,
+            lookahead: 3,
+            embedded: false,
+            alt_borders: Vec::new(),
+            fill_chars: parse_fill_chars(DEFAULT_FILL_CHARS).unwrap(),
+            align_numbers: false,
+            single_target: false,
+            target_strategy: TargetStrategy::Rightmost,
+            repair_corners: false,
+            complete_boxes: false,
+            allow_shrink: false,
+            format_tables: false,
+            on_change: None,
+            block_name: None,
+            stdin_filename: None,
+            max_line_length: None,
+            no_pad_past: None,
+            max_pad: None,
+            normalize_eol: None,
+            lint_severities: BTreeMap::new(),
+            scoring: ScoringWeights::default(),
+            lines: None,
+            file_lines: BTreeMap::new(),
+            diff_only: None,
+            apply_ids: None,
+            recursive: false,
+            glob: "*.txt,*.md".to_string(),
+            gitignore: true,
+            max_depth: 0,
+            follow_symlinks: false,
+            jobs: 1,
+            cached: false,
+            cache_file: PathBuf::from(CACHE_FILE),
+            color: ColorMode::Auto,
+            verbose: false,
+            diff: false,
+            show_padding: false,
+            diff_format: DiffFormat::Unified,
+            dry_run: false,
+            list_different: false,
+            check: false,
+            null: false,
+            require_diagrams: false,
+            watch: false,
+            debounce_ms: 500,
+            backup: false,
+            backup_ext: ".bak".to_string(),
+            backup_mode: BackupMode::Simple,
+            backup_keep: None,
+            preserve_metadata: false,
+            force_write: false,
+            json: false,
+            json_lines: false,
+            output_format: OutputFormat::Content,
+            reports: Vec::new(),
+            error_format: ErrorFormat::Text,
+            ci: None,
+        }
+    }
+
+    /// Create VerboseStyle for tests (no colors)
+    fn make_test_styles() -> VerboseStyle {
+        VerboseStyle::new(false)
+    }
+
+    // =========================================================================
+    // Args parsing + validation tests
+    // =========================================================================
+
+    #[test]
+    fn test_args_defaults() {
+        let args = Args::parse_from(["aadc"]);
+        assert!(args.inputs.is_empty());
+        assert!(!args.recursive);
+        assert_eq!(args.glob, "*.txt,*.md");
+        assert!(!args.no_gitignore);
+        assert_eq!(args.max_depth, 0);
+        assert!(!args.in_place);
+        assert!(args.preset.is_none());
+        assert_eq!(args.max_iters, 10);
+        assert_eq!(args.min_score, 0.5);
+        assert_eq!(args.tab_width, 4);
+        assert!(!args.all);
+        assert!(!args.verbose);
+        assert!(matches!(args.color, ColorMode::Auto));
+        assert!(!args.diff);
+        assert!(!args.dry_run);
+    }
+
+    #[test]
+    fn test_args_custom() {
+        let args = Args::parse_from([
+            "aadc", "-i", "-m", "20", "-s", "0.7", "-t", "2", "-a", "-v", "-d", "file.txt",
+        ]);
+        assert_eq!(args.inputs, vec![PathBuf::from("file.txt")]);
+        assert!(args.in_place);
+        assert_eq!(args.max_iters, 20);
+        assert_eq!(args.min_score, 0.7);
+        assert_eq!(args.tab_width, 2);
+        assert!(args.all);
+        assert!(args.verbose);
+        assert!(args.diff);
+    }
+
+    #[test]
+    fn test_args_multiple_files() {
+        let args = Args::parse_from(["aadc", "file1.txt", "file2.txt", "file3.txt"]);
+        assert_eq!(
+            args.inputs,
+            vec![
+                PathBuf::from("file1.txt"),
+                PathBuf::from("file2.txt"),
+                PathBuf::from("file3.txt")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_args_multiple_files_with_inplace() {
+        let args = Args::parse_from(["aadc", "-i", "file1.txt", "file2.txt"]);
+        assert_eq!(
+            args.inputs,
+            vec![PathBuf::from("file1.txt"), PathBuf::from("file2.txt")]
+        );
+        assert!(args.in_place);
+    }
+
+    #[test]
+    fn test_args_recursive_defaults() {
+        let args = Args::parse_from(["aadc", "-r", "docs"]);
+        assert!(args.recursive);
+        assert_eq!(args.glob, "*.txt,*.md");
+        assert!(!args.no_gitignore);
+        assert_eq!(args.max_depth, 0);
+        assert_eq!(args.inputs, vec![PathBuf::from("docs")]);
+    }
+
+    #[test]
+    fn test_args_recursive_custom() {
+        let args = Args::parse_from([
+            "aadc",
+            "--recursive",
+            "--glob",
+            "*.md",
+            "--max-depth",
+            "2",
+            "--no-gitignore",
+            "docs",
+        ]);
+        assert!(args.recursive);
+        assert_eq!(args.glob, "*.md");
+        assert!(args.no_gitignore);
+        assert_eq!(args.max_depth, 2);
+    }
+
+    #[test]
+    fn test_args_preset_long() {
+        let args = Args::parse_from(["aadc", "--preset", "strict", "file.txt"]);
+        assert_eq!(args.inputs, vec![PathBuf::from("file.txt")]);
+        assert!(matches!(args.preset, Some(Preset::Strict)));
+    }
+
+    #[test]
+    fn test_args_preset_short() {
+        let args = Args::parse_from(["aadc", "-P", "aggressive", "file.txt"]);
+        assert!(matches!(args.preset, Some(Preset::Aggressive)));
+    }
+
+    #[test]
+    fn test_args_preset_relaxed() {
+        let args = Args::parse_from(["aadc", "--preset", "relaxed", "file.txt"]);
+        assert!(matches!(args.preset, Some(Preset::Relaxed)));
+    }
+
+    #[test]
+    fn test_args_preset_conflicts_with_min_score() {
+        let result = Args::try_parse_from([
+            "aadc",
+            "--preset",
+            "strict",
+            "--min-score",
+            "0.3",
+            "file.txt",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_effective_min_score_with_preset() {
+        let config = Config {
+            max_iters: 10,
+            min_score: 0.5,
+            preset: Some(Preset::Strict),
+            tab_width: 4,
+            keep_tabs: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            editorconfig: true,
+            tab_width_pinned: false,
+            write_bom: false,
+            modelines: true,
+            encoding: None,
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            all_blocks: false,
+            block_confidence: 0.3,
+            blank_gap: 1,
+            lookahead: 3,
+            embedded: false,
+            alt_borders: Vec::new(),
+            fill_chars: parse_fill_chars(DEFAULT_FILL_CHARS).unwrap(),
+            align_numbers: false,
+            single_target: false,
+            target_strategy: TargetStrategy::Rightmost,
+            repair_corners: false,
+            complete_boxes: false,
+            allow_shrink: false,
+            format_tables: false,
+            on_change: None,
+            block_name: None,
+            stdin_filename: None,
+            max_line_length: None,
+            no_pad_past: None,
+            max_pad: None,
+            normalize_eol: None,
+            lint_severities: BTreeMap::new(),
+            scoring: ScoringWeights::default(),
+            lines: None,
+            file_lines: BTreeMap::new(),
+            diff_only: None,
+            apply_ids: None,
+            recursive: false,
+            glob: "*.txt,*.md".to_string(),
+            gitignore: true,
+            max_depth: 0,
+            follow_symlinks: false,
+            jobs: 1,
+            cached: false,
+            cache_file: PathBuf::from(CACHE_FILE),
+            color: ColorMode::Auto,
+            verbose: false,
+            diff: false,
+            show_padding: false,
+            diff_format: DiffFormat::Unified,
+            dry_run: false,
+            list_different: false,
+            check: false,
+            null: false,
+            require_diagrams: false,
+            watch: false,
+            debounce_ms: 500,
+            backup: false,
+            backup_ext: ".bak".to_string(),
+            backup_mode: BackupMode::Simple,
+            backup_keep: None,
+            preserve_metadata: false,
+            force_write: false,
+            json: false,
+            json_lines: false,
+            output_format: OutputFormat::Content,
+            reports: Vec::new(),
+            error_format: ErrorFormat::Text,
+            ci: None,
+        };
+        assert_eq!(config.effective_min_score(), 0.8);
+    }
+
+    #[test]
+    fn test_effective_min_score_without_preset() {
+        let config = Config {
+            max_iters: 10,
+            min_score: 0.42,
+            preset: None,
+            tab_width: 4,
+            keep_tabs: false,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            editorconfig: true,
+            tab_width_pinned: false,
+            write_bom: false,
+            modelines: true,
+            encoding: None,
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            all_blocks: false,
+            block_confidence: 0.3,
+            blank_gap: 1,
+            lookahead: 3,
+            embedded: false,
+            alt_borders: Vec::new(),
+            fill_chars: parse_fill_chars(DEFAULT_FILL_CHARS).unwrap(),
+            align_numbers: false,
+            single_target: false,
+            target_strategy: TargetStrategy::Rightmost,
+            repair_corners: false,
+            complete_boxes: false,
+            allow_shrink: false,
+            format_tables: false,
+            on_change: None,
+            block_name: None,
+            stdin_filename: None,
+            max_line_length: None,
+            no_pad_past: None,
+            max_pad: None,
+            normalize_eol: None,
+            lint_severities: BTreeMap::new(),
+            scoring: ScoringWeights::default(),
+            lines: None,
+            file_lines: BTreeMap::new(),
+            diff_only: None,
+            apply_ids: None,
+            recursive: false,
+            glob: "*.txt,*.md".to_string(),
+            gitignore: true,
+            max_depth: 0,
+            follow_symlinks: false,
+            jobs: 1,
+            cached: false,
+            cache_file: PathBuf::from(CACHE_FILE),
+            color: ColorMode::Auto,
+            verbose: false,
+            diff: false,
+            show_padding: false,
+            diff_format: DiffFormat::Unified,
+            dry_run: false,
+            list_different: false,
+            check: false,
+            null: false,
+            require_diagrams: false,
+            watch: false,
+            debounce_ms: 500,
+            backup: false,
+            backup_ext: ".bak".to_string(),
+            backup_mode: BackupMode::Simple,
+            backup_keep: None,
+            preserve_metadata: false,
+            force_write: false,
+            json: false,
+            json_lines: false,
+            output_format: OutputFormat::Content,
+            reports: Vec::new(),
+            error_format: ErrorFormat::Text,
+            ci: None,
+        };
+        assert_eq!(config.effective_min_score(), 0.42);
+    }
+
+    #[test]
+    fn test_validate_args_min_score_bounds() {
+        let mut args = make_args();
+        args.min_score = -0.1;
+        assert!(validate_args(&args).is_err());
+        args.min_score = 1.1;
+        assert!(validate_args(&args).is_err());
+        args.min_score = 0.0;
+        assert!(validate_args(&args).is_ok());
+        args.min_score = 1.0;
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn test_validate_args_max_iters_zero() {
+        let mut args = make_args();
+        args.max_iters = 0;
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_validate_args_in_place_requires_file() {
+        let mut args = make_args();
+        args.in_place = true;
+        assert!(validate_args(&args).is_err());
+        args.inputs = vec![PathBuf::from("diagram.txt")];
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn test_validate_args_recursive_requires_path() {
+        let mut args = make_args();
+        args.recursive = true;
+        assert!(validate_args(&args).is_err());
+        args.inputs = vec![PathBuf::from("docs")];
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn test_validate_args_tab_width_bounds() {
+        let mut args = make_args();
+        args.tab_width = 0;
+        assert!(validate_args(&args).is_err());
+        args.tab_width = 17;
+        assert!(validate_args(&args).is_err());
+        args.tab_width = 1;
+        assert!(validate_args(&args).is_ok());
+        args.tab_width = 16;
+        assert!(validate_args(&args).is_ok());
+        args.tab_width = 4;
+        assert!(validate_args(&args).is_ok());
+    }
+
+    #[test]
+    fn test_args_dry_run() {
+        let args = Args::parse_from(["aadc", "-n", "file.txt"]);
+        assert!(args.dry_run);
+        assert!(!args.in_place);
+    }
+
+    #[test]
+    fn test_args_list_different() {
+        let args = Args::parse_from(["aadc", "-l", "-0", "a.txt", "b.txt"]);
+        assert!(args.list_different);
+        assert!(args.null);
+
+        assert!(Args::try_parse_from(["aadc", "-l", "-i", "a.txt"]).is_err());
+        assert!(Args::try_parse_from(["aadc", "-0", "a.txt"]).is_err());
+        assert!(Args::try_parse_from(["aadc", "--files-from", "-", "-0"]).is_ok());
+    }
+
+    #[test]
+    fn test_create_config_searches_from_stdin_filename() {
+        let temp = tempfile::tempdir().unwrap();
+        let docs = temp.path().join("docs");
+        fs::create_dir_all(&docs).unwrap();
+        fs::write(temp.path().join(".aadcrc"), "tab_width = 2\n").unwrap();
+
+        let mut args = make_args();
+        args.stdin_filename = Some(docs.join("guide.md"));
+        assert_eq!(create_config(&args).unwrap().tab_width, 2);
+
+        args.inputs = vec![PathBuf::from("-")];
+        let config = create_config(&args).unwrap();
+        assert_eq!(config.tab_width, 2);
+        assert_eq!(
+            stdin_label(&config),
+            docs.join("guide.md").display().to_string()
+        );
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config = Config::from_toml("preset = \"strict\"\ntab_width = 2").unwrap();
+        assert_eq!(config.preset, Some(Preset::Strict));
+        assert_eq!(config.tab_width, 2);
+        assert_eq!(Config::default().tab_width, 4);
+        assert!(Config::from_toml("tab_width = 0").is_err());
+        assert!(Config::from_toml("tab_width = ").is_err());
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_correct_json() {
+        let output =
+            wasm::correct_json("+------+\n| ab |\n+------+\n", r#"{"preset": "normal"}"#).unwrap();
+        let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(output["text"], "+------+\n| ab   |\n+------+\n");
+        assert_eq!(output["stats"]["revisions_applied"], 1);
+
+        assert!(wasm::correct_json("text", r#"{"tab_width": 0}"#).is_err());
+    }
+
+    #[test]
+    fn test_args_batch() {
+        let args = Args::parse_from(["aadc", "--batch", "--separator", "\\n---\\n"]);
+        assert!(args.batch);
+        assert!(validate_args(&args).is_ok());
+        assert!(Args::try_parse_from(["aadc", "--separator", "x"]).is_err());
+        assert!(Args::try_parse_from(["aadc", "--batch", "--json"]).is_err());
+        let args = Args::parse_from(["aadc", "--batch", "a.txt"]);
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_separator() {
+        assert_eq!(parse_separator("\\x0c").unwrap(), vec![0x0c]);
+        assert_eq!(parse_separator("\\0").unwrap(), vec![0]);
+        assert_eq!(parse_separator("--\\n").unwrap(), b"--\n".to_vec());
+        assert!(parse_separator("").is_err());
+        assert!(parse_separator("\\xZZ").is_err());
+        assert!(parse_separator("\\q").is_err());
+    }
+
+    #[test]
+    fn test_split_batch() {
+        assert_eq!(
+            split_batch(b"a\n\x0cb\n\x0c\n", b"\x0c"),
+            vec![&b"a\n"[..], &b"b\n"[..]]
+        );
+        assert_eq!(
+            split_batch(b"a\x0c\x0cb", b"\x0c"),
+            vec![&b"a"[..], &b""[..], &b"b"[..]]
+        );
+        assert!(split_batch(b"", b"\x0c").is_empty());
+    }
+
+    #[test]
+    fn test_args_require_diagrams() {
+        let args = Args::parse_from(["aadc", "--require-diagrams", "a.txt"]);
+        assert!(Config::from(&args).require_diagrams);
+        assert!(Args::try_parse_from(["aadc", "--require-diagrams", "-w", "a.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_args_dry_run_long() {
+        let args = Args::parse_from(["aadc", "--dry-run", "file.txt"]);
+        assert!(args.dry_run);
+    }
+
+    #[test]
+    fn test_args_dry_run_with_diff() {
+        let args = Args::parse_from(["aadc", "-n", "-d", "file.txt"]);
+        assert!(args.dry_run);
+        assert!(args.diff);
+    }
+
+    #[test]
+    fn test_args_dry_run_with_verbose() {
+        let args = Args::parse_from(["aadc", "-n", "-v", "file.txt"]);
+        assert!(args.dry_run);
+        assert!(args.verbose);
+    }
+
+    #[test]
+    fn test_args_backup() {
+        let args = Args::parse_from(["aadc", "-i", "--backup", "file.txt"]);
+        assert!(args.in_place);
+        assert!(args.backup);
+        assert_eq!(args.backup_ext, ".bak");
+    }
+
+    #[test]
+    fn test_args_backup_custom_ext() {
+        let args = Args::parse_from([
+            "aadc",
+            "-i",
+            "--backup",
+            "--backup-ext",
+            ".orig",
+            "file.txt",
+        ]);
+        assert!(args.backup);
+        assert_eq!(args.backup_ext, ".orig");
+    }
+
+    #[test]
+    fn test_create_backup() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("test.txt");
+        fs::write(&file, "original content").unwrap();
+
+        let backup = create_backup(&file, ".bak", &fs::read(&file).unwrap()).unwrap();
+
+        assert!(backup.exists());
+        assert_eq!(backup.file_name().unwrap(), "test.txt.bak");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "original content");
+        // Original file should still exist unchanged
+        assert!(file.exists());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "original content");
+    }
+
+    #[test]
+    fn test_create_backup_preserves_extension() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("diagram.md");
+        fs::write(&file, "# Diagram").unwrap();
+
+        let backup = create_backup(&file, ".bak", &fs::read(&file).unwrap()).unwrap();
+
+        // Should be diagram.md.bak, not diagram.bak
+        assert_eq!(backup.file_name().unwrap(), "diagram.md.bak");
+    }
+
+    #[test]
+    fn test_create_backup_custom_extension() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("test.txt");
+        fs::write(&file, "content").unwrap();
+
+        let backup = create_backup(&file, ".orig", &fs::read(&file).unwrap()).unwrap();
+
+        assert!(backup.to_str().unwrap().ends_with(".orig"));
+    }
+
+    #[test]
+    fn test_args_json() {
+        let args = Args::parse_from(["aadc", "--json", "file.txt"]);
+        assert!(args.json);
+    }
+
+    #[test]
+    fn test_json_output_structure() {
+        // Test that JsonOutput serializes correctly
+        let output = JsonOutput {
+            version: JSON_FORMAT_VERSION,
+            status: "success".to_string(),
+            file: Some("test.txt".to_string()),
+            input: InputStats {
+                lines: 5,
+                bytes: 50,
+                hash: None,
+            },
+            processing: ProcessingStats {
+                blocks_detected: 1,
+                blocks_modified: 1,
+                revisions_applied: 2,
+                revisions_skipped: 0,
+                revisions_refused: 0,
+                eol_changes: 0,
+                revision_kinds: BTreeMap::new(),
+                revision_rules: BTreeMap::new(),
+                score_deciles: Vec::new(),
+            },
+            output: Some(OutputStats {
+                lines: 5,
+                bytes: 52,
+                changed: true,
+            }),
+            content: Some("corrected content".to_string()),
+            blocks: Vec::new(),
+            revisions: Vec::new(),
+            edits: Vec::new(),
+        };
+
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("\"version\":\"2.0\""));
+        assert!(json.contains("\"status\":\"success\""));
+        assert!(json.contains("\"blocks_detected\":1"));
+    }
+
+    #[test]
+    fn test_json_output_dry_run_status() {
+        let output = JsonOutput {
+            version: JSON_FORMAT_VERSION,
+            status: "dry_run".to_string(),
+            file: Some("test.txt".to_string()),
+            input: InputStats {
+                lines: 3,
+                bytes: 30,
+                hash: None,
+            },
+            processing: ProcessingStats {
+                blocks_detected: 1,
+                blocks_modified: 1,
+                revisions_applied: 1,
+                revisions_skipped: 0,
+                revisions_refused: 0,
+                eol_changes: 0,
+                revision_kinds: BTreeMap::new(),
+                revision_rules: BTreeMap::new(),
+                score_deciles: Vec::new(),
+            },
+            output: Some(OutputStats {
+                lines: 3,
+                bytes: 32,
+                changed: true,
+            }),
+            content: None, // No content in dry-run
+            blocks: Vec::new(),
+            revisions: Vec::new(),
+            edits: Vec::new(),
+        };
+
+        let json = serde_json::to_string(&output).unwrap();
+        assert!(json.contains("\"status\":\"dry_run\""));
+        // Content should not appear when None
+        assert!(!json.contains("\"content\""));
+    }
+
+    #[test]
+    fn test_line_edit_between() {
+        let edit = LineEdit::between(1, "│ Box │", "│ Box   │").unwrap();
+        assert_eq!(
+            edit,
+            LineEdit {
+                line: 2,
+                column: 7,
+                delete: 0,
+                insert_text: "  ".to_string(),
+            }
+        );
+
+        let edit = LineEdit::between(0, "+--+   |", "+--+|").unwrap();
+        assert_eq!((edit.column, edit.delete), (5, 3));
+        assert!(edit.insert_text.is_empty());
+
+        assert_eq!(LineEdit::between(0, "| a |", "| a |"), None);
+    }
+
+    #[test]
+    fn test_line_edit_apply() {
+        let mut lines = vec!["┌──┐".to_string(), "│ é│".to_string()];
+        let edit = LineEdit::between(1, "│ é│", "│ é │").unwrap();
+        edit.apply(&mut lines).unwrap();
+        assert_eq!(lines[1], "│ é │");
+
+        let trim = LineEdit::between(1, "│ é │", "│ é│").unwrap();
+        trim.apply(&mut lines).unwrap();
+        assert_eq!(lines[1], "│ é│");
+
+        let past_end = LineEdit {
+            line: 2,
+            column: 5,
+            delete: 1,
+            insert_text: String::new(),
+        };
+        assert!(past_end.apply(&mut lines).is_err());
+        let missing_line = LineEdit { line: 3, ..trim };
+        assert!(missing_line.apply(&mut lines).is_err());
+    }
+
+    #[test]
+    fn test_lsp_formatting_returns_line_edits() {
+        let mut server = LspServer::new(&make_test_config());
+        let uri = "file:///tmp/doc%20one.txt";
+        let opened = server.handle(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "text": "┌─────┐\n│ Bøx │\n│ Bøx│\n└─────┘\n" } },
+        }));
+        assert_eq!(opened[0]["method"], "textDocument/publishDiagnostics");
+        let diagnostics = opened[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+
+        let reply = server.handle(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "textDocument/formatting",
+            "params": { "textDocument": { "uri": uri } },
+        }));
+        assert_eq!(reply[0]["id"], 1);
+        let edits = reply[0]["result"].as_array().unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0]["range"]["start"]["line"], 2);
+        assert_eq!(edits[0]["range"]["start"]["character"], 5);
+        assert_eq!(edits[0]["newText"], " ");
+
+        let reply = server.handle(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "textDocument/hover",
+        }));
+        assert_eq!(reply[0]["error"]["code"], LSP_METHOD_NOT_FOUND);
+        assert!(
+            server
+                .handle(&serde_json::json!({ "jsonrpc": "2.0", "method": "initialized" }))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_mcp_tools() {
+        let config = make_test_config();
+        let call = |name: &str, text: &str| {
+            let message = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "method": "tools/call",
+                "params": { "name": name, "arguments": { "text": text } },
+            });
+            mcp_handle(&message, &config).unwrap()["result"].clone()
+        };
+        let text = "+------+\n| Box |\n+------+\n";
+
+        let fixed = call("fix_diagram", text);
+        assert_eq!(
+            fixed["content"][0]["text"],
+            "+------+\n| Box  |\n+------+\n"
+        );
+        assert_eq!(fixed["structuredContent"]["changed"], true);
+
+        let detected = call("detect_blocks", text);
+        let blocks = detected["structuredContent"]["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["end_line"], 3);
+
+        let scored = call("score", text);
+        assert_eq!(
+            scored["structuredContent"]["blocks"][0]["misaligned_lines"],
+            1
+        );
+        let score = scored["structuredContent"]["score"].as_f64().unwrap();
+        assert!((score - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(
+            call("score", "no diagrams")["structuredContent"]["score"],
+            1.0
+        );
+
+        assert_eq!(call("draw_diagram", text)["isError"], true);
+        let notification =
+            serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(mcp_handle(&notification, &config).is_none());
+    }
+
+    #[test]
+    fn test_lsp_uri_to_path() {
+        assert_eq!(
+            lsp_uri_to_path("file:///home/me/My%20Docs/a.md"),
+            PathBuf::from("/home/me/My Docs/a.md")
+        );
+        assert_eq!(lsp_uri_to_path("file:///a%2"), PathBuf::from("/a%2"));
+    }
+
+    #[test]
+    fn test_read_lsp_message() {
+        let body = r#"{"jsonrpc":"2.0","method":"exit"}"#;
+        let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        let mut input = io::Cursor::new(framed.into_bytes());
+        let message = read_lsp_message(&mut input).unwrap().unwrap();
+        assert_eq!(message["method"], "exit");
+        assert!(read_lsp_message(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_edit_sets() {
+        let single = r#"{"file": "a.md", "input": {"hash": "01"}, "edits": []}"#;
+        assert_eq!(parse_edit_sets(single).unwrap().len(), 1);
+
+        let failed = r#"{"status": "error", "file": "b.md", "error": {}}"#;
+        let run = format!(r#"{{"files": [{single}, {failed}], "summary": {{}}}}"#);
+        assert_eq!(parse_edit_sets(&run).unwrap().len(), 2);
+
+        let lines = format!("{single}\n\n{single}\n");
+        assert_eq!(parse_edit_sets(&lines).unwrap().len(), 2);
+
+        assert!(parse_edit_sets("not json").is_err());
+    }
+
+    #[test]
+    fn test_json_edits_on_original_text() {
+        let source = parse_bytes_to_lines(
+            b"+------+\n| Box |\n+------+\n\n+------+\n\t| ab |\n+------+\n".to_vec(),
+            "test",
+        )
+        .unwrap();
+        let result = process_input(
+            source,
+            "t.txt".to_string(),
+            &make_test_config(),
+            &Console::new(),
+            &make_test_styles(),
+        );
+        let edits = json_edits(&result);
+        assert_eq!(edits.len(), 4, "{:?}", result.corrected);
+
+        let pad = &edits[0];
+        assert_eq!((pad.line, pad.column, pad.delete), (2, 7, 0));
+        assert_eq!(pad.insert_text, " ");
+        assert_eq!(pad.revision, Some(1));
+
+        // Positions count the tab, and the edit replaces it
+        let tab = &edits[2];
+        assert_eq!((tab.line, tab.column), (6, 1));
+        let mut lines = result.original.clone();
+        LineEdit {
+            line: tab.line,
+            column: tab.column,
+            delete: tab.delete,
+            insert_text: tab.insert_text.clone(),
+        }
+        .apply(&mut lines)
+        .unwrap();
+        assert_eq!(lines[5], result.corrected[5]);
+    }
+
+    // =========================================================================
+    // Quick scan passthrough tests
+    // =========================================================================
+
+    #[test]
+    fn test_quick_scan_plain_text() {
+        let lines = vec![
+            "Hello world".to_string(),
+            "This is plain text".to_string(),
+            "No diagrams here".to_string(),
+        ];
+        let result = quick_scan_for_diagrams(&lines);
+
+        assert!(!result.likely_has_diagrams);
+        assert_eq!(result.lines_with_box_chars, 0);
+    }
+
+    #[test]
+    fn test_quick_scan_with_diagram_lines() {
+        let lines = vec![
+            "+---+".to_string(),
+            "| a |".to_string(),
+            "+---+".to_string(),
+        ];
+        let result = quick_scan_for_diagrams(&lines);
+
+        assert!(result.likely_has_diagrams);
+        assert!(result.ratio >= QUICK_SCAN_THRESHOLD);
+    }
+
+    #[test]
+    fn test_quick_scan_threshold_boundary() {
+        let mut lines = Vec::new();
+        for i in 0..100 {
+            if i == 0 {
+                lines.push("+---+".to_string());
+            } else {
+                lines.push("plain text".to_string());
+            }
+        }
+        let result = quick_scan_for_diagrams(&lines);
+
+        assert_eq!(result.lines_scanned, 100);
+        assert_eq!(result.lines_with_box_chars, 1);
+        assert!(result.ratio >= QUICK_SCAN_THRESHOLD);
+        assert!(result.likely_has_diagrams);
+    }
+
+    #[test]
+    fn test_correct_lines_passthrough_skips_tabs() {
+        let lines = vec!["\tPlain text".to_string()];
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+        let (corrected, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+
+        assert_eq!(corrected, lines);
+        assert_eq!(stats.blocks_found, 0);
+        assert_eq!(stats.total_revisions, 0);
+    }
+
+    #[test]
+    fn test_block_confidence_threshold() {
+        let lines = to_lines("+------+\n| text\ntext | here\na - b\n");
+        let blocks = |block_confidence| {
+            let opts = DetectOptions {
+                block_confidence,
+                ..Default::default()
+            };
+            find_diagram_blocks(&lines, &opts)
+        };
+        let confidence = blocks(0.0)[0].confidence;
+        assert!(confidence < 1.0, "confidence={}", confidence);
+        assert_eq!(blocks(confidence).len(), 1);
+        assert!(blocks(confidence + 0.01).is_empty());
+
+        let mut args = make_args();
+        args.block_confidence = Some(1.5);
+        assert!(validate_args(&args).is_err());
+        let tmp = write_temp_config("block_confidence = -0.1\n");
+        assert!(load_config_file(tmp.path()).is_err());
+    }
+
+    #[test]
+    fn test_block_grouping_options() {
+        let lines = to_lines("+---+\n| a |\n+---+\n\n\n+---+\nnote\n+---+\n");
+        let ranges = |blank_gap, lookahead| {
+            let opts = DetectOptions {
+                blank_gap,
+                lookahead,
+                ..Default::default()
+            };
+            find_diagram_blocks(&lines, &opts)
+                .iter()
+                .map(|b| (b.start, b.end))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ranges(1, 3), vec![(0, 3), (5, 8)]);
+        assert_eq!(ranges(2, 3), vec![(0, 8)]);
+        assert_eq!(ranges(1, 0), vec![(0, 3), (5, 6), (7, 8)]);
+
+        let mut config = make_test_config();
+        let file_config: FileConfig = toml::from_str("blank_gap = 0\nlookahead = 1").unwrap();
+        apply_file_config(&mut config, &make_args(), file_config);
+        assert_eq!((config.blank_gap, config.lookahead), (0, 1));
+    }
+
+    #[test]
+    fn test_disabled_ranges() {
+        let lines =
+            to_lines("a\n<!-- aadc:off -->\nb\n# aadc:off\n<!-- aadc:on -->\nc\n// aadc:off\nd\n");
+        // A second aadc:off inside a region does not restart it
+        assert_eq!(disabled_ranges(&lines), vec![(1, 5), (6, 8)]);
+        assert!(disabled_ranges(&to_lines("a\n# aadc:on\nb\n")).is_empty());
+    }
+
+    #[test]
+    fn test_correct_lines_skips_disabled_regions() {
+        let input = concat!(
+            "<!-- aadc:off -->\n",
+            "+------+\n",
+            "|\thand-made|\n",
+            "+------+\n",
+            "<!-- aadc:on -->\n",
+            "\n",
+            "+------+\n",
+            "| a |\n",
+            "+------+\n",
+        );
+        let config = make_test_config();
+        let (corrected, _) = correct(to_lines(input), &config);
+        assert_eq!(corrected[..5], to_lines(input)[..5]);
+        assert_eq!(corrected[7], "| a    |");
+
+        let blocks = find_diagram_blocks(&to_lines(input), &config.detect_options());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].start, 6);
+    }
+
+    #[test]
+    fn test_correct_embedded_skips_disabled_regions() {
+        let lines = to_lines(concat!(
+            "# aadc:off\n",
+            "def f():\n",
+            "    \"\"\"\n",
+            "    +-----+\n",
+            "    | a|\n",
+            "    +-----+\n",
+            "    \"\"\"\n",
+            "# aadc:on\n",
+        ));
+        let (output, stats) = correct_embedded(
+            lines.clone(),
+            EmbedSyntax::Python,
+            &make_test_config(),
+            &Console::new(),
+            &make_test_styles(),
+        );
+        assert_eq!(output, lines);
+        assert_eq!(stats.total_revisions, 0);
+    }
+
+    #[test]
+    fn test_correct_lines_all_blocks_bypasses_quick_scan() {
+        // The box sits past the quick scan limit, so only --all finds it
+        let mut lines = vec!["Plain text".to_string(); QUICK_SCAN_LIMIT];
+        lines.extend(to_lines("+------+\n| ab |\n+------+"));
+        let mut config = make_test_config();
+        config.all_blocks = true;
+        let console = Console::new();
+        let styles = make_test_styles();
+        let (corrected, _stats) = correct_lines(lines.clone(), &config, &console, &styles);
+
+        assert_ne!(corrected, lines);
+        assert_eq!(corrected[QUICK_SCAN_LIMIT + 1], "| ab   |");
+    }
+
+    // =========================================================================
+    // Recursive discovery tests
+    // =========================================================================
+
+    #[test]
+    fn test_discover_recursive_files_glob_matching() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join("a.txt"), "content").unwrap();
+        fs::write(temp.path().join("b.md"), "content").unwrap();
+        fs::write(temp.path().join("c.rs"), "content").unwrap();
+
+        let mut config = make_test_config();
+        config.recursive = true;
+        config.gitignore = false;
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let files =
+            discover_recursive_files(&[temp.path().to_path_buf()], &config, &console, &styles)
+                .unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .filter_map(|p| p.file_name().and_then(|n| n.to_str()))
+            .collect();
+
+        assert!(names.contains(&"a.txt"));
+        assert!(names.contains(&"b.md"));
+        assert!(!names.contains(&"c.rs"));
+    }
+
+    #[test]
+    fn test_for_each_processed_keeps_input_order() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..20 {
+            let path = temp.path().join(format!("{i:02}.txt"));
+            fs::write(&path, format!("+------+\n| {i:02} |\n+------+\n")).unwrap();
+            paths.push(path);
+        }
+        paths.push(temp.path().join("missing.txt"));
+
+        let console = Console::new();
+        let styles = make_test_styles();
+        let config = make_test_config();
+        let mut seen = Vec::new();
+        for_each_processed(
+            &paths,
+            4,
+            &config,
+            None,
+            &console,
+            &styles,
+            |path, result| {
+                assert_eq!(result.is_ok(), path.exists());
+                if let Ok(result) = result {
+                    assert!(result.would_change);
+                    assert_eq!(result.filename, path.display().to_string());
+                }
+                seen.push(path.clone());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(seen, paths);
+    }
+
+    #[test]
+    fn test_validate_args_jobs() {
+        let mut args = make_args();
+        args.jobs = Some(0);
+        assert!(validate_args(&args).is_err());
+        args.jobs = Some(8);
+        assert!(validate_args(&args).is_ok());
+        assert_eq!(Config::from(&args).jobs, 8);
+    }
+
+    #[test]
+    fn test_discover_recursive_files_max_depth() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp.path().join("a/b")).unwrap();
+        fs::write(temp.path().join("top.txt"), "").unwrap();
+        fs::write(temp.path().join("a/mid.txt"), "").unwrap();
+        fs::write(temp.path().join("a/b/deep.txt"), "").unwrap();
+
+        let mut config = make_test_config();
+        config.recursive = true;
+        config.glob = "*.txt".to_string();
+        config.gitignore = false;
+        config.max_depth = 2;
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let files =
+            discover_recursive_files(&[temp.path().to_path_buf()], &config, &console, &styles)
+                .unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .filter_map(|p| p.file_name().and_then(|n| n.to_str()))
+            .collect();
+
+        assert!(names.contains(&"top.txt"));
+        assert!(names.contains(&"mid.txt"));
+        assert!(!names.contains(&"deep.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discover_recursive_files_follow_symlinks() {
+        let temp = tempfile::tempdir().unwrap();
+        let docs = temp.path().join("docs");
+        let shared = temp.path().join("shared");
+        fs::create_dir_all(&docs).unwrap();
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(docs.join("own.txt"), "").unwrap();
+        fs::write(shared.join("linked.txt"), "").unwrap();
+        std::os::unix::fs::symlink(&shared, docs.join("shared")).unwrap();
+        // A link back up the tree must not loop
+        std::os::unix::fs::symlink(&docs, docs.join("shared").join("back")).unwrap();
+
+        let mut config = make_test_config();
+        config.recursive = true;
+        config.glob = "*.txt".to_string();
+        config.gitignore = false;
+        let console = Console::new();
+        let styles = make_test_styles();
+        let names = |config: &Config| {
+            discover_recursive_files(std::slice::from_ref(&docs), config, &console, &styles)
+                .unwrap()
+                .iter()
+                .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(str::to_string))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&config), vec!["own.txt"]);
+        config.follow_symlinks = true;
+        let mut found = names(&config);
+        found.sort();
+        assert_eq!(found, vec!["linked.txt", "own.txt"]);
+    }
+
+    #[test]
+    fn test_discover_recursive_files_respects_gitignore() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(temp.path().join(".gitignore"), "ignored.txt\n").unwrap();
+        fs::write(temp.path().join("included.txt"), "").unwrap();
+        fs::write(temp.path().join("ignored.txt"), "").unwrap();
+
+        fs::create_dir(temp.path().join(".git")).unwrap();
+
+        let mut config = make_test_config();
+        config.recursive = true;
+        config.glob = "*.txt".to_string();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let files =
+            discover_recursive_files(&[temp.path().to_path_buf()], &config, &console, &styles)
+                .unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .filter_map(|p| p.file_name().and_then(|n| n.to_str()))
+            .collect();
+
+        assert!(names.contains(&"included.txt"));
+        assert!(!names.contains(&"ignored.txt"));
+    }
+
+    // =========================================================================
+    // is_corner() tests - 13 corner characters
+    // =========================================================================
+
+    #[test]
+    fn test_is_corner_ascii() {
+        assert!(is_corner('+'), "ASCII plus should be corner");
+    }
+
+    #[test]
+    fn test_is_corner_light() {
+        assert!(is_corner('┌'), "light top-left corner");
+        assert!(is_corner('┐'), "light top-right corner");
+        assert!(is_corner('└'), "light bottom-left corner");
+        assert!(is_corner('┘'), "light bottom-right corner");
+    }
+
+    #[test]
+    fn test_is_corner_double() {
+        assert!(is_corner('╔'), "double top-left corner");
+        assert!(is_corner('╗'), "double top-right corner");
+        assert!(is_corner('╚'), "double bottom-left corner");
+        assert!(is_corner('╝'), "double bottom-right corner");
+    }
+
+    #[test]
+    fn test_is_corner_rounded() {
+        assert!(is_corner('╭'), "rounded top-left corner");
+        assert!(is_corner('╮'), "rounded top-right corner");
+        assert!(is_corner('╯'), "rounded bottom-right corner");
+        assert!(is_corner('╰'), "rounded bottom-left corner");
+    }
+
+    #[test]
+    fn test_is_corner_negative() {
+        assert!(!is_corner('-'), "horizontal fill is not corner");
+        assert!(!is_corner('|'), "vertical border is not corner");
+        assert!(!is_corner('a'), "letter is not corner");
+        assert!(!is_corner(' '), "space is not corner");
+        assert!(!is_corner('─'), "horizontal line is not corner");
+        assert!(!is_corner('┼'), "junction is not corner");
+    }
+
+    // =========================================================================
+    // is_horizontal_fill() tests - 12 horizontal fill characters
+    // =========================================================================
+
+    #[test]
+    fn test_is_horizontal_fill_ascii() {
+        assert!(is_horizontal_fill('-'), "ASCII dash");
+        assert!(is_horizontal_fill('~'), "ASCII tilde");
+        assert!(is_horizontal_fill('='), "ASCII equals");
+    }
+
+    #[test]
+    fn test_is_horizontal_fill_light() {
+        assert!(is_horizontal_fill('─'), "light horizontal");
+        assert!(is_horizontal_fill('╌'), "light dashed 2");
+        assert!(is_horizontal_fill('┄'), "light dashed 3");
+        assert!(is_horizontal_fill('┈'), "light dashed 4");
+    }
+
+    #[test]
+    fn test_is_horizontal_fill_heavy() {
+        assert!(is_horizontal_fill('━'), "heavy horizontal");
+        assert!(is_horizontal_fill('╍'), "heavy dashed 2");
+        assert!(is_horizontal_fill('┅'), "heavy dashed 3");
+        assert!(is_horizontal_fill('┉'), "heavy dashed 4");
+    }
+
+    #[test]
+    fn test_is_horizontal_fill_double() {
+        assert!(is_horizontal_fill('═'), "double horizontal");
+    }
+
+    #[test]
+    fn test_is_horizontal_fill_negative() {
+        assert!(!is_horizontal_fill('|'), "vertical is not horizontal");
+        assert!(!is_horizontal_fill('+'), "corner is not horizontal fill");
+        assert!(!is_horizontal_fill('a'), "letter is not horizontal fill");
+        assert!(!is_horizontal_fill(' '), "space is not horizontal fill");
+        assert!(!is_horizontal_fill('│'), "vertical line is not horizontal");
+    }
+
+    // =========================================================================
+    // is_vertical_border() tests - 10 vertical border characters
+    // =========================================================================
+
+    #[test]
+    fn test_is_vertical_border_ascii() {
+        assert!(is_vertical_border('|'), "ASCII pipe");
+    }
+
+    #[test]
+    fn test_is_vertical_border_light() {
+        assert!(is_vertical_border('│'), "light vertical");
+        assert!(is_vertical_border('╎'), "light dashed 2");
+        assert!(is_vertical_border('┆'), "light dashed 3");
+        assert!(is_vertical_border('┊'), "light dashed 4");
+    }
+
+    #[test]
+    fn test_is_vertical_border_heavy() {
+        assert!(is_vertical_border('┃'), "heavy vertical");
+        assert!(is_vertical_border('╏'), "heavy dashed 2");
+        assert!(is_vertical_border('┇'), "heavy dashed 3");
+        assert!(is_vertical_border('┋'), "heavy dashed 4");
+    }
+
+    #[test]
+    fn test_is_vertical_border_double() {
+        assert!(is_vertical_border('║'), "double vertical");
+    }
+
+    #[test]
+    fn test_is_vertical_border_negative() {
+        assert!(!is_vertical_border('-'), "horizontal is not vertical");
+        assert!(!is_vertical_border('+'), "corner is not vertical border");
+        assert!(!is_vertical_border('a'), "letter is not vertical border");
+        assert!(!is_vertical_border(' '), "space is not vertical border");
+        assert!(!is_vertical_border('─'), "horizontal line is not vertical");
+    }
+
+    // =========================================================================
+    // is_junction() tests - 16 junction characters
+    // =========================================================================
+
+    #[test]
+    fn test_is_junction_light() {
+        assert!(is_junction('┬'), "light down and horizontal");
+        assert!(is_junction('┴'), "light up and horizontal");
+        assert!(is_junction('├'), "light vertical and right");
+        assert!(is_junction('┤'), "light vertical and left");
+        assert!(is_junction('┼'), "light vertical and horizontal");
+    }
+
+    #[test]
+    fn test_is_junction_double() {
+        assert!(is_junction('╦'), "double down and horizontal");
+        assert!(is_junction('╩'), "double up and horizontal");
+        assert!(is_junction('╠'), "double vertical and right");
+        assert!(is_junction('╣'), "double vertical and left");
+        assert!(is_junction('╬'), "double vertical and horizontal");
+    }
+
+    #[test]
+    fn test_is_junction_mixed() {
+        assert!(is_junction('╤'), "down single and horizontal double");
+        assert!(is_junction('╧'), "up single and horizontal double");
+        assert!(is_junction('╟'), "vertical double and right single");
+        assert!(is_junction('╢'), "vertical double and left single");
+        assert!(is_junction('╫'), "vertical double and horizontal single");
+        assert!(is_junction('╪'), "vertical single and horizontal double");
+    }
+
+    #[test]
+    fn test_is_junction_negative() {
+        assert!(!is_junction('+'), "ASCII plus is corner, not junction");
+        assert!(!is_junction('┌'), "corner is not junction");
+        assert!(!is_junction('─'), "horizontal is not junction");
+        assert!(!is_junction('│'), "vertical is not junction");
+        assert!(!is_junction('a'), "letter is not junction");
+    }
+
+    // =========================================================================
+    // is_box_char() tests - composite function
+    // =========================================================================
+
+    #[test]
+    fn test_is_box_char_corners() {
+        assert!(is_box_char('+'), "ASCII corner is box char");
+        assert!(is_box_char('┌'), "light corner is box char");
+        assert!(is_box_char('╔'), "double corner is box char");
+        assert!(is_box_char('╭'), "rounded corner is box char");
+    }
+
+    #[test]
+    fn test_is_box_char_horizontals() {
+        assert!(is_box_char('-'), "ASCII dash is box char");
+        assert!(is_box_char('─'), "light horizontal is box char");
+        assert!(is_box_char('═'), "double horizontal is box char");
+    }
+
+    #[test]
+    fn test_is_box_char_verticals() {
+        assert!(is_box_char('|'), "ASCII pipe is box char");
+        assert!(is_box_char('│'), "light vertical is box char");
+        assert!(is_box_char('║'), "double vertical is box char");
+    }
+
+    #[test]
+    fn test_is_box_char_junctions() {
+        assert!(is_box_char('┼'), "light junction is box char");
+        assert!(is_box_char('╬'), "double junction is box char");
+        assert!(is_box_char('╪'), "mixed junction is box char");
+    }
+
+    #[test]
+    fn test_is_box_char_negative() {
+        assert!(!is_box_char('a'), "letter is not box char");
+        assert!(!is_box_char(' '), "space is not box char");
+        assert!(!is_box_char('0'), "digit is not box char");
+        assert!(!is_box_char('\n'), "newline is not box char");
+        assert!(!is_box_char('中'), "CJK char is not box char");
+    }
+
+    // =========================================================================
+    // is_border_char() tests
+    // =========================================================================
+
+    #[test]
+    fn test_is_border_char_verticals() {
+        assert!(is_border_char('|'), "ASCII pipe is border char");
+        assert!(is_border_char('│'), "light vertical is border char");
+        assert!(is_border_char('║'), "double vertical is border char");
+    }
+
+    #[test]
+    fn test_is_border_char_corners() {
+        assert!(is_border_char('+'), "ASCII corner is border char");
+        assert!(is_border_char('┐'), "unicode corner is border char");
+        assert!(is_border_char('╝'), "double corner is border char");
+    }
+
+    #[test]
+    fn test_is_border_char_junctions() {
+        assert!(is_border_char('┤'), "junction is border char");
+        assert!(is_border_char('╣'), "double junction is border char");
+        assert!(is_border_char('╢'), "mixed junction is border char");
+    }
+
+    #[test]
+    fn test_is_border_char_negative() {
+        assert!(!is_border_char('-'), "horizontal fill is not border char");
+        assert!(!is_border_char('a'), "letter is not border char");
+        assert!(!is_border_char(' '), "space is not border char");
+    }
+
+    // =========================================================================
+    // detect_vertical_border() tests - frequency-based detection
+    // =========================================================================
+
+    #[test]
+    fn test_detect_vertical_border_ascii() {
+        let lines = vec!["| hello |", "| world |"];
+        assert_eq!(detect_vertical_border(&lines), '|');
+    }
+
+    #[test]
+    fn test_detect_vertical_border_unicode_light() {
+        let lines = vec!["│ hello │", "│ world │"];
+        assert_eq!(detect_vertical_border(&lines), '│');
+    }
+
+    #[test]
+    fn test_detect_vertical_border_unicode_double() {
+        let lines = vec!["║ hello ║", "║ world ║"];
+        assert_eq!(detect_vertical_border(&lines), '║');
+    }
+
+    #[test]
+    fn test_detect_vertical_border_mixed_prefers_most_common() {
+        let lines = vec!["│ a │", "│ b │", "│ c │", "| d |"];
+        // 6 occurrences of │ vs 2 occurrences of |
+        assert_eq!(detect_vertical_border(&lines), '│');
+    }
+
+    #[test]
+    fn test_detect_vertical_border_empty_defaults_to_ascii() {
+        let lines: Vec<&str> = vec![];
+        assert_eq!(detect_vertical_border(&lines), '|');
+    }
+
+    #[test]
+    fn test_detect_vertical_border_no_borders_defaults_to_ascii() {
+        let lines = vec!["hello world", "no borders here"];
+        assert_eq!(detect_vertical_border(&lines), '|');
+    }
+
+    // =========================================================================
+    // Revision::score() tests
+    // =========================================================================
+
+    fn make_analyzed_lines(lines: &[&str]) -> Vec<AnalyzedLine> {
+        lines
+            .iter()
+            .map(|l| analyze_line(l, &DetectOptions::default()))
+            .collect()
+    }
+
+    #[test]
+    fn test_revision_score_pad_small_adjustment() {
+        let lines = vec!["| short|", "| longer |"];
+        let analyzed = make_analyzed_lines(&lines);
+        // Small padding (2 spaces) should have high score
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 2,
+            target_column: 10,
+        };
+        let score = rev.score(&analyzed, 0, &ScoringWeights::default());
+        // Base 0.8 - 0.2 penalty + 0.2 strong bonus = 0.8 for strong line
+        assert!(
+            (0.6..=1.0).contains(&score),
+            "score={} should be in [0.6, 1.0]",
+            score
+        );
+    }
+
+    #[test]
+    fn test_revision_score_pad_large_adjustment() {
+        let lines = vec!["| x|", "| very long content |"];
+        let analyzed = make_analyzed_lines(&lines);
+        // Large padding should have lower score
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 10,
+            target_column: 20,
+        };
+        let score = rev.score(&analyzed, 0, &ScoringWeights::default());
+        // 10 spaces = 1.0 penalty capped at 0.5, so 0.8 - 0.5 = 0.3 base
+        assert!(
+            (0.0..=0.8).contains(&score),
+            "large adjustment score={} should be lower",
+            score
+        );
+    }
+
+    #[test]
+    fn test_revision_score_pad_strong_line_bonus() {
+        let lines = vec!["+---+", "| x |"];
+        let analyzed = make_analyzed_lines(&lines);
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 2,
+            target_column: 8,
+        };
+        let score = rev.score(&analyzed, 0, &ScoringWeights::default());
+        // Strong line gets 0.2 bonus
+        assert!(score > 0.7, "strong line should get bonus, score={}", score);
+    }
+
+    #[test]
+    fn test_revision_score_add_border_base() {
+        let lines = vec!["| text", "| other |"];
+        let analyzed = make_analyzed_lines(&lines);
+        let rev = Revision::AddSuffixBorder {
+            line_idx: 0,
+            border_char: '|',
+            target_column: 10,
+        };
+        let score = rev.score(&analyzed, 0, &ScoringWeights::default());
+        // AddSuffixBorder has base 0.5 + 0.1-0.2 strength bonus
+        assert!(
+            (0.5..=0.8).contains(&score),
+            "add border score={} should be moderate",
+            score
+        );
+    }
+
+    #[test]
+    fn test_revision_score_add_border_strong_line() {
+        let lines = vec!["+----", "+----+"];
+        let analyzed = make_analyzed_lines(&lines);
+        let rev = Revision::AddSuffixBorder {
+            line_idx: 0,
+            border_char: '+',
+            target_column: 6,
+        };
+        let score = rev.score(&analyzed, 0, &ScoringWeights::default());
+        // Strong line gets 0.2 bonus instead of 0.1
+        assert!(
+            score >= 0.6,
+            "strong line add border score={} should be higher",
+            score
+        );
+    }
+
+    #[test]
+    fn test_revision_score_with_block_offset() {
+        // Test that block_start offset is correctly applied
+        let lines = vec!["| hello|", "| world |"];
+        let analyzed = make_analyzed_lines(&lines);
+        // Simulate being at block offset 5 in global lines
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 5,
+            spaces_to_add: 2,
+            target_column: 10,
+        };
+        let score = rev.score(&analyzed, 5, &ScoringWeights::default());
+        assert!(score > 0.0, "should correctly index with block offset");
+    }
+
+    // =========================================================================
+    // Revision::apply() tests
+    // =========================================================================
+
+    #[test]
+    fn test_revision_apply_pad_ascii() {
+        let mut lines = vec!["| short|".to_string()];
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 3,
+            target_column: 10,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "| short   |", "should pad before closing border");
+    }
+
+    #[test]
+    fn test_revision_apply_pad_unicode() {
+        let mut lines = vec!["│ text│".to_string()];
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 2,
+            target_column: 10,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "│ text  │", "should pad before unicode border");
+    }
+
+    #[test]
+    fn test_revision_apply_pad_corner() {
+        let mut lines = vec!["+---+".to_string()];
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 2,
+            target_column: 7,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "+---  +", "should pad before corner");
+    }
+
+    #[test]
+    fn test_revision_apply_pad_preserves_other_lines() {
+        let mut lines = vec!["| first|".to_string(), "| second |".to_string()];
+        let rev = Revision::PadBeforeSuffixBorder {
+            line_idx: 0,
+            spaces_to_add: 2,
+            target_column: 10,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "| first  |");
+        assert_eq!(lines[1], "| second |", "other lines should be unchanged");
+    }
+
+    #[test]
+    fn test_revision_apply_add_border_ascii() {
+        let mut lines = vec!["| text".to_string()];
+        let rev = Revision::AddSuffixBorder {
+            line_idx: 0,
+            border_char: '|',
+            target_column: 10,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(
+            lines[0], "| text    |",
+            "should add border at target column"
+        );
+    }
+
+    #[test]
+    fn test_revision_apply_add_border_unicode() {
+        let mut lines = vec!["│ hello".to_string()];
+        let rev = Revision::AddSuffixBorder {
+            line_idx: 0,
+            border_char: '│',
+            target_column: 12,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "│ hello     │", "should add unicode border");
+    }
+
+    #[test]
+    fn test_revision_apply_add_corner() {
+        let mut lines = vec!["+----".to_string()];
+        let rev = Revision::AddSuffixBorder {
+            line_idx: 0,
+            border_char: '+',
+            target_column: 6,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "+---- +", "should add corner");
+    }
+
+    #[test]
+    fn test_revision_apply_add_border_no_extra_padding() {
+        let mut lines = vec!["| exact len|".to_string()];
+        // If current width >= target, padding should be 0
+        let rev = Revision::AddSuffixBorder {
+            line_idx: 0,
+            border_char: '|',
+            target_column: 5, // Less than current width
+        };
+        rev.apply(&mut lines);
+        // Should add border with no padding
+        assert!(lines[0].ends_with('|'), "should still add border");
+    }
+
+    #[test]
+    fn test_revision_apply_extend_horizontal_border() {
+        let mut lines = vec!["└──┘".to_string()];
+        let rev = Revision::ExtendHorizontalBorder {
+            line_idx: 0,
+            fill_char: '─',
+            spaces_to_add: 3,
+            target_column: 6,
+        };
+        rev.apply(&mut lines);
+        assert_eq!(lines[0], "└─────┘");
+        assert_eq!(rev.kind(), "extend_border");
+    }
+
+    #[test]
+    fn test_correct_extends_horizontal_borders_with_fill() {
+        let input = "+----+\n| abcdef |\n+------+\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected, to_lines("+--------+\n| abcdef |\n+--------+\n"));
+
+        let input = "╔══╗\n║ abcdef ║\n╠════╣\n╚════╝\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected[0], "╔════════╗");
+        assert_eq!(corrected[2], "╠════════╣");
+
+        // A label right before the corner is not a border run
+        let line = analyze_line("+-- Title +", &DetectOptions::default());
+        assert_eq!(horizontal_border_fill(&line), None);
+    }
+
+    // =========================================================================
+    // classify_line() tests
+    // =========================================================================
+
+    #[test]
+    fn test_classify_line_blank_empty() {
+        assert_eq!(classify_line(""), LineKind::Blank);
+    }
+
+    #[test]
+    fn test_classify_line_blank_spaces() {
+        assert_eq!(classify_line("   "), LineKind::Blank);
+        assert_eq!(classify_line("      "), LineKind::Blank);
+    }
+
+    #[test]
+    fn test_classify_line_blank_tabs() {
+        assert_eq!(classify_line("\t"), LineKind::Blank);
+        assert_eq!(classify_line("\t\t"), LineKind::Blank);
+    }
+
+    #[test]
+    fn test_classify_line_blank_mixed_whitespace() {
+        assert_eq!(classify_line("  \t  "), LineKind::Blank);
+    }
+
+    #[test]
+    fn test_classify_line_none_plain_text() {
+        assert_eq!(classify_line("hello world"), LineKind::None);
+        assert_eq!(classify_line("fn main() {}"), LineKind::None);
+    }
+
+    #[test]
+    fn test_classify_line_none_numbers() {
+        assert_eq!(classify_line("12345"), LineKind::None);
+        assert_eq!(classify_line("3.14159"), LineKind::None);
+    }
+
+    #[test]
+    fn test_classify_line_none_punctuation() {
+        assert_eq!(classify_line("..."), LineKind::None);
+        assert_eq!(classify_line("???"), LineKind::None);
+    }
+
+    #[test]
+    fn test_classify_line_strong_ascii_corners() {
+        assert_eq!(classify_line("+---+"), LineKind::Strong);
+        assert_eq!(classify_line("+--+"), LineKind::Strong);
+    }
+
+    #[test]
+    fn test_classify_line_strong_border_both_sides() {
+        assert_eq!(classify_line("| x |"), LineKind::Strong);
+        assert_eq!(classify_line("| content |"), LineKind::Strong);
+    }
+
+    #[test]
+    fn test_classify_line_strong_unicode_light() {
+        assert_eq!(classify_line("┌───┐"), LineKind::Strong);
+        assert_eq!(classify_line("│ y │"), LineKind::Strong);
+        assert_eq!(classify_line("└───┘"), LineKind::Strong);
+    }
+
+    #[test]
+    fn test_classify_line_strong_unicode_double() {
+        assert_eq!(classify_line("╔═══╗"), LineKind::Strong);
+        assert_eq!(classify_line("║ z ║"), LineKind::Strong);
+        assert_eq!(classify_line("╚═══╝"), LineKind::Strong);
+    }
+
+    #[test]
+    fn test_classify_line_strong_high_ratio() {
+        // More than 1/3 box chars = strong
+        assert_eq!(classify_line("---"), LineKind::Strong);
+        assert_eq!(classify_line("───────"), LineKind::Strong);
+    }
+
+    #[test]
+    fn test_classify_line_weak_few_box_chars() {
+        // Has box chars but doesn't meet strong criteria
+        assert_eq!(classify_line("text | here"), LineKind::Weak);
+        assert_eq!(classify_line("a - b"), LineKind::Weak);
+    }
+
+    #[test]
+    fn test_classify_line_weak_single_border() {
+        // Only one side has border
+        assert_eq!(classify_line("| text"), LineKind::Weak);
+        assert_eq!(classify_line("text |"), LineKind::Weak);
+    }
+
+    #[test]
+    fn test_classify_line_connector() {
+        assert_eq!(classify_line("A --> B"), LineKind::Connector);
+        assert_eq!(classify_line("    +---> next"), LineKind::Connector);
+        assert_eq!(classify_line("<---+"), LineKind::Connector);
+        assert_eq!(classify_line("◀── back"), LineKind::Connector);
+        // Arrows inside boxes or between them leave the borders in charge
+        assert_ne!(classify_line("| a --> b |"), LineKind::Connector);
+        assert_eq!(classify_line("+---+ --> +---+"), LineKind::Strong);
+        // A single dash is not a shaft
+        assert_eq!(classify_line("x -> y"), LineKind::Weak);
+    }
+
+    #[test]
+    fn test_connector_lines_get_no_border() {
+        let input = "+---+\n| A |\n+---+\nA --> B\n+-----+\n| C   |\n+-----+\n";
+        let lines = to_lines(input);
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1, "the arrow keeps the boxes together");
+        assert!(
+            analyze_line("A --> B", &DetectOptions::default())
+                .suffix_border()
+                .is_none()
+        );
+
+        let (corrected, _) = correct(lines, &make_test_config());
+        assert_eq!(corrected[3], "A --> B");
+        assert_eq!(corrected[1], "| A   |");
+    }
+
+    // =========================================================================
+    // visual_width() tests
+    // =========================================================================
+
+    #[test]
+    fn test_visual_width_empty() {
+        assert_eq!(visual_width(""), 0);
+    }
+
+    #[test]
+    fn test_visual_width_ascii() {
+        assert_eq!(visual_width("hello"), 5);
+        assert_eq!(visual_width("a b c"), 5);
+        assert_eq!(visual_width("test!"), 5);
+    }
+
+    #[test]
+    fn test_visual_width_box_chars() {
+        assert_eq!(visual_width("│──│"), 4);
+        assert_eq!(visual_width("┌──┐"), 4);
+        assert_eq!(visual_width("╔══╗"), 4);
+    }
+
+    #[test]
+    fn test_visual_width_cjk() {
+        // CJK characters are double-width
+        assert_eq!(visual_width("中"), 2);
+        assert_eq!(visual_width("中文"), 4);
+        assert_eq!(visual_width("日本語"), 6);
+    }
+
+    #[test]
+    fn test_visual_width_mixed_ascii_cjk() {
+        // "a中b" = 1 + 2 + 1 = 4
+        assert_eq!(visual_width("a中b"), 4);
+        assert_eq!(visual_width("hi中文"), 6); // 2 + 2 + 2
+    }
+
+    #[test]
+    fn test_visual_width_unicode_tables() {
+        // Halfwidth katakana and fullwidth Latin
+        assert_eq!(visual_width("ｱｲｳ"), 3);
+        assert_eq!(visual_width("ＡＢ"), 4);
+        // Arrows and geometric shapes are narrow; emoji are wide
+        assert_eq!(visual_width("→▶"), 2);
+        assert_eq!(visual_width("✅"), 2);
+        // Combining marks take no column of their own
+        assert_eq!(visual_width("e\u{301}"), 1);
+        assert_eq!(visual_width("a\u{200b}b"), 2);
+    }
+
+    #[test]
+    fn test_visual_width_grapheme_clusters() {
+        // ZWJ family, flag, skin tone modifier, keycap: one glyph each
+        assert_eq!(visual_width("👨\u{200d}👩\u{200d}👧"), 2);
+        assert_eq!(visual_width("🇺🇸"), 2);
+        assert_eq!(visual_width("👍🏽"), 2);
+        assert_eq!(visual_width("1\u{fe0f}\u{20e3}"), 2);
+        // Emoji presentation selector widens a narrow symbol
+        assert_eq!(visual_width("❤\u{fe0f}"), 2);
+        // Combining marks stay with their base when walking columns
+        assert_eq!(byte_at_column("e\u{301}|", 1), Some(3));
+        assert_eq!(byte_at_column("中|", 1), None);
+    }
+
+    #[test]
+    fn test_visual_width_skips_ansi_escapes() {
+        assert_eq!(visual_width("\x1b[1;31merror\x1b[0m"), 5);
+        // OSC 8 hyperlink, terminated by BEL and by ESC \
+        assert_eq!(
+            visual_width("\x1b]8;;https://example.com\x07link\x1b]8;;\x1b\\"),
+            4
+        );
+        assert_eq!(ansi_escape_len("\x1b[38;5;208mx"), Some(11));
+        assert_eq!(ansi_escape_len("\x1b]unterminated"), None);
+        assert_eq!(strip_ansi("\x1b[32m| ok |\x1b[0m"), "| ok |");
+        assert_eq!(
+            split_visible_end("\x1b[34m| a |\x1b[0m  "),
+            ("\x1b[34m| a |", "\x1b[0m  ")
+        );
+        assert_eq!(expand_tabs("\x1b[1mab\x1b[0m\tc", 4), "\x1b[1mab\x1b[0m  c");
+    }
+
+    #[test]
+    fn test_correct_preserves_ansi_escapes() {
+        let input = concat!(
+            "\x1b[34m+----------+\x1b[0m\n",
+            "\x1b[34m|\x1b[0m \x1b[31mfail\x1b[0m \x1b[34m|\x1b[0m\n",
+            "| plain |\n",
+            "\x1b[34m+----------+\x1b[0m\n",
+        );
+        let (corrected, _stats) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected[0], "\x1b[34m+----------+\x1b[0m");
+        assert_eq!(
+            corrected[1],
+            "\x1b[34m|\x1b[0m \x1b[31mfail\x1b[0m \x1b[34m    |\x1b[0m"
+        );
+        assert_eq!(corrected[2], "| plain    |");
+    }
+
+    #[test]
+    fn test_correct_box_with_zwj_sequence() {
+        let input = "+--------+\n| 👨\u{200d}👩\u{200d}👧 |\n| ab |\n+--------+\n";
+        let (corrected, _stats) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected[1], "| 👨\u{200d}👩\u{200d}👧     |");
+        assert_eq!(corrected[2], "| ab     |");
+    }
+
+    #[test]
+    fn test_visual_width_box_and_cjk() {
+        // Box chars in CJK context
+        assert_eq!(visual_width("│中│"), 4); // 1 + 2 + 1
+    }
+
+    // =========================================================================
+    // analyze_line() tests
+    // =========================================================================
+
+    #[test]
+    fn test_analyze_line_blank() {
+        let result = analyze_line("", &DetectOptions::default());
+        assert_eq!(result.kind, LineKind::Blank);
+        assert_eq!(result.visual_width, 0);
+        assert!(result.suffix_border.is_none());
+    }
+
+    #[test]
+    fn test_analyze_line_strong_with_border() {
+        let result = analyze_line("| hello |", &DetectOptions::default());
+        assert_eq!(result.kind, LineKind::Strong);
+        assert_eq!(result.visual_width, 9);
+        assert!(result.suffix_border.is_some());
+        let border = result.suffix_border.unwrap();
+        assert_eq!(border.char, '|');
+    }
+
+    #[test]
+    fn test_analyze_line_indented() {
+        let result = analyze_line("  | text |", &DetectOptions::default());
+        assert_eq!(result.indent, 2);
+        assert_eq!(result.kind, LineKind::Strong);
+    }
+
+    #[test]
+    fn test_analyze_line_no_suffix_border() {
+        let result = analyze_line("| missing end", &DetectOptions::default());
+        assert_eq!(result.kind, LineKind::Weak);
+        assert!(result.suffix_border.is_none());
+    }
+
+    #[test]
+    fn test_analyze_line_unicode_border() {
+        let result = analyze_line("│ content │", &DetectOptions::default());
+        assert_eq!(result.kind, LineKind::Strong);
+        assert!(result.suffix_border.is_some());
+        let border = result.suffix_border.unwrap();
+        assert_eq!(border.char, '│');
+    }
+
+    // =========================================================================
+    // detect_suffix_border() tests
+    // =========================================================================
+
+    #[test]
+    fn test_detect_suffix_border_ascii_pipe() {
+        let border = detect_suffix_border("| hello |");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '|');
+        assert!(!b.is_closing);
+        assert_eq!(b.column, 8);
+    }
+
+    #[test]
+    fn test_detect_suffix_border_unicode_light() {
+        let border = detect_suffix_border("│ text │");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '│');
+        assert!(!b.is_closing);
+    }
+
+    #[test]
+    fn test_detect_suffix_border_corner() {
+        let border = detect_suffix_border("+---+");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '+');
+        assert!(b.is_closing);
+    }
+
+    #[test]
+    fn test_detect_suffix_border_unicode_corner() {
+        let border = detect_suffix_border("┌───┐");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '┐');
+        assert!(b.is_closing);
+    }
+
+    #[test]
+    fn test_detect_suffix_border_junction() {
+        let border = detect_suffix_border("│ a ┤");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '┤');
+        assert!(b.is_closing);
+    }
+
+    #[test]
+    fn test_detect_suffix_border_none_no_border() {
+        let border = detect_suffix_border("hello world");
+        assert!(border.is_none());
+    }
+
+    #[test]
+    fn test_detect_suffix_border_none_empty() {
+        let border = detect_suffix_border("");
+        assert!(border.is_none());
+    }
+
+    #[test]
+    fn test_detect_suffix_border_trailing_spaces() {
+        // Should detect border despite trailing spaces
+        let border = detect_suffix_border("| text |   ");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '|');
+    }
+
+    #[test]
+    fn test_detect_suffix_border_column_position() {
+        let border = detect_suffix_border("| ab |");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        // "| ab |" has visual width 6, column of | is 5 (0-indexed)
+        assert_eq!(b.column, 5);
+    }
+
+    // =========================================================================
+    // expand_tabs() tests
+    // =========================================================================
+
+    #[test]
+    fn test_expand_tabs_start_of_line() {
+        assert_eq!(expand_tabs("\thello", 4), "    hello");
+    }
+
+    #[test]
+    fn test_expand_tabs_middle_of_line() {
+        assert_eq!(expand_tabs("a\tb", 4), "a   b");
+        assert_eq!(expand_tabs("ab\tc", 4), "ab  c");
+        assert_eq!(expand_tabs("abc\td", 4), "abc d");
+    }
+
+    #[test]
+    fn test_expand_tabs_multiple() {
+        assert_eq!(expand_tabs("\t\t", 4), "        ");
+        assert_eq!(expand_tabs("a\tb\tc", 4), "a   b   c");
+    }
+
+    #[test]
+    fn test_expand_tabs_width_2() {
+        assert_eq!(expand_tabs("\thello", 2), "  hello");
+        assert_eq!(expand_tabs("a\tb", 2), "a b");
+    }
+
+    #[test]
+    fn test_expand_tabs_width_8() {
+        assert_eq!(expand_tabs("\thello", 8), "        hello");
+    }
+
+    #[test]
+    fn test_expand_tabs_no_tabs() {
+        assert_eq!(expand_tabs("no tabs here", 4), "no tabs here");
+    }
+
+    #[test]
+    fn test_expand_tabs_empty() {
+        assert_eq!(expand_tabs("", 4), "");
+    }
+
+    #[test]
+    fn test_expand_tabs_with_cjk() {
+        // CJK character "中" has visual width 2, so:
+        // "中\tx" with tab_width=4: col starts at 0, "中" takes cols 0-1 (width 2),
+        // tab at col 2 should expand to 2 spaces to reach col 4
+        assert_eq!(expand_tabs("中\tx", 4), "中  x");
+
+        // "a中\tx" with tab_width=4: "a" at col 0 (width 1), "中" at cols 1-2 (width 2),
+        // col is now 3, tab expands to 1 space to reach col 4
+        assert_eq!(expand_tabs("a中\tx", 4), "a中 x");
+
+        // "中中\tx" with tab_width=4: two CJK chars = width 4, col is 4,
+        // tab at col 4 expands to 4 spaces to reach col 8
+        assert_eq!(expand_tabs("中中\tx", 4), "中中    x");
+    }
+
+    // =========================================================================
+    // find_diagram_blocks() tests
+    // =========================================================================
+
+    #[test]
+    fn test_find_diagram_blocks_simple() {
+        let lines: Vec<String> = vec![
+            "Some text".to_string(),
+            "+---+".to_string(),
+            "| x |".to_string(),
+            "+---+".to_string(),
+            "More text".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].start, 1);
+        assert_eq!(blocks[0].end, 4);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_no_diagrams() {
+        let lines: Vec<String> = vec![
+            "Just plain text".to_string(),
+            "No diagrams here".to_string(),
+            "More text".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 0);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_multiple() {
+        // Need more than 3 non-boxy lines to prevent lookahead merging
+        let lines: Vec<String> = vec![
+            "+--+".to_string(),
+            "| A|".to_string(),
+            "+--+".to_string(),
+            "plain text".to_string(),
+            "more text".to_string(),
+            "even more".to_string(),
+            "still more".to_string(),
+            "+--+".to_string(),
+            "| B|".to_string(),
+            "+--+".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 2, "should find two separate blocks");
+        assert_eq!(blocks[0].start, 0);
+        assert_eq!(blocks[0].end, 3);
+        assert_eq!(blocks[1].start, 7);
+        assert_eq!(blocks[1].end, 10);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_with_blank_gap() {
+        let lines: Vec<String> = vec![
+            "+---+".to_string(),
+            "| a |".to_string(),
+            "".to_string(), // Single blank allowed
+            "| b |".to_string(),
+            "+---+".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1, "single blank gap should be allowed");
+        assert_eq!(blocks[0].start, 0);
+        assert_eq!(blocks[0].end, 5);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_large_gap_splits() {
+        let lines: Vec<String> = vec![
+            "+--+".to_string(),
+            "| A|".to_string(),
+            "+--+".to_string(),
+            "".to_string(),
+            "".to_string(), // Two blank lines should split
+            "+--+".to_string(),
+            "| B|".to_string(),
+            "+--+".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 2, "double blank gap should split blocks");
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_unicode() {
+        let lines: Vec<String> = vec![
+            "┌───┐".to_string(),
+            "│ x │".to_string(),
+            "└───┘".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].start, 0);
+        assert_eq!(blocks[0].end, 3);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_at_start() {
+        let lines: Vec<String> = vec!["+--+".to_string(), "|xy|".to_string(), "+--+".to_string()];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].start, 0);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_at_end() {
+        let lines: Vec<String> = vec![
+            "text".to_string(),
+            "+--+".to_string(),
+            "|xy|".to_string(),
+            "+--+".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].end, 4, "should go to end of lines");
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_confidence_high() {
+        let lines: Vec<String> = vec![
+            "+------+".to_string(),
+            "| text |".to_string(),
+            "| more |".to_string(),
+            "+------+".to_string(),
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert!(
+            blocks[0].confidence > 0.5,
+            "all strong lines should have high confidence"
+        );
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_all_flag() {
+        let lines: Vec<String> = vec![
+            "text | here".to_string(), // Weak line
+            "more".to_string(),
+        ];
+
+        // Without all_blocks flag, low confidence blocks are skipped
+        let blocks_default = find_diagram_blocks(&lines, &DetectOptions::default());
+
+        // With all_blocks flag, low confidence blocks are included
+        let blocks_all = find_diagram_blocks(
+            &lines,
+            &DetectOptions {
+                all_blocks: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(
+            blocks_all.len() >= blocks_default.len(),
+            "all_blocks=true should include more blocks"
+        );
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_trims_trailing_blank() {
+        let lines: Vec<String> = vec![
+            "+--+".to_string(),
+            "|ab|".to_string(),
+            "+--+".to_string(),
+            "".to_string(), // Trailing blank
+        ];
+
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].end, 3, "should trim trailing blank");
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_empty_input() {
+        let lines: Vec<String> = vec![];
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 0);
+    }
+
+    #[test]
+    fn test_find_diagram_blocks_only_blanks() {
+        let lines: Vec<String> = vec!["".to_string(), "   ".to_string(), "".to_string()];
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 0);
+    }
+
+    // =========================================================================
+    // detect_suffix_border() tests (old location kept for reference)
+    // =========================================================================
+
+    #[test]
+    fn test_detect_suffix_border() {
+        let border = detect_suffix_border("| hello |");
+        assert!(border.is_some());
+        let b = border.unwrap();
+        assert_eq!(b.char, '|');
+        assert!(!b.is_closing);
+
+        let no_border = detect_suffix_border("hello world");
+        assert!(no_border.is_none());
+    }
+
+    #[test]
+    fn test_correction_simple() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "+------+".to_string(),
+            "| short|".to_string(),
+            "| longer |".to_string(),
+            "+------+".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines, &config, &console, &styles);
+
+        // Should find and process the block
+        assert_eq!(stats.blocks_found, 1);
+
+        // All right borders should be aligned
+        let widths: Vec<usize> = corrected
+            .iter()
+            .filter(|l| classify_line(l).is_boxy())
+            .map(|l| visual_width(l.trim_end()))
+            .collect();
+
+        // Check that boxy lines have consistent width
+        if !widths.is_empty() {
+            let first = widths[0];
+            assert!(widths.iter().all(|&w| w == first || w >= first - 2));
+        }
+    }
+
+    // =========================================================================
+    // correct_lines() integration tests
+    // =========================================================================
+
+    #[test]
+    fn test_correction_no_diagrams() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "Just plain text".to_string(),
+            "No diagrams here".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 0);
+        assert_eq!(stats.blocks_modified, 0);
+        assert_eq!(corrected, lines, "content should be unchanged");
+    }
+
+    #[test]
+    fn test_correction_already_aligned() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "+------+".to_string(),
+            "| text |".to_string(),
+            "+------+".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 1);
+        // Perfectly aligned blocks should not be modified
+        assert_eq!(corrected, lines);
+    }
+
+    #[test]
+    fn test_correction_unicode() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "┌───────┐".to_string(),
+            "│ short│".to_string(),
+            "│ longer │".to_string(),
+            "└───────┘".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 1);
+        // Verify correction ran successfully (at least one block found and processed)
+        assert!(!corrected.is_empty());
+    }
+
+    #[test]
+    fn test_correction_with_tabs() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "+------+".to_string(),
+            "|\thi|".to_string(), // Tab should be expanded
+            "+------+".to_string(),
+        ];
+
+        let (corrected, _) = correct_lines(lines, &config, &console, &styles);
+        // Tab should be expanded to spaces
+        assert!(!corrected[1].contains('\t'), "tabs should be expanded");
+    }
+
+    #[test]
+    fn test_retab_indent() {
+        assert_eq!(retab_indent("      x  y", 4), "\t  x  y");
+        assert_eq!(retab_indent("        x", 4), "\t\tx");
+        assert_eq!(retab_indent("x", 4), "x");
+    }
+
+    #[test]
+    fn test_correction_keep_tabs() {
+        let console = Console::new();
+        let mut config = make_test_config();
+        config.keep_tabs = true;
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "\t+------+".to_string(),
+            "\t| ab |".to_string(),
+            "\t| a\t|".to_string(),
+            "\t+------+".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+        assert!(stats.total_revisions > 0);
+        assert_eq!(corrected[0], lines[0], "unrevised lines keep their tabs");
+        assert_eq!(corrected[1], "\t| ab   |");
+        assert_eq!(corrected[2], "\t| a    |");
+    }
+
+    #[test]
+    fn test_correction_leaves_tabs_outside_blocks() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "Intro\twith a tab".to_string(),
+            "".to_string(),
+            "+------+".to_string(),
+            "|\thi|".to_string(),
+            "+------+".to_string(),
+            "".to_string(),
+            "\tindented text".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 1);
+        assert_eq!(corrected[0], lines[0]);
+        assert_eq!(corrected[6], lines[6]);
+        assert!(!corrected[3].contains('\t'), "block lines are expanded");
+    }
+
+    #[test]
+    fn test_correction_max_iters_limit() {
+        let console = Console::new();
+        let mut config = make_test_config();
+        config.max_iters = 1; // Only 1 iteration
+        config.min_score = 0.1;
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "+--------+".to_string(),
+            "| a|".to_string(),
+            "| longer |".to_string(),
+            "+--------+".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 1);
+        // With limited iterations, some progress should still be made
+        assert!(corrected.len() == 4);
+    }
+
+    #[test]
+    fn test_correction_min_score_filter() {
+        let console = Console::new();
+        let mut config = make_test_config();
+        config.min_score = 0.95; // Very strict
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "+------+".to_string(),
+            "| text|".to_string(),
+            "+------+".to_string(),
+        ];
+
+        let (corrected, _) = correct_lines(lines.clone(), &config, &console, &styles);
+        // With very strict min_score, fewer changes should be made
+        // The exact behavior depends on the scoring implementation
+        assert!(corrected.len() == 3);
+    }
+
+    #[test]
+    fn test_correction_multiple_blocks() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "+--+".to_string(),
+            "| A|".to_string(),
+            "+--+".to_string(),
+            "text".to_string(),
+            "more".to_string(),
+            "even more".to_string(),
+            "still more".to_string(),
+            "+--+".to_string(),
+            "| B|".to_string(),
+            "+--+".to_string(),
+        ];
+
+        let (corrected, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 2, "should find two blocks");
+        assert_eq!(corrected.len(), 10);
+    }
+
+    #[test]
+    fn test_correction_empty_input() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines: Vec<String> = vec![];
+        let (corrected, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(stats.blocks_found, 0);
+        assert!(corrected.is_empty());
+    }
+
+    #[test]
+    fn test_correction_preserves_non_diagram_content() {
+        let console = Console::new();
+        let config = make_test_config();
+        let styles = make_test_styles();
+
+        let lines = vec![
+            "# Header".to_string(),
+            "".to_string(),
+            "+---+".to_string(),
+            "| x|".to_string(),
+            "+---+".to_string(),
+            "".to_string(),
+            "Footer text".to_string(),
+        ];
+
+        let (corrected, _) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(corrected[0], "# Header");
+        assert_eq!(corrected[6], "Footer text");
+    }
+
+    // =========================================================================
+    // Hook management tests
+    // =========================================================================
+
+    #[test]
+    fn test_generate_check_hook() {
+        let hook = generate_check_hook(&["*.md", "*.txt"]);
+
+        assert!(hook.starts_with("#!/bin/sh\n"));
+        assert!(!hook.contains("bash"));
+        assert!(hook.contains("# aadc pre-commit hook (check mode)"));
+        assert!(hook.contains("exec aadc hook run --patterns '*.md','*.txt'\n"));
+        assert!(!hook.contains("--auto-fix"));
+    }
+
+    #[test]
+    fn test_generate_autofix_hook() {
+        let hook = generate_autofix_hook(&["*.md"]);
+
+        assert!(hook.starts_with("#!/bin/sh\n"));
+        assert!(hook.contains("# aadc pre-commit hook (auto-fix mode)"));
+        assert!(hook.contains("exec aadc hook run --auto-fix --patterns '*.md'\n"));
+    }
+
+    #[test]
+    fn test_default_hook_is_check_mode() {
+        let default = generate_default_hook(&["*.txt"]);
+        let check = generate_check_hook(&["*.txt"]);
+
+        // Default should be identical to check mode
+        assert_eq!(default, check);
+    }
+
+    #[test]
+    fn test_hook_patterns() {
+        let hook = generate_check_hook(&["*.rs", "*.go", "it's*.py"]);
+
+        assert!(hook.contains("--patterns '*.rs','*.go','it'\\''s*.py'\n"));
+    }
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\C:\docs\a.md")),
+            PathBuf::from(r"C:\docs\a.md")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\UNC\server\share\a.md")),
+            PathBuf::from(r"\\server\share\a.md")
+        );
+        // Verbatim paths without a drive have no plain equivalent
+        assert_eq!(
+            strip_verbatim_prefix(Path::new(r"\\?\Volume{1}\a.md")),
+            PathBuf::from(r"\\?\Volume{1}\a.md")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(Path::new("docs/a.md")),
+            PathBuf::from("docs/a.md")
+        );
+    }
+
+    #[test]
+    fn test_expand_input_globs() {
+        let temp = tempfile::tempdir().unwrap();
+        let docs = temp.path().join("docs");
+        fs::create_dir_all(docs.join("nested")).unwrap();
+        for name in ["b.md", "a.md", "notes.txt", ".hidden.md", "nested/c.md"] {
+            fs::write(docs.join(name), "x\n").unwrap();
+        }
+
+        let expanded = expand_input_globs(&[docs.join("*.md")]).unwrap();
+        assert_eq!(expanded, vec![docs.join("a.md"), docs.join("b.md")]);
+
+        let expanded = expand_input_globs(&[docs.join("**").join("*.md")]).unwrap();
+        assert_eq!(expanded.len(), 3);
+        assert!(expanded.contains(&docs.join("nested").join("c.md")));
+
+        // No match, or a literal path: kept as given
+        let missing = docs.join("*.rst");
+        assert_eq!(
+            expand_input_globs(std::slice::from_ref(&missing)).unwrap(),
+            vec![missing]
+        );
+        let literal = docs.join("notes.txt");
+        assert_eq!(
+            expand_input_globs(std::slice::from_ref(&literal)).unwrap(),
+            vec![literal]
+        );
+    }
+
+    #[test]
+    fn test_find_git_dir_not_in_repo() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir that's not a git repo
+        let temp = tempfile::tempdir().unwrap();
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = find_git_dir();
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Not in a git repository")
+        );
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_find_git_dir_in_repo() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        fs::create_dir(&git_dir).unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = find_git_dir();
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), git_dir);
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_install_creates_hook() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git/hooks
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        fs::create_dir(&git_dir).unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = hook_install(true, false, None);
+        assert!(result.is_ok());
+
+        // Verify hook was created
+        let hook_path = git_dir.join("hooks").join("pre-commit");
+        assert!(hook_path.exists());
+
+        let content = fs::read_to_string(&hook_path).unwrap();
+        assert!(content.contains("# aadc pre-commit hook (check mode)"));
+
+        // Verify it's executable on Unix
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let perms = fs::metadata(&hook_path).unwrap().permissions();
+            assert_eq!(perms.mode() & 0o755, 0o755);
+        }
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_install_autofix_mode() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        fs::create_dir(&git_dir).unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = hook_install(false, true, None);
+        assert!(result.is_ok());
+
+        let hook_path = git_dir.join("hooks").join("pre-commit");
+        let content = fs::read_to_string(&hook_path).unwrap();
+        assert!(content.contains("# aadc pre-commit hook (auto-fix mode)"));
+        assert!(content.contains("aadc hook run --auto-fix"));
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_install_custom_patterns() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        fs::create_dir(&git_dir).unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let patterns = vec!["*.rs".to_string(), "*.go".to_string()];
+        let result = hook_install(true, false, Some(&patterns));
+        assert!(result.is_ok());
+
+        let hook_path = git_dir.join("hooks").join("pre-commit");
+        let content = fs::read_to_string(&hook_path).unwrap();
+        assert!(content.contains("--patterns '*.rs','*.go'"));
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_install_backs_up_existing() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git/hooks and existing hook
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        let hooks_dir = git_dir.join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+
+        let existing_hook = hooks_dir.join("pre-commit");
+        fs::write(&existing_hook, "#!/bin/bash\necho 'existing hook'").unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = hook_install(true, false, None);
+        assert!(result.is_ok());
+
+        // Verify backup was created
+        let backup_path = hooks_dir.join("pre-commit.pre-aadc");
+        assert!(backup_path.exists());
+        let backup_content = fs::read_to_string(&backup_path).unwrap();
+        assert!(backup_content.contains("existing hook"));
+
+        // Verify new hook was installed
+        let content = fs::read_to_string(&existing_hook).unwrap();
+        assert!(content.contains("# aadc pre-commit hook"));
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_uninstall_removes_aadc_hook() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git/hooks and aadc hook
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        let hooks_dir = git_dir.join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+
+        let hook_path = hooks_dir.join("pre-commit");
+        fs::write(&hook_path, generate_check_hook(&["*.md"])).unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = hook_uninstall();
+        assert!(result.is_ok());
+        assert!(!hook_path.exists());
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_uninstall_refuses_non_aadc_hook() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git/hooks and non-aadc hook
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        let hooks_dir = git_dir.join("hooks");
+        fs::create_dir_all(&hooks_dir).unwrap();
+
+        let hook_path = hooks_dir.join("pre-commit");
+        fs::write(&hook_path, "#!/bin/bash\necho 'other hook'").unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        let result = hook_uninstall();
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("not installed by aadc")
+        );
+
+        // Hook should still exist
+        assert!(hook_path.exists());
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_hook_status_no_hook() {
+        let _guard = acquire_cwd_lock();
+        let _restore = SafeOriginalDir::new();
+
+        // Create a temp dir with .git but no hooks
+        let temp = tempfile::tempdir().unwrap();
+        let git_dir = temp.path().join(".git");
+        fs::create_dir(&git_dir).unwrap();
+
+        std::env::set_current_dir(temp.path()).unwrap();
+
+        // hook_status should succeed even with no hook
+        let result = hook_status();
+        assert!(result.is_ok());
+        // SafeOriginalDir restores cwd on drop
+    }
+
+    #[test]
+    fn test_filter_clean_corrects_and_passes_through() {
+        let config = make_test_config();
+        let input = b"+------+\n| ab |\n+------+\n";
+        let output = filter_clean(input, Some(Path::new("doc.md")), &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(output, b"+------+\n| ab   |\n+------+\n");
+
+        assert!(
+            filter_clean(b"plain text\n", None, &config)
+                .unwrap()
+                .is_none()
+        );
+        assert!(filter_clean(b"\x00\x01binary", None, &config).is_err());
+    }
+
+    #[test]
+    fn test_add_filter_attributes_skips_assigned_patterns() {
+        let (content, added) =
+            add_filter_attributes("*.md filter=aadc\n*.png binary", &["*.md", "*.txt"]);
+        assert_eq!(
+            content,
+            "*.md filter=aadc\n*.png binary\n*.txt filter=aadc\n"
+        );
+        assert_eq!(added, vec!["*.txt"]);
+
+        let (again, added) = add_filter_attributes(&content, &["*.md", "*.txt"]);
+        assert_eq!(again, content);
+        assert!(added.is_empty());
+    }
+
+    #[test]
+    fn test_hook_subcommand_parsing() {
+        let args = Args::parse_from(["aadc", "hook", "install"]);
+        assert!(args.command.is_some());
+        if let Some(Commands::Hook { action }) = args.command {
+            assert!(matches!(action, HookAction::Install { .. }));
+        } else {
+            panic!("Expected Hook command");
+        }
+    }
+
+    #[test]
+    fn test_hook_subcommand_install_check_only() {
+        let args = Args::parse_from(["aadc", "hook", "install", "--check-only"]);
+        if let Some(Commands::Hook { action }) = args.command {
+            if let HookAction::Install {
+                check_only,
+                auto_fix,
+                ..
+            } = action
+            {
+                assert!(check_only);
+                assert!(!auto_fix);
+            } else {
+                panic!("Expected Install action");
+            }
+        } else {
+            panic!("Expected Hook command");
+        }
+    }
+
+    #[test]
+    fn test_hook_subcommand_install_autofix() {
+        let args = Args::parse_from(["aadc", "hook", "install", "--auto-fix"]);
+        if let Some(Commands::Hook { action }) = args.command {
+            if let HookAction::Install {
+                check_only,
+                auto_fix,
+                ..
+            } = action
+            {
+                assert!(!check_only);
+                assert!(auto_fix);
+            } else {
+                panic!("Expected Install action");
+            }
+        } else {
+            panic!("Expected Hook command");
+        }
+    }
+
+    #[test]
+    fn test_hook_subcommand_uninstall() {
+        let args = Args::parse_from(["aadc", "hook", "uninstall"]);
+        if let Some(Commands::Hook { action }) = args.command {
+            assert!(matches!(action, HookAction::Uninstall));
+        } else {
+            panic!("Expected Hook command");
+        }
+    }
+
+    #[test]
+    fn test_hook_subcommand_status() {
+        let args = Args::parse_from(["aadc", "hook", "status"]);
+        if let Some(Commands::Hook { action }) = args.command {
+            assert!(matches!(action, HookAction::Status));
+        } else {
+            panic!("Expected Hook command");
+        }
+    }
+
+    // =========================================================================
+    // Config file tests
+    // =========================================================================
+
+    #[test]
+    fn test_find_config_file_in_current_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(&config_path, "min_score = 0.7").unwrap();
+
+        let found = find_config_file(temp.path());
+        assert!(found.is_some());
+        assert_eq!(found.unwrap(), config_path);
+    }
+
+    #[test]
+    fn test_find_config_file_in_parent_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(&config_path, "min_score = 0.7").unwrap();
+
+        // Create a subdirectory
+        let subdir = temp.path().join("subdir");
+        fs::create_dir(&subdir).unwrap();
+
+        // Should find config in parent
+        let found = find_config_file(&subdir);
+        assert!(found.is_some());
+        assert_eq!(found.unwrap(), config_path);
+    }
+
+    #[test]
+    fn test_find_config_file_alternative_names() {
+        let temp = tempfile::tempdir().unwrap();
+
+        // Test .aadcrc.toml variant
+        let config_path = temp.path().join(".aadcrc.toml");
+        fs::write(&config_path, "min_score = 0.7").unwrap();
+
+        let found = find_config_file(temp.path());
+        assert!(found.is_some());
+        assert_eq!(found.unwrap(), config_path);
+    }
+
+    #[test]
+    fn test_find_config_file_not_found() {
+        let temp = tempfile::tempdir().unwrap();
+        // No config file created
+
+        let found = find_config_file(temp.path());
+        // May find a config in home dir or return None
+        // We can't control home dir, so just verify it doesn't panic
+        let _ = found;
+    }
+
+    #[test]
+    fn test_load_config_file_basic() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(
+            &config_path,
+            r#"
+min_score = 0.7
+max_iters = 20
+tab_width = 2
+verbose = true
+"#,
+        )
+        .unwrap();
+
+        let config = load_config_file(&config_path).unwrap();
+        assert_eq!(config.min_score, Some(0.7));
+        assert_eq!(config.max_iters, Some(20));
+        assert_eq!(config.tab_width, Some(2));
+        assert_eq!(config.verbose, Some(true));
+    }
+
+    #[test]
+    fn test_load_config_file_with_preset() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(
+            &config_path,
+            r#"
+preset = "aggressive"
+"#,
+        )
+        .unwrap();
+
+        let config = load_config_file(&config_path).unwrap();
+        assert_eq!(config.preset, Some(Preset::Aggressive));
+    }
+
+    #[test]
+    fn test_load_config_file_all_options() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(
+            &config_path,
+            r#"
+min_score = 0.6
+max_iters = 15
+tab_width = 8
+verbose = true
+json = true
+backup = true
+backup_ext = ".backup"
+recursive = true
+glob = "*.rs"
+gitignore = false
+max_depth = 5
+all = true
+"#,
+        )
+        .unwrap();
+
+        let config = load_config_file(&config_path).unwrap();
+        assert_eq!(config.min_score, Some(0.6));
+        assert_eq!(config.max_iters, Some(15));
+        assert_eq!(config.tab_width, Some(8));
+        assert_eq!(config.verbose, Some(true));
+        assert_eq!(config.json, Some(true));
+        assert_eq!(config.backup, Some(true));
+        assert_eq!(config.backup_ext, Some(".backup".to_string()));
+        assert_eq!(config.recursive, Some(true));
+        assert_eq!(config.glob, Some("*.rs".to_string()));
+        assert_eq!(config.gitignore, Some(false));
+        assert_eq!(config.max_depth, Some(5));
+        assert_eq!(config.all, Some(true));
+    }
+
+    #[test]
+    fn test_load_config_file_empty() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(&config_path, "").unwrap();
+
+        let config = load_config_file(&config_path).unwrap();
+        assert!(config.min_score.is_none());
+        assert!(config.max_iters.is_none());
+    }
+
+    #[test]
+    fn test_load_config_file_unknown_keys_tolerated() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(
+            &config_path,
+            r#"
+min_score = 0.7
+unknown_key = "should be ignored"
+another_unknown = 42
+"#,
+        )
+        .unwrap();
+
+        // Should not fail on unknown keys (toml serde default behavior)
+        let config = load_config_file(&config_path);
+        assert!(config.is_ok());
+        assert_eq!(config.unwrap().min_score, Some(0.7));
+    }
+
+    #[test]
+    fn test_load_config_file_invalid_toml() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(&config_path, "this is not valid toml [[[").unwrap();
+
+        let result = load_config_file(&config_path);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("parse"));
+    }
+
+    #[test]
+    fn test_load_config_file_invalid_preset() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(
+            &config_path,
+            r#"
+preset = "nonexistent"
+"#,
+        )
+        .unwrap();
+
+        let result = load_config_file(&config_path);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_create_config_no_config_flag() {
+        let args = Args::parse_from(["aadc", "--no-config"]);
+        let config = create_config(&args).unwrap();
+        // Should use CLI defaults, not load any config file
+        assert_eq!(config.min_score, 0.5);
+        assert_eq!(config.max_iters, 10);
+    }
+
+    #[test]
+    fn test_create_config_explicit_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join("custom.toml");
+        fs::write(&config_path, "max_iters = 25\n").unwrap();
+
+        let args = Args::parse_from(["aadc", "--config", config_path.to_str().unwrap()]);
+        let config = create_config(&args).unwrap();
+        assert_eq!(config.max_iters, 25);
+    }
+
+    #[test]
+    fn test_create_config_explicit_file_not_found() {
+        let args = Args::parse_from(["aadc", "--config", "/nonexistent/path/config.toml"]);
+        let result = create_config(&args);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_create_config_cli_overrides_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(
+            &config_path,
+            r#"
+max_iters = 25
+tab_width = 8
+verbose = true
+"#,
+        )
+        .unwrap();
+
+        // Create a test file in the temp dir so config is found
+        let test_file = temp.path().join("test.txt");
+        fs::write(&test_file, "").unwrap();
+
+        let args = Args::parse_from(["aadc", "--max-iters", "5", test_file.to_str().unwrap()]);
+        let config = create_config(&args).unwrap();
+
+        // CLI value should override file
+        assert_eq!(config.max_iters, 5);
+        // File value should be used when CLI uses default
+        assert_eq!(config.tab_width, 8);
+        assert!(config.verbose);
+    }
+
+    #[test]
+    fn test_create_config_preset_from_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let config_path = temp.path().join(".aadcrc");
+        fs::write(&config_path, "preset = \"strict\"\n").unwrap();
+
+        let test_file = temp.path().join("test.txt");
+        fs::write(&test_file, "").unwrap();
+
+        let args = Args::parse_from(["aadc", test_file.to_str().unwrap()]);
+        let config = create_config(&args).unwrap();
+
+        assert_eq!(config.preset, Some(Preset::Strict));
+    }
+
+    #[test]
+    fn test_config_subcommand_parsing() {
+        let args = Args::parse_from(["aadc", "config", "init"]);
+        assert!(args.command.is_some());
+        if let Some(Commands::Config { action }) = args.command {
+            assert!(matches!(action, ConfigAction::Init { global: false }));
+        } else {
+            panic!("Expected Config command");
+        }
+    }
+
+    #[test]
+    fn test_config_subcommand_init_global() {
+        let args = Args::parse_from(["aadc", "config", "init", "--global"]);
+        if let Some(Commands::Config { action }) = args.command {
+            if let ConfigAction::Init { global } = action {
+                assert!(global);
+            } else {
+                panic!("Expected Init action");
+            }
+        } else {
+            panic!("Expected Config command");
+        }
+    }
+
+    #[test]
+    fn test_config_subcommand_show() {
+        let args = Args::parse_from(["aadc", "config", "show"]);
+        if let Some(Commands::Config { action }) = args.command {
+            assert!(matches!(action, ConfigAction::Show));
+        } else {
+            panic!("Expected Config command");
+        }
+    }
+
+    #[test]
+    fn test_config_subcommand_path() {
+        let args = Args::parse_from(["aadc", "config", "path"]);
+        if let Some(Commands::Config { action }) = args.command {
+            assert!(matches!(action, ConfigAction::Path));
+        } else {
+            panic!("Expected Config command");
+        }
+    }
+
+    #[test]
+    fn test_args_config_file_option() {
+        let args = Args::parse_from(["aadc", "--config", "/path/to/config"]);
+        assert_eq!(args.config_file, Some(PathBuf::from("/path/to/config")));
+    }
+
+    #[test]
+    fn test_args_no_config_option() {
+        let args = Args::parse_from(["aadc", "--no-config"]);
+        assert!(args.no_config);
+    }
+
+    // =========================================================================
+    // Line range parsing tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_simple_range() {
+        let ranges = parse_line_ranges("10-50").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 10);
+        assert_eq!(ranges[0].end, 50);
+    }
+
+    #[test]
+    fn test_parse_multiple_ranges() {
+        let ranges = parse_line_ranges("1-10, 20-30, 50-60").unwrap();
+        assert_eq!(ranges.len(), 3);
+        assert_eq!(ranges[0], LineRange { start: 1, end: 10 });
+        assert_eq!(ranges[1], LineRange { start: 20, end: 30 });
+        assert_eq!(ranges[2], LineRange { start: 50, end: 60 });
+    }
+
+    #[test]
+    fn test_parse_open_ended_start() {
+        let ranges = parse_line_ranges("50-").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 50);
+        assert_eq!(ranges[0].end, usize::MAX);
+    }
+
+    #[test]
+    fn test_parse_open_ended_end() {
+        let ranges = parse_line_ranges("-100").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 1);
+        assert_eq!(ranges[0].end, 100);
+    }
+
+    #[test]
+    fn test_parse_single_line() {
+        let ranges = parse_line_ranges("42").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 42);
+        assert_eq!(ranges[0].end, 42);
+    }
+
+    #[test]
+    fn test_merge_overlapping_ranges() {
+        let ranges = parse_line_ranges("1-50, 40-100").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 1);
+        assert_eq!(ranges[0].end, 100);
+    }
+
+    #[test]
+    fn test_merge_adjacent_ranges() {
+        let ranges = parse_line_ranges("1-10, 11-20").unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 1);
+        assert_eq!(ranges[0].end, 20);
+    }
+
+    #[test]
+    fn test_invalid_range_reversed() {
+        let result = parse_line_ranges("50-10");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("start (50) > end (10)"));
+    }
+
+    #[test]
+    fn test_invalid_range_non_numeric() {
+        let result = parse_line_ranges("abc");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid line number"));
+    }
+
+    #[test]
+    fn test_invalid_range_zero() {
+        let result = parse_line_ranges("0-10");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Line numbers start at 1"));
+    }
+
+    #[test]
+    fn test_line_in_ranges() {
+        let ranges = vec![
+            LineRange { start: 1, end: 10 },
+            LineRange { start: 20, end: 30 },
+        ];
+        assert!(line_in_ranges(5, &ranges));
+        assert!(line_in_ranges(1, &ranges));
+        assert!(line_in_ranges(10, &ranges));
+        assert!(line_in_ranges(25, &ranges));
+        assert!(!line_in_ranges(15, &ranges));
+        assert!(!line_in_ranges(31, &ranges));
+    }
+
+    #[test]
+    fn test_split_input_ranges() {
+        let temp = tempfile::tempdir().unwrap();
+        let colon = temp.path().join("notes:12");
+        fs::write(&colon, "x").unwrap();
+        let mut inputs = vec![
+            PathBuf::from("doc.md:10-50,80-"),
+            PathBuf::from("plain.md"),
+            PathBuf::from("C:notes"),
+            colon.clone(),
+        ];
+
+        let file_lines = split_input_ranges(&mut inputs).unwrap();
+        assert_eq!(inputs[0], PathBuf::from("doc.md"));
+        assert_eq!(inputs[2], PathBuf::from("C:notes"));
+        assert_eq!(inputs[3], colon, "Existing file names are kept whole");
+        assert_eq!(
+            file_lines.get(Path::new("doc.md")),
+            Some(&vec![
+                LineRange { start: 10, end: 50 },
+                LineRange {
+                    start: 80,
+                    end: usize::MAX
+                }
+            ])
+        );
+        assert_eq!(file_lines.len(), 1);
+
+        let mut inputs = vec![PathBuf::from("doc.md:0-5")];
+        assert!(split_input_ranges(&mut inputs).is_err());
+    }
+
+    #[test]
+    fn test_block_overlaps_ranges() {
+        let ranges = vec![LineRange { start: 10, end: 20 }];
+
+        // Block fully inside range
+        let block_inside = DiagramBlock {
+            start: 11, // 0-indexed, so line 12
+            end: 15,   // exclusive, so through line 15
+            confidence: 1.0,
+            kind: BlockKind::Boxes,
+            annotations: BlockAnnotations::default(),
+        };
+        assert!(block_overlaps_ranges(&block_inside, &ranges));
+
+        // Block overlapping start of range
+        let block_overlap_start = DiagramBlock {
+            start: 5,
+            end: 12,
+            confidence: 1.0,
+            kind: BlockKind::Boxes,
+            annotations: BlockAnnotations::default(),
+        };
+        assert!(block_overlaps_ranges(&block_overlap_start, &ranges));
+
+        // Block overlapping end of range
+        let block_overlap_end = DiagramBlock {
+            start: 18,
+            end: 25,
+            confidence: 1.0,
+            kind: BlockKind::Boxes,
+            annotations: BlockAnnotations::default(),
+        };
+        assert!(block_overlaps_ranges(&block_overlap_end, &ranges));
+
+        // Block completely outside range
+        let block_outside = DiagramBlock {
+            start: 25,
+            end: 30,
+            confidence: 1.0,
+            kind: BlockKind::Boxes,
+            annotations: BlockAnnotations::default(),
+        };
+        assert!(!block_overlaps_ranges(&block_outside, &ranges));
+    }
+
+    #[test]
+    fn test_shift_line_ranges() {
+        let ranges = parse_line_ranges("1-10, 20-30, 50-").unwrap();
+        assert_eq!(
+            shift_line_ranges(&ranges, 25),
+            vec![
+                LineRange { start: 1, end: 5 },
+                LineRange {
+                    start: 25,
+                    end: usize::MAX
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_line_ranges() {
+        let ranges = vec![
+            LineRange { start: 1, end: 10 },
+            LineRange { start: 20, end: 30 },
+        ];
+        let formatted = format_line_ranges(&ranges, 100);
+        assert!(formatted.contains("1-10"));
+        assert!(formatted.contains("20-30"));
+        assert!(formatted.contains("21 of 100 lines"));
+    }
+
+    #[test]
+    fn test_format_line_ranges_open_ended() {
+        let ranges = vec![LineRange {
+            start: 50,
+            end: usize::MAX,
+        }];
+        let formatted = format_line_ranges(&ranges, 100);
+        assert!(formatted.contains("50-"));
+        assert!(formatted.contains("51 of 100 lines"));
+    }
+
+    #[test]
+    fn test_args_lines_parsing() {
+        let args = Args::parse_from(["aadc", "--lines", "10-50", "file.txt"]);
+        assert!(args.lines.is_some());
+        assert_eq!(args.lines.as_ref().unwrap(), "10-50");
+
+        // Verify it parses correctly via Config
+        let config = Config::from(&args);
+        let ranges = config.lines.unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start, 10);
+        assert_eq!(ranges[0].end, 50);
+    }
+
+    #[test]
+    fn test_args_lines_multiple_ranges() {
+        let args = Args::parse_from(["aadc", "-L", "1-10,50-60", "file.txt"]);
+        assert!(args.lines.is_some());
+        assert_eq!(args.lines.as_ref().unwrap(), "1-10,50-60");
+
+        // Verify it parses correctly via Config
+        let config = Config::from(&args);
+        let ranges = config.lines.unwrap();
+        assert_eq!(ranges.len(), 2);
+    }
+
+    #[test]
+    fn test_correct_lines_with_ranges() {
+        // Test that line ranges filter which blocks get processed
+        // We use a single diagram and verify it's only processed when within range
+        let input = r#"Line 1 prose
+Line 2 prose
++------+
+| Hi|
++------+
+Line 6 prose
+Line 7 prose"#;
+
+        let lines: Vec<String> = input.lines().map(String::from).collect();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        // Test 1: Process lines 3-5 (where diagram is) - diagram SHOULD be corrected
+        let mut config = make_test_config();
+        config.lines = Some(vec![LineRange { start: 3, end: 5 }]);
+        config.all_blocks = true;
+
+        let (output, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+
+        // Diagram lines should be corrected (right border aligned)
+        assert!(
+            output[3].contains("| Hi") && output[3].ends_with("|"),
+            "Diagram should be corrected when in range, got: {:?}",
+            output[3]
+        );
+        assert!(
+            stats.blocks_modified >= 1,
+            "At least one block should be modified"
+        );
+
+        // Test 2: Process lines 1-2 (before diagram) - diagram should NOT be corrected
+        let mut config2 = make_test_config();
+        config2.lines = Some(vec![LineRange { start: 1, end: 2 }]);
+        config2.all_blocks = true;
+
+        let (output2, stats2) = correct_lines(lines.clone(), &config2, &console, &styles);
+
+        // Diagram should be unchanged (original input)
+        assert_eq!(
+            output2[3], "| Hi|",
+            "Diagram outside range should be unchanged"
+        );
+        assert_eq!(
+            stats2.blocks_modified, 0,
+            "No blocks should be modified when range excludes diagram"
+        );
+
+        // Test 3: Process lines 6-7 (after diagram) - diagram should NOT be corrected
+        let mut config3 = make_test_config();
+        config3.lines = Some(vec![LineRange { start: 6, end: 7 }]);
+        config3.all_blocks = true;
+
+        let (output3, stats3) = correct_lines(lines.clone(), &config3, &console, &styles);
+
+        // Diagram should be unchanged
+        assert_eq!(
+            output3[3], "| Hi|",
+            "Diagram outside range should be unchanged"
+        );
+        assert_eq!(
+            stats3.blocks_modified, 0,
+            "No blocks should be modified when range excludes diagram"
+        );
+    }
+
+    // =========================================================================
+    // Embedded diagram tests
+    // =========================================================================
+
+    fn to_lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_embed_syntax_from_path() {
+        assert_eq!(EmbedSyntax::from_path("a/b.yaml"), Some(EmbedSyntax::Yaml));
+        assert_eq!(EmbedSyntax::from_path("c.YML"), Some(EmbedSyntax::Yaml));
+        assert_eq!(EmbedSyntax::from_path("d.json"), Some(EmbedSyntax::Json));
+        assert_eq!(EmbedSyntax::from_path("e.md"), None);
+        assert_eq!(EmbedSyntax::from_path("stdin"), None);
+    }
+
+    #[test]
+    fn test_yaml_literal_header() {
+        assert_eq!(yaml_literal_header("diagram: |"), Some(None));
+        assert_eq!(yaml_literal_header("  topology: |- # comment"), Some(None));
+        assert_eq!(yaml_literal_header("- |2"), Some(Some(2)));
+        assert_eq!(yaml_literal_header("key: value"), None);
+        assert_eq!(yaml_literal_header("| cell |"), None);
+        assert_eq!(yaml_literal_header("folded: >"), None);
+    }
+
+    #[test]
+    fn test_find_yaml_block_scalars() {
+        let lines =
+            to_lines("data:\n  topology: |\n    +----+\n    | a|\n\n    +----+\n  other: x\n");
+        let scalars = find_yaml_block_scalars(&lines);
+        assert_eq!(
+            scalars,
+            vec![IndentedRegion {
+                start: 2,
+                end: 6,
+                indent: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_correct_embedded_yaml_block_scalar() {
+        let lines = to_lines(
+            "kind: ConfigMap\ndata:\n  topology: |\n    +--------+\n    | web|\n    | db |\n    +--------+\n  name: x\n",
+        );
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) =
+            correct_embedded(lines, EmbedSyntax::Yaml, &config, &console, &styles);
+
+        assert_eq!(output[4], "    | web    |");
+        assert_eq!(output[5], "    | db     |");
+        assert_eq!(output[0], "kind: ConfigMap");
+        assert_eq!(output[7], "  name: x");
+        assert!(stats.total_revisions > 0);
+        assert_eq!(stats.total_lines, 8);
+    }
+
+    #[test]
+    fn test_correct_embedded_json_escaped_string() {
+        let lines = to_lines(r#"{"name": "x", "diagram": "+------+\n| ab|\n+------+\n"}"#);
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) =
+            correct_embedded(lines, EmbedSyntax::Json, &config, &console, &styles);
+
+        assert_eq!(
+            output[0],
+            r#"{"name": "x", "diagram": "+------+\n| ab   |\n+------+\n"}"#
+        );
+    }
+
+    #[test]
+    fn test_correct_embedded_leaves_plain_text_alone() {
+        let lines = to_lines("key: |\n  just some text\n  more text\n");
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) =
+            correct_embedded(lines.clone(), EmbedSyntax::Yaml, &config, &console, &styles);
+
+        assert_eq!(output, lines);
+        assert_eq!(stats.total_revisions, 0);
+    }
+
+    #[test]
+    fn test_args_embedded() {
+        let args = Args::parse_from(["aadc", "--embedded", "cm.yaml"]);
+        assert!(args.embedded);
+        assert!(Config::from(&args).embedded);
+    }
+
+    #[test]
+    fn test_find_python_docstrings() {
+        let lines = to_lines(concat!(
+            "def f():\n",
+            "    \"\"\"Summary.\n",
+            "\n",
+            "    +---+\n",
+            "    | a|\n",
+            "    +---+\n",
+            "    \"\"\"\n",
+            "    SQL = \"\"\"\n",
+            "    select 1\n",
+            "    \"\"\"\n",
+            "    '''one line'''\n",
+        ));
+        let regions = find_python_docstrings(&lines);
+        assert_eq!(
+            regions,
+            vec![IndentedRegion {
+                start: 2,
+                end: 6,
+                indent: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_find_python_docstrings_skips_assignment_strings() {
+        // The closing quotes of an assignment string open no docstring, so
+        // the comment box in code below it is not a region
+        let lines = to_lines(concat!(
+            "SQL = \"\"\"\n",
+            "select 1\n",
+            "\"\"\"\n",
+            "# +-------+\n",
+            "# | ingest|\n",
+            "# +-------+\n",
+            "x = 1  # \"\"\" in a comment\n",
+            "def f():\n",
+            "    \"\"\"Doc.\n",
+            "    | a |\n",
+            "    \"\"\"\n",
+        ));
+        assert_eq!(
+            find_python_docstrings(&lines),
+            vec![IndentedRegion {
+                start: 9,
+                end: 10,
+                indent: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_correct_embedded_python_docstring() {
+        let lines = to_lines(concat!(
+            "class Pipeline:\n",
+            "    \"\"\"Data flow.\n",
+            "\n",
+            "    +----------+\n",
+            "    | ingest|\n",
+            "    | store |\n",
+            "    +----------+\n",
+            "    \"\"\"\n",
+            "\n",
+            "    x = \"| a|\\n\"\n",
+        ));
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_embedded(
+            lines.clone(),
+            EmbedSyntax::Python,
+            &config,
+            &console,
+            &styles,
+        );
+
+        assert_eq!(output[4], "    | ingest   |");
+        assert_eq!(output[5], "    | store    |");
+        // Code outside docstrings is untouched, including escaped strings
+        assert_eq!(output[9], lines[9]);
+        assert!(stats.total_revisions > 0);
+    }
+
+    #[test]
+    fn test_find_backtick_strings_go() {
+        let lines = to_lines(concat!(
+            "// not a `raw string\n",
+            "const banner = `\n",
+            "+----+\n",
+            "| hi|\n",
+            "+----+`\n",
+            "var r = '`'\n",
+            "var s = `one line`\n",
+        ));
+        let spans = find_backtick_strings(&lines, EmbedSyntax::Go);
+        assert_eq!(
+            spans,
+            vec![RawStringSpan {
+                start_line: 1,
+                start_byte: 16,
+                end_line: 4,
+                end_byte: 6
+            }]
+        );
+    }
+
+    #[test]
+    fn test_mask_placeholders_round_trip() {
+        let text = "| ${user.name} has ${n} items|";
+        let (masked, placeholders) = mask_placeholders(text);
+        assert_eq!(placeholders, vec!["${user.name}", "${n}"]);
+        assert_eq!(visual_width(&masked), visual_width(text));
+        assert!(!masked.contains('$'));
+        assert_eq!(unmask_placeholders(&masked, &placeholders), text);
+    }
+
+    #[test]
+    fn test_correct_embedded_go_raw_string() {
+        let lines = to_lines(concat!(
+            "package main\n",
+            "\n",
+            "const usage = `\n",
+            "+----------+\n",
+            "| aadc|\n",
+            "+----------+\n",
+            "`\n",
+        ));
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_embedded(lines, EmbedSyntax::Go, &config, &console, &styles);
+
+        assert_eq!(output[4], "| aadc     |");
+        assert_eq!(output[2], "const usage = `");
+        assert_eq!(output[6], "`");
+        assert!(stats.total_revisions > 0);
+    }
+
+    #[test]
+    fn test_correct_embedded_js_template_placeholders_are_opaque() {
+        let lines = to_lines(concat!(
+            "const help = `\n",
+            "+--------------+\n",
+            "| ${name}|\n",
+            "| v${a - b} |\n",
+            "+--------------+`;\n",
+        ));
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) =
+            correct_embedded(lines, EmbedSyntax::JavaScript, &config, &console, &styles);
+
+        assert_eq!(output[2], "| ${name}      |");
+        assert_eq!(output[3], "| v${a - b}    |");
+        assert_eq!(output[4], "+--------------+`;");
+    }
+
+    // =========================================================================
+    // Alternative border tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_alt_borders() {
+        assert_eq!(parse_alt_borders(":!").unwrap(), vec![':', '!']);
+        assert_eq!(parse_alt_borders(": , :").unwrap(), vec![':']);
+        assert!(parse_alt_borders("").unwrap().is_empty());
+        assert!(parse_alt_borders(":#").is_err());
+    }
+
+    #[test]
+    fn test_alt_border_frame() {
+        assert_eq!(alt_border_frame(": text :", &[':']), Some(':'));
+        assert_eq!(alt_border_frame("  ! a !  ", &['!']), Some('!'));
+        assert_eq!(alt_border_frame(": text :", &['!']), None);
+        assert_eq!(alt_border_frame("Note: see below:", &[':']), None);
+        assert_eq!(alt_border_frame("::", &[':']), None);
+    }
+
+    #[test]
+    fn test_correct_lines_alt_borders() {
+        let lines = to_lines("+---------+\n: short :\n: longer :\n+---------+\n");
+        let mut config = make_test_config();
+        config.alt_borders = vec![':'];
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines.clone(), &config, &console, &styles);
+        assert_eq!(output[1], ": short   :");
+        assert_eq!(output[2], ": longer  :");
+        assert!(stats.total_revisions > 0);
+
+        // Disabled by default: colon lines are left alone
+        let (output, _stats) = correct_lines(lines, &make_test_config(), &console, &styles);
+        assert_eq!(output[1], ": short :");
+    }
+
+    #[test]
+    fn test_alt_borders_score_below_standard_borders() {
+        let opts = DetectOptions {
+            alt_borders: vec![':'],
+            ..Default::default()
+        };
+        let analyzed = vec![analyze_line("| a |", &opts), analyze_line(": a :", &opts)];
+        let pad = |line_idx| Revision::PadBeforeSuffixBorder {
+            line_idx,
+            spaces_to_add: 2,
+            target_column: 6,
+        };
+        assert!(
+            pad(1).score(&analyzed, 0, &ScoringWeights::default())
+                < pad(0).score(&analyzed, 0, &ScoringWeights::default())
+        );
+    }
+
+    #[test]
+    fn test_validate_args_alt_borders() {
+        let mut args = make_args();
+        args.alt_borders = Some(":!".to_string());
+        assert!(validate_args(&args).is_ok());
+        args.alt_borders = Some("#".to_string());
+        assert!(validate_args(&args).is_err());
+    }
+
+    // =========================================================================
+    // Diagonal line tests
+    // =========================================================================
+
+    #[test]
+    fn test_is_diagonal() {
+        assert!(is_diagonal('/'));
+        assert!(is_diagonal('\\'));
+        assert!(is_diagonal('╱'));
+        assert!(!is_diagonal('|'));
+        assert!(!is_diagonal('-'));
+    }
+
+    #[test]
+    fn test_analyze_line_diagonal_at_border() {
+        let opts = DetectOptions::default();
+        assert!(analyze_line("| a  /|", &opts).diagonal_at_border);
+        assert!(analyze_line("+--+  \\", &opts).diagonal_at_border);
+        assert!(!analyze_line("| a / |", &opts).diagonal_at_border);
+        assert!(!analyze_line("| a |", &opts).diagonal_at_border);
+    }
+
+    #[test]
+    fn test_correct_lines_keeps_diagonals_attached() {
+        let lines = to_lines("+--------+\n| a    /|\n| b      |\n| c     |\n+--------+\n");
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[1], "| a    /|");
+        assert_eq!(output[3], "| c      |");
+        assert!(stats.revisions_skipped > 0);
+    }
+
+    #[test]
+    fn test_correct_lines_no_border_after_trailing_diagonal() {
+        let lines = to_lines("+-----+\n| top |\n+-----+ \\\n|     |  \\\n+-----+\n");
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[2], "+-----+ \\");
+        assert_eq!(output[3], "|     |  \\");
+    }
+
+    // =========================================================================
+    // Fill character tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_fill_chars() {
+        assert_eq!(parse_fill_chars(".·").unwrap(), vec!['.', '·']);
+        assert!(parse_fill_chars("").unwrap().is_empty());
+        assert!(parse_fill_chars("|").is_err());
+        assert!(parse_fill_chars("+").is_err());
+        assert!(parse_fill_chars("\u{200b}").is_err());
+        assert!(parse_fill_chars(".\u{301}").is_err());
+    }
+
+    #[test]
+    fn test_find_fill_run() {
+        let fills = ['.', '—'];
+        assert_eq!(find_fill_run("| name ..... 1 |", &fills), Some(('.', 12)));
+        assert_eq!(
+            find_fill_run("| a ———— 42 |", &fills),
+            Some(('—', "| a ————".len()))
+        );
+        // Trailing ellipsis is not a leader
+        assert_eq!(find_fill_run("| loading... |", &fills), None);
+        // Too short to be a leader
+        assert_eq!(find_fill_run("| a .. b |", &fills), None);
+    }
+
+    #[test]
+    fn test_correct_lines_extends_dot_leaders() {
+        let lines = to_lines("+-------------+\n| cpu ..... 4 |\n| mem ... 8 |\n+-------------+\n");
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) = correct_lines(lines.clone(), &config, &console, &styles);
+        assert_eq!(output[2], "| mem ..... 8 |");
+
+        // With leader extension disabled, plain padding is used
+        let mut config = make_test_config();
+        config.fill_chars.clear();
+        let (output, _stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[2], "| mem ... 8   |");
+    }
+
+    #[test]
+    fn test_validate_args_fill_chars() {
+        let mut args = make_args();
+        args.fill_chars = Some("".to_string());
+        assert!(validate_args(&args).is_ok());
+        args.fill_chars = Some("+".to_string());
+        assert!(validate_args(&args).is_err());
+    }
+
+    // =========================================================================
+    // Numeric column alignment tests
+    // =========================================================================
+
+    #[test]
+    fn test_is_numeric_cell() {
+        assert!(is_numeric_cell("42"));
+        assert!(is_numeric_cell("-3.5"));
+        assert!(is_numeric_cell("1,024"));
+        assert!(is_numeric_cell("$9.99"));
+        assert!(is_numeric_cell("87%"));
+        assert!(!is_numeric_cell("v1"));
+        assert!(!is_numeric_cell("-"));
+        assert!(!is_numeric_cell("count"));
+    }
+
+    #[test]
+    fn test_split_table_row() {
+        assert_eq!(split_table_row("| a | bc |"), Some(vec![(1, 4), (5, 9)]));
+        assert_eq!(split_table_row("+---+----+"), None);
+        assert_eq!(split_table_row("| open"), None);
+    }
+
+    #[test]
+    fn test_align_numeric_columns() {
+        let mut lines = to_lines(
+            "+------+-------+\n| name | count |\n+------+-------+\n|    a | 5     |\n| bb   |   120 |\n+------+-------+\n",
+        );
+        let changed = align_numeric_columns(&mut lines);
+
+        assert_eq!(lines[1], "| name | count |");
+        assert_eq!(lines[3], "| a    |     5 |");
+        assert_eq!(lines[4], "| bb   |   120 |");
+        assert_eq!(lines[0], "+------+-------+");
+        assert_eq!(changed, 1);
+    }
+
+    #[test]
+    fn test_correct_lines_align_numbers_opt_in() {
+        let lines = to_lines("+-----+-----+\n| x   | 7   |\n| y   | 42  |\n+-----+-----+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) = correct_lines(lines.clone(), &make_test_config(), &console, &styles);
+        assert_eq!(output[1], "| x   | 7   |");
+
+        let mut config = make_test_config();
+        config.align_numbers = true;
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[1], "| x   |   7 |");
+        assert_eq!(output[2], "| y   |  42 |");
+        assert_eq!(stats.blocks_modified, 1);
+    }
+
+    // =========================================================================
+    // On-change command tests
+    // =========================================================================
+
+    #[test]
+    fn test_writes_in_place() {
+        let mut args = make_args();
+        let mut config = make_test_config();
+        assert!(!writes_in_place(&args, &config));
+
+        args.in_place = true;
+        assert!(writes_in_place(&args, &config));
+        config.dry_run = true;
+        assert!(!writes_in_place(&args, &config));
+        config.json = true;
+        assert!(writes_in_place(&args, &config));
+    }
+
+    #[test]
+    fn test_run_on_change_noop_without_command_or_changes() {
+        let mut config = make_test_config();
+        assert!(run_on_change(&config, &[PathBuf::from("a.md")]).is_ok());
+
+        config.on_change = Some("exit 1".to_string());
+        assert!(run_on_change(&config, &[]).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_on_change_passes_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let log = temp.path().join("log.txt");
+        let mut config = make_test_config();
+        config.on_change = Some(format!(
+            "echo \"$# $1 $AADC_CHANGED_FILES\" > '{}'",
+            log.display()
+        ));
+
+        run_on_change(&config, &[PathBuf::from("a.md")]).unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "1 a.md a.md\n");
+
+        config.on_change = Some("exit 3".to_string());
+        let err = run_on_change(&config, &[PathBuf::from("a.md")]).unwrap_err();
+        assert!(err.to_string().contains("--on-change command failed"));
+    }
+
+    // =========================================================================
+    // Extract subcommand tests
+    // =========================================================================
+
+    #[test]
+    fn test_extract_subcommand_parsing() {
+        let args = Args::parse_from([
+            "aadc",
+            "extract",
+            "doc.md",
+            "--out-dir",
+            "out",
+            "--manifest",
+        ]);
+        if let Some(Commands::Extract {
+            file,
+            out_dir,
+            manifest,
+        }) = args.command
+        {
+            assert_eq!(file, PathBuf::from("doc.md"));
+            assert_eq!(out_dir, PathBuf::from("out"));
+            assert!(manifest);
+        } else {
+            panic!("Expected Extract command");
+        }
+    }
+
+    #[test]
+    fn test_run_extract_command_writes_blocks_and_manifest() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("doc.md");
+        fs::write(
+            &source,
+            "# Doc\n\n+---+\n| a|\n+---+\n\ntext\n\n┌──┐\n│ b│\n└──┘\n",
+        )
+        .unwrap();
+        let out_dir = temp.path().join("diagrams");
+
+        run_extract_command(&source, &out_dir, true, &make_test_config()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(out_dir.join("block-001.txt")).unwrap(),
+            "+---+\n| a|\n+---+\n"
+        );
+        assert_eq!(
+            fs::read_to_string(out_dir.join("block-002.txt")).unwrap(),
+            "┌──┐\n│ b│\n└──┘\n"
+        );
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(out_dir.join("manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(manifest["blocks"][0]["start_line"], 3);
+        assert_eq!(manifest["blocks"][0]["end_line"], 5);
+        assert_eq!(manifest["blocks"][1]["file"], "block-002.txt");
+    }
+
+    #[test]
+    fn test_run_extract_command_rejects_file_name_collisions() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("doc.md");
+        let out_dir = temp.path().join("diagrams");
+        let block = "+---+\n| a |\n+---+\n";
+        // The unnamed second block is extracted to block-002.txt
+        for names in [
+            ["name=flow", "name=flow"],
+            ["name=Flow", "name=flow"],
+            ["name=block-002", ""],
+        ] {
+            fs::write(
+                &source,
+                format!(
+                    "<!-- aadc:block {} -->\n{block}\ntext\n\n<!-- aadc:block {} -->\n{block}",
+                    names[0], names[1]
+                ),
+            )
+            .unwrap();
+            let err = run_extract_command(&source, &out_dir, false, &make_test_config())
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("would both be extracted"), "{names:?}: {err}");
+            assert!(!out_dir.exists(), "Nothing is written on a collision");
+        }
+    }
+
+    // =========================================================================
+    // Inject subcommand tests
+    // =========================================================================
+
+    #[test]
+    fn test_inject_subcommand_parsing() {
+        let args = Args::parse_from([
+            "aadc", "inject", "doc.md", "--block", "2", "--from", "new.txt",
+        ]);
+        if let Some(Commands::Inject { file, block, from }) = args.command {
+            assert_eq!(file, PathBuf::from("doc.md"));
+            assert_eq!(block, Some(2));
+            assert_eq!(from, PathBuf::from("new.txt"));
+        } else {
+            panic!("Expected Inject command");
+        }
+
+        assert!(
+            Args::try_parse_from(["aadc", "inject", "doc.md", "--block", "0", "--from", "x"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_run_inject_command_replaces_and_aligns_block() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("doc.md");
+        fs::write(&source, "# Doc\n\n+---+\n| a |\n+---+\n\nafter\n").unwrap();
+        let replacement = temp.path().join("new.txt");
+        fs::write(&replacement, "+------+\n| new|\n| box  |\n+------+\n").unwrap();
+
+        run_inject_command(&source, Some(1), &replacement, &make_test_config()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&source).unwrap(),
+            "# Doc\n\n+------+\n| new  |\n| box  |\n+------+\n\nafter\n"
+        );
+    }
+
+    #[test]
+    fn test_run_inject_command_keeps_crlf() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("doc.md");
+        fs::write(&source, "# Doc\r\n\r\n+---+\r\n| a |\r\n+---+\r\n\r\nafter").unwrap();
+        let replacement = temp.path().join("new.txt");
+        fs::write(&replacement, "+------+\n| new|\n+------+\n").unwrap();
+
+        run_inject_command(&source, Some(1), &replacement, &make_test_config()).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&source).unwrap(),
+            "# Doc\r\n\r\n+------+\r\n| new  |\r\n+------+\r\n\r\nafter"
+        );
+    }
+
+    #[test]
+    fn test_run_inject_command_rejects_missing_block() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("doc.md");
+        fs::write(&source, "+---+\n| a |\n+---+\n").unwrap();
+        let replacement = temp.path().join("new.txt");
+        fs::write(&replacement, "+---+\n").unwrap();
+
+        let err =
+            run_inject_command(&source, Some(2), &replacement, &make_test_config()).unwrap_err();
+        assert!(err.downcast_ref::<ArgError>().is_some());
+        assert_eq!(
+            fs::read_to_string(&source).unwrap(),
+            "+---+\n| a |\n+---+\n"
+        );
+    }
+
+    // =========================================================================
+    // Block fingerprint tests
+    // =========================================================================
+
+    #[test]
+    fn test_block_fingerprint_ignores_whitespace_and_position() {
+        let a = block_fingerprint(&to_lines("+---+\n| a|\n+---+\n"));
+        let b = block_fingerprint(&to_lines("  +---+\n  | a |\n\n  +---+\n"));
+        let c = block_fingerprint(&to_lines("+---+\n| b |\n+---+\n"));
+
+        assert_eq!(a.len(), 16);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        // Pinned so the format stays stable across releases
+        assert_eq!(block_fingerprint(&[]), "cbf29ce484222325");
+    }
+
+    #[test]
+    fn test_correct_lines_reports_block_fingerprints() {
+        // Extending the borders changes more than whitespace, so the
+        // report must hash the block as it was read (as `extract` does)
+        let lines = to_lines("intro\n\n+---+\n| abc |\n+---+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines.clone(), &make_test_config(), &console, &styles);
+        assert_eq!(stats.blocks.len(), 1);
+        assert_eq!((stats.blocks[0].start, stats.blocks[0].end), (2, 5));
+        assert_ne!(output[2], lines[2]);
+        assert_eq!(stats.blocks[0].fingerprint, block_fingerprint(&lines[2..5]));
+    }
+
+    #[test]
+    fn test_correct_lines_reports_block_detail() {
+        let lines = to_lines("┌──────┐\n│ ab │\n└──────┘\n\ntext\n\n+--+\n| x|\n+--+\n");
+        let mut config = make_test_config();
+        config.lines = Some(vec![LineRange { start: 1, end: 3 }]);
+        config.min_score = 0.75;
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (_, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(stats.blocks.len(), 2);
+
+        let first = &stats.blocks[0];
+        assert_eq!(first.status, BlockStatus::Modified);
+        assert_eq!(first.style, Some(BorderStyle::Light));
+        assert_eq!(first.target_column, Some(8));
+        assert_eq!(first.revisions.len(), 1);
+        assert_eq!(first.revisions[0].line, 2);
+        assert!(first.skipped.is_empty());
+
+        let second = &stats.blocks[1];
+        assert_eq!(second.status, BlockStatus::Skipped);
+        assert_eq!(second.style, Some(BorderStyle::Ascii));
+        assert!(second.revisions.is_empty());
+
+        let json = serde_json::to_value(JsonBlock::from_report(first)).unwrap();
+        assert_eq!(json["style"], "light");
+        assert_eq!(json["status"], "modified");
+        assert_eq!(json["revisions"][0]["kind"], "pad");
+    }
+
+    #[test]
+    fn test_correct_lines_reports_skipped_revisions_once() {
+        let lines = to_lines("+------+\n| ab |\n+------+\n");
+        let mut config = make_test_config();
+        config.min_score = 0.9;
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (_, stats) = correct_lines(lines, &config, &console, &styles);
+        let block = &stats.blocks[0];
+        assert_eq!(block.status, BlockStatus::Unchanged);
+        assert_eq!(block.skipped.len(), 1);
+        assert_eq!(block.skipped[0].line, 2);
+        assert_eq!(block.skipped[0].kind, "pad");
+    }
+
+    // =========================================================================
+    // Named block tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_directive() {
+        assert_eq!(
+            parse_directive("<!-- aadc:block name=arch -->"),
+            Some(("block", "name=arch"))
+        );
+        assert_eq!(parse_directive("# aadc:off"), Some(("off", "")));
+        assert_eq!(parse_directive("  // aadc:width 72"), Some(("width", "72")));
+        assert_eq!(
+            parse_directive("/* aadc:block name=x */"),
+            Some(("block", "name=x"))
+        );
+        assert_eq!(parse_directive("-- aadc:on"), Some(("on", "")));
+        assert_eq!(parse_directive("<!-- just a comment -->"), None);
+        assert_eq!(parse_directive("aadc:block name=x"), None);
+    }
+
+    #[test]
+    fn test_block_annotations_name() {
+        let lines = to_lines(
+            "<!-- aadc:block name=arch -->\n```text\n+---+\n| a |\n+---+\n```\n\ntext\n\n+---+\n| b |\n+---+\n",
+        );
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].start, 2);
+        assert_eq!(blocks[0].annotations.name.as_deref(), Some("arch"));
+        assert_eq!(blocks[1].annotations.name, None);
+    }
+
+    #[test]
+    fn test_is_valid_block_name() {
+        assert!(is_valid_block_name("arch-v2.1"));
+        assert!(!is_valid_block_name("../etc"));
+        assert!(!is_valid_block_name("a/b"));
+        assert!(!is_valid_block_name(""));
+    }
+
+    #[test]
+    fn test_correct_lines_block_name_targets_one_block() {
+        let lines =
+            to_lines("# aadc:block name=fix\n+---+\n| a|\n+---+\n\ntext\n\n+---+\n| b|\n+---+\n");
+        let mut config = make_test_config();
+        config.block_name = Some("fix".to_string());
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[2], "| a |");
+        assert_eq!(output[8], "| b|");
+        assert_eq!(stats.blocks_skipped, 1);
+        assert_eq!(stats.blocks[0].name.as_deref(), Some("fix"));
+    }
+
+    #[test]
+    fn test_extract_and_inject_by_block_name() {
+        let temp = tempfile::tempdir().unwrap();
+        let source = temp.path().join("doc.md");
+        fs::write(
+            &source,
+            "+---+\n| a |\n+---+\n\ntext\n\n<!-- aadc:block name=flow -->\n+---+\n| b |\n+---+\n",
+        )
+        .unwrap();
+        let out_dir = temp.path().join("out");
+        let mut config = make_test_config();
+        config.block_name = Some("flow".to_string());
+
+        run_extract_command(&source, &out_dir, false, &config).unwrap();
+        assert_eq!(
+            fs::read_to_string(out_dir.join("flow.txt")).unwrap(),
+            "+---+\n| b |\n+---+\n"
+        );
+        assert!(!out_dir.join("block-001.txt").exists());
+
+        let replacement = temp.path().join("new.txt");
+        fs::write(&replacement, "+---+\n| c |\n+---+\n").unwrap();
+        run_inject_command(&source, None, &replacement, &config).unwrap();
+        let updated = fs::read_to_string(&source).unwrap();
+        assert!(updated.contains("| a |"));
+        assert!(updated.ends_with("<!-- aadc:block name=flow -->\n+---+\n| c |\n+---+\n"));
+    }
+
+    // =========================================================================
+    // Width lock tests
+    // =========================================================================
+
+    #[test]
+    fn test_block_annotations_width() {
+        let lines = to_lines("<!-- aadc:width 12 -->\n+---+\n| a |\n+---+\n");
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks[0].annotations.width, Some(12));
+
+        let lines = to_lines("# aadc:width wide\n+---+\n| a |\n+---+\n");
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks[0].annotations.width, None);
+    }
+
+    #[test]
+    fn test_correct_lines_width_lock_pins_target() {
+        let lines = to_lines("# aadc:width 10\n  +--------+\n  | a|\n  | bb |\n  +--------+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) = correct_lines(lines, &make_test_config(), &console, &styles);
+        // Width is measured from the block's left edge (column 2)
+        assert_eq!(output[2], "  | a      |");
+        assert_eq!(output[3], "  | bb     |");
+        assert_eq!(output[1], "  +--------+");
+    }
+
+    #[test]
+    fn test_correct_lines_width_lock_never_shrinks() {
+        let lines = to_lines("# aadc:width 5\n+-------+\n| a |\n| long  |\n+-------+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines.clone(), &make_test_config(), &console, &styles);
+        assert_eq!(output, lines);
+        assert_eq!(stats.total_revisions, 0);
+    }
+
+    // =========================================================================
+    // Per-block threshold annotation tests
+    // =========================================================================
+
+    #[test]
+    fn test_block_annotations_min_score() {
+        let parse = |text: &str| {
+            find_diagram_blocks(&to_lines(text), &DetectOptions::default())[0]
+                .annotations
+                .min_score
+        };
+        assert_eq!(
+            parse("<!-- aadc:preset strict -->\n+---+\n| a |\n+---+\n"),
+            Some(0.8)
+        );
+        assert_eq!(
+            parse("# aadc:min-score 0.9\n+---+\n| a |\n+---+\n"),
+            Some(0.9)
+        );
+        assert_eq!(parse("# aadc:min-score 2\n+---+\n| a |\n+---+\n"), None);
+        assert_eq!(parse("# aadc:preset bogus\n+---+\n| a |\n+---+\n"), None);
+    }
+
+    #[test]
+    fn test_correct_lines_min_score_annotation_applies_to_next_block_only() {
+        // Padding 4 columns on a strong line scores 0.6: above 0.5, below 0.8
+        let lines = to_lines(
+            "<!-- aadc:preset strict -->\n+-------+\n| a |\n+-------+\n\ntext\n\n+-------+\n| b |\n+-------+\n",
+        );
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, _stats) = correct_lines(lines, &make_test_config(), &console, &styles);
+        assert_eq!(output[2], "| a |");
+        assert_eq!(output[8], "| b     |");
+    }
+
+    // =========================================================================
+    // Revision breakdown tests
+    // =========================================================================
+
+    #[test]
+    fn test_revision_breakdown_record_and_merge() {
+        let mut a = RevisionBreakdown::default();
+        a.record("pad", 0.85, true);
+        a.record("pad", 0.3, false);
+        a.record("add_border", 1.0, true);
+        a.record("add_border", -0.2, false);
+
+        assert_eq!(
+            a.by_kind["pad"],
+            RevisionCounts {
+                applied: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(a.by_decile[8].applied, 1);
+        assert_eq!(a.by_decile[9].applied, 1);
+        assert_eq!(a.by_decile[3].skipped, 1);
+        assert_eq!(a.by_decile[0].skipped, 1);
+
+        let mut b = RevisionBreakdown::default();
+        b.record("pad", 0.9, true);
+        a.merge(&b);
+        assert_eq!(a.by_kind["pad"].applied, 2);
+        assert_eq!(a.by_decile[9].applied, 2);
+    }
+
+    #[test]
+    fn test_correct_lines_records_revision_kinds() {
+        let lines = to_lines("+-----+\n| a |\n| b\n+-----+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (_output, stats) = correct_lines(lines, &make_test_config(), &console, &styles);
+        let kinds = &stats.breakdown.by_kind;
+        let total_applied: usize = kinds.values().map(|c| c.applied).sum();
+        assert_eq!(total_applied, stats.total_revisions);
+        assert!(kinds["pad"].applied >= 1);
+    }
+
+    #[test]
+    fn test_processing_stats_json_breakdown() {
+        let mut stats = Stats::default();
+        stats.breakdown.record("pad", 0.75, true);
+        let json = serde_json::to_value(ProcessingStats::from_stats(&stats)).unwrap();
+
+        assert_eq!(json["revision_kinds"]["pad"]["applied"], 1);
+        assert_eq!(json["score_deciles"][0]["range"], "0.7-0.8");
+        assert_eq!(json["score_deciles"].as_array().unwrap().len(), 1);
+    }
+
+    // =========================================================================
+    // Max line length tests
+    // =========================================================================
+
+    #[test]
+    fn test_revision_resulting_width() {
+        let pad = Revision::PadBeforeSuffixBorder {
+            line_idx: 3,
+            spaces_to_add: 2,
+            target_column: 9,
+        };
+        assert_eq!(pad.resulting_width(), 10);
+        assert_eq!(pad.line_idx(), 3);
+    }
+
+    #[test]
+    fn test_correct_lines_max_line_length_refuses_revisions() {
+        let lines = to_lines("+--------+\n| a      |\n| b |\n| c  |\n+-----+\n");
+        let mut config = make_test_config();
+        config.max_line_length = Some(8);
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+        // Target column is 9 (width 10), so every revision is refused
+        assert_eq!(output[2], "| b |");
+        assert_eq!(output[4], "+-----+");
+        assert_eq!(stats.total_revisions, 0);
+        assert_eq!(stats.revisions_refused, 3);
+        assert_eq!(stats.refused_lines, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_correct_lines_max_line_length_allows_fitting_revisions() {
+        let lines = to_lines("+-----+\n| a |\n+-----+\n");
+        let mut config = make_test_config();
+        config.max_line_length = Some(7);
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[1], "| a   |");
+        assert_eq!(stats.revisions_refused, 0);
+    }
+
+    #[test]
+    fn test_correct_lines_skips_lines_past_max_line_bytes() {
+        let long = format!("| {} |", "x".repeat(40));
+        let input = format!("+-----+\n| a |\n{}\n+-----+\n", long);
+        let mut config = make_test_config();
+        config.max_line_bytes = 32;
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (output, stats) = correct_lines(to_lines(&input), &config, &console, &styles);
+        // The long row neither sets the target nor gets padded
+        assert_eq!(output[2], long);
+        assert_eq!(stats.long_lines, vec![2]);
+
+        config.max_line_bytes = 0;
+        let (_, stats) = correct_lines(to_lines(&input), &config, &console, &styles);
+        assert!(stats.long_lines.is_empty());
+    }
+
+    #[test]
+    fn test_validate_args_max_line_length_zero() {
+        let mut args = make_args();
+        args.max_line_length = Some(0);
+        assert!(validate_args(&args).is_err());
+        args.max_line_length = Some(120);
+        assert!(validate_args(&args).is_ok());
+    }
+
+    // =========================================================================
+    // Lint tests
+    // =========================================================================
+
+    #[test]
+    fn test_lint_lines_reports_rule_diagnostics() {
+        let lines = to_lines("+-----+\n| a |\n| b\n+-----+\n");
+        let diagnostics = lint_lines(&lines, &make_test_config());
+
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].rule, "AD001");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 5));
+        assert_eq!(
+            diagnostics[0].message,
+            "right border at column 5, expected column 7"
+        );
+        assert_eq!(diagnostics[1].rule, "AD002");
+        assert_eq!(diagnostics[1].name, "unclosed-box");
+        assert_eq!((diagnostics[1].line, diagnostics[1].column), (3, 4));
+    }
+
+    #[test]
+    fn test_lint_lines_clean_diagram() {
+        let lines = to_lines("+-----+\n| a   |\n+-----+\n");
+        assert!(lint_lines(&lines, &make_test_config()).is_empty());
+    }
+
+    #[test]
+    fn test_lint_lines_severity_overrides() {
+        let lines = to_lines("+-----+\n| a |\n| b\n+-----+\n");
+        let mut config = make_test_config();
+        config.lint_severities.insert("AD001", Severity::Off);
+        config.lint_severities.insert("AD002", Severity::Error);
+
+        let diagnostics = lint_lines(&lines, &config);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "AD002");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_parse_lint_severities_accepts_ids_and_names() {
+        let table = BTreeMap::from([
+            ("ad001".to_string(), Severity::Warning),
+            ("unclosed-box".to_string(), Severity::Off),
+        ]);
+        let severities = parse_lint_severities(&table).unwrap();
+        assert_eq!(severities["AD001"], Severity::Warning);
+        assert_eq!(severities["AD002"], Severity::Off);
+
+        let table = BTreeMap::from([("AD999".to_string(), Severity::Error)]);
+        assert!(parse_lint_severities(&table).is_err());
+    }
+
+    #[test]
+    fn test_load_config_lint_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".aadcrc");
+        fs::write(&path, "[lint]\nmisaligned-right-border = \"warning\"\n").unwrap();
+        let file_config = load_config_file(&path).unwrap();
+        let table = file_config.lint.unwrap();
+        assert_eq!(table["misaligned-right-border"], Severity::Warning);
+
+        fs::write(&path, "[lint]\nAD042 = \"error\"\n").unwrap();
+        assert!(load_config_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_load_config_scoring_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".aadcrc");
+        fs::write(&path, "[scoring]\npad_base = 0.6\n").unwrap();
+        let weights = load_config_file(&path).unwrap().scoring.unwrap();
+        assert_eq!(weights.pad_base, 0.6);
+        assert_eq!(weights.add_border_base, 0.5);
+
+        fs::write(&path, "[scoring]\npad_base = 1.5\n").unwrap();
+        assert!(load_config_file(&path).is_err());
+        fs::write(&path, "[scoring]\npad_penalty_divisor = 0.0\n").unwrap();
+        assert!(load_config_file(&path).is_err());
+        fs::write(&path, "[scoring]\npad_bias = 0.1\n").unwrap();
+        assert!(load_config_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_scoring_weights_change_decisions() {
+        let input = "+------+\n| ab |\n+------+\n";
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (corrected, _) = correct_lines(to_lines(input), &make_test_config(), &console, &styles);
+        assert_eq!(corrected[1], "| ab   |");
+
+        let mut config = make_test_config();
+        config.scoring.pad_base = 0.2;
+        let (corrected, _) = correct_lines(to_lines(input), &config, &console, &styles);
+        assert_eq!(corrected[1], "| ab |");
+    }
+
+    #[test]
+    fn test_processing_stats_json_revision_rules() {
+        let mut stats = Stats::default();
+        stats.breakdown.record("pad", 0.75, true);
+        stats.breakdown.record("extend_fill", 0.9, true);
+        stats.breakdown.record("add_border", 0.6, false);
+        stats.breakdown.record("align_cells", 1.0, true);
+        let json = serde_json::to_value(ProcessingStats::from_stats(&stats)).unwrap();
+
+        assert_eq!(json["revision_rules"]["AD001"]["applied"], 2);
+        assert_eq!(json["revision_rules"]["AD002"]["skipped"], 1);
+        assert_eq!(json["revision_rules"].as_object().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_lint_lines_mixed_border_style() {
+        let lines = to_lines("┌─────┐\n│ a   │\n+-----+\n");
+        let diagnostics = lint_lines(&lines, &make_test_config());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "AD003");
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 1));
+        assert_eq!(
+            diagnostics[0].message,
+            "ASCII border '+' in a block drawn with light borders"
+        );
+    }
+
+    #[test]
+    fn test_mixed_style_lines_light_and_double() {
+        let lines = to_lines("╔═════╗\n║ a   ║\n║ b   │\n╚═════╝\n");
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        let found = mixed_style_lines(&lines, &blocks[0]);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].0, found[0].1), (2, 7));
+        assert!(found[0].2.starts_with("light border '│'"));
+    }
+
+    #[test]
+    fn test_mixed_style_lines_ignores_ascii_in_labels() {
+        let lines = to_lines("┌───────┐\n│ a + b │\n│ x|y   │\n└───────┘\n");
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert!(mixed_style_lines(&lines, &blocks[0]).is_empty());
+    }
+
+    #[test]
+    fn test_lint_lines_trailing_whitespace() {
+        let lines = to_lines("+-----+  \n| a   |\t\n| b     \n+-----+\n");
+        let diagnostics: Vec<_> = lint_lines(&lines, &make_test_config())
+            .into_iter()
+            .filter(|d| d.rule == "AD004")
+            .collect();
+
+        // Line 3 has no closing border, so its whitespace is not ours
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 8));
+        assert_eq!(diagnostics[1].line, 2);
+    }
+
+    #[test]
+    fn test_fix_lint_diagnostics_trims_after_border_only() {
+        let mut lines = to_lines("+-----+  \n| a   | \n+-----+\n");
+        let diagnostics = lint_lines(&lines, &make_test_config());
+        let (remaining, fixed) = fix_lint_diagnostics(&mut lines, diagnostics);
+
+        assert_eq!(fixed, 2);
+        assert!(remaining.is_empty());
+        assert_eq!(lines, to_lines("+-----+\n| a   |\n+-----+\n"));
+    }
+
+    // =========================================================================
+    // Left border alignment tests
+    // =========================================================================
+
+    #[test]
+    fn test_align_left_border_indents_under_indented_rows() {
+        let input = "  +--------+\n  | alpha  |\n | beta  |\n  | gamma  |\n  +--------+\n";
+        let (corrected, stats) = correct(to_lines(input), &make_test_config());
+
+        assert_eq!(
+            corrected,
+            to_lines(&input.replace(" | beta  |", "  | beta   |"))
+        );
+        let kinds: Vec<_> = stats.revisions.iter().map(|r| r.kind).collect();
+        assert!(kinds.contains(&"align_left"), "kinds={:?}", kinds);
+    }
+
+    #[test]
+    fn test_align_left_border_needs_a_dominant_column() {
+        // Two rows at each column: no column wins, so nothing is indented
+        let lines = make_analyzed_lines(&[" +---+", " |   |", "+---+", "|   |"]);
+        let config = make_test_config();
+        assert_eq!(left_border_shifts(&lines, 5, &config), vec![0; 4]);
+
+        // A lone `|` is a connector, not a box row
+        let lines = make_analyzed_lines(&["  +--+", "  |  |", "  +--+", " |"]);
+        assert_eq!(left_border_shifts(&lines, 5, &config), vec![0; 4]);
+
+        // Shifting must not push the right border past the block's edge
+        let lines = make_analyzed_lines(&[" +--+", " |  |", "| wide |", " +--+"]);
+        assert_eq!(left_border_shifts(&lines, 4, &config), vec![0; 4]);
+    }
+
+    #[test]
+    fn test_align_left_border_score_and_apply() {
+        let analyzed = make_analyzed_lines(&[" +---+", " |   |", "|   |", " +---+"]);
+        let rev = Revision::AlignLeftBorder {
+            line_idx: 2,
+            spaces_to_add: 1,
+            target_column: 1,
+            new_width: 6,
+        };
+        // 0.6 base + 0.3 * 3/4 agreeing - 0.1 for one column
+        let score = rev.score(&analyzed, 0, &ScoringWeights::default());
+        assert!((score - 0.725).abs() < 1e-9, "score={}", score);
+        assert_eq!(rev.kind(), "align_left");
+        assert_eq!(rev.resulting_width(), 6);
+
+        let mut lines = to_lines(" +---+\n |   |\n|   |\n +---+\n");
+        rev.apply(&mut lines);
+        assert_eq!(lines[2], " |   |");
+    }
+
+    #[test]
+    fn test_align_left_border_below_threshold_pads_right_edge() {
+        let input = "    +------+\n    | a    |\n| b  |\n    +------+\n";
+        let mut config = make_test_config();
+        config.scoring.align_left_base = 0.1;
+        let (corrected, stats) = correct(to_lines(input), &config);
+
+        // The indent is refused, so the right border closes the whole gap
+        assert_eq!(corrected[2], "| b        |");
+        assert!(stats.revisions.iter().all(|r| r.kind != "align_left"));
+    }
+
+    #[test]
+    fn test_lint_lines_misaligned_left_border() {
+        let lines = to_lines("  +-----+\n  | a   |\n | b   |\n  +-----+\n");
+        let diagnostics: Vec<_> = lint_lines(&lines, &make_test_config())
+            .into_iter()
+            .filter(|d| d.rule == "AD005")
+            .collect();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (3, 2));
+        assert!(diagnostics[0].message.contains("expected column 3"));
+    }
+
+    // =========================================================================
+    // Column separator alignment tests
+    // =========================================================================
+
+    #[test]
+    fn test_interior_separators() {
+        let columns = |line: &str| {
+            interior_separators(&analyze_line(line, &DetectOptions::default())).map(|seps| {
+                seps.into_iter()
+                    .map(|(column, _)| column)
+                    .collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(columns("| a | bb |"), Some(vec![4]));
+        assert_eq!(columns("+---+----+"), Some(vec![4]));
+        assert_eq!(columns("├──┼───┤"), Some(vec![3]));
+        // A `+` between spaces is text, not a junction
+        assert_eq!(columns("| a + b |"), Some(vec![]));
+        assert_eq!(columns("no borders"), None);
+    }
+
+    #[test]
+    fn test_correct_aligns_interior_separators() {
+        let input = "+-----+------+\n| a | bb |\n| ccc | d |\n+-----+------+\n";
+        let (corrected, stats) = correct(to_lines(input), &make_test_config());
+        assert_eq!(
+            corrected,
+            to_lines("+-----+------+\n| a   | bb   |\n| ccc | d    |\n+-----+------+\n")
+        );
+        assert!(stats.revisions.iter().any(|r| r.kind == "align_separator"));
+
+        // Border rows are widened with their own fill
+        let input = "┌──┬──┐\n│ name │ v │\n└──┴──┘\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected[0], "┌──────┬───┐");
+        assert_eq!(corrected[2], "└──────┴───┘");
+    }
+
+    #[test]
+    fn test_separator_pads_need_matching_neighbours() {
+        let config = make_test_config();
+        // A spanning row has no separators, so it splits the runs and
+        // each single row is left alone
+        let lines = make_analyzed_lines(&["| a | bb |", "| spanning |", "| ccc | d |"]);
+        assert!(separator_pads(&lines, &config).iter().all(Vec::is_empty));
+
+        let lines = make_analyzed_lines(&["| a | b | c |", "| aa | bbb | c |"]);
+        let pads = separator_pads(&lines, &config);
+        assert_eq!(pads[0], vec![(4, ' ', 1, 5), (8, ' ', 2, 11)]);
+        assert!(pads[1].is_empty());
+    }
+
+    #[test]
+    fn test_lint_lines_misaligned_separator() {
+        let lines = to_lines("+-----+----+\n| a | b    |\n+-----+----+\n");
+        let diagnostics: Vec<_> = lint_lines(&lines, &make_test_config())
+            .into_iter()
+            .filter(|d| d.rule == "AD006")
+            .collect();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 5));
+        assert!(diagnostics[0].message.contains("expected column 7"));
+    }
+
+    // =========================================================================
+    // Corner repair tests
+    // =========================================================================
+
+    #[test]
+    fn test_misplaced_corner() {
+        let corner = |line: &str| misplaced_corner(&analyze_line(line, &DetectOptions::default()));
+        assert_eq!(corner("+----|"), Some(('+', '-')));
+        assert_eq!(corner("  └───│"), Some(('┘', '─')));
+        assert_eq!(corner("╠══║"), Some(('╣', '═')));
+        // Already closed, or the bar ends text rather than a border run
+        assert_eq!(corner("+----+"), None);
+        assert_eq!(corner("+-- x |"), None);
+        assert_eq!(corner("| text |"), None);
+    }
+
+    #[test]
+    fn test_repair_corners_is_opt_in() {
+        let input = "┌────│\n│ abcdef │\n└──────┘\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected[0], "┌────    │");
+
+        let mut config = make_test_config();
+        config.repair_corners = true;
+        let (corrected, stats) = correct(to_lines(input), &config);
+        assert_eq!(corrected, to_lines("┌────────┐\n│ abcdef │\n└────────┘\n"));
+        let replaced: Vec<_> = stats
+            .revisions
+            .iter()
+            .filter(|r| r.kind == "replace_corner")
+            .collect();
+        assert_eq!(replaced.len(), 1);
+        assert_eq!(replaced[0].description, "replace '│' with '┐' at column 6");
+    }
+
+    #[test]
+    fn test_repair_corners_from_config_file() {
+        let mut config = make_test_config();
+        let file_config: FileConfig = toml::from_str("repair_corners = true").unwrap();
+        apply_file_config(&mut config, &make_args(), file_config);
+        assert!(config.repair_corners);
+    }
+
+    // =========================================================================
+    // Box completion tests
+    // =========================================================================
+
+    #[test]
+    fn test_complete_boxes_inserts_bottom_border() {
+        let input = "Intro\n\n┌── Title ─┬───┐\n│ a        │ b │\n│ cc       │ d │\n\nAfter\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected, to_lines(input), "off by default");
+
+        let mut config = make_test_config();
+        config.complete_boxes = true;
+        let (corrected, stats) = correct(to_lines(input), &config);
+        assert_eq!(corrected.len(), 8);
+        assert_eq!(corrected[5], "└──────────┴───┘");
+        assert_eq!(corrected[7], "After");
+        assert_eq!(stats.blocks_modified, 1);
+        assert_eq!((stats.blocks[0].start, stats.blocks[0].end), (2, 6));
+        assert_eq!(stats.breakdown.by_kind["complete_box"].applied, 1);
+    }
+
+    #[test]
+    fn test_find_open_box_is_conservative() {
+        let mut config = make_test_config();
+        config.complete_boxes = true;
+        let open = |input: &str| {
+            let lines = to_lines(input);
+            let blocks = find_diagram_blocks(&lines, &config.detect_options());
+            find_open_box(&lines, &blocks[0], &config).map(|b| (b.border, b.score))
+        };
+
+        let (border, score) = open("  +---+\n  | a |\n  | b |\n").unwrap();
+        assert_eq!(border, "  +---+");
+        assert!((score - 0.6).abs() < 1e-9);
+        // An unclosed row lowers the score below the default threshold
+        let (_, score) = open("+---+\n| a |\n| b\n").unwrap();
+        assert!(score < 0.5, "score={}", score);
+
+        // Already closed, or rows that are not under the top corner
+        assert!(open("+---+\n| a |\n+---+\n").is_none());
+        assert!(open("+---+\n  | a |\n").is_none());
+        assert!(open("| a |\n| b |\n").is_none());
+    }
+
+    // =========================================================================
+    // Shrink tests
+    // =========================================================================
+
+    #[test]
+    fn test_allow_shrink_trims_over_padded_border() {
+        let input = "+--------+\n| x            |\n| longer |\n+--------+\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected[1], "| x            |", "insert-only by default");
+
+        let mut config = make_test_config();
+        config.allow_shrink = true;
+        let (corrected, stats) = correct(to_lines(input), &config);
+        assert_eq!(
+            corrected,
+            to_lines("+--------+\n| x      |\n| longer |\n+--------+\n")
+        );
+        let shrunk: Vec<_> = stats
+            .revisions
+            .iter()
+            .filter(|r| r.kind == "shrink")
+            .collect();
+        assert_eq!(shrunk.len(), 1);
+        assert_eq!(
+            shrunk[0].description,
+            "remove 6 space(s) before border (to column 10)"
+        );
+    }
+
+    #[test]
+    fn test_allow_shrink_never_removes_content() {
+        let mut config = make_test_config();
+        config.allow_shrink = true;
+        // The long row cannot shrink past its text, so the rest pad out to it
+        let input = "+----------+\n| a        |\n| text here    |\n+----------+\n";
+        let (corrected, _) = correct(to_lines(input), &config);
+        assert_eq!(
+            corrected,
+            to_lines("+-----------+\n| a         |\n| text here |\n+-----------+\n")
+        );
+    }
+
+    #[test]
+    fn test_allow_shrink_from_config_file() {
+        let mut config = make_test_config();
+        let file_config: FileConfig = toml::from_str("allow_shrink = true").unwrap();
+        apply_file_config(&mut config, &make_args(), file_config);
+        assert!(config.allow_shrink);
+    }
+
+    // =========================================================================
+    // Padding limit tests
+    // =========================================================================
+
+    #[test]
+    fn test_max_pad_skips_large_insertions() {
+        // A stray far-right border sets a bogus single target
+        let input = "+---+\n| a |\n| b                   |\n+---+\n";
+        let mut config = make_test_config();
+        config.single_target = true;
+        config.max_pad = Some(8);
+        let (corrected, stats) = correct(to_lines(input), &config);
+        assert_eq!(corrected, to_lines(input));
+        assert_eq!(stats.revisions_skipped, 3);
+
+        config.max_pad = None;
+        let (corrected, _) = correct(to_lines(input), &config);
+        assert_eq!(corrected[1], "| a                   |");
+    }
+
+    #[test]
+    fn test_max_pad_from_config_file() {
+        let mut config = make_test_config();
+        let file_config: FileConfig = toml::from_str("max_pad = 12").unwrap();
+        apply_file_config(&mut config, &make_args(), file_config);
+        assert_eq!(config.max_pad, Some(12));
+
+        let tmp = write_temp_config("max_pad = 0\n");
+        assert!(load_config_file(tmp.path()).is_err());
+    }
+
+    // =========================================================================
+    // Tree diagram tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_tree_line() {
+        assert_eq!(
+            parse_tree_line("├── main.rs"),
+            Some(TreeLine {
+                guides: vec![],
+                branch: Some((0, 4))
+            })
+        );
+        assert_eq!(
+            parse_tree_line("│   └── b.rs"),
+            Some(TreeLine {
+                guides: vec![0],
+                branch: Some((4, 8))
+            })
+        );
+        assert_eq!(
+            parse_tree_line("|   `-- c"),
+            Some(TreeLine {
+                guides: vec![0],
+                branch: Some((4, 8))
+            })
+        );
+        assert_eq!(
+            parse_tree_line("│"),
+            Some(TreeLine {
+                guides: vec![0],
+                branch: None
+            })
+        );
+        // Boxes are not trees
+        assert_eq!(parse_tree_line("+---+"), None);
+        assert_eq!(parse_tree_line("| a |"), None);
+        assert_eq!(parse_tree_line("|- a"), None);
+    }
+
+    #[test]
+    fn test_tree_blocks_are_detected() {
+        let lines = to_lines("src/\n├── main.rs\n├── lib/\n│   └── a.rs\n└── README.md\n");
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].kind, BlockKind::Tree);
+
+        let lines = to_lines("+---+\n| a |\n+---+\n");
+        let blocks = find_diagram_blocks(&lines, &DetectOptions::default());
+        assert_eq!(blocks[0].kind, BlockKind::Boxes);
+    }
+
+    #[test]
+    fn test_tree_pads_follow_parents() {
+        // `lib/` is one column short; its children and their guide move
+        // with it
+        let lines = to_lines("├── a\n│  ├── lib/\n│  │   └── x\n│  └── b\n└── c\n");
+        let pads = tree_pads(&lines);
+        assert!(pads[0].is_empty());
+        assert_eq!(pads[1], vec![(3, 1, 4)]);
+        assert_eq!(pads[2], vec![(3, 1, 4)]);
+        assert_eq!(pads[3], vec![(3, 1, 4)]);
+        assert!(pads[4].is_empty());
+    }
+
+    #[test]
+    fn test_correct_aligns_trees_without_borders() {
+        let input = "src/\n├── main.rs\n├── lib/\n│  ├── a.rs\n│  └── b.rs\n└── README.md\n";
+        let (corrected, stats) = correct(to_lines(input), &make_test_config());
+        assert_eq!(
+            corrected,
+            to_lines("src/\n├── main.rs\n├── lib/\n│   ├── a.rs\n│   └── b.rs\n└── README.md\n")
+        );
+        assert!(stats.revisions.iter().all(|r| r.kind == "align_tree"));
+
+        // Over-indented lines are left alone
+        let input = "├── a\n│     └── deep\n└── b\n";
+        let (corrected, _) = correct(to_lines(input), &make_test_config());
+        assert_eq!(corrected, to_lines(input));
+    }
+
+    // =========================================================================
+    // Border conversion tests
+    // =========================================================================
+
+    #[test]
+    fn test_convert_lines_to_unicode() {
+        let input = to_lines(
+            "Use a-b in text.\n\n+-----+-----+\n| a-b | c   |\n+-----+-----+\n| d   | e   |\n+-----+-----+\n",
+        );
+        let converted = convert_lines(input, ConvertTarget::Unicode, &make_test_config());
+        assert_eq!(converted[0], "Use a-b in text.");
+        assert_eq!(converted[2], "┌─────┬─────┐");
+        assert_eq!(converted[3], "│ a-b │ c   │");
+        assert_eq!(converted[4], "├─────┼─────┤");
+        assert_eq!(converted[6], "└─────┴─────┘");
+    }
+
+    #[test]
+    fn test_convert_lines_arrows_and_labels() {
+        let input = to_lines("+---+    +---+\n| A |--->| B |\n+---+    +---+\n| C++ x + y |\n");
+        let converted = convert_lines(input, ConvertTarget::Unicode, &make_test_config());
+        assert_eq!(converted[0], "┌───┐    ┌───┐");
+        assert_eq!(converted[1], "│ A │───▶│ B │");
+        assert_eq!(converted[3], "│ C++ x + y │");
+    }
+
+    #[test]
+    fn test_convert_lines_to_ascii() {
+        let input = to_lines("╔═══╗\n┃ a ┃ ──▶\n┌───┬───┐\n│ b │ c │\n└───┴───┘\n");
+        let converted = convert_lines(input, ConvertTarget::Ascii, &make_test_config());
+        assert_eq!(
+            converted,
+            to_lines("+===+\n| a | -->\n+---+---+\n| b | c |\n+---+---+\n")
+        );
+    }
+
+    #[test]
+    fn test_convert_after_correct_aligns() {
+        let (corrected, _stats) =
+            correct(to_lines("+------+\n| ab|\n+------+\n"), &make_test_config());
+        let converted = convert_lines(corrected, ConvertTarget::Unicode, &make_test_config());
+        assert_eq!(converted, to_lines("┌──────┐\n│ ab   │\n└──────┘\n"));
+    }
+
+    #[test]
+    fn test_run_convert_command_keeps_crlf() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("doc.md");
+        fs::write(&path, "text\r\n+------+\r\n| ab|\r\n+------+\r\n").unwrap();
+
+        run_convert_command(
+            std::slice::from_ref(&path),
+            ConvertTarget::Unicode,
+            true,
+            &make_test_config(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "text\r\n┌──────┐\r\n│ ab   │\r\n└──────┘\r\n"
+        );
+    }
+
+    #[test]
+    fn test_run_convert_command_keeps_bom() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("doc.md");
+        fs::write(&path, "\u{feff}+------+\n| ab   |\n+------+\n").unwrap();
+
+        run_convert_command(
+            std::slice::from_ref(&path),
+            ConvertTarget::Unicode,
+            true,
+            &make_test_config(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "\u{feff}┌──────┐\n│ ab   │\n└──────┘\n"
+        );
+    }
+
+    // =========================================================================
+    // Structured error tests
+    // =========================================================================
+
+    #[test]
+    fn test_error_kind_classification() {
+        let parse: anyhow::Error = ParseError("binary".to_string()).into();
+        let args: anyhow::Error = ArgError("bad".to_string()).into();
+        let io = anyhow::Error::from(io::Error::from(io::ErrorKind::NotFound))
+            .context("Failed to read file: x.md");
+        let other = anyhow::anyhow!("something else");
+
+        assert_eq!(error_kind(&parse), "parse");
+        assert_eq!(error_kind(&args), "invalid_args");
+        assert_eq!(error_kind(&io), "io");
+        assert_eq!(error_kind(&other), "error");
+    }
+
+    #[test]
+    fn test_config_json_errors() {
+        let mut config = make_test_config();
+        assert!(!config.json_errors());
+        config.error_format = ErrorFormat::Json;
+        assert!(config.json_errors());
+        config.error_format = ErrorFormat::Text;
+        config.json = true;
+        assert!(config.json_errors());
+    }
+
+    // =========================================================================
+    // Concurrent modification tests
+    // =========================================================================
+
+    #[test]
+    fn test_ensure_unchanged_detects_concurrent_edit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.md");
+        fs::write(&path, "+---+\n| a|\n+---+\n").unwrap();
+        let mut config = make_test_config();
+        let (_source, snapshot) = read_file_with_snapshot(&path, &config).unwrap();
+
+        let current = fs::read(&path).unwrap();
+        assert!(ensure_unchanged(&path, &current, Some(&snapshot), &config).is_ok());
+
+        fs::write(&path, "+---+\n| b|\n+---+\n").unwrap();
+        let err = write_in_place(&path, "x\n", Some(&snapshot), None, &config).unwrap_err();
+        assert!(err.to_string().contains("--force-write"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "+---+\n| b|\n+---+\n");
+
+        config.force_write = true;
+        write_in_place(&path, "x\n", Some(&snapshot), None, &config).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "x\n");
+    }
+
+    #[test]
+    fn test_file_snapshot_compares_content() {
+        assert_eq!(FileSnapshot::of(b"abc"), FileSnapshot::of(b"abc"));
+        assert_ne!(FileSnapshot::of(b"abc"), FileSnapshot::of(b"abd"));
+        assert_ne!(FileSnapshot::of(b"abc"), FileSnapshot::of(b"abc\n"));
+    }
+
+    #[test]
+    fn test_write_in_place_waits_for_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.md");
+        fs::write(&path, "before\n").unwrap();
+
+        let holder = fs::File::open(&path).unwrap();
+        holder.lock_exclusive().unwrap();
+
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            write_in_place(&writer_path, "after\n", None, None, &make_test_config()).unwrap();
+        });
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "before\n");
+
+        drop(holder);
+        writer.join().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "after\n");
+    }
+
+    #[test]
+    fn test_write_in_place_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("doc.md");
+        fs::write(&path, "before\n").unwrap();
+
+        let backup = write_in_place(&path, "after\n", None, Some(".bak"), &make_test_config())
+            .unwrap()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "before\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "after\n");
+    }
+
+    // =========================================================================
+    // EditorConfig tests
+    // =========================================================================
+
+    #[test]
+    fn test_editorconfig_properties_match_sections() {
+        let content = "root = true\n\n[*]\nindent_size = 2\n\n[docs/*.md]\nTab_Width = 8\n\n[*.{txt,rs}]\ncharset = UTF-8-BOM\n";
+        let (root, props) = editorconfig_properties(content, Path::new("docs/a.md"));
+        assert!(root);
+        assert_eq!(
+            props,
+            vec![
+                ("indent_size".to_string(), "2".to_string()),
+                ("tab_width".to_string(), "8".to_string()),
+            ]
+        );
+
+        let (_, props) = editorconfig_properties(content, Path::new("src/docs/a.md"));
+        assert_eq!(props.len(), 1, "anchored sections match from the root only");
+        let (_, props) = editorconfig_properties(content, Path::new("src/notes.txt"));
+        assert_eq!(props[1], ("charset".to_string(), "utf-8-bom".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_editorconfig_nearest_wins() {
+        let temp = tempfile::tempdir().unwrap();
+        let docs = temp.path().join("docs");
+        fs::create_dir(&docs).unwrap();
+        fs::write(
+            temp.path().join(".editorconfig"),
+            "root = true\n[*]\nindent_size = 2\ncharset = utf-8\n",
+        )
+        .unwrap();
+        fs::write(docs.join(".editorconfig"), "[*.md]\ntab_width = 8\n").unwrap();
+
+        let resolved = resolve_editorconfig(&docs.join("a.md"));
+        assert_eq!(resolved.tab_width, Some(8));
+        assert_eq!(resolved.charset.as_deref(), Some("utf-8"));
+        assert_eq!(
+            resolve_editorconfig(&temp.path().join("b.txt")).tab_width,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_config_for_path_respects_pinned_tab_width() {
+        let temp = tempfile::tempdir().unwrap();
+        fs::write(
+            temp.path().join(".editorconfig"),
+            "root = true\n[*]\ntab_width = 8\n",
+        )
+        .unwrap();
+        let path = temp.path().join("a.txt");
+
+        let config = make_test_config();
+        assert_eq!(config_for_path(&path, &config).tab_width, 8);
+
+        let mut pinned = make_test_config();
+        pinned.tab_width_pinned = true;
+        assert_eq!(config_for_path(&path, &pinned).tab_width, 4);
+
+        let mut disabled = make_test_config();
+        disabled.editorconfig = false;
+        assert_eq!(config_for_path(&path, &disabled).tab_width, 4);
+    }
+
+    // =========================================================================
+    // Modeline tests
+    // =========================================================================
+
+    #[test]
+    fn test_modeline_tab_width_vim() {
+        assert_eq!(vim_modeline_tab_width("// vim: ts=8"), Some(8));
+        assert_eq!(
+            vim_modeline_tab_width("# vim: set sw=4 tabstop=2 :"),
+            Some(2)
+        );
+        assert_eq!(vim_modeline_tab_width("/* vi:noet:ts=3: */"), Some(3));
+        assert_eq!(vim_modeline_tab_width("avim: ts=8"), None);
+        assert_eq!(vim_modeline_tab_width("no modeline"), None);
+    }
+
+    #[test]
+    fn test_modeline_tab_width_emacs() {
+        assert_eq!(
+            emacs_modeline_tab_width("# -*- mode: text; tab-width: 8 -*-"),
+            Some(8)
+        );
+        assert_eq!(emacs_modeline_tab_width("tab-width: 8"), None);
+
+        let lines = to_lines("text\n\nLocal Variables:\ntab-width: 2\nEnd:\n");
+        assert_eq!(modeline_tab_width(&lines), Some(2));
+    }
+
+    #[test]
+    fn test_modeline_only_near_ends() {
+        let mut lines = vec!["text".to_string(); 20];
+        lines[10] = "// vim: ts=8".to_string();
+        assert_eq!(modeline_tab_width(&lines), None);
+        lines[18] = "// vim: ts=8".to_string();
+        assert_eq!(modeline_tab_width(&lines), Some(8));
+    }
+
+    #[test]
+    fn test_config_for_lines_modeline() {
+        let lines = to_lines("// vim: ts=8\n+------+\n");
+        assert_eq!(config_for_lines(&lines, &make_test_config()).tab_width, 8);
+
+        let mut config = make_test_config();
+        config.modelines = false;
+        assert_eq!(config_for_lines(&lines, &config).tab_width, 4);
+        config.modelines = true;
+        config.tab_width_pinned = true;
+        assert_eq!(config_for_lines(&lines, &config).tab_width, 4);
+    }
+
+    // =========================================================================
+    // Encoding tests
+    // =========================================================================
+
+    #[test]
+    fn test_encoding_detect() {
+        assert_eq!(Encoding::detect(b"plain"), Encoding::Utf8);
+        assert_eq!(Encoding::detect("caf\u{e9}".as_bytes()), Encoding::Utf8);
+        assert_eq!(Encoding::detect(b"caf\xE9"), Encoding::Latin1);
+        assert_eq!(Encoding::detect(b"\xFF\xFEa\0"), Encoding::Utf16le);
+        assert_eq!(Encoding::detect(b"a\0b\0"), Encoding::Utf16le);
+        assert_eq!(Encoding::detect(b"\0a\0b"), Encoding::Utf16be);
+        assert_eq!(
+            Encoding::detect(b"a\0\0b"),
+            Encoding::Utf8,
+            "binary stays an error"
+        );
+    }
+
+    #[test]
+    fn test_encoding_round_trip() {
+        let source = decode_to_lines(b"| caf\xE9 |\n".to_vec(), "t", Encoding::Auto).unwrap();
+        assert_eq!(source.encoding, Encoding::Latin1);
+        assert_eq!(source.lines, vec!["| café |"]);
+        assert_eq!(Encoding::Latin1.encode("| café |").unwrap(), b"| caf\xE9 |");
+        assert!(Encoding::Latin1.encode("┌─┐").is_err());
+
+        let bytes = b"\xFE\xFF\0a\0\n".to_vec();
+        let source = decode_to_lines(bytes, "t", Encoding::Utf16).unwrap();
+        assert_eq!(source.encoding, Encoding::Utf16be);
+        assert!(source.bom);
+        assert_eq!(source.lines, vec!["a"]);
+        assert_eq!(
+            Encoding::Utf16be.encode("\u{feff}a\n").unwrap(),
+            b"\xFE\xFF\0a\0\n"
+        );
+    }
+
+    #[test]
+    fn test_encoding_utf8_rejects_latin1() {
+        let err = decode_to_lines(b"caf\xE9".to_vec(), "t", Encoding::Utf8).unwrap_err();
+        assert!(err.to_string().contains("Invalid UTF-8"));
+    }
+
+    // =========================================================================
+    // File size limit tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("1048576"), Ok(1_048_576));
+        assert_eq!(parse_byte_size("500K"), Ok(500 * 1024));
+        assert_eq!(parse_byte_size("200MB"), Ok(200 * 1024 * 1024));
+        assert_eq!(parse_byte_size("1.5 GiB"), Ok(3 * 512 * 1024 * 1024));
+        assert_eq!(parse_byte_size("0"), Ok(0));
+        assert!(parse_byte_size("12 parsecs").is_err());
+        assert!(parse_byte_size("MB").is_err());
+    }
+
+    #[test]
+    fn test_format_byte_size() {
+        assert_eq!(format_byte_size(DEFAULT_MAX_FILE_SIZE), "100 MB");
+        assert_eq!(format_byte_size(1536), "1.5 KB");
+        assert_eq!(format_byte_size(300), "300 bytes");
+    }
+
+    #[test]
+    fn test_read_file_respects_max_file_size() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("big.txt");
+        fs::write(&path, "x".repeat(2048)).unwrap();
+
+        let mut config = make_test_config();
+        config.max_file_size = 1024;
+        let err = read_file_with_snapshot(&path, &config).unwrap_err();
+        assert!(err.to_string().contains("Maximum size is 1 KB"), "{err}");
+
+        config.max_file_size = 0;
+        assert!(read_file_with_snapshot(&path, &config).is_ok());
+    }
+
+    #[test]
+    fn test_load_config_max_file_size() {
+        let tmp = write_temp_config("max_file_size = \"2MB\"\n");
+        let fc = load_config_file(tmp.path()).unwrap();
+        assert_eq!(fc.max_file_size.unwrap().bytes(), Ok(2 * 1024 * 1024));
+
+        let tmp = write_temp_config("max_file_size = 4096\n");
+        let fc = load_config_file(tmp.path()).unwrap();
+        assert_eq!(fc.max_file_size.unwrap().bytes(), Ok(4096));
+
+        let tmp = write_temp_config("max_file_size = \"lots\"\n");
+        assert!(load_config_file(tmp.path()).is_err());
+    }
+
+    // =========================================================================
+    // EOL normalization tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_bytes_to_lines_records_endings() {
+        let source = parse_bytes_to_lines(b"a\r\nb\nc".to_vec(), "test").unwrap();
+        assert_eq!(source.lines, vec!["a", "b", "c"]);
+        assert_eq!(source.endings, vec![Some(Eol::Crlf), Some(Eol::Lf), None]);
+    }
+
+    #[test]
+    fn test_parse_bytes_to_lines_strips_bom() {
+        let source = parse_bytes_to_lines(b"\xEF\xBB\xBF+--+\n|a |\n".to_vec(), "test").unwrap();
+        assert!(source.bom);
+        assert_eq!(source.lines, vec!["+--+", "|a |"]);
+
+        let console = Console::new();
+        let styles = make_test_styles();
+        let result = process_input(
+            source,
+            "t.txt".to_string(),
+            &make_test_config(),
+            &console,
+            &styles,
+        );
+        assert!(result.output_text().starts_with("\u{feff}+--+\n"));
+    }
+
+    #[test]
+    fn test_process_input_normalize_eol_counts_changes() {
+        let source = parse_bytes_to_lines(b"a\r\nb\nc\r\n".to_vec(), "test").unwrap();
+        let mut config = make_test_config();
+        config.normalize_eol = Some(Eol::Crlf);
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let result = process_input(source, "t.txt".to_string(), &config, &console, &styles);
+        assert_eq!(result.stats.eol_changes, 1);
+        assert!(result.would_change);
+        assert_eq!(result.output_text(), "a\r\nb\r\nc\r\n");
+        assert_eq!(result.original_text(), "a\r\nb\nc\r\n");
+    }
+
+    #[test]
+    fn test_process_input_without_normalize_eol_ignores_endings() {
+        let source = parse_bytes_to_lines(b"a\r\nb\n".to_vec(), "test").unwrap();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let result = process_input(
+            source,
+            "t.txt".to_string(),
+            &make_test_config(),
+            &console,
+            &styles,
+        );
+        assert_eq!(result.stats.eol_changes, 0);
+        assert!(!result.would_change);
+        assert_eq!(result.output_text(), "a\r\nb\n");
+    }
+
+    #[test]
+    fn test_process_input_keeps_original_endings() {
+        let console = Console::new();
+        let styles = make_test_styles();
+        for input in ["a\nb", "a\nb\r\nc\n", "a\r\n\r\nb\n", ""] {
+            let source = parse_bytes_to_lines(input.as_bytes().to_vec(), "test").unwrap();
+            let result = process_input(
+                source,
+                "t.txt".to_string(),
+                &make_test_config(),
+                &console,
+                &styles,
+            );
+            assert!(!result.would_change);
+            assert_eq!(result.output_text(), input);
+        }
+
+        // Revised lines get the dominant ending; the final newline stays off
+        let source =
+            parse_bytes_to_lines(b"intro\n+----+\r\n| a |\r\n+----+".to_vec(), "test").unwrap();
+        let result = process_input(
+            source,
+            "t.txt".to_string(),
+            &make_test_config(),
+            &console,
+            &styles,
+        );
+        assert_eq!(result.output_text(), "intro\n+----+\r\n| a  |\r\n+----+");
+    }
+
+    #[test]
+    fn test_process_input_keeps_dominant_crlf() {
+        let source =
+            parse_bytes_to_lines(b"+--+\r\n|ab|\r\n|abc|\r\n+--+\n".to_vec(), "test").unwrap();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let result = process_input(
+            source,
+            "t.txt".to_string(),
+            &make_test_config(),
+            &console,
+            &styles,
+        );
+        assert_eq!(result.eol, Eol::Crlf);
+        assert!(result.would_change);
+        assert!(result.output_text().ends_with("|\r\n+---+\r\n"));
+        assert!(result.original_text().contains("|ab|\r\n"));
+    }
+
+    // =========================================================================
+    // Report tests
+    // =========================================================================
+
+    #[test]
+    fn test_parse_report() {
+        assert_eq!(
+            parse_report("junit=out/aadc.xml"),
+            Ok(ReportTarget {
+                format: ReportFormat::Junit,
+                path: PathBuf::from("out/aadc.xml"),
+            })
+        );
+        assert!(parse_report("junit").is_err());
+        assert!(parse_report("junit=").is_err());
+        assert!(
+            parse_report("html=x")
+                .unwrap_err()
+                .contains("unknown format")
+        );
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let cases = vec![
+            ReportCase {
+                name: "ok.md".to_string(),
+                elapsed: Duration::from_millis(2),
+                failure: None,
+                error: None,
+            },
+            ReportCase {
+                name: "a&b.md".to_string(),
+                elapsed: Duration::ZERO,
+                failure: Some((
+                    "1 block(s) need correction".to_string(),
+                    "-| x |\u{c}\n+| x  |\n".to_string(),
+                )),
+                error: None,
+            },
+            ReportCase {
+                name: "gone.md".to_string(),
+                elapsed: Duration::ZERO,
+                failure: None,
+                error: Some("Failed to read <gone.md>".to_string()),
+            },
+        ];
+        let xml = junit_report(&cases);
+        assert!(xml.contains(
+            "<testsuite name=\"aadc\" tests=\"3\" failures=\"1\" errors=\"1\" time=\"0.002\">"
+        ));
+        assert!(xml.contains("<testcase classname=\"aadc\" name=\"ok.md\" time=\"0.002\"/>"));
+        assert!(xml.contains("name=\"a&amp;b.md\""));
+        assert!(
+            xml.contains(">-| x |\n+| x  |\n</failure>"),
+            "form feed dropped: {xml}"
+        );
+        assert!(xml.contains("<error message=\"Failed to read &lt;gone.md&gt;\"/>"));
+    }
+
+    // =========================================================================
+    // Streaming tests
+    // =========================================================================
+
+    #[test]
+    fn test_stream_correct_matches_whole_file() {
+        let mut text = String::from("+--+\n|ab|\n|abc|\n+--+\n");
+        for i in 0..20 {
+            text.push_str(&format!("prose line {}\n", i));
+        }
+        text.push_str("+----+\n| x |\n+----+\ntail\n");
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let mut output = Vec::new();
+        let (stats, would_change) = stream_correct(
+            io::Cursor::new(text.clone()),
+            &mut output,
+            "test",
+            &config,
+            &console,
+            &styles,
+        )
+        .unwrap();
+
+        let (expected, _) = correct_lines(to_lines(&text), &config, &console, &styles);
+        let mut expected = expected.join("\n");
+        expected.push('\n');
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+        assert_eq!(stats.blocks_found, 2);
+        assert!(would_change);
+    }
+
+    #[test]
+    fn test_stream_chunk_does_not_cut_inside_disabled_region() {
+        let detect = make_test_config().detect_options();
+        let mut chunk = StreamChunk::default();
+        chunk.push("<!-- aadc:off -->".to_string(), Some(Eol::Lf), &detect);
+        for _ in 0..10 {
+            chunk.push("text".to_string(), Some(Eol::Lf), &detect);
+        }
+        assert!(!chunk.at_safe_cut(3));
+
+        chunk.push("<!-- aadc:on -->".to_string(), Some(Eol::Lf), &detect);
+        assert!(!chunk.at_safe_cut(3), "directive line is never a cut point");
+        chunk.push("text".to_string(), Some(Eol::Lf), &detect);
+        assert!(chunk.at_safe_cut(3));
+    }
+
+    // =========================================================================
+    // Result cache tests
+    // =========================================================================
+
+    #[test]
+    fn test_result_cache_skips_clean_files() {
+        let temp = tempfile::tempdir().unwrap();
+        let clean = temp.path().join("clean.md");
+        let dirty = temp.path().join("dirty.md");
+        fs::write(&clean, "+----+\n| ok |\n+----+\n").unwrap();
+        fs::write(&dirty, "+----+\n| x |\n+----+\n").unwrap();
+        let cache_file = temp.path().join("cache");
+        let config = make_test_config();
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let cache = ResultCache::load(&cache_file);
+        for path in [&clean, &dirty] {
+            process_path(path, &config, Some(&cache), &console, &styles).unwrap();
+        }
+        cache.save().unwrap();
+
+        let cache = ResultCache::load(&cache_file);
+        let (_, snapshot) = read_file_with_snapshot(&clean, &config).unwrap();
+        let fingerprint = config_fingerprint(&config);
+        let clean_name = clean.display().to_string();
+        assert_eq!(cache.lookup(&clean_name, &snapshot, &fingerprint), Some(1));
+        assert!(cache.entries().get(&dirty.display().to_string()).is_none());
+
+        let mut other = config.clone();
+        other.tab_width = 8;
+        assert_eq!(
+            cache.lookup(&clean_name, &snapshot, &config_fingerprint(&other)),
+            None
+        );
+
+        fs::write(&clean, "+----+\n| ok |\n+----+\n\n").unwrap();
+        let (_, snapshot) = read_file_with_snapshot(&clean, &config).unwrap();
+        assert_eq!(cache.lookup(&clean_name, &snapshot, &fingerprint), None);
+    }
+
+    #[test]
+    fn test_config_fingerprint_ignores_output_modes() {
+        let config = make_test_config();
+        let mut modes = config.clone();
+        modes.dry_run = true;
+        modes.json = true;
+        modes.jobs = 8;
+        assert_eq!(config_fingerprint(&config), config_fingerprint(&modes));
+
+        let mut options = config.clone();
+        options.min_score = 0.9;
+        assert_ne!(config_fingerprint(&config), config_fingerprint(&options));
+    }
+
+    // =========================================================================
+    // Numbered backup tests
+    // =========================================================================
+
+    #[test]
+    fn test_create_numbered_backup_increments() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("doc.md");
+        fs::write(&file, "v1").unwrap();
+
+        let first = create_numbered_backup(&file, ".bak", b"v1", None).unwrap();
+        let second = create_numbered_backup(&file, ".bak", b"v2", None).unwrap();
+
+        assert_eq!(first.file_name().unwrap(), "doc.md.bak.1");
+        assert_eq!(second.file_name().unwrap(), "doc.md.bak.2");
+        assert_eq!(fs::read_to_string(&first).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "v2");
+    }
+
+    #[test]
+    fn test_create_numbered_backup_retention() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("doc.md");
+        fs::write(&file, "content").unwrap();
+        // Unrelated files sharing the prefix are left alone
+        fs::write(temp.path().join("doc.md.bak.old"), "x").unwrap();
+
+        for _ in 0..4 {
+            create_numbered_backup(&file, ".bak", b"content", Some(2)).unwrap();
+        }
+
+        let numbers: Vec<usize> = numbered_backups(&file, ".bak")
+            .unwrap()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(numbers, vec![3, 4]);
+        assert!(temp.path().join("doc.md.bak.old").exists());
+    }
+
+    #[test]
+    fn test_validate_args_backup_keep_zero() {
+        let mut args = make_args();
+        args.backup_keep = Some(0);
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_write_in_place_skips_identical_content() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("same.txt");
+        fs::write(&path, "unchanged\n").unwrap();
+        let before = fs::metadata(&path).unwrap().modified().unwrap();
+
+        let backup = write_in_place(
+            &path,
+            "unchanged\n",
+            None,
+            Some(".bak"),
+            &make_test_config(),
+        )
+        .unwrap();
+
+        assert!(backup.is_none());
+        assert!(!temp.path().join("same.txt.bak").exists());
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), before);
+    }
+
+    #[test]
+    fn test_write_in_place_preserve_metadata() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("old.txt");
+        fs::write(&path, "before\n").unwrap();
+        let old = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let mut config = make_test_config();
+        config.preserve_metadata = true;
+
+        write_in_place(&path, "after\n", None, None, &config).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "after\n");
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), old);
+    }
+
+    // =========================================================================
+    // CI detection tests
+    // =========================================================================
+
+    #[test]
+    fn test_ci_provider_detect_from() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(CiProvider::detect_from(env(&[])), None);
+        assert_eq!(
+            CiProvider::detect_from(env(&[("CI", "true"), ("GITHUB_ACTIONS", "true")])),
+            Some(CiProvider::GithubActions)
+        );
+        assert_eq!(
+            CiProvider::detect_from(env(&[("GITLAB_CI", "true")])),
+            Some(CiProvider::GitlabCi)
+        );
+        assert_eq!(
+            CiProvider::detect_from(env(&[("CI", "1")])),
+            Some(CiProvider::Generic)
+        );
+        assert_eq!(CiProvider::detect_from(env(&[("CI", "false")])), None);
+    }
+
+    #[test]
+    fn test_lint_format_default_for_ci() {
+        assert_eq!(LintFormat::default_for(None), LintFormat::Text);
+        assert_eq!(
+            LintFormat::default_for(Some(CiProvider::GitlabCi)),
+            LintFormat::Text
+        );
+        assert_eq!(
+            LintFormat::default_for(Some(CiProvider::GithubActions)),
+            LintFormat::Github
+        );
+    }
+
+    #[test]
+    fn test_github_escape() {
+        assert_eq!(github_escape_data("50% done\nnext"), "50%25 done%0Anext");
+        assert_eq!(github_escape_property("a:b,c"), "a%3Ab%2Cc");
+    }
+
+    // =========================================================================
+    // Pandoc filter tests
+    // =========================================================================
+
+    #[test]
+    fn test_correct_pandoc_blocks() {
+        let mut ast: serde_json::Value = serde_json::from_str(
+            r#"{"pandoc-api-version":[1,23],"meta":{},"blocks":[
+                {"t":"Para","c":[{"t":"Str","c":"+--+ | x |"}]},
+                {"t":"BlockQuote","c":[
+                    {"t":"CodeBlock","c":[["",["text"],[]],"+------+\n| ab |\n+------+"]}
+                ]},
+                {"t":"RawBlock","c":["html","<p>no diagram</p>"]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let changed = correct_pandoc_blocks(&mut ast, &make_test_config());
+
+        assert_eq!(changed, 1);
+        assert_eq!(
+            ast["blocks"][1]["c"][0]["c"][1],
+            "+------+\n| ab   |\n+------+"
+        );
+        assert_eq!(ast["blocks"][0]["c"][0]["c"], "+--+ | x |");
+        assert_eq!(ast["blocks"][2]["c"][1], "<p>no diagram</p>");
+    }
+
+    #[test]
+    fn test_correct_block_text_without_diagram() {
+        let config = make_test_config();
+        assert_eq!(correct_block_text("a\tb\nplain", &config), None);
+    }
+
+    #[test]
+    fn test_correct_block_text_keeps_crlf() {
+        let config = make_test_config();
+        assert_eq!(
+            correct_block_text("+------+\r\n| ab |\r\n+------+", &config).as_deref(),
+            Some("+------+\r\n| ab   |\r\n+------+")
+        );
+    }
+
+    // =========================================================================
+    // Sub-region target tests
+    // =========================================================================
+
+    #[test]
+    fn test_box_row_ranges_split_stacked_boxes() {
+        let rows = |text: &str| -> Vec<AnalyzedLine> {
+            let detect = make_test_config().detect_options();
+            text.lines().map(|l| analyze_line(l, &detect)).collect()
+        };
+        // Boxes directly on top of each other, and after a blank row
+        assert_eq!(
+            box_row_ranges(&rows("+--+\n| a |\n+--+\n+-----+\n| b |\n+-----+")),
+            vec![(0, 2, true), (3, 5, true)]
+        );
+        assert_eq!(
+            box_row_ranges(&rows("+--+\n| a |\n+--+\n\n+-----+\n| b |\n+-----+")),
+            vec![(0, 2, true), (3, 3, false), (4, 6, true)]
+        );
+        // Separator rows stay inside their box
+        assert_eq!(
+            box_row_ranges(&rows("+--+\n| a |\n+--+\n| b |\n+--+")),
+            vec![(0, 4, true)]
+        );
+        // No border rows: one range
+        assert_eq!(box_row_ranges(&rows("| a |\n| bb |")), vec![(0, 1, false)]);
+    }
+
+    #[test]
+    fn test_column_clusters_split_on_gap() {
+        assert_eq!(column_clusters([6, 5, 6]), vec![(5, 6)]);
+        assert_eq!(column_clusters([5, 6, 19, 17, 19]), vec![(5, 6), (17, 19)]);
+        // A gap of exactly CLUSTER_GAP stays in one cluster
+        assert_eq!(column_clusters([10, 18]), vec![(10, 18)]);
+        assert!(column_clusters([]).is_empty());
+    }
+
+    #[test]
+    fn test_correct_lines_flow_rows_align_to_their_cluster() {
+        // No border rows: short and long rows form two column clusters
+        let input = "| step one |\n| a much longer second step |\n| two  |\n\
+                     | the last step, also long    |\n";
+        let (output, _stats) = correct(to_lines(input), &make_test_config());
+        assert_eq!(
+            output,
+            to_lines(
+                "| step one |\n| a much longer second step   |\n| two      |\n\
+                 | the last step, also long    |\n"
+            )
+        );
+
+        let mut config = make_test_config();
+        config.single_target = true;
+        let (output, _stats) = correct(to_lines(input), &config);
+        assert_eq!(output[0], "| step one                    |");
+    }
+
+    #[test]
+    fn test_correct_lines_box_targets() {
+        let lines = to_lines(
+            "+-----+\n| a  |\n+-----+\n+------------------+\n| b              |\n+------------------+\n",
+        );
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        // Each box is aligned to its own cluster of borders by default
+        let (output, stats) = correct_lines(lines.clone(), &make_test_config(), &console, &styles);
+        assert_eq!(output[0], "+-----+");
+        assert_eq!(output[1], "| a   |");
+        assert_eq!(output[4], "| b                |");
+        assert_eq!(stats.total_revisions, 2);
+
+        // --single-target pads everything out to the widest border
+        let mut config = make_test_config();
+        config.single_target = true;
+        let (output, _stats) = correct_lines(lines, &config, &console, &styles);
+        assert_eq!(output[1], "| a                |");
+    }
+
+    #[test]
+    fn test_correct_lines_narrow_box_above_wide_box() {
+        let config = make_test_config();
+        let input = "+--------+\n| narrow |\n+--------+\n\n\
+                     +------------------------------+\n| wide box |\n| second row |\n\
+                     +------------------------------+\n";
+        let (output, _stats) = correct(to_lines(input), &config);
+        assert_eq!(
+            output,
+            to_lines(
+                "+--------+\n| narrow |\n+--------+\n\n\
+                 +------------------------------+\n\
+                 | wide box                     |\n\
+                 | second row                   |\n\
+                 +------------------------------+\n"
+            )
+        );
+
+        // Stacked without a blank row, with a misaligned row in each box
+        let input =
+            "+----+\n| a |\n+----+\n+--------------------+\n| b |\n+--------------------+\n";
+        let (output, _stats) = correct(to_lines(input), &config);
+        assert_eq!(
+            output,
+            to_lines(
+                "+----+\n| a  |\n+----+\n+--------------------+\n\
+                 | b                  |\n+--------------------+\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_target_strategy_pick() {
+        let borders = [(9, true), (7, false), (7, false), (30, false), (8, false)];
+        assert_eq!(TargetStrategy::Rightmost.pick(&borders), Some(30));
+        assert_eq!(TargetStrategy::Mode.pick(&borders), Some(7));
+        assert_eq!(TargetStrategy::Median.pick(&borders), Some(8));
+        assert_eq!(TargetStrategy::TopBorder.pick(&borders), Some(9));
+        // Ties go right; no border row falls back to the rightmost
+        assert_eq!(
+            TargetStrategy::Mode.pick(&[(4, false), (6, false)]),
+            Some(6)
+        );
+        assert_eq!(
+            TargetStrategy::TopBorder.pick(&[(4, false), (6, false)]),
+            Some(6)
+        );
+        assert_eq!(TargetStrategy::Median.pick(&[]), None);
+    }
+
+    #[test]
+    fn test_correct_target_mode_ignores_overlong_line() {
+        let input = "+------+\n| a    |\n| bb  |\n| c           |\n+------+\n";
+        let mut config = make_test_config();
+        config.target_strategy = TargetStrategy::Mode;
+        let (corrected, _) = correct(to_lines(input), &config);
+        assert_eq!(
+            corrected,
+            to_lines("+------+\n| a    |\n| bb   |\n| c           |\n+------+\n")
+        );
+
+        // With shrinking allowed the overlong line is pulled in too
+        config.allow_shrink = true;
+        let (corrected, _) = correct(to_lines(input), &config);
+        assert_eq!(corrected[3], "| c    |");
+    }
+
+    #[test]
+    fn test_target_from_config_file() {
+        let mut config = make_test_config();
+        let file_config: FileConfig = toml::from_str("target = \"top-border\"").unwrap();
+        apply_file_config(&mut config, &make_args(), file_config);
+        assert_eq!(config.target_strategy, TargetStrategy::TopBorder);
+
+        let mut args = make_args();
+        args.target = Some(TargetStrategy::Median);
+        let mut config = Config::from(&args);
+        let file_config: FileConfig = toml::from_str("target = \"mode\"").unwrap();
+        apply_file_config(&mut config, &args, file_config);
+        assert_eq!(config.target_strategy, TargetStrategy::Median);
+    }
+
+    #[test]
+    fn test_single_target_overrides_file_config() {
+        let mut args = make_args();
+        args.single_target = true;
+        let mut config = Config::from(&args);
+        let file_config = FileConfig {
+            single_target: Some(false),
+            ..Default::default()
+        };
+        apply_file_config(&mut config, &args, file_config);
+        assert!(config.single_target);
+
+        let args = make_args();
+        let mut config = Config::from(&args);
+        assert!(!config.single_target);
+        let file_config = FileConfig {
+            single_target: Some(true),
+            ..Default::default()
+        };
+        apply_file_config(&mut config, &args, file_config);
+        assert!(config.single_target);
+    }
+
+    // =========================================================================
+    // Protected column tests
+    // =========================================================================
+
+    #[test]
+    fn test_correct_lines_no_pad_past_ignores_long_lines() {
+        let lines =
+            to_lines("+------+\n| ab |\n| a very long row that overruns everything |\n+------+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let mut config = make_test_config();
+        config.no_pad_past = Some(20);
+        let (output, _stats) = correct_lines(lines, &config, &console, &styles);
+
+        assert_eq!(output[0], "+------+");
+        assert_eq!(output[1], "| ab   |");
+        assert_eq!(output[2], "| a very long row that overruns everything |");
+    }
+
+    #[test]
+    fn test_validate_args_no_pad_past_zero() {
+        let mut args = make_args();
+        args.no_pad_past = Some(0);
+        assert!(validate_args(&args).is_err());
+        args.no_pad_past = Some(100);
+        assert!(validate_args(&args).is_ok());
+    }
+
+    // =========================================================================
+    // Revision ID tests
+    // =========================================================================
+
+    #[test]
+    fn test_correct_lines_numbers_revisions() {
+        let lines = to_lines("+------+\n| ab |\n| abc |\n+------+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let (_output, stats) = correct_lines(lines, &make_test_config(), &console, &styles);
+
+        let ids: Vec<(usize, usize, bool)> = stats
+            .revisions
+            .iter()
+            .map(|r| (r.id, r.line, r.applied))
+            .collect();
+        assert_eq!(ids, vec![(1, 2, true), (2, 3, true)]);
+        assert_eq!(stats.revisions[0].kind, "pad");
+    }
+
+    #[test]
+    fn test_correct_lines_apply_ids_subset() {
+        let lines = to_lines("+------+\n| ab |\n| abc |\n+------+\n");
+        let console = Console::new();
+        let styles = make_test_styles();
+
+        let mut config = make_test_config();
+        config.apply_ids = Some(parse_line_ranges("2").unwrap());
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+
+        assert_eq!(output[1], "| ab |");
+        assert_eq!(output[2], "| abc  |");
+        assert_eq!(stats.total_revisions, 1);
+        assert!(!stats.revisions[0].applied);
+        assert!(stats.revisions[1].applied);
+    }
+
+    #[test]
+    fn test_validate_args_apply_ids() {
+        let mut args = make_args();
+        args.apply_ids = Some("1,4,7-9".to_string());
+        assert!(validate_args(&args).is_ok());
+        args.apply_ids = Some("0".to_string());
+        assert!(validate_args(&args).is_err());
+    }
+
+    #[test]
+    fn test_mark_padding_shows_inserted_spaces() {
+        assert_eq!(mark_padding("| ab |", "| ab   |"), "| ab ··|");
+        assert_eq!(mark_padding("| a", "| a |"), "| a·|");
+        // Replaced characters pass through unmarked
+        assert_eq!(mark_padding("+--+", "+----+"), "+----+");
+    }
+
+    #[test]
+    fn test_side_by_side_diff_pairs_changed_lines() {
+        let layout = SideBySide {
+            width: 40,
+            color: false,
+            show_padding: true,
+            tab_width: 4,
+        };
+        let mut out = Vec::new();
+        write_side_by_side_diff(
+            &mut out,
+            "a/x",
+            "b/x",
+            "+------+\n| ab |\n+------+\n",
+            "+------+\n| ab   |\n+------+\nnew\n",
+            &layout,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "--- a/x\n+++ b/x\n@@ -1,3 +1,4 @@\n\
+             1 +------+           1 +------+\n\
+             2 | ab |           | 2 | ab ··|\n\
+             3 +------+           3 +------+\n\
+             \u{20}                  > 4 new\n"
+        );
+    }
+
+    #[test]
+    fn test_fit_runs_marks_cut_lines() {
+        let fit = |text: &str, width| -> String {
+            fit_runs(vec![(false, text.to_string())], width)
+                .into_iter()
+                .map(|(_, text)| text)
+                .collect()
+        };
+        assert_eq!(fit("| ab |", 6), "| ab |");
+        assert_eq!(fit("| abcdef |", 6), "| abc…");
+        // Wide characters are not split
+        assert_eq!(fit("| 日本語 |", 6), "| 日…");
+
+        let runs = vec![
+            (false, "| ab ".to_string()),
+            (true, "   ".to_string()),
+            (false, "|".to_string()),
+        ];
+        assert_eq!(
+            fit_runs(runs, 7),
+            vec![
+                (false, "| ab ".to_string()),
+                (true, " ".to_string()),
+                (false, "…".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_runs_compare_characters() {
+        let (old, new) = changed_runs("| ab |", "| ab   |", false);
+        assert_eq!(old, vec![(false, "| ab |".to_string())]);
+        assert_eq!(
+            new,
+            vec![
+                (false, "| ab ".to_string()),
+                (true, "  ".to_string()),
+                (false, "|".to_string())
+            ]
+        );
+        let (_, new) = changed_runs("| ab |", "| ab   |", true);
+        assert_eq!(new[1], (true, "··".to_string()));
+    }
+
+    #[test]
+    fn test_colored_unified_diff_emphasizes_inserted_padding() {
+        let mut out = Vec::new();
+        write_unified_diff(
+            &mut out,
+            "a/x",
+            "b/x",
+            "+------+\n| ab |\n",
+            "+------+\n| ab   |\n",
+            false,
+            true,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("\x1b[31m-| ab |\x1b[0m\n"), "{out:?}");
+        assert!(
+            out.contains("\x1b[32m+| ab \x1b[7m  \x1b[27m|\x1b[0m\n"),
+            "{out:?}"
+        );
+        assert!(out.contains(" +------+\n"), "context is uncolored: {out:?}");
+    }
+
+    // =========================================================================
+    // Table tests
+    // =========================================================================
+
+    #[test]
+    fn test_find_output_tables() {
+        let lines = to_lines(
+            "+----+------+\n| id | name |\n+----+------+\n| 1 | bob |\n+----+------+\n\n \
+             id | name\n----+------\n 1 | alice\n(1 row)\n",
+        );
+        let tables = find_output_tables(&lines);
+        assert_eq!(tables.len(), 2);
+        assert_eq!((tables[0].start, tables[0].end), (0, 5));
+        assert_eq!(tables[0].style, OutputTableStyle::Mysql);
+        assert_eq!((tables[1].start, tables[1].end), (6, 9));
+        assert_eq!(tables[1].style, OutputTableStyle::Psql);
+    }
+
+    #[test]
+    fn test_find_output_tables_ignores_boxes() {
+        // A box whose last line is not a separator is a diagram
+        let lines = to_lines("+----+\n| ab |\n|  c |\n");
+        assert!(find_output_tables(&lines).is_empty());
+    }
+
+    #[test]
+    fn test_format_output_table_widens_and_aligns() {
+        let mut lines = to_lines(
+            "+----+------+\n| id | name |\n+----+------+\n| 10 | charlotte |\n| 2 | bob |\n+----+------+",
+        );
+        let tables = find_output_tables(&lines);
+        assert_eq!(tables.len(), 1);
+        let changed = format_output_table(&mut lines, &tables[0]);
+
+        assert_eq!(changed, 5);
+        assert_eq!(lines[0], "+----+-----------+");
+        assert_eq!(lines[1], "| id | name      |");
+        assert_eq!(lines[3], "| 10 | charlotte |");
+        assert_eq!(lines[4], "|  2 | bob       |");
+    }
+
+    #[test]
+    fn test_markdown_cells() {
+        assert_eq!(markdown_cells("| a | b |"), Some(vec!["a", "b"]));
+        assert_eq!(markdown_cells("a | b"), Some(vec!["a", "b"]));
+        assert_eq!(markdown_cells("| a \\| b |  |"), Some(vec!["a \\| b", ""]));
+        assert_eq!(markdown_cells("no pipes"), None);
+    }
+
+    #[test]
+    fn test_markdown_delimiter() {
+        let aligns = markdown_delimiter("|---|:--|:-:|--:|").unwrap();
+        assert!(matches!(
+            aligns[..],
+            [
+                None,
+                Some(CellAlign::Left),
+                Some(CellAlign::Center),
+                Some(CellAlign::Right)
+            ]
+        ));
+        assert!(markdown_delimiter("| a | b |").is_none());
+        assert!(markdown_delimiter("|").is_none());
+    }
+
+    #[test]
+    fn test_format_markdown_table() {
+        let mut lines =
+            to_lines("| Name | Count |\n|---|--:|\n| alpha | 1 |\n| b | 12345 |\n| c |\n\nafter");
+        let tables = find_output_tables(&lines);
+        assert_eq!(tables.len(), 1);
+        assert_eq!((tables[0].start, tables[0].end), (0, 5));
+        assert_eq!(tables[0].style, OutputTableStyle::Markdown);
+
+        assert_eq!(format_output_table(&mut lines, &tables[0]), 5);
+        assert_eq!(lines[0], "| Name  | Count |");
+        assert_eq!(lines[1], "| ----- | ----: |");
+        assert_eq!(lines[2], "| alpha |     1 |");
+        assert_eq!(lines[3], "| b     | 12345 |");
+        assert_eq!(lines[4], "| c     |       |");
+    }
+
+    #[test]
+    fn test_find_markdown_table_rejects_extra_cells() {
+        // Reformatting would drop the third cell
+        let lines = to_lines("| a | b |\n|---|---|\n| 1 | 2 | 3 |");
+        assert!(find_output_tables(&lines).is_empty());
+    }
+
+    #[test]
+    fn test_format_tables_keeps_markdown_tables_out_of_diagrams() {
+        let lines = to_lines("| a | b |\n|---|---|\n| long cell | x |");
+        let console = Console::new();
+        let styles = make_test_styles();
+        let mut config = make_test_config();
+        config.format_tables = true;
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+
+        assert_eq!(output[0], "| a         | b   |");
+        assert_eq!(output[1], "| --------- | --- |");
+        assert_eq!(output[2], "| long cell | x   |");
+        assert_eq!(stats.blocks_found, 0);
+    }
+
+    #[test]
+    fn test_format_tables_keeps_psql_tables_out_of_diagrams() {
+        let lines = to_lines(" id | name\n----+------\n 1 | charlotte\n(1 row)");
+        let console = Console::new();
+        let styles = make_test_styles();
+        let mut config = make_test_config();
+        config.format_tables = true;
+        let (output, stats) = correct_lines(lines, &config, &console, &styles);
+
+        assert_eq!(output[0], " id |   name");
+        assert_eq!(output[1], "----+-----------");
+        assert_eq!(output[2], "  1 | charlotte");
+        assert_eq!(output[3], "(1 row)");
+        assert_eq!(stats.blocks_found, 0);
+    }
+
+    // =========================================================================
+    // GitHub suggestion tests
+    // =========================================================================
+
+    #[test]
+    fn test_patch_new_ranges() {
+        let patch = "@@ -1,3 +1,4 @@\n a\n+b\n c\n@@ -10 +11 @@ fn x\n-y\n+z\n@@ -20,2 +22,0 @@\n";
+        assert_eq!(patch_new_ranges(patch), vec![(1, 4), (11, 11)]);
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_suggestion_comments_group_changed_lines() {
+        let original = to_lines("intro\n+-----+\n| a |\n| b |\n+-----+\n\n+--+\n|x|\n+--+\n");
+        let mut corrected = original.clone();
+        corrected[2] = "| a   |".to_string();
+        corrected[3] = "| b   |".to_string();
+        corrected[7] = "|x |".to_string();
+
+        let (comments, outside) = suggestion_comments("doc.md", &original, &corrected, &[(1, 5)]);
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].start_line, Some(3));
+        assert_eq!(comments[0].line, 4);
+        assert_eq!(comments[0].body, "```suggestion\n| a   |\n| b   |\n```");
+        assert_eq!(outside, vec![(8, 8)]);
+
+        let json = serde_json::to_value(&comments[0]).unwrap();
+        assert_eq!(json["side"], "RIGHT");
+        assert_eq!(json["start_side"], "RIGHT");
+    }
+}
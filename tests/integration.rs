@@ -62,14 +62,23 @@ fn get_binary_path() -> PathBuf {
     }
 }
 
+/// Command for the aadc binary, with CI detection variables cleared so
+/// results don't depend on where the tests run
+fn aadc_command() -> Command {
+    let mut cmd = Command::new(get_binary_path());
+    cmd.env_remove("CI")
+        .env_remove("GITHUB_ACTIONS")
+        .env_remove("GITLAB_CI");
+    cmd
+}
+
 fn run_aadc_stdin(input: &str, args: &[&str]) -> (String, String, i32) {
     test_log!("RUN", "aadc with args: {:?}", args);
     test_log!("INPUT", "Input length: {} bytes", input.len());
 
-    let binary = get_binary_path();
-    test_log!("BIN", "Using binary: {}", binary.display());
+    test_log!("BIN", "Using binary: {}", get_binary_path().display());
 
-    let mut child = Command::new(&binary)
+    let mut child = aadc_command()
         .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -102,11 +111,10 @@ fn run_aadc_stdin(input: &str, args: &[&str]) -> (String, String, i32) {
 fn run_aadc_file(file_path: &str, args: &[&str]) -> (String, String, i32) {
     test_log!("RUN", "aadc {} with args: {:?}", file_path, args);
 
-    let binary = get_binary_path();
     let mut cmd_args: Vec<&str> = args.to_vec();
     cmd_args.push(file_path);
 
-    let output = Command::new(&binary)
+    let output = aadc_command()
         .args(&cmd_args)
         .output()
         .expect("Failed to run aadc");
@@ -123,8 +131,7 @@ fn run_aadc_file(file_path: &str, args: &[&str]) -> (String, String, i32) {
 fn run_aadc_args(args: &[&str]) -> (String, String, i32) {
     test_log!("RUN", "aadc with args: {:?}", args);
 
-    let binary = get_binary_path();
-    let output = Command::new(&binary)
+    let output = aadc_command()
         .args(args)
         .output()
         .expect("Failed to run aadc");
@@ -594,8 +601,7 @@ fn test_e2e_multiple_files() {
     )
     .expect("Failed to write temp file 2");
 
-    let binary = get_binary_path();
-    let output = Command::new(&binary)
+    let output = aadc_command()
         .arg(file1.to_str().unwrap())
         .arg(file2.to_str().unwrap())
         .output()
@@ -709,3 +715,1656 @@ fn test_e2e_binary_file_detection() {
 
     test_log!("END", "Test PASSED");
 }
+
+#[cfg(unix)]
+#[test]
+fn test_e2e_on_change_runs_for_modified_files() {
+    test_log!("START", "--on-change runs once with the modified files");
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    let changed = root.join("changed.md");
+    let clean = root.join("clean.md");
+    let log = root.join("log.txt");
+    fs::write(&changed, "+---+\n| a|\n+---+\n").unwrap();
+    fs::write(&clean, "+---+\n| a |\n+---+\n").unwrap();
+
+    let command = format!("echo \"$@\" >> '{}'", log.display());
+    let (_stdout, stderr, code) = run_aadc_args(&[
+        "-i",
+        "--on-change",
+        &command,
+        changed.to_str().unwrap(),
+        clean.to_str().unwrap(),
+    ]);
+
+    assert_eq!(code, 0, "Should exit successfully: {stderr}");
+    let logged = fs::read_to_string(&log).unwrap();
+    assert_eq!(logged, format!("{}\n", changed.display()));
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_show_padding_in_diff() {
+    test_log!("START", "--show-padding renders inserted spaces in diffs");
+
+    let input = "+------+\n| ab |\n+------+\n";
+    let (stdout, stderr, code) = run_aadc_stdin(input, &["--no-config", "-d", "--show-padding"]);
+    assert_eq!(code, 0, "Diff should succeed: {stderr}");
+    assert!(stdout.contains("-| ab |"), "Removed line is shown as-is");
+    assert!(
+        stdout.contains("+| ab ··|"),
+        "Inserted padding is visible: {stdout}"
+    );
+
+    let (stdout, _stderr, _code) = run_aadc_stdin(input, &["--no-config", "-d"]);
+    assert!(stdout.contains("+| ab   |"), "Plain diffs are unchanged");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_colored_diff_highlights_padding() {
+    test_log!(
+        "START",
+        "--color always highlights inserted padding in diffs"
+    );
+
+    let input = "+------+\n| ab |\n+------+\n";
+    let (stdout, stderr, code) = run_aadc_stdin(input, &["--no-config", "-d", "--color", "always"]);
+    assert_eq!(code, 0, "Diff should succeed: {stderr}");
+    assert!(
+        stdout.contains("\x1b[32m+| ab \x1b[7m  \x1b[27m|\x1b[0m"),
+        "Inserted spaces are emphasized: {stdout:?}"
+    );
+
+    let (stdout, _stderr, _code) =
+        run_aadc_stdin(input, &["--no-config", "-d", "--color", "never"]);
+    assert!(!stdout.contains('\x1b'), "No color when disabled");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_side_by_side_diff() {
+    test_log!(
+        "START",
+        "--diff-format side-by-side pairs original and corrected lines"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("box.md");
+    fs::write(&file, "+------+\n| ab |\n+------+\n").unwrap();
+
+    let output = aadc_command()
+        .env("COLUMNS", "40")
+        .args(["--no-config", "--color", "never", "-d", "--diff-format"])
+        .arg("side-by-side")
+        .arg(&file)
+        .output()
+        .expect("Failed to run aadc");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        stdout.contains("2 | ab |           | 2 | ab   |"),
+        "Changed lines share a row: {stdout}"
+    );
+    assert!(stdout.lines().all(|line| line.chars().count() <= 40));
+
+    let (_stdout, stderr, code) = run_aadc_stdin("x\n", &["--diff-format", "side-by-side"]);
+    assert_eq!(code, 2, "--diff-format requires --diff: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_require_diagrams_exit_code() {
+    test_log!(
+        "START",
+        "--require-diagrams exits 6 when an input has no diagrams"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let prose = temp.path().join("prose.md");
+    let diagram = temp.path().join("diagram.md");
+    fs::write(&prose, "Just some text.\n").unwrap();
+    fs::write(&diagram, "+---+\n| a |\n+---+\n").unwrap();
+    let prose = prose.to_str().unwrap();
+    let diagram = diagram.to_str().unwrap();
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "--require-diagrams", diagram]);
+    assert_eq!(code, 0, "A clean diagram is not flagged: {stderr}");
+
+    let (_stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "--require-diagrams", diagram, prose]);
+    assert_eq!(code, 6, "Should return 6 (NO_DIAGRAMS)");
+    assert!(stderr.contains(&format!("No diagrams found: {prose}")));
+    assert!(!stderr.contains(&format!("No diagrams found: {diagram}")));
+
+    let (stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "--require-diagrams", "--json", prose]);
+    assert_eq!(code, 6);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["status"], "no_diagrams");
+
+    let (_stdout, _stderr, code) = run_aadc_args(&["--no-config", prose]);
+    assert_eq!(code, 0, "Without the flag, prose is fine");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_fixtures_check_and_update() {
+    test_log!(
+        "START",
+        "fixtures check reports stale expectations, update fixes them"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let dir = temp.path().join("fixtures").join("boxes");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("box.input.txt"), "+-----+\n| a |\n+-----+\n").unwrap();
+    fs::write(dir.join("box.expected.txt"), "+-----+\n| a |\n+-----+\n").unwrap();
+    fs::write(dir.join("new.input.txt"), "+---+\n| b|\n+---+\n").unwrap();
+    fs::write(dir.join("notes.txt"), "not a fixture\n").unwrap();
+    let root = temp.path().join("fixtures");
+    let root = root.to_str().unwrap();
+
+    let (stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "fixtures", "check", "--diff", root]);
+    assert_eq!(code, 3, "Stale fixtures should exit 3: {stderr}");
+    assert!(stdout.contains("FAIL"));
+    assert!(stdout.contains("MISSING"));
+    assert!(stdout.contains("+| a   |"), "Diff shows the actual output");
+    assert!(stderr.contains("2 fixture(s): 0 passed, 2 failed"));
+
+    let (stdout, _stderr, code) = run_aadc_args(&["--no-config", "fixtures", "update", root]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.matches("updated").count(), 2);
+    assert_eq!(
+        fs::read_to_string(dir.join("box.expected.txt")).unwrap(),
+        "+-----+\n| a   |\n+-----+\n"
+    );
+    assert!(dir.join("new.expected.txt").exists());
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "fixtures", "check", root]);
+    assert_eq!(code, 0, "Updated fixtures should pass: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_lint_reports_rules_and_exit_code() {
+    test_log!(
+        "START",
+        "lint reports rule diagnostics and exits 5 on errors"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.md");
+    fs::write(&file, "+-----+\n| a |\n| b\n+-----+\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let (stdout, _stderr, code) = run_aadc_args(&["--no-config", "lint", path]);
+    assert_eq!(code, 5, "Should return 5 (LINT_ERRORS)");
+    assert!(stdout.contains(&format!(
+        "{path}:2:5: error[AD001] misaligned-right-border: "
+    )));
+    assert!(stdout.contains(&format!("{path}:3:4: warning[AD002] unclosed-box: ")));
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "+-----+\n| a |\n| b\n+-----+\n",
+        "Lint must not modify files"
+    );
+
+    let (stdout, _stderr, code) = run_aadc_args(&["--no-config", "lint", "--format", "json", path]);
+    assert_eq!(code, 5);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["errors"], 1);
+    assert_eq!(report["warnings"], 1);
+    assert_eq!(report["files"][0]["diagnostics"][0]["rule"], "AD001");
+
+    let (json_stdout, _stderr, code) = run_aadc_args(&["--no-config", "--json", "lint", path]);
+    assert_eq!(code, 5);
+    assert_eq!(json_stdout, stdout, "--json should select the JSON report");
+
+    let (_stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "--json", "lint", "--format", "text", path]);
+    assert_eq!(
+        code, 2,
+        "--json with a non-JSON --format is an argument error"
+    );
+    assert!(stderr.contains("--json conflicts with lint --format"));
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_lint_severity_from_config() {
+    test_log!("START", "lint severities come from the [lint] config table");
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.md");
+    let config = temp.path().join("lint.toml");
+    fs::write(&file, "+-----+\n| a |\n+-----+\n").unwrap();
+    fs::write(&config, "[lint]\nAD001 = \"warning\"\n").unwrap();
+
+    let (stdout, _stderr, code) = run_aadc_args(&[
+        "--config",
+        config.to_str().unwrap(),
+        "lint",
+        file.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0, "Warnings alone should not fail lint");
+    assert!(stdout.contains("warning[AD001]"));
+
+    let (stdout, _stderr, code) =
+        run_aadc_args(&["--config", config.to_str().unwrap(), "lint", "--list-rules"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("AD001  misaligned-right-border  warning"));
+    assert!(stdout.contains("AD002  unclosed-box"));
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_lint_fix_trailing_whitespace() {
+    test_log!(
+        "START",
+        "lint --fix removes trailing whitespace after borders"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.md");
+    fs::write(&file, "+-----+ \n| a |  \n+-----+\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let (stdout, stderr, code) = run_aadc_args(&["--no-config", "lint", "--fix", path]);
+    assert_eq!(code, 5, "Unfixable misalignment is still an error");
+    assert!(stderr.contains("Fixed 2 problem(s)"));
+    assert!(!stdout.contains("AD004"));
+    assert!(stdout.contains("error[AD001]"));
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "+-----+\n| a |\n+-----+\n"
+    );
+
+    // Only the fixed line changes: BOM, CRLF and the missing final
+    // newline are kept
+    fs::write(&file, "\u{feff}+-----+\r\n| a   |  \r\n+-----+").unwrap();
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "lint", "--fix", path]);
+    assert_eq!(code, 0);
+    assert!(stderr.contains("Fixed 1 problem(s)"));
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "\u{feff}+-----+\r\n| a   |\r\n+-----+"
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_error_format_json() {
+    test_log!("START", "--error-format json emits structured errors");
+
+    let temp = TempDir::new().unwrap();
+    let binary = temp.path().join("data.bin");
+    let missing = temp.path().join("missing.md");
+    fs::write(&binary, b"+---+\0| a |\0+---+").unwrap();
+
+    let (_stdout, stderr, code) = run_aadc_args(&[
+        "--error-format",
+        "json",
+        binary.to_str().unwrap(),
+        missing.to_str().unwrap(),
+    ]);
+    assert_ne!(code, 0);
+
+    let errors: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("stderr line should be JSON"))
+        .collect();
+    let for_path = |path: &std::path::Path| {
+        errors
+            .iter()
+            .find(|e| e["path"] == path.to_str().unwrap())
+            .unwrap_or_else(|| panic!("no error for {}", path.display()))
+    };
+    assert_eq!(for_path(&binary)["kind"], "parse");
+    assert_eq!(for_path(&binary)["code"], 4);
+    assert_eq!(for_path(&missing)["kind"], "io");
+    assert_eq!(for_path(&missing)["code"], 1);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_normalize_eol() {
+    test_log!("START", "--normalize-eol converts line endings visibly");
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.md");
+    fs::write(&file, "# Doc\r\ntext\r\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let (_stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "--dry-run", "--normalize-eol", "lf", path]);
+    assert_eq!(code, 3, "EOL conversion counts as a change");
+
+    let (stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "--diff", "--normalize-eol", "lf", path]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("-# Doc\r\n"));
+    assert!(stdout.contains("+# Doc\n"));
+
+    let (_stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "-i", "--normalize-eol", "lf", path]);
+    assert_eq!(code, 0);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "# Doc\ntext\n");
+
+    let (_stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "--dry-run", "--normalize-eol", "lf", path]);
+    assert_eq!(code, 0, "Already normalized");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_changed_since() {
+    test_log!(
+        "START",
+        "--changed-since only checks files git reports as changed"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(temp.path())
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !git(&["init", "-q"]) {
+        test_log!("SKIP", "git is not available");
+        return;
+    }
+    let dirty = "+------+\n| Box |\n+------+\n";
+    fs::write(temp.path().join("old.txt"), dirty).unwrap();
+    fs::write(temp.path().join("new.txt"), "text\n").unwrap();
+    assert!(git(&["add", "."]));
+    assert!(git(&["commit", "-q", "-m", "base"]));
+    fs::write(temp.path().join("new.txt"), dirty).unwrap();
+
+    let output = aadc_command()
+        .args(["--no-config", "-l", "-r", "--changed-since", "HEAD", "."])
+        .current_dir(temp.path())
+        .output()
+        .expect("Failed to run aadc");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "stdout: {stdout}");
+    assert!(stdout.contains("new.txt"));
+    assert!(!stdout.contains("old.txt"), "Unchanged file is not checked");
+
+    let (_stdout, stderr, code) = run_aadc_stdin(dirty, &["--changed-since", "HEAD"]);
+    assert_eq!(code, 2, "stderr: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_hook_run_matches_repo_paths() {
+    test_log!(
+        "START",
+        "hook run matches --patterns against repo-relative paths"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(temp.path())
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !git(&["init", "-q"]) {
+        test_log!("SKIP", "git is not available");
+        return;
+    }
+    let dirty = "+------+\n| Box |\n+------+\n";
+    for dir in ["docs", "notes"] {
+        fs::create_dir(temp.path().join(dir)).unwrap();
+        fs::write(temp.path().join(dir).join("a.md"), dirty).unwrap();
+    }
+    assert!(git(&["add", "."]));
+
+    let hook_run = |extra: &[&str]| {
+        let output = aadc_command()
+            .args(["--no-config", "hook", "run"])
+            .args(extra)
+            .current_dir(temp.path())
+            .output()
+            .expect("Failed to run aadc");
+        (
+            String::from_utf8_lossy(&output.stdout).to_string(),
+            output.status.code(),
+        )
+    };
+
+    let (stdout, code) = hook_run(&["--patterns", "docs/*.md"]);
+    assert_eq!(code, Some(1), "stdout: {stdout}");
+    assert!(stdout.contains("docs/a.md"), "stdout: {stdout}");
+    assert!(!stdout.contains("notes/a.md"), "stdout: {stdout}");
+
+    // The default `*.md` still reaches into subdirectories
+    let (stdout, code) = hook_run(&[]);
+    assert_eq!(code, Some(1), "stdout: {stdout}");
+    assert!(stdout.contains("notes/a.md"), "stdout: {stdout}");
+
+    // Auto-fixed files are passed to `on_change`
+    let logs = TempDir::new().unwrap();
+    let log = logs.path().join("log.txt");
+    let command = format!("echo \"$@\" >> '{}'", log.display());
+    let output = aadc_command()
+        .args(["--no-config", "--on-change", &command, "hook", "run"])
+        .args(["--auto-fix", "--patterns", "docs/*.md"])
+        .current_dir(temp.path())
+        .output()
+        .expect("Failed to run aadc");
+    assert_eq!(output.status.code(), Some(0));
+    let logged = fs::read_to_string(&log).unwrap();
+    assert!(logged.ends_with("docs/a.md\n"), "logged: {logged}");
+    assert_eq!(logged.lines().count(), 1);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_diff_only() {
+    test_log!(
+        "START",
+        "--diff-only corrects only blocks touched since a git ref"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(temp.path())
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !git(&["init", "-q"]) {
+        test_log!("SKIP", "git is not available");
+        return;
+    }
+    let file = temp.path().join("doc.txt");
+    fs::write(
+        &file,
+        "+------+\n| old |\n+------+\n\ntext\n\n+------+\n| a  |\n+------+\n",
+    )
+    .unwrap();
+    assert!(git(&["add", "."]));
+    assert!(git(&["commit", "-q", "-m", "base"]));
+    fs::write(
+        &file,
+        "+------+\n| old |\n+------+\n\ntext\n\n+------+\n| new |\n+------+\n",
+    )
+    .unwrap();
+
+    let output = aadc_command()
+        .args(["--no-config", "--diff-only", "doc.txt"])
+        .current_dir(temp.path())
+        .output()
+        .expect("Failed to run aadc");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        stdout.contains("| old |\n"),
+        "Untouched block is left alone: {stdout}"
+    );
+    assert!(
+        stdout.contains("| new  |\n"),
+        "Edited block is corrected: {stdout}"
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_per_file_line_ranges() {
+    test_log!("START", "FILE:RANGES limits each input to its own lines");
+
+    let temp = TempDir::new().unwrap();
+    let content = "+------+\n| a |\n+------+\n\ntext\n\n+------+\n| b |\n+------+\n";
+    let first = temp.path().join("first.txt");
+    let second = temp.path().join("second.txt");
+    fs::write(&first, content).unwrap();
+    fs::write(&second, content).unwrap();
+
+    let first_arg = format!("{}:1-3", first.display());
+    let second_arg = format!("{}:7-", second.display());
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "-i", &first_arg, &second_arg]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+
+    let first = fs::read_to_string(&first).unwrap();
+    let second = fs::read_to_string(&second).unwrap();
+    assert!(first.contains("| a    |") && first.contains("| b |"));
+    assert!(second.contains("| a |") && second.contains("| b    |"));
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_check_mode() {
+    test_log!(
+        "START",
+        "--check reports files needing correction on stderr only"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let clean = temp.path().join("clean.txt");
+    let dirty = temp.path().join("dirty.txt");
+    fs::write(&clean, "+------+\n| Box  |\n+------+\n").unwrap();
+    fs::write(&dirty, "+------+\n| Box |\n+------+\n").unwrap();
+    let clean = clean.to_str().unwrap();
+    let dirty = dirty.to_str().unwrap();
+
+    let (stdout, stderr, code) = run_aadc_args(&["--no-config", "--check", clean, dirty]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+    assert!(stdout.is_empty(), "Nothing on stdout: {stdout}");
+    assert!(stderr.contains(&format!("Would correct: {dirty}\n")));
+    assert!(!stderr.contains(&format!("Would correct: {clean}")));
+    assert!(stderr.ends_with("1 of 2 file(s) would be corrected\n"));
+
+    let (stdout, stderr, code) = run_aadc_args(&["--no-config", "--check", clean]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    assert!(stdout.is_empty());
+    assert_eq!(stderr, "All 1 file(s) correct\n");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_output_format_github() {
+    test_log!(
+        "START",
+        "--output-format github annotates blocks needing changes"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.txt");
+    fs::write(&file, "intro\n\n+------+\n| Box |\n+------+\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let (stdout, stderr, code) = run_aadc_args(&["--no-config", "--output-format", "github", path]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+    assert_eq!(
+        stdout,
+        format!(
+            "::error file={path},line=3,endLine=5,title=aadc::Misaligned diagram block (1 revision(s))\n"
+        )
+    );
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "intro\n\n+------+\n| Box |\n+------+\n",
+        "Reporting never modifies the file"
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_output_format_tap() {
+    test_log!("START", "--output-format tap emits a TAP test per file");
+
+    let temp = TempDir::new().unwrap();
+    let clean = temp.path().join("clean.txt");
+    let dirty = temp.path().join("dirty.txt");
+    fs::write(&clean, "+------+\n| Box  |\n+------+\n").unwrap();
+    fs::write(&dirty, "+------+\n| Box |\n+------+\n").unwrap();
+    let clean = clean.to_str().unwrap();
+    let dirty = dirty.to_str().unwrap();
+
+    let (stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "--output-format", "tap", clean, dirty]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+    assert_eq!(
+        stdout,
+        format!(
+            "TAP version 13\n1..2\nok 1 - {clean}\nnot ok 2 - {dirty}\n  ---\n  message: \"1 block(s) need correction\"\n  blocks:\n    - lines: 1-3\n      revisions: 1\n  ...\n"
+        )
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_json_multiple_files() {
+    test_log!("START", "--json wraps several files in one document");
+
+    let temp = TempDir::new().unwrap();
+    let clean = temp.path().join("clean.txt");
+    let dirty = temp.path().join("dirty.txt");
+    fs::write(&clean, "+------+\n| Box  |\n+------+\n").unwrap();
+    fs::write(&dirty, "+------+\n| Box |\n+------+\n").unwrap();
+
+    let (stdout, stderr, code) = run_aadc_args(&[
+        "--no-config",
+        "--json",
+        clean.to_str().unwrap(),
+        dirty.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let files = json["files"].as_array().unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[1]["file"], dirty.to_str().unwrap());
+    assert_eq!(files[1]["output"]["changed"], true);
+    assert_eq!(json["summary"]["files_processed"], 2);
+    assert_eq!(json["summary"]["files_changed"], 1);
+    assert_eq!(json["summary"]["processing"]["blocks_detected"], 2);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_apply_exported_edits() {
+    test_log!("START", "aadc apply writes the edits of a --json dry run");
+
+    let temp = TempDir::new().unwrap();
+    let doc = temp.path().join("doc.txt");
+    let edits = temp.path().join("edits.json");
+    fs::write(&doc, "+------+\n| Box |\n+------+\n").unwrap();
+
+    let (stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "--json", "--dry-run", doc.to_str().unwrap()]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+    fs::write(&edits, stdout).unwrap();
+
+    let (_stdout, stderr, code) = run_aadc_args(&["apply", edits.to_str().unwrap()]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    assert!(stderr.contains("Applied 1 edit(s)"), "stderr: {stderr}");
+    assert_eq!(
+        fs::read_to_string(&doc).unwrap(),
+        "+------+\n| Box  |\n+------+\n"
+    );
+
+    // The file no longer matches the recorded hash
+    let (_stdout, stderr, code) = run_aadc_args(&["apply", edits.to_str().unwrap()]);
+    assert_eq!(code, 1);
+    assert!(stderr.contains("has changed"), "stderr: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_apply_edits_match_in_place() {
+    test_log!(
+        "START",
+        "Exported edits reproduce -i output for a file with tabs"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let content = "Intro\n\n+------+\n\t| ab |\n| Box |\n+------+\n";
+    let doc = temp.path().join("doc.txt");
+    let in_place = temp.path().join("in_place.txt");
+    let edits = temp.path().join("edits.json");
+    fs::write(&doc, content).unwrap();
+    fs::write(&in_place, content).unwrap();
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "-i", in_place.to_str().unwrap()]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+
+    let (stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "--json", "--dry-run", doc.to_str().unwrap()]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+    fs::write(&edits, stdout).unwrap();
+    let (_stdout, stderr, code) = run_aadc_args(&["apply", edits.to_str().unwrap()]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+
+    assert_eq!(
+        fs::read_to_string(&doc).unwrap(),
+        fs::read_to_string(&in_place).unwrap()
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_json_schema_covers_output() {
+    test_log!("START", "aadc json-schema describes every --json field");
+
+    let (schema, stderr, code) = run_aadc_args(&["json-schema"]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let schema: serde_json::Value = serde_json::from_str(&schema).unwrap();
+    let defs = &schema["$defs"];
+
+    let (stdout, stderr, code) =
+        run_aadc_stdin("+------+\n| Box |\n+------+\n", &["--no-config", "--json"]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let output: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let version = output["version"].as_str().unwrap();
+    assert!(schema["title"].as_str().unwrap().ends_with(version));
+
+    for (def, value) in [
+        ("JsonOutput", &output),
+        ("ProcessingStats", &output["processing"]),
+        ("JsonBlock", &output["blocks"][0]),
+        ("JsonEdit", &output["edits"][0]),
+    ] {
+        for key in value.as_object().unwrap().keys() {
+            assert!(
+                defs[def]["properties"].get(key).is_some(),
+                "{def} schema lacks {key}"
+            );
+        }
+    }
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_json_output_validates_against_schema() {
+    test_log!("START", "--json output validates against aadc json-schema");
+
+    let (schema, stderr, code) = run_aadc_args(&["json-schema"]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let schema: serde_json::Value = serde_json::from_str(&schema).unwrap();
+    let validator = jsonschema::validator_for(&schema).unwrap();
+
+    let temp = TempDir::new().unwrap();
+    let doc = temp.path().join("doc.txt");
+    let prose = temp.path().join("prose.txt");
+    fs::write(&doc, "+------+\n| Box |\n+------+\n").unwrap();
+    fs::write(&prose, "No diagrams here.\n").unwrap();
+    let (doc, prose) = (doc.to_str().unwrap(), prose.to_str().unwrap());
+
+    for args in [
+        vec!["--no-config", "--json", prose],
+        vec!["--no-config", "--json", doc],
+        vec!["--no-config", "--json", "--dry-run", doc, prose],
+    ] {
+        let (stdout, stderr, _code) = run_aadc_args(&args);
+        let output: serde_json::Value =
+            serde_json::from_str(&stdout).unwrap_or_else(|err| panic!("{args:?}: {err}: {stderr}"));
+        let errors: Vec<String> = validator
+            .iter_errors(&output)
+            .map(|err| format!("{} at {}", err, err.instance_path))
+            .collect();
+        assert!(errors.is_empty(), "{args:?}: {errors:#?}");
+    }
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_json_reports_errors_in_document() {
+    test_log!("START", "--json reports unreadable inputs as error entries");
+
+    let temp = TempDir::new().unwrap();
+    let doc = temp.path().join("doc.txt");
+    let binary = temp.path().join("data.bin");
+    fs::write(&doc, "+------+\n| Box |\n+------+\n").unwrap();
+    fs::write(&binary, b"+---+\0| a |\0+---+").unwrap();
+
+    let (stdout, _stderr, code) = run_aadc_args(&[
+        "--no-config",
+        "--json",
+        doc.to_str().unwrap(),
+        binary.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 4);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let failed = &json["files"][1];
+    assert_eq!(failed["status"], "error");
+    assert_eq!(failed["file"], binary.to_str().unwrap());
+    assert_eq!(failed["error"]["kind"], "parse");
+    assert_eq!(failed["error"]["code"], 4);
+    assert_eq!(json["summary"]["files_failed"], 1);
+
+    // A single input's failure is the whole document
+    let (stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "--json", binary.to_str().unwrap()]);
+    assert_eq!(code, 4);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(json["status"], "error");
+    assert_eq!(json["error"]["kind"], "parse");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_lsp_session() {
+    test_log!(
+        "START",
+        "aadc lsp answers over stdio and exits after shutdown"
+    );
+
+    let frame = |body: &str| format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///doc.txt","text":"+------+\n| Box |\n+------+\n"}}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/formatting","params":{"textDocument":{"uri":"file:///doc.txt"}}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ]
+    .map(frame)
+    .concat();
+
+    let (stdout, stderr, code) = run_aadc_stdin(&input, &["--no-config", "lsp"]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let replies: Vec<serde_json::Value> = stdout
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|frame| serde_json::from_str(frame.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+    assert_eq!(replies.len(), 4, "stdout: {stdout}");
+    assert_eq!(
+        replies[0]["result"]["capabilities"]["documentFormattingProvider"],
+        true
+    );
+    assert_eq!(replies[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(
+        replies[2]["result"][0]["range"]["start"],
+        serde_json::json!({ "line": 1, "character": 6 })
+    );
+    assert_eq!(replies[2]["result"][0]["newText"], " ");
+    assert_eq!(replies[3]["id"], 3);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_mcp_session() {
+    test_log!("START", "aadc mcp lists and runs tools over stdio");
+
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26"}}"#,
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"tools/call","params":{"name":"fix_diagram","arguments":{"text":"+------+\n| Box |\n+------+\n"}}}"#,
+    ]
+    .join("\n");
+
+    let (stdout, stderr, code) = run_aadc_stdin(&input, &["--no-config", "mcp"]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let replies: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(replies.len(), 3, "stdout: {stdout}");
+    assert_eq!(replies[0]["result"]["protocolVersion"], "2025-03-26");
+    let tools: Vec<&str> = replies[1]["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(tools, ["fix_diagram", "detect_blocks", "score"]);
+    assert_eq!(
+        replies[2]["result"]["content"][0]["text"],
+        "+------+\n| Box  |\n+------+\n"
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_filter_clean() {
+    test_log!(
+        "START",
+        "filter-clean corrects text and passes other input through"
+    );
+
+    let input = "+------+\n| ab |\n+------+\n";
+    let (stdout, _stderr, code) = run_aadc_stdin(input, &["filter-clean", "doc.md"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout, "+------+\n| ab   |\n+------+\n");
+
+    let (stdout, stderr, code) = run_aadc_stdin("bin\0ary", &["filter-clean", "img.md"]);
+    assert_eq!(code, 0, "filter-clean must not fail the stream");
+    assert_eq!(stdout, "bin\0ary");
+    assert!(stderr.contains("img.md"), "stderr: {}", stderr);
+}
+
+#[test]
+fn test_e2e_json_lines() {
+    test_log!(
+        "START",
+        "--json-lines prints one compact JSON object per file"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let clean = temp.path().join("clean.txt");
+    let dirty = temp.path().join("dirty.txt");
+    fs::write(&clean, "+------+\n| Box  |\n+------+\n").unwrap();
+    fs::write(&dirty, "+------+\n| Box |\n+------+\n").unwrap();
+
+    let (stdout, stderr, code) = run_aadc_args(&[
+        "--no-config",
+        "--json-lines",
+        clean.to_str().unwrap(),
+        dirty.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2, "stdout: {stdout}");
+    assert_eq!(lines[0]["output"]["changed"], false);
+    assert_eq!(lines[1]["output"]["changed"], true);
+    assert_eq!(lines[1]["file"], dirty.to_str().unwrap());
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_output_format_gnu() {
+    test_log!(
+        "START",
+        "--output-format gnu prints file:line:col diagnostics"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let clean = temp.path().join("clean.txt");
+    let dirty = temp.path().join("dirty.txt");
+    fs::write(&clean, "+------+\n| Box  |\n+------+\n").unwrap();
+    fs::write(&dirty, "+------+\n| Box |\n+------+\n").unwrap();
+    let clean = clean.to_str().unwrap();
+    let dirty = dirty.to_str().unwrap();
+
+    let (stdout, stderr, code) =
+        run_aadc_args(&["--no-config", "--output-format", "gnu", clean, dirty]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+    assert_eq!(
+        stdout,
+        format!("{dirty}:2:7: warning: right border 1 column(s) short of target 8\n")
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_junit_report() {
+    test_log!("START", "--report junit=PATH writes a test case per file");
+
+    let temp = TempDir::new().unwrap();
+    let clean = temp.path().join("clean.txt");
+    let dirty = temp.path().join("dirty.txt");
+    let report = temp.path().join("aadc.xml");
+    fs::write(&clean, "+------+\n| Box  |\n+------+\n").unwrap();
+    fs::write(&dirty, "+------+\n| Box |\n+------+\n").unwrap();
+    let report_arg = format!("junit={}", report.display());
+
+    let (_stdout, stderr, code) = run_aadc_args(&[
+        "--no-config",
+        "--check",
+        "--report",
+        &report_arg,
+        clean.to_str().unwrap(),
+        dirty.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 3, "stderr: {stderr}");
+
+    let xml = fs::read_to_string(&report).unwrap();
+    assert!(xml.contains("tests=\"2\" failures=\"1\" errors=\"0\""));
+    assert!(xml.contains("<failure type=\"misaligned\" message=\"1 block(s) need correction\">"));
+    assert!(xml.contains("+| Box  |"), "Diff is attached: {xml}");
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--report", "html=x.html"]);
+    assert_eq!(code, 2, "stderr: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_cached_skips_unchanged_files() {
+    test_log!(
+        "START",
+        "--cached skips files found clean by an earlier run"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.txt");
+    let cache = temp.path().join("cache.json");
+    fs::write(&file, "+------+\n| Box  |\n+------+\n").unwrap();
+    let path = file.to_str().unwrap();
+    let cache_arg = cache.to_str().unwrap();
+    let args = [
+        "--no-config",
+        "--cached",
+        "--cache-file",
+        cache_arg,
+        "--verbose",
+        "--dry-run",
+        path,
+    ];
+
+    let (stdout, stderr, code) = run_aadc_args(&args);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    assert!(!stdout.contains("clean in cache"));
+    assert!(cache.exists());
+
+    let (stdout, stderr, code) = run_aadc_args(&args);
+    assert_eq!(code, 0, "stderr: {stderr}");
+    assert!(
+        stdout.contains("clean in cache, skipped"),
+        "stdout: {stdout}"
+    );
+
+    fs::write(&file, "+------+\n| Box |\n+------+\n").unwrap();
+    let (_stdout, stderr, code) = run_aadc_args(&args);
+    assert_eq!(code, 3, "Edited file is checked again: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_preserves_crlf() {
+    test_log!("START", "CRLF files keep their line endings when corrected");
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.txt");
+    fs::write(&file, "+------+\r\n| Box |\r\n+------+\r\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let (_stdout, _stderr, code) = run_aadc_args(&["--no-config", "-i", path]);
+    assert_eq!(code, 0);
+    let written = fs::read_to_string(&file).unwrap();
+    assert_eq!(written, "+------+\r\n| Box  |\r\n+------+\r\n");
+
+    let (_stdout, _stderr, code) = run_aadc_args(&["--no-config", "--dry-run", path]);
+    assert_eq!(code, 0, "Unchanged CRLF file is not a change");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_mixed_eol_only_revised_lines_converted() {
+    test_log!(
+        "START",
+        "Mixed-EOL files keep the endings of lines outside revised blocks"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.txt");
+    let prose = "Intro\r\nnotes\n\r\n";
+    let untouched_box = "\r\n+--+\n|ok|\r\n+--+\r\n";
+    fs::write(
+        &file,
+        format!("{prose}+------+\r\n| Box |\n+------+\r\n{untouched_box}"),
+    )
+    .unwrap();
+    let path = file.to_str().unwrap();
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "-i", path]);
+    assert_eq!(code, 0, "{stderr}");
+    // The revised line takes the dominant CRLF; everything else is as it was
+    let written = String::from_utf8(fs::read(&file).unwrap()).unwrap();
+    assert_eq!(
+        written,
+        format!("{prose}+------+\r\n| Box  |\r\n+------+\r\n{untouched_box}")
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_in_place_leaves_plain_files_untouched() {
+    test_log!(
+        "START",
+        "Files without diagrams keep their exact bytes under -i"
+    );
+
+    let temp = TempDir::new().unwrap();
+    for (name, content) in [("no_newline.txt", "a\nb"), ("mixed.txt", "a\nb\r\nc\n")] {
+        let file = temp.path().join(name);
+        fs::write(&file, content).unwrap();
+        let path = file.to_str().unwrap();
+
+        let (_stdout, stderr, code) = run_aadc_args(&["--no-config", "-i", path]);
+        assert_eq!(code, 0, "{name}: {stderr}");
+        assert_eq!(fs::read(&file).unwrap(), content.as_bytes(), "{name}");
+
+        for flag in ["--dry-run", "--check", "-l"] {
+            let (_stdout, _stderr, code) = run_aadc_args(&["--no-config", flag, path]);
+            assert_eq!(code, 0, "{name} {flag}");
+        }
+        let (stdout, _stderr, _code) = run_aadc_args(&["--no-config", path]);
+        assert_eq!(stdout, content, "{name} on stdout");
+    }
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_stream_stdin() {
+    test_log!("START", "--stream corrects stdin chunk by chunk");
+
+    let mut input = String::from("+----+\n| a |\n+----+\n");
+    for i in 0..50 {
+        input.push_str(&format!("line {}\n", i));
+    }
+    input.push_str("+----+\n| b |\n+----+\n");
+
+    let (stdout, _stderr, code) = run_aadc_stdin(&input, &["--no-config", "--stream"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("+----+\n| a  |\n+----+\nline 0\n"));
+    assert!(stdout.ends_with("line 49\n+----+\n| b  |\n+----+\n"));
+
+    // Line ranges select lines of the whole input, not of each chunk
+    let (stdout, _stderr, code) =
+        run_aadc_stdin(&input, &["--no-config", "--stream", "--lines", "54-56"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("+----+\n| a |\n+----+\nline 0\n"));
+    assert!(stdout.ends_with("line 49\n+----+\n| b  |\n+----+\n"));
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("many.txt");
+    fs::write(&file, &input).unwrap();
+    let with_ranges = format!("{}:54-56", file.display());
+    let (file_stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "--stream", with_ranges.as_str()]);
+    assert_eq!(code, 0);
+    assert_eq!(file_stdout, stdout);
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--stream", "a.txt", "b.txt"]);
+    assert_eq!(code, 2, "stderr: {stderr}");
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_encoding_round_trip() {
+    test_log!(
+        "START",
+        "--encoding keeps Latin-1 and UTF-16 files in their encoding"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let latin1 = temp.path().join("latin1.txt");
+    fs::write(&latin1, b"+-------+\n| caf\xE9 |\n+-------+\n").unwrap();
+
+    let (_stdout, stderr, code) = run_aadc_args(&["--no-config", latin1.to_str().unwrap()]);
+    assert_eq!(code, 4, "Latin-1 is not UTF-8 by default: {}", stderr);
+
+    let (_stdout, _stderr, code) = run_aadc_args(&[
+        "--no-config",
+        "--encoding",
+        "auto",
+        "-i",
+        latin1.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        fs::read(&latin1).unwrap(),
+        b"+-------+\n| caf\xE9  |\n+-------+\n"
+    );
+
+    let utf16 = temp.path().join("utf16.txt");
+    let encode = |text: &str| -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    };
+    fs::write(&utf16, encode("+------+\n| Box |\n+------+\n")).unwrap();
+    let (_stdout, _stderr, code) = run_aadc_args(&[
+        "--no-config",
+        "--encoding",
+        "auto",
+        "-i",
+        utf16.to_str().unwrap(),
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        fs::read(&utf16).unwrap(),
+        encode("+------+\n| Box  |\n+------+\n")
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_list_different() {
+    test_log!(
+        "START",
+        "--list-different prints only paths that would change"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let broken = temp.path().join("broken.txt");
+    let clean = temp.path().join("clean.txt");
+    fs::write(&broken, "+------+\n| ab |\n+------+\n").unwrap();
+    fs::write(&clean, "plain text\n").unwrap();
+    let broken_path = broken.to_str().unwrap();
+    let clean_path = clean.to_str().unwrap();
+
+    let (stdout, _stderr, code) = run_aadc_args(&["--no-config", "-l", broken_path, clean_path]);
+    assert_eq!(code, 1, "Exits 1 when a file would change");
+    assert_eq!(stdout, format!("{}\n", broken_path));
+    assert_eq!(
+        fs::read_to_string(&broken).unwrap(),
+        "+------+\n| ab |\n+------+\n",
+        "Files are not modified"
+    );
+
+    let (stdout, _stderr, code) =
+        run_aadc_args(&["--no-config", "-l", "-0", broken_path, clean_path]);
+    assert_eq!(code, 1);
+    assert_eq!(stdout, format!("{}\0", broken_path));
+
+    let (stdout, _stderr, code) = run_aadc_args(&["--no-config", "-l", clean_path]);
+    assert_eq!(code, 0);
+    assert!(stdout.is_empty());
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_batch_documents() {
+    test_log!(
+        "START",
+        "--batch corrects each document in a separated stream"
+    );
+
+    let input = "+------+\n| ab |\n+------+\n\x0cplain text\n\x0c";
+    let (stdout, _stderr, code) = run_aadc_stdin(input, &["--no-config", "--batch"]);
+    assert_eq!(code, 0);
+
+    let frames: Vec<&str> = stdout.split('\x0c').collect();
+    assert_eq!(
+        frames.len(),
+        3,
+        "Each document is followed by the separator"
+    );
+    assert!(frames[2].is_empty());
+    assert!(frames[0].starts_with("+------+\n| ab   |\n+------+\n{"));
+    assert!(frames[0].ends_with("\"changed\":true,\"blocks\":1,\"revisions\":1}\n"));
+    assert_eq!(
+        frames[1],
+        "plain text\n{\"document\":2,\"changed\":false,\"blocks\":0,\"revisions\":0}\n"
+    );
+
+    let (stdout, _stderr, code) = run_aadc_stdin(
+        "a\n---\nb\n",
+        &["--no-config", "--batch", "--separator", "---\\n"],
+    );
+    assert_eq!(code, 0);
+    assert_eq!(stdout.matches("---\n").count(), 2);
+    assert!(stdout.starts_with("a\n{\"document\":1,"));
+
+    // A document without a final newline still gets its trailer on its own line
+    let (stdout, _stderr, code) = run_aadc_stdin("abc\x0cdef", &["--no-config", "--batch"]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "abc\n{\"document\":1,\"changed\":false,\"blocks\":0,\"revisions\":0,\"newline_added\":true}\n\x0c\
+         def\n{\"document\":2,\"changed\":false,\"blocks\":0,\"revisions\":0,\"newline_added\":true}\n\x0c"
+    );
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_ci_detection() {
+    test_log!(
+        "START",
+        "CI detection switches lint output and prints the config when verbose"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let file = temp.path().join("doc.md");
+    fs::write(&file, "+------+\n| ab |\n+------+\n").unwrap();
+    let path = file.to_str().unwrap();
+
+    let output = aadc_command()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-config", "lint", path])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.starts_with(&format!("::error file={},line=2,", path)),
+        "GitHub annotations by default: {stdout}"
+    );
+    assert!(
+        !stderr.contains("detected"),
+        "Config is only printed with --verbose: {stderr}"
+    );
+
+    let output = aadc_command()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-config", "--verbose", "lint", path])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("GitHub Actions detected"));
+    assert!(stderr.contains("  min_score: 0.5"));
+
+    let output = aadc_command()
+        .env("GITHUB_ACTIONS", "true")
+        .args(["--no-config", "--no-ci", "lint", path])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(&format!("{}:2:", path)));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("detected"));
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_cargo_aadc_doc_comments() {
+    test_log!(
+        "START",
+        "cargo aadc corrects doc comments and docs, not code"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    fs::create_dir(root.join("src")).unwrap();
+    let lib = "//! +------+\n//! | ab |\n//! +------+\n\n\
+               pub fn or(a: u8, b: u8) -> u8 {\n    a | b\n}\n";
+    fs::write(root.join("src/lib.rs"), lib).unwrap();
+    let crlf = "/// +------+\r\n/// | ab |\r\n/// +------+\r\npub struct S;\r\n";
+    fs::write(root.join("src/crlf.rs"), crlf).unwrap();
+    fs::write(root.join("README.md"), "+------+\n| ab |\n+------+\n").unwrap();
+    fs::create_dir(root.join("target")).unwrap();
+    fs::write(root.join("target/skip.md"), "+------+\n| ab |\n+------+\n").unwrap();
+
+    let cargo_aadc = || {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_cargo-aadc"));
+        cmd.current_dir(root)
+            .arg("aadc")
+            .env_remove("CI")
+            .env_remove("GITHUB_ACTIONS")
+            .env_remove("GITLAB_CI");
+        cmd
+    };
+
+    let output = cargo_aadc().arg("--check").output().unwrap();
+    assert_eq!(output.status.code(), Some(3), "--check reports changes");
+    assert_eq!(fs::read_to_string(root.join("src/lib.rs")).unwrap(), lib);
+
+    let output = cargo_aadc().output().unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+        "//! +------+\n//! | ab   |\n//! +------+\n\n\
+         pub fn or(a: u8, b: u8) -> u8 {\n    a | b\n}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("src/crlf.rs")).unwrap(),
+        "/// +------+\r\n/// | ab   |\r\n/// +------+\r\npub struct S;\r\n",
+        "CRLF line endings are kept"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("README.md")).unwrap(),
+        "+------+\n| ab   |\n+------+\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("target/skip.md")).unwrap(),
+        "+------+\n| ab |\n+------+\n",
+        "target/ is excluded"
+    );
+
+    let output = cargo_aadc().arg("--check").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+
+    test_log!("END", "Test PASSED");
+}
+
+// ============================================================================
+// File List Tests
+// ============================================================================
+
+#[test]
+fn test_e2e_files_from_null_separated_stdin() {
+    test_log!("START", "--files-from - with NUL separators");
+
+    let temp = TempDir::new().unwrap();
+    let input = "+---+\n| a|\n+---+\n";
+    let a = temp.path().join("a file.md");
+    let b = temp.path().join("b.md");
+    let untouched = temp.path().join("c.md");
+    for path in [&a, &b, &untouched] {
+        fs::write(path, input).unwrap();
+    }
+
+    let list = format!("{}\0{}\0", a.display(), b.display());
+    let (_stdout, stderr, code) = run_aadc_stdin(&list, &["--files-from", "-", "-0", "-i"]);
+
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    assert!(fs::read_to_string(&a).unwrap().contains("| a |"));
+    assert!(fs::read_to_string(&b).unwrap().contains("| a |"));
+    assert_eq!(fs::read_to_string(&untouched).unwrap(), input);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_files_from_list_file_dry_run() {
+    test_log!("START", "--files-from FILE with --dry-run");
+
+    let temp = TempDir::new().unwrap();
+    let doc = temp.path().join("doc.md");
+    fs::write(&doc, "+---+\n| a|\n+---+\n").unwrap();
+    let list = temp.path().join("files.txt");
+    fs::write(&list, format!("{}\r\n\n", doc.display())).unwrap();
+
+    let (_stdout, _stderr, code) =
+        run_aadc_args(&["--files-from", list.to_str().unwrap(), "--dry-run"]);
+    assert_eq!(code, 3, "dry run should report pending changes");
+
+    // An empty list is a no-op rather than a read from stdin
+    fs::write(&list, "").unwrap();
+    let (_stdout, _stderr, code) = run_aadc_args(&["--files-from", list.to_str().unwrap()]);
+    assert_eq!(code, 0);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_stdin_placeholder_among_files() {
+    test_log!("START", "\"-\" reads stdin between files");
+
+    let temp = TempDir::new().unwrap();
+    let a = temp.path().join("a.txt");
+    let b = temp.path().join("b.txt");
+    fs::write(&a, "+---+\n| a|\n+---+\n").unwrap();
+    fs::write(&b, "+---+\n| b|\n+---+\n").unwrap();
+
+    let (stdout, stderr, code) = run_aadc_stdin(
+        "+---+\n| s|\n+---+\n",
+        &[a.to_str().unwrap(), "-", b.to_str().unwrap()],
+    );
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    let a_at = stdout.find("| a |").expect("a corrected");
+    let s_at = stdout.find("| s |").expect("stdin corrected");
+    let b_at = stdout.find("| b |").expect("b corrected");
+    assert!(
+        a_at < s_at && s_at < b_at,
+        "inputs stay in order:\n{}",
+        stdout
+    );
+    assert!(stdout.contains("==> stdin <=="));
+
+    // Stdin cannot be written back in place
+    let (_stdout, _stderr, code) = run_aadc_args(&["-i", a.to_str().unwrap(), "-"]);
+    assert_eq!(code, 2);
+
+    test_log!("END", "Test PASSED");
+}
+
+#[test]
+fn test_e2e_stdin_filename_labels_and_syntax() {
+    test_log!("START", "--stdin-filename names stdin content");
+
+    let input = "+---+\n| a|\n+---+\n";
+    let (stdout, stderr, code) = run_aadc_stdin(
+        input,
+        &["--no-config", "--diff", "--stdin-filename", "docs/guide.md"],
+    );
+    assert_eq!(code, 0, "stderr: {}", stderr);
+    assert!(stdout.contains("--- a/docs/guide.md"), "{}", stdout);
+    assert!(stdout.contains("+++ b/docs/guide.md"), "{}", stdout);
+
+    // The pseudo-path's extension selects the embedded syntax: only the
+    // YAML block scalar is corrected, not the plain value
+    let yaml = "plain: \"| a|\"\ndiagram: |\n  +---+\n  | a|\n  +---+\n";
+    let (stdout, _stderr, code) = run_aadc_stdin(
+        yaml,
+        &["--no-config", "--embedded", "--stdin-filename", "c.yaml"],
+    );
+    assert_eq!(code, 0);
+    assert!(stdout.contains("  | a |"), "{}", stdout);
+
+    test_log!("END", "Test PASSED");
+}
+
+/// Serve canned GitHub API responses, keyed by request path, on a local
+/// port; returns the base URL
+#[cfg(feature = "net")]
+fn serve_github(routes: Vec<(String, String)>) -> String {
+    use std::io::{BufRead, BufReader};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                header.clear();
+            }
+            let target = request_line.split(' ').nth(1).unwrap_or_default();
+            let path = target.split('?').next().unwrap_or_default();
+            let (status, body) = routes
+                .iter()
+                .find(|(route, _)| route == path)
+                .map_or(("404 Not Found", "{}"), |(_, body)| ("200 OK", body));
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+        }
+    });
+    url
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn test_e2e_suggest_requires_pr_head_checkout() {
+    test_log!(
+        "START",
+        "suggest only runs on a clean checkout of the pull request head"
+    );
+
+    let temp = TempDir::new().unwrap();
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(temp.path())
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| {
+                String::from_utf8_lossy(&output.stdout)
+                    .trim_end()
+                    .to_string()
+            })
+    };
+    if git(&["init", "-q"]).is_none() {
+        test_log!("SKIP", "git is not available");
+        return;
+    }
+    let doc = "+------+\n| ab |\n+------+\n";
+    fs::create_dir(temp.path().join("docs")).unwrap();
+    fs::write(temp.path().join("docs/doc.md"), doc).unwrap();
+    git(&["add", "."]).unwrap();
+    git(&["commit", "-q", "-m", "head"]).unwrap();
+    let sha = git(&["rev-parse", "HEAD"]).unwrap();
+
+    let api = serve_github(vec![
+        (
+            "/repos/o/n/pulls/1".to_string(),
+            format!("{{\"head\":{{\"sha\":\"{sha}\"}}}}"),
+        ),
+        (
+            "/repos/o/n/pulls/1/files".to_string(),
+            r#"[{"filename":"docs/doc.md","status":"added","patch":"@@ -0,0 +1,3 @@"}]"#
+                .to_string(),
+        ),
+    ]);
+    // Run from a subdirectory: PR file names are relative to the top level
+    let suggest = || {
+        aadc_command()
+            .args([
+                "--no-config",
+                "suggest",
+                "--repo",
+                "o/n",
+                "--pr",
+                "1",
+                "--dry-run",
+            ])
+            .current_dir(temp.path().join("docs"))
+            .env("GITHUB_API_URL", &api)
+            .env_remove("GITHUB_TOKEN")
+            .env_remove("GH_TOKEN")
+            .output()
+            .unwrap()
+    };
+
+    let output = suggest();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains("| ab   |"), "{stdout}");
+
+    fs::write(temp.path().join("docs/doc.md"), format!("intro\n{doc}")).unwrap();
+    let output = suggest();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("uncommitted changes"));
+
+    git(&["commit", "-q", "-am", "later"]).unwrap();
+    let output = suggest();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("check out the head first"));
+
+    test_log!("END", "Test PASSED");
+}